@@ -12,6 +12,7 @@ defmodule PRQL do
           :generic
           | :mssql
           | :mysql
+          | :oracle
           | :postgres
           | :ansi
           | :bigquery
@@ -36,8 +37,8 @@ defmodule PRQL do
   ## Options
 
     * `:target` - Dialect used for generate SQL. Accepted values are
-    `:generic`, `:mssql`, `:mysql`, `:postgres`, `:ansi`, `:bigquery`,
-    `:clickhouse`, `:glaredb`, `:sqlite`, `:snowflake`
+    `:generic`, `:mssql`, `:mysql`, `:oracle`, `:postgres`, `:ansi`,
+    `:bigquery`, `:clickhouse`, `:glaredb`, `:sqlite`, `:snowflake`
 
     * `:format` - Formats the output, defaults to `true`
 
@@ -71,6 +72,33 @@ defmodule PRQL do
     end
   end
 
+  @doc ~S"""
+  Format a `PRQL` query, by parsing it and emitting it again.
+
+  Returns `{:ok, prql}` on success. On error, `{:error, reason}` is returned
+  where `reason` is a `JSON` string, as in `compile/2`.
+
+  ## Examples
+
+      iex> PRQL.format("from   customers|take 10")
+      {:ok, "from customers\ntake 10\n"}
+  """
+  @spec format(binary()) :: {:ok, binary()} | {:error, binary()}
+  def format(prql_query) when is_binary(prql_query) do
+    PRQL.Native.format(prql_query)
+  end
+
+  @doc """
+  The same as `format/1` but raises `PRQL.PRQLError` exception in case of error
+  """
+  @spec format!(binary()) :: binary()
+  def format!(prql_query) when is_binary(prql_query) do
+    case format(prql_query) do
+      {:ok, result} -> result
+      {:error, reason} -> raise PRQL.PRQLError, reason
+    end
+  end
+
   @doc """
   PRQL to PL AST
   """
@@ -4,6 +4,8 @@ defmodule PRQL.Native do
 
   def compile(_prql_query, _options), do: e()
 
+  def format(_prql_query), do: e()
+
   def prql_to_pl(_prql_query), do: e()
 
   def pl_to_rq(_pl_json), do: e()
@@ -21,6 +23,7 @@ defmodule PRQL.Native.CompileOptions do
           :generic
           | :mssql
           | :mysql
+          | :oracle
           | :postgres
           | :ansi
           | :bigquery
@@ -23,6 +23,7 @@ mod atoms {
       generic,
       mssql,
       mysql,
+      oracle,
       postgres,
       sqlite,
       snowflake
@@ -61,6 +62,8 @@ fn target_from_atom(a: Atom) -> prqlc::Target {
         MsSql
     } else if a == atoms::mysql() {
         MySql
+    } else if a == atoms::oracle() {
+        Oracle
     } else if a == atoms::postgres() {
         Postgres
     } else if a == atoms::sqlite() {
@@ -127,6 +130,16 @@ pub fn compile(prql_query: &str, options: CompileOptions) -> NifResult<Response>
     to_result_tuple(prqlc::compile(prql_query, &options.into()))
 }
 
+#[rustler::nif]
+/// format a prql query, by parsing it and emitting it again
+pub fn format(prql_query: &str) -> NifResult<Response> {
+    to_result_tuple(
+        Ok(prql_query)
+            .and_then(prqlc::prql_to_pl)
+            .and_then(|x| prqlc::pl_to_prql(&x)),
+    )
+}
+
 #[rustler::nif]
 /// convert a prql query into PL AST
 pub fn prql_to_pl(prql_query: &str) -> NifResult<Response> {
@@ -17,6 +17,18 @@ defmodule PRQLTest do
               """}
   end
 
+  test "formats PRQL" do
+    prql_query = "from   customers|filter (  age >   30)\n\n\n   select {name,age}  "
+
+    assert PRQL.format(prql_query) ==
+             {:ok,
+              """
+              from customers
+              filter age > 30
+              select {name, age}
+              """}
+  end
+
   test "return errors on invalid query" do
     {:ok, expected_json} =
       Jason.decode(~S"""
@@ -15,6 +15,13 @@ public class PrqlCompiler {
      */
     public static native String toSql(String query, String target, boolean format, boolean signature) throws Exception;
     public static native String toJson(String query) throws Exception;
+    /**
+     * compile PRQL to RQ (relational query IR) serialized as JSON
+     * @param query PRQL query
+     * @return RQ JSON
+     * @throws Exception PRQL compile exception
+     */
+    public static native String toRq(String query) throws Exception;
     public static native String format(String query) throws Exception;
 
     static {
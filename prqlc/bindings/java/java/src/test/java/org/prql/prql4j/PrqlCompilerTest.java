@@ -21,4 +21,18 @@ public class PrqlCompilerTest {
     public void compileWithError() throws Exception {
        PrqlCompiler.toSql("from table | filter id >> 1", "sql.mysql", true, true);
     }
+
+    @Test
+    public void compileToRq() throws Exception {
+        String found = PrqlCompiler.toRq("from my_table | select {a, b}");
+
+        assert found.startsWith("{");
+        assert found.contains("\"relation\"");
+        assert found.contains("\"my_table\"");
+    }
+
+    @Test(expected = Exception.class)
+    public void compileToRqWithError() throws Exception {
+        PrqlCompiler.toRq("from table | filter id >> 1");
+    }
 }
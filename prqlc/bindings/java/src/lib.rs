@@ -3,7 +3,7 @@ use std::str::FromStr;
 use jni::objects::{JClass, JString};
 use jni::sys::{jboolean, jstring};
 use jni::JNIEnv;
-use prqlc::{json, pl_to_prql, prql_to_pl, ErrorMessages, Options, Target};
+use prqlc::{json, pl_to_prql, pl_to_rq, prql_to_pl, ErrorMessages, Options, Target};
 
 #[no_mangle]
 #[allow(non_snake_case)]
@@ -65,6 +65,23 @@ pub extern "system" fn Java_org_prql_prql4j_PrqlCompiler_toJson(
     java_string_with_exception(result, &mut env)
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "system" fn Java_org_prql_prql4j_PrqlCompiler_toRq(
+    mut env: JNIEnv,
+    _class: JClass,
+    query: JString,
+) -> jstring {
+    let prql_query: String = env
+        .get_string(&query)
+        .expect("Couldn't get java string!")
+        .into();
+    let result = prql_to_pl(&prql_query)
+        .and_then(pl_to_rq)
+        .and_then(|x| json::from_rq(&x));
+    java_string_with_exception(result, &mut env)
+}
+
 fn java_string_with_exception(result: Result<String, ErrorMessages>, env: &mut JNIEnv) -> jstring {
     if let Ok(text) = result {
         env.new_string(text)
@@ -133,6 +133,16 @@ fn return_or_throw(result: Result<String, prqlc::ErrorMessages>) -> Option<Strin
 
     match result {
         Ok(sql) => Some(sql),
-        Err(e) => wasm_bindgen::throw_str(&e.to_json()),
+        Err(e) => {
+            // the message stays the JSON of the errors, while `diagnostics`
+            // holds them as objects, so they don't need to be parsed
+            let json = e.to_json();
+            let error = js_sys::Error::new(&json);
+            let diagnostics = js_sys::JSON::parse(&json)
+                .and_then(|messages| js_sys::Reflect::get(&messages, &"inner".into()))
+                .unwrap_or(JsValue::UNDEFINED);
+            js_sys::Reflect::set(&error, &"diagnostics".into(), &diagnostics).ok();
+            wasm_bindgen::throw_val(error.into())
+        }
     }
 }
@@ -129,5 +129,20 @@ describe("prqlc-js", () => {
         assert(errorMessages[0].code == "E0001");
       }
     });
+
+    it("should contain diagnostics", () => {
+      try {
+        prqlc.compile("from x | select {a");
+        assert.fail("expected an error");
+      } catch (error) {
+        assert(error instanceof Error);
+        const diagnostics = error.diagnostics;
+
+        assert(diagnostics.length > 0);
+        assert(diagnostics[0].reason.includes("tuple"));
+        assert.deepStrictEqual(diagnostics[0].location.start, [0, 18]);
+        assert(diagnostics[0].span != null);
+      }
+    });
   });
 });
@@ -2,16 +2,27 @@
 
 #include <prqlc.h>
 
+const char *kind_label(MessageKind kind) {
+  switch (kind) {
+  case Warning:
+    return "Warning";
+  case Lint:
+    return "Lint";
+  default:
+    return "Error";
+  }
+}
+
 void print_result(CompileResult res) {
-  printf("---- [ Compiled with %ld errors ]----\n", res.messages_len);
+  printf("---- [ Compiled with %ld messages ]----\n", res.messages_len);
   for (int i = 0; i < res.messages_len; i++) {
     Message const *e = &res.messages[i];
     if (e->display != NULL) {
       printf("%s", *e->display);
     } else if (e->code != NULL) {
-      printf("[%s] Error: %s\n", *e->code, e->reason);
+      printf("[%s] %s: %s\n", *e->code, kind_label(e->kind), e->reason);
     } else {
-      printf("Error: %s", e->reason);
+      printf("%s: %s", kind_label(e->kind), e->reason);
     }
   }
   if (*res.output == '\0') {
@@ -45,6 +56,16 @@ int main() {
     return 1;
   result_destroy(res);
 
+  // params are left in the SQL, to be bound when executing it
+  res = compile("from albums | filter artist_id == $artist", &opts);
+  print_result(res);
+  for (int i = 0; i < res.params_len; i++) {
+    printf("Param: %s\n", res.params[i]);
+  }
+  if (res.params_len != 1)
+    return 1;
+  result_destroy(res);
+
   // error handling
   res = compile("from album | select {album_id} | select {title}", NULL);
   print_result(res);
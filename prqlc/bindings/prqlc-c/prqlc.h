@@ -86,6 +86,13 @@ typedef struct CompileResult {
   const char *output;
   const struct Message *messages;
   size_t messages_len;
+  /**
+   * Names of the parameters of the query, such as `1` of `$1`, which are
+   * left in the SQL as placeholders. They are ordered by their first
+   * occurrence in the query.
+   */
+  const char *const *params;
+  size_t params_len;
 } CompileResult;
 
 /**
@@ -122,6 +129,9 @@ typedef struct Options {
  *
  * See `Options` struct for available compilation options.
  *
+ * On success, `messages` contains any warnings produced during compilation
+ * and `params` contains the names of the parameters of the query.
+ *
  * # Safety
  *
  * This function assumes zero-terminated input strings.
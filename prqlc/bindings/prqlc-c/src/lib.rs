@@ -7,8 +7,8 @@ use std::ffi::CString;
 use std::str::FromStr;
 
 use libc::{c_char, size_t};
-use prqlc::ErrorMessages;
 use prqlc::Target;
+use prqlc::{CompileOutput, ErrorMessages};
 
 /// Compile a PRQL string into a SQL string.
 ///
@@ -17,6 +17,9 @@ use prqlc::Target;
 ///
 /// See `Options` struct for available compilation options.
 ///
+/// On success, `messages` contains any warnings produced during compilation
+/// and `params` contains the names of the parameters of the query.
+///
 /// # Safety
 ///
 /// This function assumes zero-terminated input strings.
@@ -32,13 +35,7 @@ pub unsafe extern "C" fn compile(
     let options = options.as_ref().map(convert_options).transpose();
 
     let result = options
-        .and_then(|opts| {
-            Ok(prql_query.as_str())
-                .and_then(prqlc::prql_to_pl)
-                .and_then(prqlc::pl_to_rq)
-                .and_then(|rq| prqlc::rq_to_sql(rq, &opts.unwrap_or_default()))
-        })
-        .map_err(|e| e.composed(&prql_query.into()));
+        .and_then(|opts| prqlc::compile_with_warnings(&prql_query, &opts.unwrap_or_default()));
 
     result_into_c_str(result)
 }
@@ -62,7 +59,7 @@ pub unsafe extern "C" fn prql_to_pl(prql_query: *const c_char) -> CompileResult
     let result = Ok(prql_query.as_str())
         .and_then(prqlc::prql_to_pl)
         .and_then(|x| prqlc::json::from_pl(&x));
-    result_into_c_str(result)
+    result_into_c_str(result.map(without_warnings))
 }
 
 /// Finds variable references, validates functions calls, determines frames and converts PL to RQ.
@@ -86,7 +83,7 @@ pub unsafe extern "C" fn pl_to_rq(pl_json: *const c_char) -> CompileResult {
         .and_then(prqlc::json::to_pl)
         .and_then(prqlc::pl_to_rq)
         .and_then(|x| prqlc::json::from_rq(&x));
-    result_into_c_str(result)
+    result_into_c_str(result.map(without_warnings))
 }
 
 /// Convert RQ AST into an SQL string. RQ is documented in the
@@ -115,7 +112,7 @@ pub unsafe extern "C" fn rq_to_sql(
             .and_then(prqlc::json::to_rq)
             .and_then(|x| prqlc::rq_to_sql(x, &options.unwrap_or_default()))
     });
-    result_into_c_str(result)
+    result_into_c_str(result.map(without_warnings))
 }
 
 /// Compilation options
@@ -145,6 +142,11 @@ pub struct CompileResult {
     pub output: *const libc::c_char,
     pub messages: *const Message,
     pub messages_len: size_t,
+    /// Names of the parameters of the query, such as `1` of `$1`, which are
+    /// left in the SQL as placeholders. They are ordered by their first
+    /// occurrence in the query.
+    pub params: *const *const libc::c_char,
+    pub params_len: size_t,
 }
 
 /// Compile message kind. Currently only Error is implemented.
@@ -236,41 +238,73 @@ pub unsafe extern "C" fn result_destroy(res: CompileResult) {
         }
     }
     drop(Vec::from_raw_parts(
-        res.messages as *mut i8,
+        res.messages as *mut Message,
         res.messages_len,
         res.messages_len,
     ));
+    for i in 0..res.params_len {
+        drop(CString::from_raw(*res.params.add(i) as *mut libc::c_char));
+    }
+    drop(Vec::from_raw_parts(
+        res.params as *mut *const libc::c_char,
+        res.params_len,
+        res.params_len,
+    ));
     drop(CString::from_raw(res.output as *mut libc::c_char));
 }
 
-unsafe fn result_into_c_str(result: Result<String, ErrorMessages>) -> CompileResult {
-    match result {
-        Ok(output) => CompileResult {
-            output: convert_string(output),
-            messages: ::std::ptr::null_mut(),
-            messages_len: 0,
-        },
-        Err(err) => {
-            let mut errors = Vec::with_capacity(err.inner.len());
-            errors.extend(err.inner.into_iter().map(|e| Message {
-                kind: MessageKind::Error,
-                code: option_to_ptr(e.code.map(convert_string)),
-                reason: convert_string(e.reason),
-                hint: option_to_ptr(if e.hints.is_empty() {
-                    None
-                } else {
-                    Some(convert_string(e.hints.join("\n")))
-                }),
-                span: option_to_ptr(e.span.map(convert_span)),
-                display: option_to_ptr(e.display.map(convert_string)),
-                location: option_to_ptr(e.location.map(convert_source_location)),
-            }));
-            CompileResult {
-                output: CString::default().into_raw(),
-                messages_len: errors.len(),
-                messages: errors.leak().as_ptr(),
-            }
-        }
+unsafe fn result_into_c_str(result: Result<CompileOutput, ErrorMessages>) -> CompileResult {
+    let (output, messages, params) = match result {
+        Ok(res) => (convert_string(res.output), res.warnings, res.params),
+        Err(err) => (
+            CString::default().into_raw() as *const _,
+            err.inner,
+            Vec::new(),
+        ),
+    };
+
+    // boxed slices guarantee that capacity equals length, which is what
+    // `result_destroy` assumes when reconstructing the vecs
+    let messages: Box<[Message]> = messages.into_iter().map(convert_message).collect();
+    let params: Box<[*const libc::c_char]> = params.into_iter().map(convert_string).collect();
+    CompileResult {
+        output,
+        messages_len: messages.len(),
+        messages: Box::leak(messages).as_ptr(),
+        params_len: params.len(),
+        params: Box::leak(params).as_ptr(),
+    }
+}
+
+fn without_warnings(output: String) -> CompileOutput {
+    CompileOutput {
+        output,
+        warnings: Vec::new(),
+        params: Vec::new(),
+    }
+}
+
+fn convert_message(e: prqlc::ErrorMessage) -> Message {
+    Message {
+        kind: convert_message_kind(e.kind),
+        code: option_to_ptr(e.code.map(convert_string)),
+        reason: convert_string(e.reason),
+        hint: option_to_ptr(if e.hints.is_empty() {
+            None
+        } else {
+            Some(convert_string(e.hints.join("\n")))
+        }),
+        span: option_to_ptr(e.span.map(convert_span)),
+        display: option_to_ptr(e.display.map(convert_string)),
+        location: option_to_ptr(e.location.map(convert_source_location)),
+    }
+}
+
+fn convert_message_kind(x: prqlc::MessageKind) -> MessageKind {
+    match x {
+        prqlc::MessageKind::Error => MessageKind::Error,
+        prqlc::MessageKind::Warning => MessageKind::Warning,
+        prqlc::MessageKind::Lint => MessageKind::Lint,
     }
 }
 
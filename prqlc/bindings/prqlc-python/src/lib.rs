@@ -157,6 +157,22 @@ fn convert_options(o: CompileOptions) -> Result<prqlc_lib::Options, prqlc_lib::E
         display: prqlc_lib::DisplayOptions::from_str(&o.display).map_err(|e| ErrorMessages {
             inner: vec![Error::new_simple(format!("Invalid display option: {}", e)).into()],
         })?,
+        output_encoding: Default::default(),
+        inline_ctes: false,
+        write_mode: Default::default(),
+        date_format: None,
+        leading_comma: false,
+        main: None,
+        aggregate_null_handling: Default::default(),
+        join_using: false,
+        uppercase_keywords: true,
+        cte_order: Default::default(),
+        explicit_null_cast: false,
+        std_lib: Default::default(),
+        nulls_order: Default::default(),
+        prelude: Default::default(),
+        trailing_semicolon: false,
+        signature_text: None,
     })
 }
 
@@ -232,7 +248,7 @@ mod test {
     fn debug_prql_lineage() {
         assert_snapshot!(
             debug::prql_lineage(r#"from a | select { beta, gamma }"#).unwrap(),
-            @r#"{"frames":[["1:9-31",{"columns":[{"Single":{"name":["a","beta"],"target_id":117,"target_name":null}},{"Single":{"name":["a","gamma"],"target_id":118,"target_name":null}}],"inputs":[{"id":115,"name":"a","table":["default_db","a"]}]}]],"nodes":[{"id":115,"kind":"Ident","span":"1:0-6","ident":{"Ident":["default_db","a"]},"parent":120},{"id":117,"kind":"Ident","span":"1:18-22","ident":{"Ident":["this","a","beta"]},"targets":[115],"parent":119},{"id":118,"kind":"Ident","span":"1:24-29","ident":{"Ident":["this","a","gamma"]},"targets":[115],"parent":119},{"id":119,"kind":"Tuple","span":"1:16-31","children":[117,118],"parent":120},{"id":120,"kind":"TransformCall: Select","span":"1:9-31","children":[115,119]}],"ast":{"name":"Project","stmts":[{"VarDef":{"kind":"Main","name":"main","value":{"Pipeline":{"exprs":[{"FuncCall":{"name":{"Ident":["from"],"span":"1:0-4"},"args":[{"Ident":["a"],"span":"1:5-6"}]},"span":"1:0-6"},{"FuncCall":{"name":{"Ident":["select"],"span":"1:9-15"},"args":[{"Tuple":[{"Ident":["beta"],"span":"1:18-22"},{"Ident":["gamma"],"span":"1:24-29"}],"span":"1:16-31"}]},"span":"1:9-31"}]},"span":"1:0-31"}},"span":"1:0-31"}]}}"#
+            @r#"{"frames":[["1:9-31",{"columns":[{"Single":{"name":["a","beta"],"target_id":137,"target_name":null}},{"Single":{"name":["a","gamma"],"target_id":138,"target_name":null}}],"inputs":[{"id":135,"name":"a","table":["default_db","a"]}]}]],"nodes":[{"id":135,"kind":"Ident","span":"1:0-6","ident":{"Ident":["default_db","a"]},"parent":140},{"id":137,"kind":"Ident","span":"1:18-22","ident":{"Ident":["this","a","beta"]},"targets":[135],"parent":139},{"id":138,"kind":"Ident","span":"1:24-29","ident":{"Ident":["this","a","gamma"]},"targets":[135],"parent":139},{"id":139,"kind":"Tuple","span":"1:16-31","children":[137,138],"parent":140},{"id":140,"kind":"TransformCall: Select","span":"1:9-31","children":[135,139]}],"ast":{"name":"Project","stmts":[{"VarDef":{"kind":"Main","name":"main","value":{"Pipeline":{"exprs":[{"FuncCall":{"name":{"Ident":["from"],"span":"1:0-4"},"args":[{"Ident":["a"],"span":"1:5-6"}]},"span":"1:0-6"},{"FuncCall":{"name":{"Ident":["select"],"span":"1:9-15"},"args":[{"Tuple":[{"Ident":["beta"],"span":"1:18-22"},{"Ident":["gamma"],"span":"1:24-29"}],"span":"1:16-31"}]},"span":"1:9-31"}]},"span":"1:0-31"}},"span":"1:0-31"}]}}"#
         );
     }
 
@@ -240,7 +256,7 @@ mod test {
     fn debug_pl_to_lineage() {
         assert_snapshot!(
             prql_to_pl(r#"from a | select { beta, gamma }"#).and_then(|x| debug::pl_to_lineage(&x)).unwrap(),
-            @r#"{"frames":[["1:9-31",{"columns":[{"Single":{"name":["a","beta"],"target_id":117,"target_name":null}},{"Single":{"name":["a","gamma"],"target_id":118,"target_name":null}}],"inputs":[{"id":115,"name":"a","table":["default_db","a"]}]}]],"nodes":[{"id":115,"kind":"Ident","span":"1:0-6","ident":{"Ident":["default_db","a"]},"parent":120},{"id":117,"kind":"Ident","span":"1:18-22","ident":{"Ident":["this","a","beta"]},"targets":[115],"parent":119},{"id":118,"kind":"Ident","span":"1:24-29","ident":{"Ident":["this","a","gamma"]},"targets":[115],"parent":119},{"id":119,"kind":"Tuple","span":"1:16-31","children":[117,118],"parent":120},{"id":120,"kind":"TransformCall: Select","span":"1:9-31","children":[115,119]}],"ast":{"name":"Project","stmts":[{"VarDef":{"kind":"Main","name":"main","value":{"Pipeline":{"exprs":[{"FuncCall":{"name":{"Ident":["from"],"span":"1:0-4"},"args":[{"Ident":["a"],"span":"1:5-6"}]},"span":"1:0-6"},{"FuncCall":{"name":{"Ident":["select"],"span":"1:9-15"},"args":[{"Tuple":[{"Ident":["beta"],"span":"1:18-22"},{"Ident":["gamma"],"span":"1:24-29"}],"span":"1:16-31"}]},"span":"1:9-31"}]},"span":"1:0-31"}},"span":"1:0-31"}]}}"#
+            @r#"{"frames":[["1:9-31",{"columns":[{"Single":{"name":["a","beta"],"target_id":137,"target_name":null}},{"Single":{"name":["a","gamma"],"target_id":138,"target_name":null}}],"inputs":[{"id":135,"name":"a","table":["default_db","a"]}]}]],"nodes":[{"id":135,"kind":"Ident","span":"1:0-6","ident":{"Ident":["default_db","a"]},"parent":140},{"id":137,"kind":"Ident","span":"1:18-22","ident":{"Ident":["this","a","beta"]},"targets":[135],"parent":139},{"id":138,"kind":"Ident","span":"1:24-29","ident":{"Ident":["this","a","gamma"]},"targets":[135],"parent":139},{"id":139,"kind":"Tuple","span":"1:16-31","children":[137,138],"parent":140},{"id":140,"kind":"TransformCall: Select","span":"1:9-31","children":[135,139]}],"ast":{"name":"Project","stmts":[{"VarDef":{"kind":"Main","name":"main","value":{"Pipeline":{"exprs":[{"FuncCall":{"name":{"Ident":["from"],"span":"1:0-4"},"args":[{"Ident":["a"],"span":"1:5-6"}]},"span":"1:0-6"},{"FuncCall":{"name":{"Ident":["select"],"span":"1:9-15"},"args":[{"Tuple":[{"Ident":["beta"],"span":"1:18-22"},{"Ident":["gamma"],"span":"1:24-29"}],"span":"1:16-31"}]},"span":"1:9-31"}]},"span":"1:0-31"}},"span":"1:0-31"}]}}"#
         );
     }
 }
@@ -30,6 +30,20 @@ pub enum ErrorSource {
     SQL,
 }
 
+/// Known machine readable error codes, so they can be matched on.
+///
+/// Codes are displayed and parsed as their names, eg, "E0001".
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumString, strum::IntoStaticStr,
+)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// The main pipeline could not be found
+    E0001,
+    /// The root module of a project could not be found
+    E0002,
+}
+
 /// Multiple prqlc errors. Used internally, exposed as prqlc::ErrorMessages.
 #[derive(Debug, Clone)]
 pub struct Errors(pub Vec<Error>);
@@ -167,7 +181,7 @@ pub trait WithErrorInfo: Sized {
 
     fn with_span_fallback(self, span: Option<Span>) -> Self;
 
-    fn with_code(self, code: &'static str) -> Self;
+    fn with_code(self, code: impl Into<&'static str>) -> Self;
 
     fn with_source(self, source: ErrorSource) -> Self;
 }
@@ -188,8 +202,8 @@ impl WithErrorInfo for Error {
         self
     }
 
-    fn with_code(mut self, code: &'static str) -> Self {
-        self.code = Some(code);
+    fn with_code(mut self, code: impl Into<&'static str>) -> Self {
+        self.code = Some(code.into());
         self
     }
 
@@ -221,7 +235,7 @@ impl<T, E: WithErrorInfo> WithErrorInfo for Result<T, E> {
         self.map_err(|e| e.with_span_fallback(span))
     }
 
-    fn with_code(self, code: &'static str) -> Self {
+    fn with_code(self, code: impl Into<&'static str>) -> Self {
         self.map_err(|e| e.with_code(code))
     }
 
@@ -7,12 +7,15 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-/// Inclusive-inclusive range.
+/// Inclusive-inclusive range, unless `end_exclusive` is set (as in `a..<b`).
 /// Missing bound means unbounded range.
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct Range<T> {
     pub start: Option<T>,
     pub end: Option<T>,
+
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub end_exclusive: bool,
 }
 
 impl<T> Range<T> {
@@ -20,6 +23,7 @@ impl<T> Range<T> {
         Range {
             start: None,
             end: None,
+            end_exclusive: false,
         }
     }
 
@@ -27,6 +31,7 @@ impl<T> Range<T> {
         Ok(Range {
             start: self.start.map(&f).transpose()?,
             end: self.end.map(f).transpose()?,
+            end_exclusive: self.end_exclusive,
         })
     }
 
@@ -34,10 +39,19 @@ impl<T> Range<T> {
         Range {
             start: self.start.map(&f),
             end: self.end.map(f),
+            end_exclusive: self.end_exclusive,
         }
     }
 }
 
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+/// Format of an interpolation whose value is quoted as an identifier, written
+/// as `{ident:x}`.
+pub const FORMAT_IDENT: &str = "ident";
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum InterpolateItem<T> {
     String(String),
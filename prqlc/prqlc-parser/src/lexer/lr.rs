@@ -44,6 +44,7 @@ pub enum TokenKind {
     Ne,          // !=
     Gte,         // >=
     Lte,         // <=
+    NullSafeEq,  // <=>
     RegexSearch, // ~=
     And,         // &&
     Or,          // ||
@@ -225,6 +226,7 @@ impl std::fmt::Display for TokenKind {
             TokenKind::Ne => f.write_str("!="),
             TokenKind::Gte => f.write_str(">="),
             TokenKind::Lte => f.write_str("<="),
+            TokenKind::NullSafeEq => f.write_str("<=>"),
             TokenKind::RegexSearch => f.write_str("~="),
             TokenKind::And => f.write_str("&&"),
             TokenKind::Or => f.write_str("||"),
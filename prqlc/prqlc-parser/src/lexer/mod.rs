@@ -83,6 +83,7 @@ fn lex_token() -> impl Parser<char, Token, Error = Cheap<char>> {
         just("==").to(TokenKind::Eq),
         just("!=").to(TokenKind::Ne),
         just(">=").to(TokenKind::Gte),
+        just("<=>").to(TokenKind::NullSafeEq),
         just("<=").to(TokenKind::Lte),
         just("~=").to(TokenKind::RegexSearch),
         just("&&").then_ignore(end_expr()).to(TokenKind::And),
@@ -304,6 +305,8 @@ fn literal() -> impl Parser<char, Literal, Error = Cheap<char>> {
             just("weeks"),
             just("months"),
             just("years"),
+            // percentage, such as in `take 10%`
+            just("%"),
         )))
         .then_ignore(end_expr())
         .try_map(|(number, unit), span| {
@@ -72,6 +72,29 @@ fn numbers() {
     assert_eq!(literal().parse("0o777").unwrap(), Literal::Integer(511));
 }
 
+#[test]
+fn percentage() {
+    assert_debug_snapshot!(Tokens(lexer().parse("take 10%").unwrap()), @r#"
+    Tokens(
+        [
+            0..4: Ident("take"),
+            5..8: Literal(ValueAndUnit(ValueAndUnit { n: 10, unit: "%" })),
+        ],
+    )
+    "#);
+
+    // not followed by the end of an expression, so this is a modulo
+    assert_debug_snapshot!(Tokens(lexer().parse("10%3").unwrap()), @r"
+    Tokens(
+        [
+            0..2: Literal(Integer(10)),
+            2..3: Control('%'),
+            3..4: Literal(Integer(3)),
+        ],
+    )
+    ");
+}
+
 #[test]
 fn debug_display() {
     assert_debug_snapshot!(Tokens(lexer().parse("5 + 3").unwrap()), @r"
@@ -50,22 +50,21 @@ pub(crate) fn expr() -> impl Parser<TokenKind, Expr, Error = PError> + Clone {
 
         let param = select! { TokenKind::Param(id) => ExprKind::Param(id) };
 
+        // s-strings and parenthesized pipelines can produce relations, whose
+        // columns can be declared.
+        let relation = declared_columns(
+            interpolation
+                .map_with_span(ExprKind::into_expr)
+                .or(pipeline_expr),
+        );
+
         let term = with_doc_comment(
-            choice((
-                literal,
-                internal,
-                tuple,
-                array,
-                interpolation,
-                ident_kind,
-                case,
-                param,
-            ))
-            .map_with_span(ExprKind::into_expr)
-            // No longer used given the TODO in `pipeline`; can remove if we
-            // don't resolve.
-            // .or(aliased(expr.clone()))
-            .or(pipeline_expr),
+            choice((literal, internal, tuple, array, ident_kind, case, param))
+                .map_with_span(ExprKind::into_expr)
+                // No longer used given the TODO in `pipeline`; can remove if we
+                // don't resolve.
+                // .or(aliased(expr.clone()))
+                .or(relation),
         )
         .boxed();
 
@@ -88,7 +87,19 @@ pub(crate) fn expr() -> impl Parser<TokenKind, Expr, Error = PError> + Clone {
 fn tuple<'a>(
     nested_expr: impl Parser<TokenKind, Expr, Error = PError> + Clone + 'a,
 ) -> impl Parser<TokenKind, ExprKind, Error = PError> + Clone + 'a {
-    sequence(maybe_aliased(nested_expr))
+    // `...` is lexed as a range followed by a dot
+    let spread = select! { TokenKind::Range { .. } => () }
+        .then(ctrl('.'))
+        .ignore_then(nested_expr.clone().map(Box::new))
+        .map(|expr| {
+            ExprKind::Unary(UnaryExpr {
+                op: UnOp::Spread,
+                expr,
+            })
+        })
+        .map_with_span(ExprKind::into_expr);
+
+    sequence(spread.or(maybe_aliased(nested_expr)))
         .delimited_by(ctrl('{'), ctrl('}'))
         .recover_with(nested_delimiters(
             TokenKind::Control('{'),
@@ -142,6 +153,24 @@ fn interpolation() -> impl Parser<TokenKind, ExprKind, Error = PError> + Clone {
     .labelled("interpolated string")
 }
 
+fn declared_columns<'a, E>(relation: E) -> impl Parser<TokenKind, Expr, Error = PError> + Clone + 'a
+where
+    E: Parser<TokenKind, Expr, Error = PError> + Clone + 'a,
+{
+    let columns = ctrl(':').ignore_then(sequence(ident_part()).delimited_by(ctrl('{'), ctrl('}')));
+
+    relation
+        .then(columns.or_not())
+        .map_with_span(|(relation, columns), span| match columns {
+            Some(columns) => ExprKind::DeclaredColumns(DeclaredColumns {
+                relation: Box::new(relation),
+                columns,
+            })
+            .into_expr(span),
+            None => relation,
+        })
+}
+
 fn case<'a>(
     expr: impl Parser<TokenKind, Expr, Error = PError> + Clone + 'a,
 ) -> impl Parser<TokenKind, ExprKind, Error = PError> + Clone + 'a {
@@ -149,12 +178,17 @@ fn case<'a>(
     let mapping = func_call(expr.clone())
         .map(Box::new)
         .then_ignore(just(TokenKind::ArrowFat))
-        .then(func_call(expr).map(Box::new))
+        .then(func_call(expr.clone()).map(Box::new))
         .map(|(condition, value)| SwitchCase { condition, value });
+    let cases = sequence(mapping).delimited_by(ctrl('['), ctrl(']'));
+
+    // The `case x [1 => "a", _ => "other"]` form, matching values of `x`
+    let switch = expr
+        .map(Box::new)
+        .then(cases.clone())
+        .map(|(value, cases)| ExprKind::Switch(Switch { value, cases }));
 
-    keyword("case")
-        .ignore_then(sequence(mapping).delimited_by(ctrl('['), ctrl(']')))
-        .map(ExprKind::Case)
+    keyword("case").ignore_then(cases.map(ExprKind::Case).or(switch))
 }
 
 fn unary<'a, E>(expr: E) -> impl Parser<TokenKind, Expr, Error = PError> + Clone + 'a
@@ -174,7 +208,7 @@ where
     E: Parser<TokenKind, Expr, Error = PError> + Clone + 'a,
 {
     // Ranges have five cases we need to parse:
-    // x..y (bounded)
+    // x..y (bounded, with `x..<y` excluding the end)
     // x..  (only start bound)
     // x    (no-op)
     //  ..y (only end bound)
@@ -182,7 +216,7 @@ where
     #[derive(Clone)]
     enum RangeCase {
         NoOp(Expr),
-        Range(Option<Expr>, Option<Expr>),
+        Range(Option<Expr>, Option<(Expr, bool)>),
     }
     choice((
         // with start bound (first 3 cases)
@@ -190,7 +224,7 @@ where
             .then(choice((
                 // range and end bound
                 just(TokenKind::range(true, true))
-                    .ignore_then(expr.clone())
+                    .ignore_then(end_bound(expr.clone()))
                     .map(|x| Some(Some(x))),
                 // range and no end bound
                 select! { TokenKind::Range { bind_left: true, .. } => Some(None) },
@@ -206,7 +240,7 @@ where
             }),
         // only end bound
         select! { TokenKind::Range { bind_right: true, .. } => () }
-            .ignore_then(expr)
+            .ignore_then(end_bound(expr))
             .map(|range| RangeCase::Range(None, Some(range))),
         // unbounded
         select! { TokenKind::Range { .. } => RangeCase::Range(None, None) },
@@ -214,15 +248,28 @@ where
     .map_with_span(|case, span| match case {
         RangeCase::NoOp(x) => x,
         RangeCase::Range(start, end) => {
+            let end_exclusive = end.as_ref().is_some_and(|(_, exclusive)| *exclusive);
             let kind = ExprKind::Range(Range {
                 start: start.map(Box::new),
-                end: end.map(Box::new),
+                end: end.map(|(end, _)| Box::new(end)),
+                end_exclusive,
             });
             kind.into_expr(span)
         }
     })
 }
 
+/// The end bound of a range, optionally prefixed with `<` to make it exclusive.
+fn end_bound<'a, E>(expr: E) -> impl Parser<TokenKind, (Expr, bool), Error = PError> + Clone + 'a
+where
+    E: Parser<TokenKind, Expr, Error = PError> + Clone + 'a,
+{
+    ctrl('<')
+        .or_not()
+        .then(expr)
+        .map(|(exclusive, end)| (end, exclusive.is_some()))
+}
+
 /// A pipeline of `expr`, separated by pipes. Doesn't require parentheses.
 pub(crate) fn pipeline<'a, E>(expr: E) -> impl Parser<TokenKind, Expr, Error = PError> + Clone + 'a
 where
@@ -528,6 +575,7 @@ fn operator_compare() -> impl Parser<TokenKind, BinOp, Error = PError> + Clone {
         just(TokenKind::Eq).to(BinOp::Eq),
         just(TokenKind::Ne).to(BinOp::Ne),
         just(TokenKind::Lte).to(BinOp::Lte),
+        just(TokenKind::NullSafeEq).to(BinOp::NullSafeEq),
         just(TokenKind::Gte).to(BinOp::Gte),
         just(TokenKind::RegexSearch).to(BinOp::RegexSearch),
         ctrl('<').to(BinOp::Lt),
@@ -1,6 +1,7 @@
 use chumsky::prelude::*;
 use itertools::Itertools;
 
+use crate::generic::FORMAT_IDENT;
 use crate::lexer::lr::{Literal, TokenKind};
 use crate::parser::perror::{ChumError, PError};
 use crate::parser::pr::*;
@@ -28,21 +29,39 @@ pub(crate) fn parse(string: String, span_base: Span) -> Result<Vec<InterpolateIt
 }
 
 fn interpolated_parser() -> impl Parser<char, Vec<InterpolateItem>, Error = ChumError<char>> {
-    let expr = interpolate_ident_part()
-        .separated_by(just('.'))
-        .at_least(1)
-        .map(Ident::from_path)
-        .map(ExprKind::Ident)
-        .map_with_span(ExprKind::into_expr)
-        .map(Box::new)
-        .labelled("interpolated string variable")
+    // `{ident:x}` marks the variable as an identifier, which is carried as its
+    // format. The prefix is parsed as an ident part, so it neither changes what
+    // is expected within braces, nor shadows a variable named `ident`.
+    let ident_binding = interpolate_ident_part()
+        .try_map(|part, span| match part.as_str() {
+            FORMAT_IDENT => Ok(part),
+            _ => Err(ChumError::custom(span, "expected `ident:`")),
+        })
+        .then_ignore(just(':'))
+        .labelled("interpolated string variable");
+
+    let expr = ident_binding
+        .or_not()
+        .then(
+            interpolate_ident_part()
+                .separated_by(just('.'))
+                .at_least(1)
+                .map(Ident::from_path)
+                .map(ExprKind::Ident)
+                .map_with_span(ExprKind::into_expr)
+                .map(Box::new)
+                .labelled("interpolated string variable"),
+        )
         .then(
             just(':')
                 .ignore_then(filter(|c| *c != '}').repeated().collect::<String>())
                 .or_not(),
         )
         .delimited_by(just('{'), just('}'))
-        .map(|(expr, format)| InterpolateItem::Expr { expr, format });
+        .map(|((binding, expr), format)| InterpolateItem::Expr {
+            expr,
+            format: binding.or(format),
+        });
 
     // Convert double braces to single braces, and fail on any single braces.
     let string = just("{{")
@@ -148,4 +167,46 @@ fn parse_interpolate() {
         ),
     ]
     "#);
+
+    assert_debug_snapshot!(
+        parse("{ident:a} {ident}".to_string(), span_base).unwrap(),
+    @r#"
+    [
+        Expr {
+            expr: Expr {
+                kind: Ident(
+                    [
+                        "a",
+                    ],
+                ),
+                span: Some(
+                    0:7-8,
+                ),
+                alias: None,
+                doc_comment: None,
+            },
+            format: Some(
+                "ident",
+            ),
+        },
+        String(
+            " ",
+        ),
+        Expr {
+            expr: Expr {
+                kind: Ident(
+                    [
+                        "ident",
+                    ],
+                ),
+                span: Some(
+                    0:11-16,
+                ),
+                alias: None,
+                doc_comment: None,
+            },
+            format: None,
+        },
+    ]
+    "#);
 }
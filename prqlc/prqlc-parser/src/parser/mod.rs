@@ -2,7 +2,7 @@ use chumsky::{prelude::*, Stream};
 
 use self::perror::PError;
 use self::pr::{Annotation, Stmt, StmtKind};
-use crate::error::Error;
+use crate::error::{Error, WithErrorInfo};
 use crate::lexer::lr;
 use crate::lexer::lr::TokenKind;
 use crate::span::Span;
@@ -19,7 +19,30 @@ mod types;
 // Note that `parse_source` is in `prqlc` crate, not in `prqlc-parser` crate,
 // because it logs using the logging framework in `prqlc`.
 
+/// Maximum nesting of parentheses, brackets and braces. Deeper nesting is an
+/// error, rather than a risk of overflowing the stack while compiling.
+pub const MAX_NESTING_DEPTH: usize = 128;
+
 pub fn parse_lr_to_pr(source_id: u16, lr: Vec<lr::Token>) -> (Option<Vec<pr::Stmt>>, Vec<Error>) {
+    parse_lr_to_pr_with_max_depth(source_id, lr, MAX_NESTING_DEPTH)
+}
+
+/// Same as [parse_lr_to_pr], but with a limit on the nesting of parentheses,
+/// brackets and braces other than [MAX_NESTING_DEPTH].
+pub fn parse_lr_to_pr_with_max_depth(
+    source_id: u16,
+    lr: Vec<lr::Token>,
+    max_depth: usize,
+) -> (Option<Vec<pr::Stmt>>, Vec<Error>) {
+    // checked before parsing, since the parser recurses for each level
+    if let Some(span) = find_too_deep_nesting(&lr, source_id, max_depth) {
+        let error = Error::new_simple(format!(
+            "expression is nested more than {max_depth} levels deep"
+        ))
+        .with_span(Some(span));
+        return (None, vec![error]);
+    }
+
     let stream = prepare_stream(lr, source_id);
     let (pr, parse_errors) = stmt::source().parse_recovery(stream);
 
@@ -29,6 +52,24 @@ pub fn parse_lr_to_pr(source_id: u16, lr: Vec<lr::Token>) -> (Option<Vec<pr::Stm
     (pr, errors)
 }
 
+/// Finds the first opening delimiter which is nested deeper than `max_depth`.
+fn find_too_deep_nesting(tokens: &[lr::Token], source_id: u16, max_depth: usize) -> Option<Span> {
+    let mut depth: usize = 0;
+    for token in tokens {
+        match token.kind {
+            TokenKind::Control('(' | '[' | '{') => {
+                depth += 1;
+                if depth > max_depth {
+                    return Some(Span::new(source_id, token.span.clone()));
+                }
+            }
+            TokenKind::Control(')' | ']' | '}') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
+
 /// Convert the output of the lexer into the input of the parser. Requires
 /// supplying the original source code.
 pub(crate) fn prepare_stream<'a>(
@@ -204,6 +245,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_max_nesting_depth() {
+        let nested = |depth: usize| {
+            let source = format!(
+                "from t | select {{a = {}1{}}}",
+                "(".repeat(depth),
+                ")".repeat(depth)
+            );
+            crate::lexer::lex_source(&source).unwrap().0
+        };
+
+        let (ast, errors) = parse_lr_to_pr(0, nested(5000));
+        assert!(ast.is_none());
+        assert_debug_snapshot!(errors, @r#"
+        [
+            Error {
+                kind: Error,
+                span: Some(
+                    0:148-149,
+                ),
+                reason: Simple(
+                    "expression is nested more than 128 levels deep",
+                ),
+                hints: [],
+                code: None,
+            },
+        ]
+        "#);
+
+        // the braces of the tuple are one level
+        let (ast, errors) = parse_lr_to_pr_with_max_depth(0, nested(4), 5);
+        assert!(ast.is_some() && errors.is_empty());
+        let (_, errors) = parse_lr_to_pr_with_max_depth(0, nested(5), 5);
+        assert_eq!(errors.len(), 1);
+    }
+
     #[test]
     fn test_no_doc_comment_in_with_doc_comment() {
         assert_debug_snapshot!(parse_with_parser(r#"hello"#, with_doc_comment(new_line().ignore_then(ident_part()))).unwrap(), @r#""hello""#);
@@ -74,6 +74,14 @@ pub enum ExprKind {
     FString(Vec<InterpolateItem>),
     Case(Vec<SwitchCase>),
 
+    /// Case over the values of an expression, such as
+    /// `case x [1 => "a", 2 => "b", _ => "other"]`.
+    Switch(Switch),
+
+    /// Relation with declared names of its columns, such as
+    /// `(s"SELECT a, b FROM x"):{a, b}`.
+    DeclaredColumns(DeclaredColumns),
+
     /// placeholder for values provided after query is compiled
     Param(String),
 
@@ -115,6 +123,26 @@ pub struct UnaryExpr {
     pub expr: Box<Expr>,
 }
 
+/// Case over the values of an expression.
+///
+/// Each condition is a value compared to the expression, except `_`, which
+/// matches any value.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Switch {
+    pub value: Box<Expr>,
+    pub cases: Vec<SwitchCase>,
+}
+
+/// Relation with declared names of its columns.
+///
+/// Columns of relations produced by s-strings can't be inferred, so they can
+/// be declared for the resolver to know which columns the relation contains.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeclaredColumns {
+    pub relation: Box<Expr>,
+    pub columns: Vec<String>,
+}
+
 /// Function call.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FuncCall {
@@ -23,6 +23,8 @@ pub enum UnOp {
     Not,
     #[strum(to_string = "==")]
     EqSelf,
+    #[strum(to_string = "...")]
+    Spread,
 }
 
 #[derive(
@@ -65,6 +67,8 @@ pub enum BinOp {
     Gte,
     #[strum(to_string = "<=")]
     Lte,
+    #[strum(to_string = "<=>")]
+    NullSafeEq,
     #[strum(to_string = "~=")]
     RegexSearch,
     #[strum(to_string = "&&")]
@@ -85,6 +85,14 @@ pub struct ImportDef {
     pub name: Ident,
 }
 
+/// Name of the annotation item holding the table that a pipeline ending with
+/// `into schema.table` writes into.
+pub const INTO_ANNOTATION: &str = "into";
+
+/// Name of the annotation marking a declaration as deprecated, either as
+/// `@deprecated` or with a message as `@{deprecated = "use new_fn instead"}`.
+pub const DEPRECATED_ANNOTATION: &str = "deprecated";
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Annotation {
     pub expr: Box<Expr>,
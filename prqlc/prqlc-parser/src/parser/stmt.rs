@@ -10,6 +10,7 @@ use crate::lexer::lr::{Literal, TokenKind};
 use crate::parser::perror::PError;
 use crate::parser::pr::*;
 use crate::parser::types::type_expr;
+use crate::span::Span;
 
 /// The top-level parser for a PRQL file
 pub fn source() -> impl Parser<TokenKind, Vec<Stmt>, Error = PError> {
@@ -53,12 +54,11 @@ fn module_contents() -> impl Parser<TokenKind, Vec<Stmt>, Error = PError> {
         // `module` if we wanted to?)
         //
         // let stmt_kind = new_line().repeated().at_least(1).ignore_then(choice((
-        let stmt_kind = new_line().repeated().ignore_then(choice((
-            module_def,
-            type_def(),
-            import_def(),
-            var_def(),
-        )));
+        let stmt_kind = new_line().repeated().ignore_then(
+            choice((module_def, type_def(), import_def()))
+                .map(|kind| (Vec::new(), kind))
+                .or(var_def()),
+        );
 
         // Currently doc comments need to be before the annotation; probably
         // should relax this?
@@ -66,6 +66,10 @@ fn module_contents() -> impl Parser<TokenKind, Vec<Stmt>, Error = PError> {
             annotation
                 .repeated()
                 .then(stmt_kind)
+                .map(|(mut annotations, (implied, kind))| {
+                    annotations.extend(implied);
+                    (annotations, kind)
+                })
                 .map_with_span(into_stmt),
         )
         .repeated()
@@ -102,7 +106,7 @@ fn query_def() -> impl Parser<TokenKind, Stmt, Error = PError> + Clone {
             // at the moment we implement this as having a HashMap with 0 or 1
             // entries... We can decide how to implement `QueryDef` later, and
             // have this awkward construction in the meantime.
-            let other = args
+            let mut other = args
                 .remove("target")
                 .map(|v| {
                     if let ExprKind::Ident(name) = v.kind {
@@ -117,6 +121,17 @@ fn query_def() -> impl Parser<TokenKind, Stmt, Error = PError> + Clone {
                     HashMap::from_iter(vec![("target".to_string(), x)])
                 });
 
+            // milliseconds after which the database aborts the query
+            if let Some(timeout) = args.remove("statement_timeout") {
+                let ExprKind::Literal(Literal::Integer(timeout)) = timeout.kind else {
+                    return Err(PError::custom(
+                        span,
+                        "statement_timeout must be an integer number of milliseconds",
+                    ));
+                };
+                other.insert("statement_timeout".to_string(), timeout.to_string());
+            }
+
             if !args.is_empty() {
                 return Err(PError::custom(
                     span,
@@ -138,7 +153,11 @@ fn query_def() -> impl Parser<TokenKind, Stmt, Error = PError> + Clone {
 /// - `let foo = 5`
 /// - `from artists` — captured as a "main"
 /// - `from artists | into x` — captured as an "into"`
-fn var_def() -> impl Parser<TokenKind, StmtKind, Error = PError> + Clone {
+/// - `from artists | into db.artists` — captured as a "main", with an
+///   `@{into = db.artists}` annotation naming the table to write into
+///
+/// Returns any annotations implied by the definition, alongside the statement.
+fn var_def() -> impl Parser<TokenKind, (Vec<Annotation>, StmtKind), Error = PError> + Clone {
     let let_ = new_line()
         .repeated()
         .ignore_then(keyword("let"))
@@ -161,26 +180,51 @@ fn var_def() -> impl Parser<TokenKind, StmtKind, Error = PError> + Clone {
         .map(Box::new)
         .then(
             pipe()
-                .ignore_then(keyword("into").ignore_then(ident_part()))
+                .ignore_then(
+                    keyword("into").ignore_then(
+                        ident_part()
+                            .separated_by(ctrl('.'))
+                            .at_least(1)
+                            .map_with_span(|path, span| (Ident::from_path(path), span)),
+                    ),
+                )
                 .or_not(),
         )
-        .map(|(value, name)| {
-            let kind = if name.is_none() {
-                VarDefKind::Main
-            } else {
-                VarDefKind::Into
+        .map(|(value, into)| {
+            let mut annotations = Vec::new();
+            let (kind, name) = match into {
+                None => (VarDefKind::Main, "main".to_string()),
+                Some((target, _)) if target.path.is_empty() => (VarDefKind::Into, target.name),
+                Some((target, span)) => {
+                    annotations.push(into_annotation(target, span));
+                    (VarDefKind::Main, "main".to_string())
+                }
             };
-            let name = name.unwrap_or_else(|| "main".to_string());
 
-            StmtKind::VarDef(VarDef {
+            let var_def = StmtKind::VarDef(VarDef {
                 name,
                 kind,
                 value: Some(value),
                 ty: None,
-            })
+            });
+            (annotations, var_def)
         });
 
-    let_.or(main_or_into)
+    let_.map(|kind| (Vec::new(), kind)).or(main_or_into)
+}
+
+/// Builds the `@{into = target}` annotation of a pipeline which writes into a
+/// table.
+fn into_annotation(target: Ident, span: Span) -> Annotation {
+    let mut target = Expr::new(ExprKind::Ident(target));
+    target.span = Some(span);
+    target.alias = Some(INTO_ANNOTATION.to_string());
+
+    let mut expr = Expr::new(ExprKind::Tuple(vec![target]));
+    expr.span = Some(span);
+    Annotation {
+        expr: Box::new(expr),
+    }
 }
 
 fn type_def() -> impl Parser<TokenKind, StmtKind, Error = PError> + Clone {
@@ -237,7 +281,7 @@ mod tests {
         assert_yaml_snapshot!(parse_with_parser(r#"
             from artists
             into x
-        "#, var_def()).unwrap(), @r#"
+        "#, var_def().map(|(_, kind)| kind)).unwrap(), @r#"
         VarDef:
           kind: Into
           name: x
@@ -256,7 +300,7 @@ mod tests {
 
         assert_yaml_snapshot!(parse_with_parser(r#"
             from artists | into x
-        "#, var_def()).unwrap(), @r#"
+        "#, var_def().map(|(_, kind)| kind)).unwrap(), @r#"
         VarDef:
           kind: Into
           name: x
@@ -274,6 +318,38 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn into_table() {
+        assert_yaml_snapshot!(parse_with_parser(r#"
+            from artists | into db.artists
+        "#, module_contents()).unwrap(), @r#"
+        - VarDef:
+            kind: Main
+            name: main
+            value:
+              FuncCall:
+                name:
+                  Ident:
+                    - from
+                  span: "0:13-17"
+                args:
+                  - Ident:
+                      - artists
+                    span: "0:18-25"
+              span: "0:13-25"
+          span: "0:0-43"
+          annotations:
+            - expr:
+                Tuple:
+                  - Ident:
+                      - db
+                      - artists
+                    span: "0:33-43"
+                    alias: into
+                span: "0:33-43"
+        "#);
+    }
+
     #[test]
     fn let_into() {
         assert_debug_snapshot!(parse_with_parser(r#"
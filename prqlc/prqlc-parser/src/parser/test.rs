@@ -185,6 +185,30 @@ fn test_ranges() {
         span: "0:13-24"
     span: "0:0-24"
     "#);
+
+    assert_yaml_snapshot!(parse_expr(r#"3..<5"#).unwrap(), @r#"
+    Range:
+      start:
+        Literal:
+          Integer: 3
+        span: "0:0-1"
+      end:
+        Literal:
+          Integer: 5
+        span: "0:4-5"
+      end_exclusive: true
+    span: "0:0-5"
+    "#);
+    assert_yaml_snapshot!(parse_expr(r#"..<7"#).unwrap(), @r#"
+    Range:
+      start: ~
+      end:
+        Literal:
+          Integer: 7
+        span: "0:3-4"
+      end_exclusive: true
+    span: "0:0-4"
+    "#);
 }
 
 #[test]
@@ -499,6 +523,31 @@ fn test_s_string_braces() {
     parse_expr(r#"s"foo{{bar}""#).unwrap_err();
 }
 
+#[test]
+fn test_s_string_declared_columns() {
+    assert_yaml_snapshot!(parse_expr(r#"(s"SELECT a, b FROM x"):{a, `B c`}"#).unwrap(), @r#"
+    DeclaredColumns:
+      relation:
+        SString:
+          - String: "SELECT a, b FROM x"
+        span: "0:1-22"
+      columns:
+        - a
+        - B c
+    span: "0:0-34"
+    "#);
+    assert_yaml_snapshot!(parse_expr(r#"s"SELECT a FROM x":{a}"#).unwrap(), @r#"
+    DeclaredColumns:
+      relation:
+        SString:
+          - String: SELECT a FROM x
+        span: "0:0-18"
+      columns:
+        - a
+    span: "0:0-22"
+    "#);
+}
+
 #[test]
 fn test_tuple() {
     assert_yaml_snapshot!(parse_expr(r#"{1 + 1, 2}"#).unwrap(), @r#"
@@ -1293,6 +1342,60 @@ fn test_case() {
     "#);
 }
 
+#[test]
+fn test_case_value() {
+    assert_yaml_snapshot!(parse_expr(r#"case status [1 => "a", _ => "b"]"#).unwrap(), @r#"
+    Switch:
+      value:
+        Ident:
+          - status
+        span: "0:5-11"
+      cases:
+        - condition:
+            Literal:
+              Integer: 1
+            span: "0:13-14"
+          value:
+            Literal:
+              String: a
+            span: "0:18-21"
+        - condition:
+            Ident:
+              - _
+            span: "0:23-24"
+          value:
+            Literal:
+              String: b
+            span: "0:28-31"
+    span: "0:0-32"
+    "#);
+}
+
+#[test]
+fn test_spread() {
+    assert_yaml_snapshot!(parse_expr(r#"{id, ...metrics amount}"#).unwrap(), @r#"
+    Tuple:
+      - Ident:
+          - id
+        span: "0:1-3"
+      - Unary:
+          op: Spread
+          expr:
+            FuncCall:
+              name:
+                Ident:
+                  - metrics
+                span: "0:8-15"
+              args:
+                - Ident:
+                    - amount
+                  span: "0:16-22"
+            span: "0:8-22"
+        span: "0:4-22"
+    span: "0:0-23"
+    "#);
+}
+
 #[test]
 fn test_params() {
     assert_yaml_snapshot!(parse_expr(r#"$2"#).unwrap(), @r#"
@@ -1,6 +1,6 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use glob::glob;
-use prqlc::{compile, pl_to_prql, pl_to_rq, prql_to_pl, Options};
+use prqlc::{compile, pl_to_prql, pl_to_rq, prql_to_pl, Compiler, Options};
 use std::collections::BTreeMap;
 use std::fs;
 
@@ -31,6 +31,19 @@ fn bench_compile(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_compiler(c: &mut Criterion) {
+    let queries = load_queries();
+    let compiler = Compiler::new(Options::default()).unwrap();
+    let mut group = c.benchmark_group("compiler");
+
+    for (name, content) in queries.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), content, |b, content| {
+            b.iter(|| compiler.compile(content));
+        });
+    }
+    group.finish();
+}
+
 fn bench_prql_to_pl(c: &mut Criterion) {
     let queries = load_queries();
     let mut group = c.benchmark_group("prql_to_pl");
@@ -72,6 +85,7 @@ fn bench_pl_to_prql(c: &mut Criterion) {
 criterion_group!(
     benches,
     bench_compile,
+    bench_compiler,
     bench_prql_to_pl,
     bench_pl_to_rq,
     bench_pl_to_prql
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+
+use prqlc::sql::Dialect;
+use prqlc::Target;
+
+/// Kind of database referenced by a URL passed to `prqlc check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbKind {
+    SQLite,
+    DuckDb,
+    Postgres,
+    MySql,
+}
+
+impl DbKind {
+    pub fn from_url(url: &str) -> Result<Self> {
+        let Some((scheme, _)) = url.split_once("://") else {
+            bail!("database URL `{url}` should start with a scheme, such as `sqlite://`")
+        };
+        Ok(match scheme {
+            "sqlite" => DbKind::SQLite,
+            "duckdb" => DbKind::DuckDb,
+            "postgres" | "postgresql" => DbKind::Postgres,
+            "mysql" => DbKind::MySql,
+            _ => bail!(
+                "unsupported database URL scheme `{scheme}`; expected one of `sqlite`, `duckdb`, `postgres` or `mysql`"
+            ),
+        })
+    }
+
+    /// The target to compile for when none is specified.
+    pub fn target(self) -> Target {
+        Target::Sql(Some(match self {
+            DbKind::SQLite => Dialect::SQLite,
+            DbKind::DuckDb => Dialect::DuckDb,
+            DbKind::Postgres => Dialect::Postgres,
+            DbKind::MySql => Dialect::MySql,
+        }))
+    }
+
+    /// Name of the database, as shown to users.
+    fn name(self) -> &'static str {
+        match self {
+            DbKind::SQLite => "SQLite",
+            DbKind::DuckDb => "DuckDB",
+            DbKind::Postgres => "PostgreSQL",
+            DbKind::MySql => "MySQL",
+        }
+    }
+
+    /// The cargo feature which enables the client for this database.
+    fn feature(self) -> &'static str {
+        match self {
+            DbKind::SQLite => "sqlite",
+            DbKind::DuckDb => "duckdb",
+            DbKind::Postgres => "postgres",
+            DbKind::MySql => "mysql",
+        }
+    }
+
+    /// Error for a database which prqlc was built without the client of.
+    fn unsupported(self) -> anyhow::Error {
+        anyhow!(
+            "prqlc was built without support for {} databases; rebuild with the `{}` feature",
+            self.name(),
+            self.feature()
+        )
+    }
+}
+
+/// Wraps the query so that only its row count is returned.
+pub fn wrap_in_count(sql: &str) -> String {
+    format!("SELECT COUNT(*) FROM ({}) _", sql.trim_end())
+}
+
+/// Runs a query returning a single integer against the database at `url`.
+#[allow(unused_variables)]
+pub fn query_count(url: &str, sql: &str) -> Result<i64> {
+    let kind = DbKind::from_url(url)?;
+
+    match kind {
+        #[cfg(feature = "sqlite")]
+        DbKind::SQLite => {
+            let (_, path) = url.split_once("://").unwrap();
+            let conn = rusqlite::Connection::open(path)?;
+            Ok(conn.query_row(sql, [], |row| row.get(0))?)
+        }
+        #[cfg(feature = "duckdb")]
+        DbKind::DuckDb => {
+            let (_, path) = url.split_once("://").unwrap();
+            let conn = if path.is_empty() {
+                duckdb::Connection::open_in_memory()?
+            } else {
+                duckdb::Connection::open(path)?
+            };
+            Ok(conn.query_row(sql, [], |row| row.get(0))?)
+        }
+        #[cfg(feature = "postgres")]
+        DbKind::Postgres => {
+            let mut client = postgres::Client::connect(url, postgres::NoTls)?;
+            Ok(client.query_one(sql, &[])?.get(0))
+        }
+        #[cfg(feature = "mysql")]
+        DbKind::MySql => {
+            use mysql::prelude::Queryable;
+
+            let mut conn = mysql::Conn::new(mysql::Opts::from_url(url)?)?;
+            conn.query_first(sql)?
+                .ok_or_else(|| anyhow!("database returned no rows"))
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(kind.unsupported()),
+    }
+}
+
+/// Runs a query against the DuckDB database at `path`, or an in-memory
+/// database, returning its results formatted as a table with a header of
+/// column names.
+#[allow(unused_variables)]
+pub fn query_table(path: Option<&Path>, sql: &str) -> Result<String> {
+    #[cfg(feature = "duckdb")]
+    {
+        use duckdb::arrow::record_batch::RecordBatch;
+        use duckdb::arrow::util::pretty::pretty_format_batches;
+
+        let conn = match path {
+            Some(path) => duckdb::Connection::open(path)?,
+            None => duckdb::Connection::open_in_memory()?,
+        };
+        let mut statement = conn.prepare(sql)?;
+        let results = statement.query_arrow([])?;
+        let schema = results.get_schema();
+
+        let mut batches: Vec<RecordBatch> = results.collect();
+        // keep the header for queries without any rows
+        if batches.is_empty() {
+            batches.push(RecordBatch::new_empty(schema));
+        }
+        Ok(format!("{}\n", pretty_format_batches(&batches)?))
+    }
+
+    #[cfg(not(feature = "duckdb"))]
+    Err(DbKind::DuckDb.unsupported())
+}
@@ -54,6 +54,7 @@ fn highlight_token_kind(token: &TokenKind) -> String {
         | TokenKind::Ne
         | TokenKind::Gte
         | TokenKind::Lte
+        | TokenKind::NullSafeEq
         | TokenKind::RegexSearch => output.push_str(&format!("{}", token)),
         TokenKind::And | TokenKind::Or => {
             output.push_str(&format!("{}", token).purple().to_string())
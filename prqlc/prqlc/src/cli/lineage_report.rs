@@ -0,0 +1,303 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use prqlc::ir::pl::{ExprKind, LineageColumn, LineageInput};
+use prqlc::semantic::reporting::{ExprGraphNode, FrameCollector};
+
+const ROW_HEIGHT: usize = 28;
+const COLUMN_WIDTH: usize = 220;
+const GRAPH_WIDTH: usize = 760;
+
+const CSS_STYLES: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+svg text { font-family: monospace; font-size: 13px; dominant-baseline: middle; }
+.column rect { fill: #f3f3f3; stroke: #bbb; }
+.column.output rect { fill: #eaf2fb; }
+.column.active rect { fill: #ffe9a8; stroke: #d19a00; }
+.edge { fill: none; stroke: #9ab; stroke-width: 1.5; }
+.edge.active { stroke: #d19a00; stroke-width: 3; }
+.column { cursor: pointer; }
+table { border-collapse: collapse; }
+td, th { border: 1px solid #ddd; padding: 0.2em 0.6em; text-align: left; font-family: monospace; }
+"#;
+
+// Highlights a column and all the edges (and columns at the other end of
+// them) when hovering over it.
+const JS_SCRIPT: &str = r#"
+document.querySelectorAll(".column").forEach(function (column) {
+  var key = column.dataset.key;
+  column.addEventListener("mouseenter", function () {
+    document.querySelectorAll(".edge").forEach(function (edge) {
+      if (edge.dataset.source === key || edge.dataset.output === key) {
+        edge.classList.add("active");
+        [edge.dataset.source, edge.dataset.output].forEach(function (k) {
+          document.querySelector('.column[data-key="' + k + '"]').classList.add("active");
+        });
+      }
+    });
+  });
+  column.addEventListener("mouseleave", function () {
+    document.querySelectorAll(".active").forEach(function (e) {
+      e.classList.remove("active");
+    });
+  });
+});
+"#;
+
+/// Generate a self-contained HTML report of the column lineage of the main
+/// pipeline, showing which source columns each output column is derived from.
+pub fn generate_lineage_html(fc: &FrameCollector) -> String {
+    let graph = LineageGraph::new(fc);
+
+    let mut body = String::new();
+
+    body.push_str("<h2>Columns</h2>\n");
+    if graph.outputs.is_empty() {
+        body.push_str("<p>None.</p>\n");
+    } else {
+        body.push_str(&graph.to_svg());
+    }
+
+    body.push_str("<h2>Frames</h2>\n");
+    body.push_str("<table>\n");
+    body.push_str("  <tr><th>span</th><th>columns</th><th>inputs</th></tr>\n");
+    for (span, lineage) in &fc.frames {
+        let span = span.map(|s| format!("{s:?}")).unwrap_or_default();
+        let columns = lineage
+            .columns
+            .iter()
+            .map(|c| escape_html(&display_column(c, &graph.inputs)))
+            .join(", ");
+        let inputs = lineage
+            .inputs
+            .iter()
+            .map(|i| escape_html(&format!("{} = {}", i.name, i.table)))
+            .join(", ");
+        body.push_str(&format!(
+            "  <tr><td>{span}</td><td>{columns}</td><td>{inputs}</td></tr>\n"
+        ));
+    }
+    body.push_str("</table>\n");
+
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <meta name="generator" content="prqlc {}">
+    <title>PRQL Lineage</title>
+    <style>{CSS_STYLES}</style>
+  </head>
+  <body>
+    <h1>Lineage</h1>
+{body}    <script>{JS_SCRIPT}</script>
+  </body>
+</html>
+"#,
+        prqlc::compiler_version(),
+    )
+}
+
+/// Source columns on one side, output columns on the other, and edges
+/// connecting each output column to the source columns it is derived from.
+struct LineageGraph {
+    inputs: HashMap<usize, LineageInput>,
+    sources: Vec<String>,
+    outputs: Vec<String>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl LineageGraph {
+    fn new(fc: &FrameCollector) -> Self {
+        // inputs of all frames, since joined relations may not be visible in
+        // the last one
+        let inputs: HashMap<_, _> = fc
+            .frames
+            .iter()
+            .flat_map(|(_, lineage)| &lineage.inputs)
+            .map(|input| (input.id, input.clone()))
+            .collect();
+        let nodes: HashMap<_, _> = fc.nodes.iter().map(|n| (n.id, n)).collect();
+
+        let mut graph = LineageGraph {
+            inputs,
+            sources: Vec::new(),
+            outputs: Vec::new(),
+            edges: Vec::new(),
+        };
+
+        let Some((_, lineage)) = fc.frames.last() else {
+            return graph;
+        };
+        for column in &lineage.columns {
+            let output = graph.outputs.len();
+            graph.outputs.push(display_column(column, &graph.inputs));
+
+            let sources = match column {
+                LineageColumn::Single {
+                    target_id,
+                    target_name,
+                    name,
+                } => {
+                    if let Some(input) = graph.inputs.get(target_id) {
+                        let column_name = target_name
+                            .clone()
+                            .or_else(|| name.as_ref().map(|n| n.name.clone()))
+                            .unwrap_or_else(|| "*".to_string());
+                        vec![format!("{}.{column_name}", input.table)]
+                    } else {
+                        let mut sources = Vec::new();
+                        let mut visited = HashSet::new();
+                        graph.trace(*target_id, &nodes, &mut visited, &mut sources);
+                        sources
+                    }
+                }
+                LineageColumn::All { input_id, .. } => graph
+                    .inputs
+                    .get(input_id)
+                    .map(|input| format!("{}.*", input.table))
+                    .into_iter()
+                    .collect(),
+            };
+
+            for source in sources.into_iter().unique() {
+                let position = match graph.sources.iter().position(|s| *s == source) {
+                    Some(position) => position,
+                    None => {
+                        graph.sources.push(source);
+                        graph.sources.len() - 1
+                    }
+                };
+                graph.edges.push((position, output));
+            }
+        }
+        graph
+    }
+
+    /// Follows the targets of an expression node until reaching idents that
+    /// reference one of the inputs.
+    fn trace(
+        &self,
+        id: usize,
+        nodes: &HashMap<usize, &ExprGraphNode>,
+        visited: &mut HashSet<usize>,
+        sources: &mut Vec<String>,
+    ) {
+        if !visited.insert(id) {
+            return;
+        }
+        let Some(node) = nodes.get(&id) else {
+            return;
+        };
+        for target in &node.targets {
+            if let Some(input) = self.inputs.get(target) {
+                let column_name = match &node.ident {
+                    Some(ExprKind::Ident(ident)) => ident.name.clone(),
+                    _ => "*".to_string(),
+                };
+                sources.push(format!("{}.{column_name}", input.table));
+            } else {
+                self.trace(*target, nodes, visited, sources);
+            }
+        }
+    }
+
+    fn to_svg(&self) -> String {
+        let rows = self.sources.len().max(self.outputs.len());
+        let height = rows * ROW_HEIGHT + 4;
+        let output_x = GRAPH_WIDTH - COLUMN_WIDTH;
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{GRAPH_WIDTH}" height="{height}">"#
+        );
+        svg.push('\n');
+
+        for (source, output) in &self.edges {
+            let y1 = row_center(*source);
+            let y2 = row_center(*output);
+            let mid = GRAPH_WIDTH / 2;
+            svg.push_str(&format!(
+                r#"  <path class="edge" data-source="source-{source}" data-output="output-{output}" d="M {COLUMN_WIDTH} {y1} C {mid} {y1}, {mid} {y2}, {output_x} {y2}"/>"#
+            ));
+            svg.push('\n');
+        }
+
+        for (i, source) in self.sources.iter().enumerate() {
+            svg.push_str(&column_box("source", i, 0, source));
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            svg.push_str(&column_box("output", i, output_x, output));
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn row_center(row: usize) -> usize {
+    row * ROW_HEIGHT + ROW_HEIGHT / 2 + 2
+}
+
+fn column_box(kind: &str, row: usize, x: usize, label: &str) -> String {
+    let y = row * ROW_HEIGHT + 4;
+    let text_y = row_center(row);
+    let label = escape_html(label);
+    format!(
+        r#"  <g class="column {kind}" data-key="{kind}-{row}"><rect x="{x}" y="{y}" width="{COLUMN_WIDTH}" height="{}" rx="4"/><text x="{}" y="{text_y}">{label}</text></g>
+"#,
+        ROW_HEIGHT - 6,
+        x + 8,
+    )
+}
+
+fn display_column(column: &LineageColumn, inputs: &HashMap<usize, LineageInput>) -> String {
+    match column {
+        LineageColumn::Single {
+            name: Some(name), ..
+        } => name.to_string(),
+        LineageColumn::Single { name: None, .. } => "?".to_string(),
+        LineageColumn::All { input_id, .. } => match inputs.get(input_id) {
+            Some(input) => format!("{}.*", input.name),
+            None => "*".to_string(),
+        },
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot;
+
+    use super::*;
+
+    #[test]
+    fn test_lineage_graph() {
+        let prql = r#"
+        from e = employees
+        join salaries (==emp_no)
+        select {e.name, total = salaries.salary + salaries.bonus}
+        "#;
+        let pl = prqlc::prql_to_pl(prql).unwrap();
+        let fc = prqlc::internal::pl_to_lineage(pl).unwrap();
+
+        let graph = LineageGraph::new(&fc);
+        let edges = graph
+            .edges
+            .iter()
+            .map(|(s, o)| format!("{} -> {}", graph.sources[*s], graph.outputs[*o]))
+            .join("\n");
+
+        assert_snapshot!(edges, @r"
+        default_db.employees.name -> e.name
+        default_db.salaries.salary -> total
+        default_db.salaries.bonus -> total
+        ");
+    }
+}
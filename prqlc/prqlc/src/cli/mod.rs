@@ -5,7 +5,7 @@ use std::env;
 use std::fs::File;
 use std::io::{self, BufWriter, Read, Write};
 use std::ops::Range;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::process::exit;
 use std::str::FromStr;
 
@@ -28,13 +28,22 @@ use prqlc::ir::{pl, rq};
 use prqlc::pr;
 use prqlc::semantic;
 use prqlc::semantic::reporting::FrameCollector;
-use prqlc::utils::maybe_strip_colors;
-use prqlc::{pl_to_prql, pl_to_rq_tree, prql_to_pl, prql_to_pl_tree, prql_to_tokens, rq_to_sql};
+use prqlc::sql;
+use prqlc::utils::{fnv1a_64, maybe_strip_colors};
+use prqlc::{pl_to_prql, prql_to_pl, prql_to_pl_tree, prql_to_tokens, rq_to_sql};
+use prqlc::{ErrorMessage, ErrorMessages, ErrorSource, Errors, WithErrorInfo};
 use prqlc::{Options, SourceTree, Target};
 
+use crate::cli::schema::Schema;
+use crate::cli::stats::CompileStats;
+
+mod check;
 mod docs_generator;
 mod highlight;
 mod jinja;
+mod lineage_report;
+mod schema;
+mod stats;
 #[cfg(test)]
 mod test;
 mod watch;
@@ -120,7 +129,15 @@ enum Command {
     #[command(name = "fmt")]
     Format {
         #[arg(value_parser, default_value = "-", value_hint(ValueHint::AnyPath))]
-        input: clio::ClioPath,
+        input: Vec<clio::ClioPath>,
+
+        /// Exit with an error listing any inputs which need formatting, without modifying them
+        #[arg(long)]
+        check: bool,
+
+        /// Print a fingerprint of each formatted input, without modifying them
+        #[arg(long, conflicts_with = "check")]
+        fingerprint: bool,
     },
 
     /// Parse the whole project and collect it into a single PRQL source file
@@ -153,9 +170,110 @@ enum Command {
         #[arg(short, long, default_value = "sql.any", env = "PRQLC_TARGET")]
         target: String,
 
+        /// JSON or YAML file listing the columns of tables, to check referenced columns exist
+        #[arg(long, value_hint(ValueHint::FilePath))]
+        schema: Option<PathBuf>,
+
         /// File path into which to write the debug log to.
         #[arg(long, env = "PRQLC_DEBUG_LOG")]
         debug_log: Option<PathBuf>,
+
+        /// Exit with an error if compilation produces any warnings
+        #[arg(long)]
+        deny_warnings: bool,
+
+        /// Print the target compiled to, which may be set by the `target` of the query header, to stderr
+        #[arg(long)]
+        print_target: bool,
+
+        /// File path into which to write the duration of each compilation stage and the peak memory use, as JSON
+        #[arg(long, value_hint(ValueHint::FilePath))]
+        stats_json: Option<PathBuf>,
+
+        /// Name of the definition to compile instead of `main`, such as `monthly_report`
+        #[arg(long, conflicts_with = "main_path", value_hint(ValueHint::Unknown))]
+        main: Option<String>,
+
+        /// Compile each input into its own SQL file within this directory, reading all positional arguments as inputs
+        #[arg(long, value_hint(ValueHint::DirPath))]
+        out_dir: Option<PathBuf>,
+
+        /// Further inputs to compile into `--out-dir`
+        #[arg(requires = "out_dir", value_hint(ValueHint::FilePath))]
+        inputs: Vec<PathBuf>,
+
+        /// Directory of the project, whose `.prql` files are modules that the input can import.
+        /// The input takes the place of the project's `main.prql`.
+        #[arg(
+            long,
+            value_parser,
+            conflicts_with = "out_dir",
+            value_hint(ValueHint::DirPath)
+        )]
+        project_dir: Option<clio::ClioPath>,
+
+        /// Annotate each CTE with a comment quoting the PRQL which produced it
+        #[arg(long)]
+        explain: bool,
+    },
+
+    /// Compile RQ, serialized as JSON, to SQL
+    ///
+    /// Saved RQ can be compiled to SQL of many targets without parsing and
+    /// resolving the PRQL again. A target of `sql.any` compiles to the target
+    /// of the query header, if there is one.
+    #[command(name = "convert")]
+    Convert {
+        #[command(flatten)]
+        io_args: IoArgs,
+
+        /// Exclude the signature comment containing the PRQL version
+        #[arg(long = "hide-signature-comment", action = clap::ArgAction::SetFalse)]
+        signature_comment: bool,
+
+        /// Emit unformatted, dense SQL
+        #[arg(long = "no-format", action = clap::ArgAction::SetFalse)]
+        format: bool,
+
+        /// Target to compile to
+        #[arg(short, long, default_value = "sql.any", env = "PRQLC_TARGET")]
+        target: String,
+    },
+
+    /// Compile a query and run it against a database, reporting its row count
+    ///
+    /// The compiled query is wrapped as `SELECT COUNT(*) FROM (<query>) _`, so
+    /// it's fully executed by the database while only returning a single row.
+    ///
+    /// Supported URLs are `sqlite://<path>`, `duckdb://<path>`,
+    /// `postgres://...` and `mysql://...`, each of which requires prqlc to be
+    /// built with the feature of that database: `sqlite`, `duckdb`, `postgres`
+    /// or `mysql`.
+    #[command(name = "check")]
+    Check {
+        #[command(flatten)]
+        io_args: IoArgs,
+
+        /// URL of the database to run the query against
+        #[arg(long, env = "PRQLC_DB_URL")]
+        db_url: String,
+
+        /// Target to compile to; inferred from the database URL if not specified
+        #[arg(short, long, env = "PRQLC_TARGET")]
+        target: Option<String>,
+    },
+
+    /// Compile a query to DuckDB SQL and run it in-process, printing the results
+    ///
+    /// Requires prqlc to be built with the `duckdb` feature.
+    #[command(name = "run")]
+    Run {
+        #[command(flatten)]
+        io_args: IoArgs,
+
+        /// DuckDB database file to run the query against; an in-memory database if not specified
+        #[arg(long, value_hint(ValueHint::FilePath))]
+        database: Option<PathBuf>,
     },
 
     /// Watch a directory and compile .prql files to .sql files
@@ -214,13 +332,24 @@ enum DebugCommand {
     ///
     /// A Python script for rendering this output as a GraphViz visualization is
     /// available at https://gist.github.com/kgutwin/efe5f03df5ff930d899249018a0a551b.
+    /// Alternatively, `--html` writes a self-contained HTML report of the
+    /// lineage graph.
     Lineage {
         #[command(flatten)]
         io_args: IoArgs,
         #[arg(value_enum, long, default_value = "yaml")]
         format: Format,
+
+        /// Also write an interactive HTML report of the lineage to a file.
+        #[arg(long, value_hint(ValueHint::FilePath))]
+        html: Option<PathBuf>,
     },
 
+    /// Print the tokens of the lexer, one per line with their spans
+    ///
+    /// Lexing continues past errors, which are printed to stderr.
+    Tokens(IoArgs),
+
     /// Print info about the AST data structure
     Ast,
 
@@ -253,8 +382,10 @@ pub struct IoArgs {
     #[arg(value_parser, default_value = "-", value_hint(ValueHint::AnyPath))]
     input: clio::ClioPath,
 
+    // Only opened once writing, so it isn't truncated when compilation fails,
+    // nor when it's an input of `compile --out-dir`.
     #[arg(value_parser, default_value = "-", value_hint(ValueHint::FilePath))]
-    output: Output,
+    output: clio::ClioPath,
 
     /// Identifier of the main pipeline.
     #[arg(value_parser, value_hint(ValueHint::Unknown))]
@@ -288,30 +419,85 @@ impl Command {
             Command::ListTargets => self.list_targets(),
             // Format is handled differently to the other IO commands, since it
             // always writes to the same output.
-            Command::Format { input } => {
-                let sources = read_files(input)?;
-                let root = sources.root;
-
-                for (path, source) in sources.sources {
-                    let ast = prql_to_pl(&source)?;
-
-                    // If we're writing to stdout (though could this be nicer?
-                    // We're discarding many of the benefits of Clio here...)
-                    if path.as_os_str() == "" {
-                        let mut output: Output = Output::new(input.path())?;
-                        output.write_all(&pl_to_prql(&ast)?.into_bytes())?;
-                        break;
-                    }
+            Command::Format {
+                input,
+                check,
+                fingerprint,
+            } => {
+                let mut unformatted = Vec::new();
+                // fingerprints are written to stdout, like formatted stdin
+                let mut fingerprints = if *fingerprint {
+                    Some(Output::new("-")?)
+                } else {
+                    None
+                };
 
-                    let path_buf = root
-                        .as_ref()
-                        .map_or_else(|| path.clone(), |root| root.join(&path));
-                    let path_str = path_buf.to_str().ok_or_else(|| {
-                        anyhow!("Path `{}` is not valid UTF-8", path_buf.display())
-                    })?;
-                    let mut output: Output = Output::new(path_str)?;
+                for input in input.iter_mut() {
+                    let sources = read_files(input, "prql")?;
+                    let root = sources.root;
+
+                    for (path, source) in sources.sources {
+                        let pl = prql_to_pl(&source)?;
+                        let formatted = pl_to_prql(&pl)?;
+
+                        // A single file (or stdin) has an empty path relative
+                        // to its root.
+                        let input_path = if path.as_os_str() == "" {
+                            input.path().to_path_buf()
+                        } else {
+                            root.as_ref()
+                                .map_or_else(|| path.clone(), |root| root.join(&path))
+                        };
+
+                        if let Some(fingerprints) = &mut fingerprints {
+                            writeln!(
+                                fingerprints,
+                                "{:016x}  {}",
+                                fingerprint_of(&pl)?,
+                                input_path.display()
+                            )?;
+                            continue;
+                        }
+
+                        if *check {
+                            if formatted != source {
+                                unformatted.push(input_path);
+                            }
+                            continue;
+                        }
+
+                        // If we're writing to stdout (though could this be nicer?
+                        // We're discarding many of the benefits of Clio here...)
+                        if path.as_os_str() == "" {
+                            let mut output: Output = Output::new(input.path())?;
+                            output.write_all(&formatted.into_bytes())?;
+                            break;
+                        }
+
+                        let path_buf = root
+                            .as_ref()
+                            .map_or_else(|| path.clone(), |root| root.join(&path));
+                        let path_str = path_buf.to_str().ok_or_else(|| {
+                            anyhow!("Path `{}` is not valid UTF-8", path_buf.display())
+                        })?;
+                        let mut output: Output = Output::new(path_str)?;
+
+                        output.write_all(&formatted.into_bytes())?;
+                    }
+                }
 
-                    output.write_all(&pl_to_prql(&ast)?.into_bytes())?;
+                if !unformatted.is_empty() {
+                    bail!(
+                        "The following files aren't formatted:\n{}",
+                        unformatted
+                            .iter()
+                            .sorted()
+                            .map(|path| match path.to_str() {
+                                Some("-") => "  <stdin>".to_string(),
+                                _ => format!("  {}", path.display()),
+                            })
+                            .join("\n")
+                    );
                 }
                 Ok(())
             }
@@ -332,6 +518,9 @@ impl Command {
                 io::stdout().write_all(&serde_json::to_string_pretty(&schema)?.into_bytes())?;
                 Ok(())
             }
+            Command::Compile {
+                out_dir: Some(_), ..
+            } => self.compile_to_dir(),
             _ => self.run_io_command(),
         }
     }
@@ -345,7 +534,72 @@ impl Command {
         let (mut file_tree, main_path) = self.read_input()?;
 
         self.execute(&mut file_tree, &main_path)
-            .and_then(|buf| Ok(self.write_output(&buf)?))
+            .and_then(|buf| self.write_output(&buf))
+    }
+
+    /// Compiles each input of `compile --out-dir` into its own SQL file,
+    /// continuing past inputs which fail to compile.
+    fn compile_to_dir(&self) -> Result<()> {
+        let Command::Compile {
+            io_args,
+            main,
+            out_dir: Some(out_dir),
+            inputs,
+            ..
+        } = self
+        else {
+            unreachable!()
+        };
+
+        // There's no output file or main path when writing into a directory,
+        // so all positional arguments are inputs.
+        let paths = [
+            io_args.input.path().to_path_buf(),
+            io_args.output.path().to_path_buf(),
+        ]
+        .into_iter()
+        .filter(|path| path != Path::new("-"))
+        .chain(io_args.main_path.iter().map(PathBuf::from))
+        .chain(inputs.iter().cloned())
+        .collect_vec();
+        if paths.is_empty() {
+            bail!("`--out-dir` requires the paths of the input files");
+        }
+
+        let mut failed = Vec::new();
+        for path in &paths {
+            let res = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("cannot read `{}`: {e}", path.display()))
+                .and_then(|source| {
+                    let mut sources = SourceTree::single(path.clone(), source);
+                    self.execute(&mut sources, main.as_deref().unwrap_or_default())
+                })
+                .and_then(|sql| {
+                    let sql_path = sql_path_in(out_dir, path);
+                    if let Some(parent) = sql_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    Ok(std::fs::write(sql_path, sql)?)
+                });
+
+            if let Err(error) = res {
+                eprintln!("{error}");
+                failed.push(path);
+            }
+        }
+
+        if !failed.is_empty() {
+            bail!(
+                "Failed to compile {} of {} files:\n{}",
+                failed.len(),
+                paths.len(),
+                failed
+                    .iter()
+                    .map(|path| format!("  {}", path.display()))
+                    .join("\n")
+            );
+        }
+        Ok(())
     }
 
     fn execute<'a>(&self, sources: &'a mut SourceTree, main_path: &'a str) -> Result<Vec<u8>> {
@@ -374,13 +628,7 @@ impl Command {
                     Format::Yaml => serde_yaml::to_string(&tokens)?.into_bytes(),
                 }
             }
-            Command::Collect(_) => {
-                let mut root_module_def = prql_to_pl_tree(sources)?;
-
-                drop_module_def(&mut root_module_def.stmts, "std");
-
-                pl_to_prql(&root_module_def)?.into_bytes()
-            }
+            Command::Collect(_) => collect(sources)?.into_bytes(),
             Command::Debug(DebugCommand::Annotate(_)) => {
                 let (_, source) = sources.sources.clone().into_iter().exactly_one().or_else(
                     |_| bail!(
@@ -411,10 +659,30 @@ impl Command {
                 // combine with source
                 combine_prql_and_frames(&source, frames).as_bytes().to_vec()
             }
-            Command::Debug(DebugCommand::Lineage { format, .. }) => {
+            Command::Debug(DebugCommand::Tokens(_)) => {
+                let s = sources.sources.values().exactly_one().or_else(|_| {
+                    bail!("Currently `debug tokens` only works with a single source, but found multiple sources")
+                })?;
+                let (tokens, errors) = prqlc::internal::prql_to_tokens_recovery(s);
+                for error in &errors.inner {
+                    eprintln!("{error}");
+                }
+
+                tokens
+                    .0
+                    .iter()
+                    .map(|token| format!("{token:?}\n"))
+                    .join("")
+                    .into_bytes()
+            }
+            Command::Debug(DebugCommand::Lineage { format, html, .. }) => {
                 let stmts = prql_to_pl_tree(sources)?;
                 let fc = pl_to_lineage(stmts)?;
 
+                if let Some(path) = html {
+                    std::fs::write(path, lineage_report::generate_lineage_html(&fc))?;
+                }
+
                 match format {
                     Format::Json => serde_json::to_string_pretty(&fc)?.into_bytes(),
                     Format::Yaml => serde_yaml::to_string(&fc)?.into_bytes(),
@@ -445,9 +713,22 @@ impl Command {
                 signature_comment,
                 format,
                 target,
+                schema,
                 debug_log,
+                deny_warnings,
+                print_target,
+                stats_json,
+                explain,
                 ..
             } => {
+                let schema = (schema.as_ref())
+                    .map(|path| -> Result<Schema> {
+                        let source = std::fs::read_to_string(path)
+                            .map_err(|e| anyhow!("cannot read schema `{}`: {e}", path.display()))?;
+                        source.parse()
+                    })
+                    .transpose()?;
+
                 if debug_log.is_some() {
                     debug::log_start();
                 }
@@ -457,18 +738,90 @@ impl Command {
                     .with_signature_comment(*signature_comment)
                     .with_format(*format);
 
-                let res = prql_to_pl_tree(sources)
-                    .and_then(|pl| {
-                        pl_to_rq_tree(pl, &main_path, &[semantic::NS_DEFAULT_DB.to_string()])
-                    })
-                    .and_then(|rq| rq_to_sql(rq, &opts))
-                    .map_err(|e| e.composed(sources));
+                let mut stats = CompileStats::default();
+                let res = match opts.target {
+                    // PRQL is only formatted, so there's nothing to warn about
+                    Target::Prql => collect(sources).map(|prql| (prql, Vec::new(), None)),
+                    Target::Sql(_) => compile_to_sql_with_warnings(
+                        sources, &main_path, &opts, schema, *explain, &mut stats,
+                    )
+                    .map(|(sql, warnings, dialect)| (sql, warnings, Some(dialect))),
+                };
 
                 if let Some(path) = debug_log {
                     write_log(path)?;
                 }
 
-                res?.as_bytes().to_vec()
+                let (sql, warnings, dialect) = res?;
+                if *print_target {
+                    match dialect {
+                        Some(dialect) => eprintln!("target: sql.{dialect}"),
+                        None => eprintln!("target: prql"),
+                    }
+                }
+                if let Some(path) = stats_json {
+                    stats.peak_rss_bytes = stats::peak_rss_bytes();
+                    let file = File::create(path)
+                        .map_err(|e| anyhow!("cannot write stats `{}`: {e}", path.display()))?;
+                    serde_json::to_writer_pretty(file, &stats)?;
+                }
+                if !warnings.is_empty() {
+                    for warning in &warnings {
+                        eprintln!("{warning}");
+                    }
+                    let summary = warnings_summary(warnings.len());
+                    if *deny_warnings {
+                        bail!("{summary}, which are denied by `--deny-warnings`");
+                    }
+                    eprintln!("{summary}");
+                }
+
+                sql.as_bytes().to_vec()
+            }
+            Command::Run { database, .. } => {
+                let opts = Options::default()
+                    .with_target(Target::Sql(Some(sql::Dialect::DuckDb)))
+                    .with_signature_comment(false);
+
+                let sql = compile_to_sql(sources, &main_path, &opts)?;
+                check::query_table(database.as_deref(), &sql)?.into_bytes()
+            }
+            Command::Convert {
+                signature_comment,
+                format,
+                target,
+                ..
+            } => {
+                let json = sources
+                    .sources
+                    .values()
+                    .exactly_one()
+                    .or_else(|_| bail!("`convert` requires a single `.json` source of RQ"))?;
+                let rq = prqlc::json::to_rq(json)?;
+
+                let opts = Options::default()
+                    .with_target(Target::from_str(target).map_err(prqlc::ErrorMessages::from)?)
+                    .with_signature_comment(*signature_comment)
+                    .with_format(*format);
+
+                rq_to_sql(rq, &opts)?.into_bytes()
+            }
+            Command::Check { db_url, target, .. } => {
+                let target = match target {
+                    Some(target) => Target::from_str(target).map_err(prqlc::ErrorMessages::from)?,
+                    None => check::DbKind::from_url(db_url)?.target(),
+                };
+                // The signature comment would swallow the closing parenthesis
+                // of the wrapping query.
+                let opts = Options::default()
+                    .with_target(target)
+                    .with_signature_comment(false)
+                    .with_format(false);
+
+                let sql = compile_to_sql(sources, &main_path, &opts)?;
+                let count = check::query_count(db_url, &check::wrap_in_count(&sql))?;
+
+                format!("Query ran successfully, returning {count} rows\n").into_bytes()
             }
             _ => unreachable!("Other commands shouldn't reach `execute`"),
         })
@@ -480,14 +833,28 @@ impl Command {
         // `self`? But possibly if everything moves to `io_args`, then this is
         // quite reasonable?
         use Command::*;
+        // Saved RQ is read instead of PRQL sources.
+        let extension = match self {
+            Convert { .. } => "json",
+            _ => "prql",
+        };
+        let project_dir = match self {
+            Compile { project_dir, .. } => project_dir.clone(),
+            _ => None,
+        };
         let io_args = match self {
             Parse { io_args, .. }
             | Lex { io_args, .. }
             | Collect(io_args)
             | Compile { io_args, .. }
-            | Debug(DebugCommand::Annotate(io_args) | DebugCommand::Lineage { io_args, .. }) => {
-                io_args
-            }
+            | Convert { io_args, .. }
+            | Check { io_args, .. }
+            | Run { io_args, .. }
+            | Debug(
+                DebugCommand::Annotate(io_args)
+                | DebugCommand::Lineage { io_args, .. }
+                | DebugCommand::Tokens(io_args),
+            ) => io_args,
             Experimental(ExperimentalCommand::GenerateDocs { io_args, .. }) => io_args,
             Experimental(ExperimentalCommand::Highlight(io_args)) => io_args,
             _ => unreachable!(),
@@ -507,30 +874,141 @@ impl Command {
             eprintln!("Enter PRQL, then press ctrl-z to compile:\n");
         }
 
-        let sources = read_files(input)?;
+        let sources = match &project_dir {
+            Some(project_dir) => read_project(input, project_dir)?,
+            None => read_files(input, extension)?,
+        };
 
         let main_path = io_args.main_path.clone().unwrap_or_default();
+        let main_path = match self {
+            Compile {
+                main: Some(main), ..
+            } => main.clone(),
+            _ => main_path,
+        };
 
         Ok((sources, main_path))
     }
 
-    fn write_output(&mut self, data: &[u8]) -> std::io::Result<()> {
-        use Command::{Collect, Compile, Debug, Experimental, Lex, Parse};
-        let mut output = match self {
+    fn write_output(&mut self, data: &[u8]) -> Result<()> {
+        use Command::{Check, Collect, Compile, Convert, Debug, Experimental, Lex, Parse, Run};
+        let path = match self {
             Parse { io_args, .. }
             | Lex { io_args, .. }
             | Collect(io_args)
             | Compile { io_args, .. }
-            | Debug(DebugCommand::Annotate(io_args) | DebugCommand::Lineage { io_args, .. }) => {
-                io_args.output.clone()
-            }
-            Experimental(ExperimentalCommand::GenerateDocs { io_args, .. }) => {
-                io_args.output.clone()
-            }
-            Experimental(ExperimentalCommand::Highlight(io_args)) => io_args.output.clone(),
+            | Convert { io_args, .. }
+            | Check { io_args, .. }
+            | Run { io_args, .. }
+            | Debug(
+                DebugCommand::Annotate(io_args)
+                | DebugCommand::Lineage { io_args, .. }
+                | DebugCommand::Tokens(io_args),
+            ) => &io_args.output,
+            Experimental(ExperimentalCommand::GenerateDocs { io_args, .. }) => &io_args.output,
+            Experimental(ExperimentalCommand::Highlight(io_args)) => &io_args.output,
             _ => unreachable!(),
         };
-        output.write_all(data)
+        let mut output = Output::new(path.path())?;
+        Ok(output.write_all(data)?)
+    }
+}
+
+/// Collects all sources into a single formatted PRQL source.
+fn collect(sources: &SourceTree) -> Result<String> {
+    let mut root_module_def = prql_to_pl_tree(sources)?;
+
+    drop_module_def(&mut root_module_def.stmts, "std");
+
+    Ok(pl_to_prql(&root_module_def)?)
+}
+
+fn compile_to_sql(sources: &SourceTree, main_path: &[String], opts: &Options) -> Result<String> {
+    compile_to_sql_with_warnings(
+        sources,
+        main_path,
+        opts,
+        None,
+        false,
+        &mut CompileStats::default(),
+    )
+    .map(|(sql, ..)| sql)
+}
+
+/// Same as [compile_to_sql], but also returns warnings produced during
+/// compilation and the dialect compiled to.
+///
+/// When a schema is passed, its tables are declared in the database module
+/// before resolving. When `explain` is set, each CTE is annotated with the
+/// PRQL which produced it.
+fn compile_to_sql_with_warnings(
+    sources: &SourceTree,
+    main_path: &[String],
+    opts: &Options,
+    schema: Option<Schema>,
+    explain: bool,
+    stats: &mut CompileStats,
+) -> Result<(String, Vec<ErrorMessage>, sql::Dialect)> {
+    let compile = || -> Result<_, ErrorMessages> {
+        let mut pl = stats::time(&mut stats.parse_ms, || prql_to_pl_tree(sources))?;
+        if let Some(schema) = schema {
+            schema.declare_tables(&mut pl);
+        }
+
+        let database_module_path = [semantic::NS_DEFAULT_DB.to_string()];
+        let (rq, warnings) = stats::time(&mut stats.resolve_ms, || {
+            semantic::resolve_and_lower_with_warnings(pl, main_path, Some(&database_module_path))
+        })
+        .map_err(|e| e.with_source(ErrorSource::NameResolver))?;
+
+        let dialect = sql::resolve_dialect(&rq, opts)?;
+        let sql = stats::time(&mut stats.sql_ms, || {
+            if explain {
+                debug::rq_to_sql_explained(rq, opts, sources)
+            } else {
+                rq_to_sql(rq, opts)
+            }
+        })?;
+        let warnings = ErrorMessages::from(Errors(warnings)).composed(sources);
+        Ok((sql, warnings.inner, dialect))
+    };
+    Ok(compile().map_err(|e| e.composed(sources))?)
+}
+
+/// Path of the SQL file compiled from `input`, keeping its relative location
+/// within `out_dir`.
+fn sql_path_in(out_dir: &Path, input: &Path) -> PathBuf {
+    let relative: PathBuf = input
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+    out_dir.join(relative).with_extension("sql")
+}
+
+/// 64-bit FNV-1a hash of the PL of a query, without its spans, so it's the same
+/// for any formatting of the query. Unlike the hashers of `std`, it's stable
+/// across Rust versions, so fingerprints can be cached between runs.
+fn fingerprint_of(pl: &pr::ModuleDef) -> Result<u64> {
+    fn strip_spans(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                fields.remove("span");
+                fields.values_mut().for_each(strip_spans);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip_spans),
+            _ => {}
+        }
+    }
+
+    let mut pl = serde_json::to_value(pl)?;
+    strip_spans(&mut pl);
+    Ok(fnv1a_64(pl.to_string().as_bytes()))
+}
+
+fn warnings_summary(count: usize) -> String {
+    match count {
+        1 => "compiled with 1 warning".to_string(),
+        n => format!("compiled with {n} warnings"),
     }
 }
 
@@ -572,12 +1050,12 @@ fn drop_module_def(stmts: &mut Vec<pr::Stmt>, name: &str) {
     stmts.retain(|x| x.kind.as_module_def().map_or(true, |m| m.name != name));
 }
 
-fn read_files(input: &mut clio::ClioPath) -> Result<SourceTree> {
+fn read_files(input: &mut clio::ClioPath, extension: &str) -> Result<SourceTree> {
     // Should this function move to a SourceTree constructor?
     let root = input.path();
 
     let mut sources = HashMap::new();
-    for file in input.clone().files(has_extension("prql"))? {
+    for file in input.clone().files(has_extension(extension))? {
         let path = file.path().strip_prefix(root)?.to_owned();
 
         let mut file_contents = String::new();
@@ -588,6 +1066,36 @@ fn read_files(input: &mut clio::ClioPath) -> Result<SourceTree> {
     Ok(SourceTree::new(sources, Some(root.to_path_buf())))
 }
 
+/// Reads the input as the root module of a project, with the other `.prql`
+/// files of the project directory as its submodules. The input replaces the
+/// project's own `main.prql`.
+fn read_project(input: &mut clio::ClioPath, project_dir: &clio::ClioPath) -> Result<SourceTree> {
+    if input.path().is_dir() {
+        bail!("`--project-dir` requires the input to be a single file");
+    }
+    // a single file is read with an empty path, which makes it the root
+    let root = read_files(input, "prql")?;
+
+    let mut sources = read_files(&mut project_dir.clone(), "prql")?.sources;
+
+    // The input is the root module, so it isn't also its own submodule. It
+    // takes the place of the project's `main.prql` (for example when reading
+    // stdin), whose `main` would otherwise clash with the input's.
+    sources.remove(Path::new("main.prql"));
+    if let Ok(input_path) = input.path().canonicalize() {
+        sources.retain(|path, _| {
+            let path = project_dir.path().join(path).canonicalize();
+            path.map_or(true, |path| path != input_path)
+        });
+    }
+    sources.extend(root.sources);
+
+    Ok(SourceTree::new(
+        sources,
+        Some(project_dir.path().to_path_buf()),
+    ))
+}
+
 fn combine_prql_and_frames(source: &str, frames: Vec<(Option<pr::Span>, pl::Lineage)>) -> String {
     let source = Source::from(source);
     let lines = source.lines().collect_vec();
@@ -684,7 +1192,16 @@ sort full
                 signature_comment: false,
                 format: true,
                 target: "sql.any".to_string(),
+                schema: None,
                 debug_log: None,
+                deny_warnings: false,
+                print_target: false,
+                stats_json: None,
+                main: None,
+                out_dir: None,
+                inputs: vec![],
+                project_dir: None,
+                explain: false,
             },
             &mut "asdf".into(),
             "",
@@ -701,6 +1218,48 @@ sort full
         ");
     }
 
+    #[test]
+    fn compile_schema() {
+        anstream::ColorChoice::Never.write_global();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let schema = temp_dir.path().join("schema.yaml");
+        std::fs::write(&schema, "albums: [album_id, title]\n").unwrap();
+
+        let result = Command::execute(
+            &Command::Compile {
+                io_args: IoArgs::default(),
+                signature_comment: false,
+                format: true,
+                target: "sql.any".to_string(),
+                schema: Some(schema),
+                debug_log: None,
+                deny_warnings: false,
+                print_target: false,
+                stats_json: None,
+                main: None,
+                out_dir: None,
+                inputs: vec![],
+                project_dir: None,
+                explain: false,
+            },
+            &mut "from albums\nselect {album_id, titel}".into(),
+            "",
+        );
+
+        assert_snapshot!(&result.unwrap_err().to_string(), @r"
+        Error:
+           ╭─[:2:19]
+           │
+         2 │ select {album_id, titel}
+           │                   ──┬──
+           │                     ╰──── Unknown name `titel`
+           │
+           │ Help: available columns: albums.album_id, albums.title
+        ───╯
+        ");
+    }
+
     #[test]
     fn compile() {
         let result = Command::execute(
@@ -709,7 +1268,16 @@ sort full
                 signature_comment: false,
                 format: true,
                 target: "sql.any".to_string(),
+                schema: None,
                 debug_log: None,
+                deny_warnings: false,
+                print_target: false,
+                stats_json: None,
+                main: None,
+                out_dir: None,
+                inputs: vec![],
+                project_dir: None,
+                explain: false,
             },
             &mut SourceTree::new(
                 [
@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use serde_yaml::Value;
+
+use prqlc::pr::{self, PrimitiveSet, Ty, TyKind, TyTupleField};
+use prqlc::semantic::NS_DEFAULT_DB;
+
+/// Columns of tables in the database, loaded from a JSON or YAML file passed to
+/// `prqlc compile --schema`.
+///
+/// The file maps table names (which may be qualified with a schema, such as
+/// `my_schema.my_table`) to either a list of column names, or a mapping of
+/// column names to their types:
+///
+/// ```yaml
+/// albums: [album_id, title]
+/// my_schema.tracks:
+///   track_id: int
+///   name: text
+/// ```
+#[derive(Debug, Default)]
+pub struct Schema {
+    tables: Vec<(String, Vec<TyTupleField>)>,
+}
+
+impl FromStr for Schema {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        // JSON is a subset of YAML, so this handles both
+        let value: Value = serde_yaml::from_str(source)?;
+        let Value::Mapping(tables) = value else {
+            bail!("schema should be a mapping of table names to their columns")
+        };
+
+        let tables = tables
+            .into_iter()
+            .map(|(name, columns)| {
+                let name = as_name(name)?;
+                let columns = parse_columns(columns)
+                    .map_err(|e| anyhow!("invalid columns of table `{name}`: {e}"))?;
+                Ok((name, columns))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Schema { tables })
+    }
+}
+
+impl Schema {
+    /// Declares the tables in the database module, so that the resolver can
+    /// check that referenced columns exist.
+    pub fn declare_tables(self, root: &mut pr::ModuleDef) {
+        for (name, columns) in self.tables {
+            let mut path = vec![NS_DEFAULT_DB.to_string()];
+            path.extend(name.split('.').map(str::to_string));
+            let name = path.pop().unwrap();
+
+            let ty = Ty::new(TyKind::Array(Some(Box::new(Ty::new(TyKind::Tuple(
+                columns,
+            ))))));
+            let def = pr::VarDef {
+                kind: pr::VarDefKind::Let,
+                name,
+                value: None,
+                ty: Some(ty),
+            };
+            find_or_create_module(root, &path)
+                .stmts
+                .push(pr::Stmt::new(pr::StmtKind::VarDef(def)));
+        }
+    }
+}
+
+fn parse_columns(columns: Value) -> Result<Vec<TyTupleField>> {
+    match columns {
+        Value::Sequence(names) => names
+            .into_iter()
+            .map(|name| Ok(TyTupleField::Single(Some(as_name(name)?), None)))
+            .collect(),
+        Value::Mapping(columns) => columns
+            .into_iter()
+            .map(|(name, ty)| {
+                let ty = match ty {
+                    Value::Null => None,
+                    Value::String(ty) => {
+                        let primitive = PrimitiveSet::from_str(&ty)
+                            .map_err(|_| anyhow!("unknown type `{ty}`"))?;
+                        Some(Ty::new(TyKind::Primitive(primitive)))
+                    }
+                    _ => bail!("column types should be strings"),
+                };
+                Ok(TyTupleField::Single(Some(as_name(name)?), ty))
+            })
+            .collect(),
+        _ => bail!("expected a list of column names, or a mapping of column names to types"),
+    }
+}
+
+fn as_name(value: Value) -> Result<String> {
+    match value {
+        Value::String(name) => Ok(name),
+        value => bail!(
+            "expected a name, found `{}`",
+            serde_yaml::to_string(&value)?.trim()
+        ),
+    }
+}
+
+fn find_or_create_module<'a>(
+    module: &'a mut pr::ModuleDef,
+    path: &[String],
+) -> &'a mut pr::ModuleDef {
+    let Some((name, rest)) = path.split_first() else {
+        return module;
+    };
+
+    let position = module
+        .stmts
+        .iter()
+        .position(|stmt| matches!(&stmt.kind, pr::StmtKind::ModuleDef(m) if &m.name == name));
+    let position = position.unwrap_or_else(|| {
+        let def = pr::ModuleDef {
+            name: name.clone(),
+            stmts: Vec::new(),
+        };
+        // declarations need to precede the statements referencing them
+        module
+            .stmts
+            .insert(0, pr::Stmt::new(pr::StmtKind::ModuleDef(def)));
+        0
+    });
+
+    let pr::StmtKind::ModuleDef(submodule) = &mut module.stmts[position].kind else {
+        unreachable!()
+    };
+    find_or_create_module(submodule, rest)
+}
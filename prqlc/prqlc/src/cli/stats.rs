@@ -0,0 +1,46 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// Durations of the stages of compiling a query and the peak memory use of
+/// the process, written by `prqlc compile --stats-json`.
+#[derive(Debug, Default, Serialize)]
+pub struct CompileStats {
+    pub parse_ms: f64,
+    pub resolve_ms: f64,
+    pub sql_ms: f64,
+    /// `null` on platforms where it can't be measured.
+    pub peak_rss_bytes: Option<u64>,
+}
+
+/// Runs a stage of compilation, adding its duration to `ms`.
+pub fn time<T>(ms: &mut f64, stage: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let res = stage();
+    *ms += start.elapsed().as_secs_f64() * 1000.0;
+    res
+}
+
+/// Peak resident set size of this process, read from `/proc`.
+#[cfg(target_os = "linux")]
+pub fn peak_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn peak_rss_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peak_rss_bytes() {
+        assert!(peak_rss_bytes().unwrap() > 0);
+    }
+}
@@ -27,6 +27,9 @@ fn help() {
       debug             Commands for meant for debugging, prone to change
       experimental      Experimental commands are prone to change
       compile           Parse, resolve, lower into RQ & compile to SQL
+      convert           Compile RQ, serialized as JSON, to SQL
+      check             Compile a query and run it against a database, reporting its row count
+      run               Compile a query to DuckDB SQL and run it in-process, printing the results
       watch             Watch a directory and compile .prql files to .sql files
       list-targets      Show available compile target names
       shell-completion  Print a shell completion for supported shells
@@ -56,6 +59,9 @@ fn help() {
       debug             Commands for meant for debugging, prone to change
       experimental      Experimental commands are prone to change
       compile           Parse, resolve, lower into RQ & compile to SQL
+      convert           Compile RQ, serialized as JSON, to SQL
+      check             Compile a query and run it against a database, reporting its row count
+      run               Compile a query to DuckDB SQL and run it in-process, printing the results
       watch             Watch a directory and compile .prql files to .sql files
       list-targets      Show available compile target names
       shell-completion  Print a shell completion for supported shells
@@ -73,7 +79,7 @@ fn help() {
 
 #[test]
 fn get_targets() {
-    assert_cmd_snapshot!(prqlc_command().arg("list-targets"), @r###"
+    assert_cmd_snapshot!(prqlc_command().arg("list-targets"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
@@ -86,12 +92,14 @@ fn get_targets() {
     sql.glaredb
     sql.mssql
     sql.mysql
+    sql.oracle
     sql.postgres
     sql.sqlite
     sql.snowflake
+    prql
 
     ----- stderr -----
-    "###);
+    ");
 }
 
 #[test]
@@ -111,10 +119,397 @@ fn compile() {
     "###);
 }
 
+#[test]
+fn compile_target_prql() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["compile", "--target", "prql"])
+        .pass_stdin("from tracks | take 10"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    from tracks
+    take 10
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn compile_deny_warnings() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["compile", "--hide-signature-comment", "--deny-warnings"])
+        .pass_stdin("from tracks"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *
+    FROM
+      tracks
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn compile_print_target() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["compile", "--hide-signature-comment", "--print-target"])
+        .pass_stdin("prql target:sql.postgres\nfrom tracks"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *
+    FROM
+      tracks
+
+    ----- stderr -----
+    target: sql.postgres
+    ");
+
+    // without a header, the generic dialect is used
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["compile", "--hide-signature-comment", "--print-target"])
+        .pass_stdin("from tracks"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *
+    FROM
+      tracks
+
+    ----- stderr -----
+    target: sql.generic
+    ");
+}
+
+#[test]
+fn compile_stats_json() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let stats_path = temp_dir.path().join("stats.json");
+
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["compile", "--hide-signature-comment", "--stats-json"])
+        .arg(&stats_path)
+        .pass_stdin("from tracks"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *
+    FROM
+      tracks
+
+    ----- stderr -----
+    ");
+
+    let stats: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(stats_path).unwrap()).unwrap();
+    let keys: Vec<_> = stats.as_object().unwrap().keys().collect();
+    assert_eq!(keys, ["parse_ms", "peak_rss_bytes", "resolve_ms", "sql_ms"]);
+    assert!(stats["resolve_ms"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+fn compile_named_main() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["compile", "--hide-signature-comment", "--main", "monthly_report"])
+        .pass_stdin("let monthly_report = (from invoices | take 5)\nfrom customers"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *
+    FROM
+      invoices
+    LIMIT
+      5
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn compile_out_dir() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    fs::write(temp_dir.path().join("a.prql"), "from artists").unwrap();
+    fs::write(
+        temp_dir.path().join("b.prql"),
+        "from tracks\ntake \"ten\"\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("sub/c.prql"), "from albums").unwrap();
+
+    // a failing input is reported, but the others are still compiled
+    assert_cmd_snapshot!(prqlc_command()
+        .current_dir(temp_dir.path())
+        .args(["compile", "--hide-signature-comment", "--out-dir", "build"])
+        .args(["a.prql", "b.prql", "sub/c.prql"]), @r#"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Error:
+       ╭─[b.prql:2:6]
+       │
+     2 │ take "ten"
+       │      ──┬──
+       │        ╰──── `take` expected int or range, but found "ten"
+    ───╯
+
+    Failed to compile 1 of 3 files:
+      b.prql
+    "#);
+
+    let build = temp_dir.path().join("build");
+    assert_eq!(
+        fs::read_to_string(build.join("a.sql")).unwrap(),
+        "SELECT\n  *\nFROM\n  artists\n"
+    );
+    assert_eq!(
+        fs::read_to_string(build.join("sub/c.sql")).unwrap(),
+        "SELECT\n  *\nFROM\n  albums\n"
+    );
+    assert!(!build.join("b.sql").exists());
+}
+
+#[test]
+fn compile_project_dir() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(
+        temp_dir.path().join("main.prql"),
+        "import helpers.double\n\nfrom invoices\nderive {doubled = double total}\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("helpers.prql"),
+        "let double = x -> x * 2\n",
+    )
+    .unwrap();
+
+    assert_cmd_snapshot!(prqlc_command()
+        .current_dir(temp_dir.path())
+        .args(["compile", "--hide-signature-comment", "--project-dir", "."])
+        .arg("main.prql"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *,
+      total * 2 AS doubled
+    FROM
+      invoices
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn compile_project_dir_stdin() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(temp_dir.path().join("main.prql"), "from invoices\n").unwrap();
+    fs::write(
+        temp_dir.path().join("helpers.prql"),
+        "let double = x -> x * 2\n",
+    )
+    .unwrap();
+
+    // stdin takes the place of the project's `main.prql`
+    assert_cmd_snapshot!(prqlc_command()
+        .current_dir(temp_dir.path())
+        .args(["compile", "--hide-signature-comment", "--project-dir", "."])
+        .pass_stdin("import helpers.double\n\nfrom tracks\nderive {doubled = double bytes}\n"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *,
+      bytes * 2 AS doubled
+    FROM
+      tracks
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn compile_explain() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["compile", "--hide-signature-comment", "--explain"])
+        .pass_stdin(r#"
+from invoices
+filter total > 10
+take 20
+derive {doubled = total * 2}
+filter doubled < 100
+take 10
+filter doubled > 30
+"#), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    -- from: from invoices filter total > 10 take 20 derive {doubled = total * 2}
+    WITH invoices_0 AS (
+      SELECT
+        *,
+        total * 2 AS doubled
+      FROM
+        invoices
+      WHERE
+        total > 10
+      LIMIT
+        20
+    ),
+    -- from: filter doubled < 100 take 10
+    invoices_1 AS (
+      SELECT
+        *
+      FROM
+        invoices_0
+      WHERE
+        doubled < 100
+      LIMIT
+        10
+    )
+    SELECT
+      *
+    FROM
+      invoices_1
+    WHERE
+      doubled > 30
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn convert() {
+    let rq = prqlc::prql_to_pl("from tracks | sort title | take 10")
+        .and_then(prqlc::pl_to_rq)
+        .and_then(|rq| prqlc::json::from_rq(&rq))
+        .unwrap();
+
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input = temp_dir.path().join("query.rq.json");
+    fs::write(&input, rq).unwrap();
+
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["convert", "--hide-signature-comment", "--target", "sql.mssql"])
+        .arg(&input), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *
+    FROM
+      tracks
+    ORDER BY
+      title OFFSET 0 ROWS
+    FETCH FIRST
+      10 ROWS ONLY
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn check_unsupported_db_url() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["check", "--db-url", "oracle://localhost"])
+        .pass_stdin("from tracks"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    unsupported database URL scheme `oracle`; expected one of `sqlite`, `duckdb`, `postgres` or `mysql`
+    ");
+}
+
+#[cfg(not(feature = "postgres"))]
+#[test]
+fn check_without_postgres() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["check", "--db-url", "postgres://localhost/chinook"])
+        .pass_stdin("from tracks"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    prqlc was built without support for PostgreSQL databases; rebuild with the `postgres` feature
+    ");
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn check() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["check", "--db-url", "sqlite://:memory:"])
+        .pass_stdin("from [{a=1}, {a=2}, {a=3}] | filter a > 1"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Query ran successfully, returning 2 rows
+
+    ----- stderr -----
+    ");
+
+    // errors from the database are reported
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["check", "--db-url", "sqlite://:memory:"])
+        .pass_stdin("from tracks"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    no such table: tracks
+    ");
+}
+
+#[cfg(feature = "duckdb")]
+#[test]
+fn run() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["run"])
+        .pass_stdin(r#"from [{a=1, b="x"}, {a=2, b="y"}] | filter a > 1"#), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    +---+---+
+    | a | b |
+    +---+---+
+    | 2 | y |
+    +---+---+
+
+    ----- stderr -----
+    ");
+}
+
+#[cfg(not(feature = "duckdb"))]
+#[test]
+fn run_without_duckdb() {
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["run"])
+        .pass_stdin("from tracks"), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    prqlc was built without support for DuckDB databases; rebuild with the `duckdb` feature
+    ");
+}
+
 #[cfg(not(windows))] // Windows has slightly different output (e.g. `prqlc.exe`), so we exclude.
 #[test]
 fn compile_help() {
-    assert_cmd_snapshot!(prqlc_command().args(["compile", "--help"]), @r#"
+    assert_cmd_snapshot!(prqlc_command().args(["compile", "--help"]), @r"
     success: true
     exit_code: 0
     ----- stdout -----
@@ -122,7 +517,7 @@ fn compile_help() {
 
     Only displays the main pipeline and does not handle loop.
 
-    Usage: prqlc compile [OPTIONS] [INPUT] [OUTPUT] [MAIN_PATH]
+    Usage: prqlc compile [OPTIONS] [INPUT] [OUTPUT] [MAIN_PATH] [INPUTS]...
 
     Arguments:
       [INPUT]
@@ -134,6 +529,9 @@ fn compile_help() {
       [MAIN_PATH]
               Identifier of the main pipeline
 
+      [INPUTS]...
+              Further inputs to compile into `--out-dir`
+
     Options:
           --hide-signature-comment
               Exclude the signature comment containing the PRQL version
@@ -147,11 +545,39 @@ fn compile_help() {
               [env: PRQLC_TARGET=]
               [default: sql.any]
 
+          --schema <SCHEMA>
+              JSON or YAML file listing the columns of tables, to check referenced columns exist
+
           --debug-log <DEBUG_LOG>
               File path into which to write the debug log to
               
               [env: PRQLC_DEBUG_LOG=]
 
+          --deny-warnings
+              Exit with an error if compilation produces any warnings
+
+          --print-target
+              Print the target compiled to, which may be set by the `target` of the query header, to
+              stderr
+
+          --stats-json <STATS_JSON>
+              File path into which to write the duration of each compilation stage and the peak memory
+              use, as JSON
+
+          --main <MAIN>
+              Name of the definition to compile instead of `main`, such as `monthly_report`
+
+          --out-dir <OUT_DIR>
+              Compile each input into its own SQL file within this directory, reading all positional
+              arguments as inputs
+
+          --project-dir <PROJECT_DIR>
+              Directory of the project, whose `.prql` files are modules that the input can import. The
+              input takes the place of the project's `main.prql`
+
+          --explain
+              Annotate each CTE with a comment quoting the PRQL which produced it
+
           --color <WHEN>
               Controls when to use color
               
@@ -162,7 +588,7 @@ fn compile_help() {
               Print help (see a summary with '-h')
 
     ----- stderr -----
-    "#);
+    ");
 }
 
 #[test]
@@ -196,7 +622,7 @@ from long_query
     success: true
     exit_code: 0
     ----- stdout -----
-    WITH table_2 AS (
+    WITH employees_0 AS (
       SELECT
         title,
         COUNT(*) AS ct
@@ -212,24 +638,24 @@ from long_query
         ct
       LIMIT
         20
-    ), table_1 AS (
+    ), employees_1 AS (
       SELECT
         title,
         ct
       FROM
-        table_2
+        employees_0
       WHERE
         ct > 200
       ORDER BY
         ct
       LIMIT
         20
-    ), table_0 AS (
+    ), employees_2 AS (
       SELECT
         title,
         ct
       FROM
-        table_1
+        employees_1
       WHERE
         ct > 200
       ORDER BY
@@ -241,7 +667,7 @@ from long_query
         title,
         ct
       FROM
-        table_0
+        employees_2
       WHERE
         ct > 200
       ORDER BY
@@ -280,7 +706,7 @@ fn compile_project() {
     success: true
     exit_code: 0
     ----- stdout -----
-    WITH table_1 AS (
+    WITH table_0 AS (
       SELECT
         120 AS artist_id,
         DATE '2023-05-18' AS last_listen
@@ -295,9 +721,9 @@ fn compile_project() {
         artist_id,
         last_listen
       FROM
-        table_1
+        table_0
     ),
-    table_0 AS (
+    table_1 AS (
       SELECT
         *
       FROM
@@ -307,7 +733,7 @@ fn compile_project() {
       SELECT
         *
       FROM
-        table_0
+        table_1
     )
     SELECT
       favorite_artists.artist_id,
@@ -330,28 +756,21 @@ fn compile_project() {
         project_path().to_str().unwrap(),
         "-",
         "favorite_artists",
-    ]), @r###"
+    ]), @r"
     success: true
     exit_code: 0
     ----- stdout -----
-    WITH table_0 AS (
-      SELECT
-        120 AS artist_id,
-        DATE '2023-05-18' AS last_listen
-      UNION
-      ALL
-      SELECT
-        7 AS artist_id,
-        DATE '2023-05-16' AS last_listen
-    )
     SELECT
-      artist_id,
-      last_listen
-    FROM
-      table_0
+      120 AS artist_id,
+      DATE '2023-05-18' AS last_listen
+    UNION
+    ALL
+    SELECT
+      7 AS artist_id,
+      DATE '2023-05-16' AS last_listen
 
     ----- stderr -----
-    "###);
+    ");
 }
 
 #[test]
@@ -385,6 +804,97 @@ fn format() {
     compare_directories(&project_path(), temp_dir.path());
 }
 
+#[test]
+fn format_check() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    copy_dir(&project_path(), temp_dir.path());
+
+    // the project is already formatted
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["fmt", "--check", temp_dir.path().to_str().unwrap()]), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    ");
+
+    let unformatted = "from tracks | take 20\n";
+    fs::write(temp_dir.path().join("a.prql"), unformatted).unwrap();
+    fs::write(temp_dir.path().join("b.prql"), "from tracks\ntake 20\n").unwrap();
+    fs::write(temp_dir.path().join("c.prql"), unformatted).unwrap();
+
+    assert_cmd_snapshot!(prqlc_command()
+        .current_dir(temp_dir.path())
+        .args(["fmt", "--check", "a.prql", "b.prql", "c.prql"]), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    The following files aren't formatted:
+      a.prql
+      c.prql
+    ");
+
+    // files aren't modified
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("a.prql")).unwrap(),
+        unformatted
+    );
+
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["fmt", "--check"])
+        .pass_stdin(unformatted), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    The following files aren't formatted:
+      <stdin>
+    ");
+}
+
+#[test]
+fn format_fingerprint() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    fs::write(temp_dir.path().join("a.prql"), "from tracks | take 20\n").unwrap();
+    fs::write(temp_dir.path().join("b.prql"), "from tracks\ntake 20\n").unwrap();
+    fs::write(temp_dir.path().join("c.prql"), "from tracks\ntake 10\n").unwrap();
+
+    // equivalent formatting has the same fingerprint
+    assert_cmd_snapshot!(prqlc_command()
+        .current_dir(temp_dir.path())
+        .args(["fmt", "--fingerprint", "a.prql", "b.prql", "c.prql"]), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    a993108f2722c00a  a.prql
+    a993108f2722c00a  b.prql
+    0bbdef9a73c669f5  c.prql
+
+    ----- stderr -----
+    ");
+
+    // files aren't modified
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("a.prql")).unwrap(),
+        "from tracks | take 20\n"
+    );
+
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["fmt", "--fingerprint"])
+        .pass_stdin("from tracks | take 20"), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    a993108f2722c00a  -
+
+    ----- stderr -----
+    ");
+}
+
 fn copy_dir(src: &Path, dst: &Path) {
     for entry in WalkDir::new(src) {
         let entry = entry.unwrap();
@@ -423,7 +933,7 @@ fn compare_directories(dir1: &Path, dir2: &Path) {
 fn debug() {
     assert_cmd_snapshot!(prqlc_command()
         .args(["debug", "lineage"])
-        .pass_stdin("from tracks | select {artist, album}"), @r#"
+        .pass_stdin("from tracks | select {artist, album}"), @r"
     success: true
     exit_code: 0
     ----- stdout -----
@@ -434,29 +944,29 @@ fn debug() {
           name:
           - tracks
           - artist
-          target_id: 117
+          target_id: 137
           target_name: null
         - !Single
           name:
           - tracks
           - album
-          target_id: 118
+          target_id: 138
           target_name: null
         inputs:
-        - id: 115
+        - id: 135
           name: tracks
           table:
           - default_db
           - tracks
     nodes:
-    - id: 115
+    - id: 135
       kind: Ident
       span: 1:0-11
       ident: !Ident
       - default_db
       - tracks
-      parent: 120
-    - id: 117
+      parent: 140
+    - id: 137
       kind: Ident
       span: 1:22-28
       ident: !Ident
@@ -464,9 +974,9 @@ fn debug() {
       - tracks
       - artist
       targets:
-      - 115
-      parent: 119
-    - id: 118
+      - 135
+      parent: 139
+    - id: 138
       kind: Ident
       span: 1:30-35
       ident: !Ident
@@ -474,21 +984,21 @@ fn debug() {
       - tracks
       - album
       targets:
-      - 115
-      parent: 119
-    - id: 119
+      - 135
+      parent: 139
+    - id: 139
       kind: Tuple
       span: 1:21-36
       children:
-      - 117
-      - 118
-      parent: 120
-    - id: 120
+      - 137
+      - 138
+      parent: 140
+    - id: 140
       kind: 'TransformCall: Select'
       span: 1:14-36
       children:
-      - 115
-      - 119
+      - 135
+      - 139
     ast:
       name: Project
       stmts:
@@ -527,7 +1037,7 @@ fn debug() {
         span: 1:0-36
 
     ----- stderr -----
-    "#);
+    ");
 
     // Don't test the output of this, since on one min-versions check it had
     // different results, and didn't repro on Mac. It having different results
@@ -541,6 +1051,29 @@ fn debug() {
         .unwrap();
 }
 
+#[test]
+fn debug_lineage_html() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let input = temp_dir.path().join("query.prql");
+    let html = temp_dir.path().join("lineage.html");
+    fs::write(&input, "from tracks | select {artist, album}").unwrap();
+
+    let status = prqlc_command()
+        .args(["debug", "lineage", "--html"])
+        .arg(&html)
+        .arg(&input)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // don't check the full contents, only that the columns are connected
+    let contents = fs::read_to_string(html).unwrap();
+    assert!(contents.starts_with("<!doctype html>"));
+    assert!(contents.contains("default_db.tracks.artist"));
+    assert!(contents.contains(r#"data-source="source-1" data-output="output-1""#));
+}
+
 // The output of `prqlc debug json-schema` is long, so rather than
 // comparing the full output as a snapshot, we just verify that the
 // standard output parses as JSON and check a couple top-level keys.
@@ -670,3 +1203,35 @@ fn lex() {
     ----- stderr -----
     "###);
 }
+
+#[test]
+fn debug_tokens() {
+    // lexing continues past the error
+    assert_cmd_snapshot!(prqlc_command()
+        .args(["debug", "tokens"])
+        .pass_stdin("from x\nderive y = 1 ; b\n"), @r#"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    0..0: Start
+    0..4: Ident("from")
+    5..6: Ident("x")
+    6..7: NewLine
+    7..13: Ident("derive")
+    14..15: Ident("y")
+    16..17: Control('=')
+    18..19: Literal(Integer(1))
+    20..23: Ident("b")
+    23..24: NewLine
+
+    ----- stderr -----
+    Error:
+       ╭─[:2:14]
+       │
+     2 │ derive y = 1 ; b
+       │              ┬
+       │              ╰── unexpected ;
+    ───╯
+
+    "#);
+}
@@ -2,6 +2,8 @@ use std::borrow::Cow;
 use std::collections::HashSet;
 use std::sync::OnceLock;
 
+use itertools::Itertools;
+use prqlc_parser::generic::FORMAT_IDENT;
 use regex::Regex;
 
 use super::{WriteOpt, WriteSource};
@@ -121,7 +123,7 @@ impl WriteSource for pr::ExprKind {
                     r += opt.consume(&start)?;
                 }
 
-                r += opt.consume("..")?;
+                r += opt.consume(if range.end_exclusive { "..<" } else { ".." })?;
 
                 if let Some(end) = &range.end {
                     r += &write_within(end.as_ref(), self, opt)?;
@@ -149,7 +151,12 @@ impl WriteSource for pr::ExprKind {
                 let mut r = String::new();
 
                 r += opt.consume(&op.to_string())?;
-                r += &write_within(expr.as_ref(), self, opt)?;
+                // a spread applies to the whole field of a tuple
+                if let pr::UnOp::Spread = op {
+                    r += &expr.write(opt)?;
+                } else {
+                    r += &write_within(expr.as_ref(), self, opt)?;
+                }
                 Some(r)
             }
             FuncCall(func_call) => {
@@ -226,6 +233,26 @@ impl WriteSource for pr::ExprKind {
                 .write_between("[", "]", opt)?;
                 Some(r)
             }
+            Switch(pr::Switch { value, cases }) => {
+                let mut r = String::new();
+                r += "case ";
+                r += opt.consume(&write_within(value.as_ref(), self, opt.clone())?)?;
+                r += " ";
+                r += &SeparatedExprs {
+                    exprs: cases,
+                    inline: ", ",
+                    line_end: ",",
+                }
+                .write_between("[", "]", opt)?;
+                Some(r)
+            }
+            DeclaredColumns(pr::DeclaredColumns { relation, columns }) => {
+                let mut r = write_within(relation.as_ref(), self, opt.clone())?;
+                r += ":{";
+                r += &columns.iter().map(|c| write_ident_part(c)).join(", ");
+                r += "}";
+                opt.consume(r)
+            }
             Param(id) => Some(format!("${id}")),
             Internal(operator_name) => Some(format!("internal {operator_name}")),
         }
@@ -268,6 +295,7 @@ fn binding_strength(expr: &pr::ExprKind) -> u8 {
             | pr::BinOp::Lt
             | pr::BinOp::Gte
             | pr::BinOp::Lte
+            | pr::BinOp::NullSafeEq
             | pr::BinOp::RegexSearch => 16,
             pr::BinOp::Coalesce => 15,
             pr::BinOp::And => 14,
@@ -295,6 +323,7 @@ fn associativity(expr: &pr::ExprKind) -> super::Position {
             | pr::BinOp::Lt
             | pr::BinOp::Gte
             | pr::BinOp::Lte
+            | pr::BinOp::NullSafeEq
             | pr::BinOp::RegexSearch => super::Position::Unspecified,
             _ => super::Position::Left,
         },
@@ -372,11 +401,30 @@ impl WriteSource for Vec<pr::Stmt> {
     }
 }
 
+fn as_into_target(annotation: &pr::Annotation) -> Option<&pr::Ident> {
+    let [item] = annotation.expr.kind.as_tuple()?.as_slice() else {
+        return None;
+    };
+    if item.alias.as_deref() != Some(pr::INTO_ANNOTATION) {
+        return None;
+    }
+    item.kind.as_ident()
+}
+
 impl WriteSource for pr::Stmt {
     fn write(&self, mut opt: WriteOpt) -> Option<String> {
         let mut r = String::new();
 
-        for annotation in &self.annotations {
+        // `@{into = schema.table}` of a main pipeline is written back as the
+        // `into` which implied it
+        let is_main =
+            matches!(&self.kind, pr::StmtKind::VarDef(v) if v.kind == pr::VarDefKind::Main);
+        let (into, annotations): (Vec<_>, Vec<_>) = self
+            .annotations
+            .iter()
+            .partition(|a| is_main && as_into_target(a).is_some());
+
+        for annotation in annotations {
             r += "@";
             r += &annotation.expr.write(opt.clone())?;
             r += "\n";
@@ -390,7 +438,7 @@ impl WriteSource for pr::Stmt {
                 if let Some(version) = &query.version {
                     r += &format!(r#" version:"{}""#, version);
                 }
-                for (key, value) in &query.other {
+                for (key, value) in query.other.iter().sorted() {
                     r += &format!(" {key}:{value}");
                 }
                 r += "\n";
@@ -428,7 +476,7 @@ impl WriteSource for pr::Stmt {
                             }
                         }
                         _ => {
-                            r += &val.write(opt)?;
+                            r += &val.write(opt.clone())?;
                             r += "\n";
                         }
                     }
@@ -437,6 +485,10 @@ impl WriteSource for pr::Stmt {
                         r += &format!("into {}", var_def.name);
                         r += "\n";
                     }
+                    for target in into.iter().filter_map(|a| as_into_target(a)) {
+                        r += &format!("into {}", target.write(opt.clone())?);
+                        r += "\n";
+                    }
                 }
             },
             pr::StmtKind::TypeDef(type_def) => {
@@ -481,8 +533,12 @@ fn display_interpolation(
         match &part {
             // We use double braces to escape braces
             pr::InterpolateItem::String(s) => r += s.replace('{', "{{").replace('}', "}}").as_str(),
-            pr::InterpolateItem::Expr { expr, .. } => {
+            pr::InterpolateItem::Expr { expr, format } => {
                 r += "{";
+                if format.as_deref() == Some(FORMAT_IDENT) {
+                    r += FORMAT_IDENT;
+                    r += ":";
+                }
                 r += &expr.write(opt.clone())?;
                 r += "}"
             }
@@ -578,6 +634,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_case_value() {
+        assert_is_formatted(r#"derive s = case (b / 8) [0 => "x", _ => "y"]"#);
+    }
+
+    #[test]
+    fn test_spread() {
+        assert_is_formatted(r#"select {id, ...metrics amount, ...{a}}"#);
+    }
+
+    #[test]
+    fn test_declared_columns() {
+        assert_is_formatted(r#"join (s"SELECT a, b FROM x"):{a, `b c`} (==a)"#);
+    }
+
     #[test]
     fn test_unary() {
         assert_is_formatted(r#"sort {-duration}"#);
@@ -0,0 +1,67 @@
+//! Annotation of the SQL with the PRQL which produced it.
+
+use itertools::Itertools;
+
+use crate::ir::rq;
+use crate::{sql, ErrorMessages, ErrorSource, Options, SourceTree, Span, WithErrorInfo};
+
+/// Generate SQL from RQ, with a comment above each CTE quoting the PRQL which
+/// produced it, such as `-- from: filter price > 10`.
+///
+/// The PRQL is read from `sources`, which the RQ was compiled from. Without
+/// formatting, the SQL is a single line, so all comments precede the query.
+pub fn rq_to_sql_explained(
+    rq: rq::RelationalQuery,
+    options: &Options,
+    sources: &SourceTree,
+) -> Result<String, ErrorMessages> {
+    let (mut sql, cte_map) = sql::compile_with_cte_map(rq, options)
+        .map_err(|e| -> ErrorMessages { e.with_source(ErrorSource::SQL).into() })?;
+
+    // inserted from the last, so the ranges of the preceding CTEs stay valid
+    for (range, span) in cte_map.mappings.into_iter().rev() {
+        let Some(prql) = source_of(sources, span) else {
+            continue;
+        };
+
+        let line_start = sql[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let indent = (sql[line_start..].chars())
+            .take_while(|c| *c == ' ')
+            .collect::<String>();
+        let comment = format!("{indent}-- from: {prql}\n");
+
+        // the first CTE follows `WITH`, while others may follow the end of
+        // the preceding CTE, from which they are split onto their own line
+        let before = sql[line_start..range.start].trim();
+        let is_line_start = before.is_empty()
+            || before.eq_ignore_ascii_case("with")
+            || before.eq_ignore_ascii_case("with recursive");
+        if is_line_start || !options.format {
+            sql.insert_str(line_start, &comment);
+        } else {
+            let split = sql[..range.start].trim_end().len();
+            sql.replace_range(split..range.start, &format!("\n{comment}{indent}"));
+        }
+    }
+    Ok(sql)
+}
+
+/// The PRQL of the lines of a span, as a single line.
+///
+/// The span covers the expressions of transforms, so it's extended to whole
+/// lines, which contain whole transforms of pipelines.
+fn source_of(sources: &SourceTree, span: Span) -> Option<String> {
+    let path = sources.get_path(span.source_id)?;
+    let chars = sources.sources.get(path)?.chars().collect_vec();
+
+    let end = span.end.min(chars.len());
+    let start = (chars[..span.start.min(end)].iter())
+        .rposition(|c| *c == '\n')
+        .map_or(0, |i| i + 1);
+    let end = (chars[end..].iter())
+        .position(|c| *c == '\n')
+        .map_or(chars.len(), |i| end + i);
+
+    let prql = chars[start..end].iter().collect::<String>();
+    Some(prql.split_whitespace().join(" "))
+}
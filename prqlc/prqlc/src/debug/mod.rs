@@ -1,7 +1,18 @@
+mod explain;
 mod log;
 mod messages;
+mod passes;
 mod render_html;
+mod resolution;
+mod rq;
+mod stages;
 
 pub use crate::debug::log::*;
+pub use explain::rq_to_sql_explained;
 pub use messages::MessageLogger;
+pub use passes::{compile_with_pass_log, PassEvent};
 pub use render_html::render_log_to_html;
+pub use resolution::resolution_trace;
+pub(crate) use resolution::ResolvedName;
+pub use rq::rq_to_debug_string;
+pub use stages::{stage_sql, TransformKind};
@@ -0,0 +1,36 @@
+//! Reporting of which compiler passes changed the query.
+
+use serde::Serialize;
+
+use crate::{sql, ErrorMessages, ErrorSource, Options, SourceTree, WithErrorInfo};
+
+/// Record of a pass that was applied to the query while translating RQ into
+/// SQL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PassEvent {
+    /// Name of the pass, such as `prune_inputs`.
+    pub name: &'static str,
+
+    /// Whether the pass changed the query.
+    pub changed: bool,
+}
+
+/// Compile a PRQL string into a SQL string, also returning the passes that
+/// were applied to the query, in order.
+///
+/// Passes are applied to each relation of the query separately, so a query
+/// with CTEs produces an event per pass for each of them.
+pub fn compile_with_pass_log(
+    prql: &str,
+    options: &Options,
+) -> Result<(String, Vec<PassEvent>), ErrorMessages> {
+    let sources = SourceTree::from(prql);
+
+    crate::prql_to_pl_tree(&sources)
+        .and_then(crate::pl_to_rq)
+        .and_then(|rq| {
+            sql::compile_with_pass_log(rq, options)
+                .map_err(|e| e.with_source(ErrorSource::SQL).into())
+        })
+        .map_err(|e| e.composed(&sources))
+}
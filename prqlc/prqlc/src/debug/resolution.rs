@@ -0,0 +1,75 @@
+//! Reporting of the declarations that names of the query resolved to.
+
+use itertools::Itertools;
+
+use crate::ir::decl::DeclKind;
+use crate::ir::pl::{ExprKind, Ident};
+use crate::{semantic, SourceTree};
+
+/// Declaration that an identifier resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ResolvedName {
+    /// The identifier, as written in the query.
+    ident: Ident,
+
+    /// Fully qualified name of the declaration.
+    fq_ident: Ident,
+
+    /// Kind of the declaration, such as `column` or `table`.
+    kind: &'static str,
+}
+
+impl ResolvedName {
+    pub(crate) fn new(ident: &Ident, fq_ident: &Ident, decl: &DeclKind) -> Self {
+        let kind = match decl {
+            DeclKind::Module(_) | DeclKind::LayeredModules(_) => "module",
+            DeclKind::TableDecl(_) => "table",
+            DeclKind::InstanceOf(..) => "relation",
+            DeclKind::Column(_) => "column",
+            DeclKind::Infer(_) => "inferred",
+            DeclKind::Expr(expr) if matches!(expr.kind, ExprKind::Func(_)) => "function",
+            DeclKind::Expr(_) => "variable",
+            DeclKind::Ty(_) => "type",
+            DeclKind::QueryDef(_) => "query definition",
+            DeclKind::Import(_) => "import",
+        };
+        ResolvedName {
+            ident: ident.clone(),
+            fq_ident: fq_ident.clone(),
+            kind,
+        }
+    }
+}
+
+/// Resolve names of a PRQL string and describe the declaration that each
+/// identifier resolved to, one per line, in the order of resolution.
+///
+/// A line contains the identifier as written, the fully qualified name of its
+/// declaration and the kind of the declaration, such as
+/// `name -> this.employees.name (column)`. Columns are qualified with the
+/// relation they belong to. When the query cannot be resolved, the trace ends
+/// with the error and its hints.
+pub fn resolution_trace(prql: &str) -> String {
+    let sources = SourceTree::from(prql);
+
+    let (trace, error) = match crate::prql_to_pl_tree(&sources) {
+        Ok(module_tree) => {
+            let (trace, res) = semantic::resolve_with_trace(module_tree);
+            (trace, res.err().map(|e| (e.reason.to_string(), e.hints)))
+        }
+        Err(e) => {
+            let error = e.inner.into_iter().next();
+            (Vec::new(), error.map(|e| (e.reason, e.hints)))
+        }
+    };
+
+    let mut lines = trace
+        .into_iter()
+        .map(|r| format!("{} -> {} ({})", r.ident, r.fq_ident, r.kind))
+        .collect_vec();
+    if let Some((reason, hints)) = error {
+        lines.push(format!("error: {reason}"));
+        lines.extend(hints.into_iter().map(|hint| format!("hint: {hint}")));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,268 @@
+//! Rendering of RQ as a human-readable tree.
+
+use std::fmt::{Result, Write};
+
+use itertools::Itertools;
+use prqlc_parser::generic;
+
+use crate::ir::generic::{ColumnSort, SortDirection, WindowFrame, WindowKind};
+use crate::ir::pl::TableExternRef;
+use crate::ir::rq::{
+    CId, Compute, Expr, ExprKind, Relation, RelationColumn, RelationKind, RelationalQuery,
+    TableDecl, TableRef, Take, Transform,
+};
+
+/// Render RQ as an indented tree, with one transform per line.
+///
+/// Tables are listed first, followed by the main relation. Columns are
+/// referred to by their ids, such as `column-3`, and tables by theirs, such
+/// as `table-0`. Unlike JSON, the output is meant to be read and compared
+/// with `diff` when debugging lowering.
+pub fn rq_to_debug_string(rq: &RelationalQuery) -> String {
+    let mut r = String::new();
+    write_query(&mut r, rq).unwrap();
+    r
+}
+
+fn write_query(w: &mut String, rq: &RelationalQuery) -> Result {
+    for table in &rq.tables {
+        write_table_decl(w, table)?;
+    }
+
+    write!(w, "main")?;
+    if let Some(into) = &rq.into {
+        write!(w, " into {into}")?;
+    }
+    writeln!(w, ":")?;
+    write_relation(w, &rq.relation, 1)
+}
+
+fn write_table_decl(w: &mut String, table: &TableDecl) -> Result {
+    write!(w, "{:?}", table.id)?;
+    if let Some(name) = &table.name {
+        write!(w, " {name}")?;
+    }
+    writeln!(w, ":")?;
+    write_relation(w, &table.relation, 1)
+}
+
+fn write_relation(w: &mut String, relation: &Relation, indent: usize) -> Result {
+    let pad = "  ".repeat(indent);
+
+    let columns = relation.columns.iter().map(relation_column).join(", ");
+    writeln!(w, "{pad}columns: [{columns}]")?;
+
+    match &relation.kind {
+        RelationKind::ExternRef(TableExternRef::LocalTable(ident)) => {
+            writeln!(w, "{pad}extern {ident}")
+        }
+        RelationKind::ExternRef(TableExternRef::Param(param)) => {
+            writeln!(w, "{pad}extern ${param}")
+        }
+        RelationKind::Pipeline(transforms) => write_transforms(w, transforms, indent),
+        RelationKind::Literal(literal) => {
+            writeln!(w, "{pad}literal [{}]", literal.columns.join(", "))?;
+            for row in &literal.rows {
+                writeln!(w, "{pad}  [{}]", row.iter().join(", "))?;
+            }
+            Ok(())
+        }
+        RelationKind::SString(items) => writeln!(w, "{pad}{}", sstring(items)),
+        RelationKind::BuiltInFunction { name, args } => {
+            writeln!(w, "{pad}{name}({})", exprs(args))
+        }
+    }
+}
+
+fn write_transforms(w: &mut String, transforms: &[Transform], indent: usize) -> Result {
+    let pad = "  ".repeat(indent);
+
+    for transform in transforms {
+        write!(w, "{pad}")?;
+        match transform {
+            Transform::From(table_ref) => writeln!(w, "from {}", table_ref_str(table_ref))?,
+            Transform::Compute(compute) => writeln!(w, "compute {}", compute_str(compute))?,
+            Transform::Select(cids) => writeln!(w, "select [{}]", columns(cids))?,
+            Transform::Filter(expr) => writeln!(w, "filter {}", expr_str(expr))?,
+            Transform::Aggregate { partition, compute } => writeln!(
+                w,
+                "aggregate [{}] by [{}]",
+                columns(compute),
+                columns(partition)
+            )?,
+            Transform::Sort(sort) => writeln!(w, "sort [{}]", sorts(sort))?,
+            Transform::Take(take) => writeln!(w, "take {}", take_str(take))?,
+            Transform::Join { side, with, filter } => writeln!(
+                w,
+                "join {} {} on {}",
+                format!("{side:?}").to_lowercase(),
+                table_ref_str(with),
+                expr_str(filter)
+            )?,
+            Transform::Append { bottom, by_name } => {
+                write!(w, "append {}", table_ref_str(bottom))?;
+                if *by_name {
+                    write!(w, " by name")?;
+                }
+                writeln!(w)?;
+            }
+            Transform::Except { bottom, all } => {
+                write!(w, "except {}", table_ref_str(bottom))?;
+                if *all {
+                    write!(w, " all")?;
+                }
+                writeln!(w)?;
+            }
+            Transform::Intersect { bottom, all } => {
+                write!(w, "intersect {}", table_ref_str(bottom))?;
+                if *all {
+                    write!(w, " all")?;
+                }
+                writeln!(w)?;
+            }
+            Transform::Loop(transforms) => {
+                writeln!(w, "loop:")?;
+                write_transforms(w, transforms, indent + 1)?;
+            }
+            Transform::Pivot(pivot) => {
+                let new_columns = (pivot.columns.iter())
+                    .map(|(value, name, cid)| format!("{cid:?} {name} = {value}"))
+                    .join(", ");
+                writeln!(
+                    w,
+                    "pivot {}({:?}) of {:?} by [{}] into [{new_columns}]",
+                    pivot.func,
+                    pivot.values_from,
+                    pivot.names_from,
+                    columns(&pivot.partition)
+                )?
+            }
+            Transform::Unpivot(unpivot) => writeln!(
+                w,
+                "unpivot [{}] into {:?} {}, {:?} {} retaining [{}]",
+                columns(&unpivot.columns),
+                unpivot.names_to.1,
+                unpivot.names_to.0,
+                unpivot.values_to.1,
+                unpivot.values_to.0,
+                columns(&unpivot.retained)
+            )?,
+        }
+    }
+    Ok(())
+}
+
+fn table_ref_str(table_ref: &TableRef) -> String {
+    let mut r = format!("{:?}", table_ref.source);
+    if let Some(name) = &table_ref.name {
+        r += &format!(" as {name}");
+    }
+    let columns = (table_ref.columns.iter())
+        .map(|(col, cid)| format!("{cid:?} = {}", relation_column(col)))
+        .join(", ");
+    r + &format!(" [{columns}]")
+}
+
+fn compute_str(compute: &Compute) -> String {
+    let mut r = format!("{:?} = {}", compute.id, expr_str(&compute.expr));
+
+    if let Some(window) = &compute.window {
+        let mut parts = Vec::new();
+        if !window.partition.is_empty() {
+            parts.push(format!("partition [{}]", columns(&window.partition)));
+        }
+        if !window.sort.is_empty() {
+            parts.push(format!("sort [{}]", sorts(&window.sort)));
+        }
+        if !window.frame.is_default() {
+            parts.push(frame_str(&window.frame));
+        }
+        r += &format!(" over ({})", parts.join(", "));
+    }
+    if compute.is_aggregation {
+        r += " (aggregation)";
+    }
+    r
+}
+
+fn frame_str(frame: &WindowFrame<Expr>) -> String {
+    let kind = match frame.kind {
+        WindowKind::Rows => "rows",
+        WindowKind::Range => "range",
+    };
+    format!("{kind} {}", range_str(&frame.range))
+}
+
+fn take_str(take: &Take) -> String {
+    let mut r = match take.percent {
+        Some(percent) => format!("{percent}%"),
+        None => range_str(&take.range),
+    };
+    if !take.partition.is_empty() {
+        r += &format!(" partition [{}]", columns(&take.partition));
+    }
+    if !take.sort.is_empty() {
+        r += &format!(" sort [{}]", sorts(&take.sort));
+    }
+    r
+}
+
+fn range_str(range: &generic::Range<Expr>) -> String {
+    let start = range.start.as_ref().map(expr_str).unwrap_or_default();
+    let end = range.end.as_ref().map(expr_str).unwrap_or_default();
+    let sep = if range.end_exclusive { "..<" } else { ".." };
+    format!("{start}{sep}{end}")
+}
+
+fn expr_str(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::ColumnRef(cid) => format!("{cid:?}"),
+        ExprKind::Literal(literal) => literal.to_string(),
+        ExprKind::SString(items) => sstring(items),
+        ExprKind::Case(cases) => {
+            let cases = (cases.iter())
+                .map(|case| format!("{} => {}", expr_str(&case.condition), expr_str(&case.value)))
+                .join(", ");
+            format!("case [{cases}]")
+        }
+        ExprKind::Operator { name, args } => format!("{name}({})", exprs(args)),
+        ExprKind::Param(param) => format!("${param}"),
+        ExprKind::Array(items) => format!("[{}]", exprs(items)),
+        ExprKind::SubQuery(tid) => format!("{tid:?}"),
+    }
+}
+
+fn exprs(exprs: &[Expr]) -> String {
+    exprs.iter().map(expr_str).join(", ")
+}
+
+fn sstring(items: &[generic::InterpolateItem<Expr>]) -> String {
+    let inner = (items.iter())
+        .map(|item| match item {
+            generic::InterpolateItem::String(s) => s.replace('{', "{{").replace('}', "}}"),
+            generic::InterpolateItem::Expr { expr, .. } => format!("{{{}}}", expr_str(expr)),
+        })
+        .join("");
+    format!("s\"{inner}\"")
+}
+
+fn sorts(sorts: &[ColumnSort<CId>]) -> String {
+    (sorts.iter())
+        .map(|sort| match sort.direction {
+            SortDirection::Asc => format!("{:?}", sort.column),
+            SortDirection::Desc => format!("-{:?}", sort.column),
+        })
+        .join(", ")
+}
+
+fn columns(cids: &[CId]) -> String {
+    cids.iter().map(|cid| format!("{cid:?}")).join(", ")
+}
+
+fn relation_column(column: &RelationColumn) -> String {
+    match column {
+        RelationColumn::Single(Some(name)) => name.clone(),
+        RelationColumn::Single(None) => "_".to_string(),
+        RelationColumn::Wildcard => "*".to_string(),
+    }
+}
@@ -0,0 +1,41 @@
+//! Reporting of the SQL produced by each transform of the query.
+
+use serde::Serialize;
+
+use crate::{sql, ErrorMessages, ErrorSource, Options, SourceTree, WithErrorInfo};
+
+/// Kind of a transform, as it appears in the SQL of the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr, strum::Display, Serialize)]
+pub enum TransformKind {
+    From,
+    Join,
+    Filter,
+    Aggregate,
+    Select,
+    Sort,
+    Take,
+    Distinct,
+    Union,
+    Except,
+    Intersect,
+}
+
+/// Compile a PRQL string and return the SQL fragment produced by each
+/// transform, in the order of the SQL clauses.
+///
+/// A transform may produce multiple fragments: an `aggregate` produces both
+/// its aggregated expressions and its `GROUP BY` clause. Fragments of CTEs
+/// precede the fragments of the main query.
+pub fn stage_sql(
+    prql: &str,
+    options: &Options,
+) -> Result<Vec<(TransformKind, String)>, ErrorMessages> {
+    let sources = SourceTree::from(prql);
+
+    crate::prql_to_pl_tree(&sources)
+        .and_then(crate::pl_to_rq)
+        .and_then(|rq| {
+            sql::compile_stages(rq, options).map_err(|e| e.with_source(ErrorSource::SQL).into())
+        })
+        .map_err(|e| e.composed(&sources))
+}
@@ -8,13 +8,14 @@ use ariadne::{Cache, Config, Label, Report, ReportKind, Source};
 use serde::Serialize;
 
 use crate::Span;
-use crate::{Error, Errors, MessageKind, SourceTree};
+use crate::{Error, ErrorCode, Errors, MessageKind, SourceTree};
 
 #[derive(Clone, Serialize)]
 pub struct ErrorMessage {
     /// Message kind. Currently only Error is implemented.
     pub kind: MessageKind,
-    /// Machine-readable identifier of the error
+    /// Machine-readable identifier of the error. Known codes can be matched
+    /// on with [ErrorMessage::error_code].
     pub code: Option<String>,
     /// Plain text of the error
     pub reason: String,
@@ -64,6 +65,15 @@ impl Display for ErrorMessage {
     }
 }
 
+impl StdError for ErrorMessage {}
+
+impl ErrorMessage {
+    /// The code of the error, if it is a known one.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        self.code.as_deref()?.parse().ok()
+    }
+}
+
 impl Debug for ErrorMessage {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         Display::fmt(&self, f)
@@ -133,6 +143,23 @@ impl ErrorMessages {
         serde_json::to_string(self).unwrap()
     }
 
+    /// Appends messages of another [ErrorMessages], for example those of a
+    /// different stage or source file.
+    pub fn merge(mut self, other: ErrorMessages) -> Self {
+        self.inner.extend(other.inner);
+        self
+    }
+
+    /// Orders messages by their source file and then by position within it.
+    /// Messages without a span come last, in their original order.
+    pub fn sorted_by_span(mut self) -> Self {
+        self.inner.sort_by_key(|e| {
+            let span = e.span.map(|s| (s.source_id, s.start, s.end));
+            (span.is_none(), span)
+        });
+        self
+    }
+
     /// Computes message location and builds the pretty display.
     pub fn composed(mut self, sources: &SourceTree) -> Self {
         let mut cache = FileTreeCache::new(sources);
@@ -171,7 +198,13 @@ impl ErrorMessage {
         let span = Range::from(self.span?);
         let error_span = (source_path.clone(), span.start..span.end);
 
-        let mut report = Report::build(ReportKind::Error, error_span.clone())
+        let kind = match self.kind {
+            MessageKind::Error => ReportKind::Error,
+            MessageKind::Warning => ReportKind::Warning,
+            MessageKind::Lint => ReportKind::Advice,
+        };
+
+        let mut report = Report::build(kind, error_span.clone())
             .with_config(config)
             .with_label(Label::new(error_span).with_message(&self.reason));
 
@@ -243,3 +276,37 @@ impl Cache<PathBuf> for FileTreeCache<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_sorted_by_span() {
+        let message = |reason: &str, span: Option<(u16, usize)>| {
+            let mut error = Error::new_simple(reason);
+            error.span = span.map(|(source_id, start)| Span {
+                start,
+                end: start + 1,
+                source_id,
+            });
+            ErrorMessage::from(error)
+        };
+
+        let first = ErrorMessages::from(vec![
+            message("b", Some((2, 10))),
+            message("no span", None),
+            message("a", Some((2, 3))),
+        ]);
+        let second = ErrorMessages::from(vec![
+            message("c", Some((1, 20))),
+            message("d", Some((3, 0))),
+        ]);
+
+        let merged = first.merge(second).sorted_by_span();
+        let reasons = merged.inner.iter().map(|e| e.reason.as_str()).collect_vec();
+        assert_eq!(reasons, ["c", "a", "b", "d", "no span"]);
+    }
+}
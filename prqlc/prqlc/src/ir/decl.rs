@@ -9,6 +9,7 @@ use crate::codegen::write_ty;
 use crate::ir::pl;
 use crate::pr::{Span, Ty};
 use crate::semantic::write_pl;
+use crate::Error;
 
 /// Context of the pipeline.
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -17,6 +18,10 @@ pub struct RootModule {
     pub module: Module,
 
     pub span_map: HashMap<usize, Span>,
+
+    /// Non-fatal messages (warnings and lints) emitted during resolution.
+    #[serde(skip)]
+    pub warnings: Vec<Error>,
 }
 
 #[derive(Default, PartialEq, Serialize, Deserialize, Clone)]
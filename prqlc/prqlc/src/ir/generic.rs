@@ -36,7 +36,8 @@ impl<T> WindowFrame<T> {
                 kind: WindowKind::Rows,
                 range: generic::Range {
                     start: None,
-                    end: None
+                    end: None,
+                    ..
                 }
             }
         )
@@ -75,6 +75,10 @@ pub enum TransformKind {
     },
     Take {
         range: Range,
+        /// When set, approximately this percentage of rows is taken instead
+        /// of the `range`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        percent: Option<i64>,
     },
     Join {
         side: JoinSide,
@@ -90,8 +94,41 @@ pub enum TransformKind {
         range: Range,
         pipeline: Box<Expr>,
     },
-    Append(Box<Expr>),
+    Append {
+        bottom: Box<Expr>,
+        /// When set, columns are matched by name instead of by position.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        by_name: bool,
+    },
+    Except {
+        bottom: Box<Expr>,
+        /// When set, duplicate rows are retained (`EXCEPT ALL`).
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        all: bool,
+    },
+    Intersect {
+        bottom: Box<Expr>,
+        /// When set, duplicate rows are retained (`INTERSECT ALL`).
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        all: bool,
+    },
     Loop(Box<Expr>),
+    Pivot {
+        /// Columns that are retained (all except `names_from` and the aggregated column).
+        by: Box<Expr>,
+        names_from: Box<Expr>,
+        /// Aggregation of a column, i.e. `sum amount`.
+        values_from: Box<Expr>,
+        /// Tuple of literals, aliased to names of the new columns.
+        values: Box<Expr>,
+    },
+    Unpivot {
+        /// Columns that are retained (all except `columns`).
+        retained: Box<Expr>,
+        columns: Box<Expr>,
+        names_to: Box<Expr>,
+        values_to: Box<Expr>,
+    },
 }
 
 /// A reference to a table that is not in scope of this query.
@@ -139,10 +139,11 @@ pub fn fold_window<F: ?Sized + PlFold>(fold: &mut F, window: WindowFrame) -> Res
     })
 }
 
-pub fn fold_range<F: ?Sized + PlFold>(fold: &mut F, Range { start, end }: Range) -> Result<Range> {
+pub fn fold_range<F: ?Sized + PlFold>(fold: &mut F, range: Range) -> Result<Range> {
     Ok(Range {
-        start: fold_optional_box(fold, start)?,
-        end: fold_optional_box(fold, end)?,
+        start: fold_optional_box(fold, range.start)?,
+        end: fold_optional_box(fold, range.end)?,
+        end_exclusive: range.end_exclusive,
     })
 }
 
@@ -248,15 +249,27 @@ pub fn fold_transform_kind<T: ?Sized + PlFold>(
         Sort { by } => Sort {
             by: fold_column_sorts(fold, by)?,
         },
-        Take { range } => Take {
+        Take { range, percent } => Take {
             range: fold_range(fold, range)?,
+            percent,
         },
         Join { side, with, filter } => Join {
             side,
             with: Box::new(fold.fold_expr(*with)?),
             filter: Box::new(fold.fold_expr(*filter)?),
         },
-        Append(bottom) => Append(Box::new(fold.fold_expr(*bottom)?)),
+        Append { bottom, by_name } => Append {
+            bottom: Box::new(fold.fold_expr(*bottom)?),
+            by_name,
+        },
+        Except { bottom, all } => Except {
+            bottom: Box::new(fold.fold_expr(*bottom)?),
+            all,
+        },
+        Intersect { bottom, all } => Intersect {
+            bottom: Box::new(fold.fold_expr(*bottom)?),
+            all,
+        },
         Group { by, pipeline } => Group {
             by: Box::new(fold.fold_expr(*by)?),
             pipeline: Box::new(fold.fold_expr(*pipeline)?),
@@ -271,6 +284,28 @@ pub fn fold_transform_kind<T: ?Sized + PlFold>(
             pipeline: Box::new(fold.fold_expr(*pipeline)?),
         },
         Loop(pipeline) => Loop(Box::new(fold.fold_expr(*pipeline)?)),
+        Pivot {
+            by,
+            names_from,
+            values_from,
+            values,
+        } => Pivot {
+            by: Box::new(fold.fold_expr(*by)?),
+            names_from: Box::new(fold.fold_expr(*names_from)?),
+            values_from: Box::new(fold.fold_expr(*values_from)?),
+            values: Box::new(fold.fold_expr(*values)?),
+        },
+        Unpivot {
+            retained,
+            columns,
+            names_to,
+            values_to,
+        } => Unpivot {
+            retained: Box::new(fold.fold_expr(*retained)?),
+            columns: Box::new(fold.fold_expr(*columns)?),
+            names_to: Box::new(fold.fold_expr(*names_to)?),
+            values_to: Box::new(fold.fold_expr(*values_to)?),
+        },
     })
 }
 
@@ -0,0 +1,195 @@
+//! Constructors for building RQ programmatically, without going through PRQL.
+//!
+//! ```
+//! use prqlc::ir::rq::{new_binop, Expr, RelationalQuery};
+//! use prqlc::Options;
+//!
+//! let query = RelationalQuery::from_table("employees", &["name", "age"]);
+//! let age = query.column("age").unwrap();
+//! let query = query
+//!     .filter(new_binop(age, "std.gt", Expr::from(30)))
+//!     .select(&["name"])
+//!     .unwrap();
+//!
+//! let sql = prqlc::rq_to_sql(query, &Options::default().no_format().no_signature()).unwrap();
+//! assert_eq!(sql, "SELECT name FROM employees WHERE age > 30");
+//! ```
+
+use itertools::Itertools;
+use prqlc_parser::lexer::lr::Literal;
+
+use super::*;
+use crate::utils::IdGenerator;
+use crate::{Error, Reason, Result};
+
+impl RelationalQuery {
+    /// Creates a query that reads the given columns from a table.
+    ///
+    /// The table name may be qualified with a schema, as in `my_schema.my_table`.
+    pub fn from_table(table: &str, columns: &[&str]) -> Self {
+        let tid = TId::from(0);
+        let table = Ident::from_path(table.split('.').map(str::to_string).collect());
+        let columns = columns
+            .iter()
+            .map(|c| RelationColumn::Single(Some(c.to_string())))
+            .collect_vec();
+
+        let table_ref = TableRef {
+            source: tid,
+            columns: (columns.iter().cloned())
+                .zip((0..).map(CId::from))
+                .collect(),
+            name: Some(table.name.clone()),
+            span: None,
+        };
+        let output = table_ref.columns.iter().map(|(_, cid)| *cid).collect();
+
+        RelationalQuery {
+            def: QueryDef::default(),
+            tables: vec![TableDecl {
+                id: tid,
+                name: Some(table.name.clone()),
+                relation: Relation {
+                    kind: RelationKind::ExternRef(TableExternRef::LocalTable(table)),
+                    columns: columns.clone(),
+                },
+            }],
+            relation: Relation {
+                kind: RelationKind::Pipeline(vec![
+                    Transform::From(table_ref),
+                    Transform::Select(output),
+                ]),
+                columns,
+            },
+            into: None,
+        }
+    }
+
+    /// Returns a reference to an output column of the query.
+    pub fn column(&self, name: &str) -> Option<Expr> {
+        let position = self
+            .relation
+            .columns
+            .iter()
+            .position(|c| matches!(c, RelationColumn::Single(Some(n)) if n == name))?;
+        let cid = self.output_cids()[position];
+        Some(Expr::from(ExprKind::ColumnRef(cid)))
+    }
+
+    /// Retains only the rows for which the condition holds.
+    pub fn filter(self, condition: Expr) -> Self {
+        self.push_transform(Transform::Filter(condition))
+    }
+
+    /// Adds a new column computed from an expression.
+    pub fn derive(self, name: &str, expr: Expr) -> Self {
+        let (mut cid_gen, _, mut query) = IdGenerator::load(self);
+        let id = cid_gen.gen();
+
+        query
+            .relation
+            .columns
+            .push(RelationColumn::Single(Some(name.to_string())));
+        let mut query = query.push_transform(Transform::Compute(Compute {
+            id,
+            expr,
+            window: None,
+            is_aggregation: false,
+        }));
+        query.output_cids_mut().push(id);
+        query
+    }
+
+    /// Retains only the given columns, in the given order.
+    pub fn select(mut self, names: &[&str]) -> Result<Self> {
+        let mut columns = Vec::with_capacity(names.len());
+        let mut cids = Vec::with_capacity(names.len());
+        for name in names {
+            let Some(ExprKind::ColumnRef(cid)) = self.column(name).map(|e| e.kind) else {
+                return Err(Error::new(Reason::NotFound {
+                    name: name.to_string(),
+                    namespace: "column".to_string(),
+                }));
+            };
+            columns.push(RelationColumn::Single(Some(name.to_string())));
+            cids.push(cid);
+        }
+
+        self.relation.columns = columns;
+        *self.output_cids_mut() = cids;
+        Ok(self)
+    }
+
+    /// Inserts a transform before the final [Transform::Select] of the main pipeline.
+    fn push_transform(mut self, transform: Transform) -> Self {
+        let pipeline = self.relation.kind.as_pipeline_mut().unwrap();
+        let select = pipeline.pop().unwrap();
+        pipeline.push(transform);
+        pipeline.push(select);
+        self
+    }
+
+    fn output_cids(&self) -> &Vec<CId> {
+        let pipeline = self.relation.kind.as_pipeline().unwrap();
+        pipeline.last().unwrap().as_select().unwrap()
+    }
+
+    fn output_cids_mut(&mut self) -> &mut Vec<CId> {
+        let pipeline = self.relation.kind.as_pipeline_mut().unwrap();
+        pipeline.last_mut().unwrap().as_select_mut().unwrap()
+    }
+}
+
+impl From<ExprKind> for Expr {
+    fn from(kind: ExprKind) -> Self {
+        Expr { kind, span: None }
+    }
+}
+
+impl From<Literal> for Expr {
+    fn from(literal: Literal) -> Self {
+        Expr::from(ExprKind::Literal(literal))
+    }
+}
+
+impl From<i64> for Expr {
+    fn from(value: i64) -> Self {
+        Expr::from(Literal::Integer(value))
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(value: &str) -> Self {
+        Expr::from(Literal::String(value.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot;
+
+    use super::*;
+    use crate::Options;
+
+    #[test]
+    fn test_derive_and_select() {
+        let query = RelationalQuery::from_table("my_schema.employees", &["name", "salary"]);
+        let salary = query.column("salary").unwrap();
+        let query = query
+            .derive("bonus", new_binop(salary, "std.mul", Expr::from(2)))
+            .select(&["bonus", "name"])
+            .unwrap();
+
+        let opts = Options::default().no_signature();
+        assert_snapshot!(crate::rq_to_sql(query.clone(), &opts).unwrap(), @r"
+        SELECT
+          salary * 2 AS bonus,
+          name
+        FROM
+          my_schema.employees
+        ");
+
+        let err = query.select(&["salary"]).unwrap_err();
+        assert_snapshot!(err.reason.to_string(), @"column `salary` not found");
+    }
+}
@@ -4,7 +4,7 @@ use prqlc_parser::lexer::lr::Literal;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use super::CId;
+use super::{CId, TId};
 use crate::Span;
 
 /// Analogous to [crate::ir::pl::Expr], but with fewer kinds.
@@ -43,6 +43,10 @@ pub enum ExprKind {
     Param(String),
 
     Array(Vec<Expr>),
+
+    /// A relation with a single column, used as an expression (for example,
+    /// as the pattern of `in`).
+    SubQuery(TId),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, JsonSchema)]
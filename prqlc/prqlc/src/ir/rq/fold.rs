@@ -78,6 +78,7 @@ fn fold_window<F: ?Sized + RqFold>(fold: &mut F, w: Window) -> Result<Window> {
             range: Range {
                 start: w.frame.range.start.map(|x| fold.fold_expr(x)).transpose()?,
                 end: w.frame.range.end.map(|x| fold.fold_expr(x)).transpose()?,
+                end_exclusive: w.frame.range.end_exclusive,
             },
         },
         partition: fold.fold_cids(w.partition)?,
@@ -121,6 +122,7 @@ pub fn fold_relation_kind<F: ?Sized + RqFold>(
 pub fn fold_table_ref<F: ?Sized + RqFold>(fold: &mut F, table_ref: TableRef) -> Result<TableRef> {
     Ok(TableRef {
         name: table_ref.name,
+        span: table_ref.span,
         source: table_ref.source,
         columns: table_ref
             .columns
@@ -144,6 +146,7 @@ pub fn fold_query<F: ?Sized + RqFold>(
             .into_iter()
             .map(|t| fold.fold_table(t))
             .try_collect()?,
+        into: query.into,
     })
 }
 
@@ -178,18 +181,53 @@ pub fn fold_transform<T: ?Sized + RqFold>(
             partition: fold.fold_cids(take.partition)?,
             sort: fold_column_sorts(fold, take.sort)?,
             range: take.range,
+            percent: take.percent,
         }),
         Join { side, with, filter } => Join {
             side,
             with: fold.fold_table_ref(with)?,
             filter: fold.fold_expr(filter)?,
         },
-        Append(bottom) => Append(fold.fold_table_ref(bottom)?),
+        Append { bottom, by_name } => Append {
+            bottom: fold.fold_table_ref(bottom)?,
+            by_name,
+        },
+        Except { bottom, all } => Except {
+            bottom: fold.fold_table_ref(bottom)?,
+            all,
+        },
+        Intersect { bottom, all } => Intersect {
+            bottom: fold.fold_table_ref(bottom)?,
+            all,
+        },
         Loop(transforms) => Loop(fold_transforms(fold, transforms)?),
+        Pivot(pivot) => Pivot(fold_pivot(fold, pivot)?),
+        Unpivot(unpivot) => Unpivot(fold_unpivot(fold, unpivot)?),
     };
     Ok(transform)
 }
 
+pub fn fold_pivot<T: ?Sized + RqFold>(fold: &mut T, pivot: Pivot) -> Result<Pivot> {
+    Ok(Pivot {
+        partition: fold.fold_cids(pivot.partition)?,
+        names_from: fold.fold_cid(pivot.names_from)?,
+        values_from: fold.fold_cid(pivot.values_from)?,
+        func: pivot.func,
+        columns: (pivot.columns.into_iter())
+            .map(|(value, name, cid)| -> Result<_> { Ok((value, name, fold.fold_cid(cid)?)) })
+            .try_collect()?,
+    })
+}
+
+pub fn fold_unpivot<T: ?Sized + RqFold>(fold: &mut T, unpivot: Unpivot) -> Result<Unpivot> {
+    Ok(Unpivot {
+        retained: fold.fold_cids(unpivot.retained)?,
+        columns: fold.fold_cids(unpivot.columns)?,
+        names_to: (unpivot.names_to.0, fold.fold_cid(unpivot.names_to.1)?),
+        values_to: (unpivot.values_to.0, fold.fold_cid(unpivot.values_to.1)?),
+    })
+}
+
 pub fn fold_column_sorts<T: ?Sized + RqFold>(
     fold: &mut T,
     sorts: Vec<ColumnSort<CId>>,
@@ -221,6 +259,7 @@ pub fn fold_expr_kind<F: ?Sized + RqFold>(fold: &mut F, kind: ExprKind) -> Resul
             args: args.into_iter().map(|a| fold.fold_expr(a)).try_collect()?,
         },
         ExprKind::Param(id) => ExprKind::Param(id),
+        ExprKind::SubQuery(tid) => ExprKind::SubQuery(tid),
 
         ExprKind::Literal(_) => kind,
         ExprKind::Array(exprs) => {
@@ -14,9 +14,11 @@ use prqlc_parser::lexer::lr;
 pub use transform::*;
 pub use utils::*;
 
-use super::pl::QueryDef;
 use super::pl::TableExternRef;
+use super::pl::{Ident, QueryDef};
+use crate::Span;
 
+mod builder;
 mod expr;
 mod fold;
 mod ids;
@@ -29,6 +31,11 @@ pub struct RelationalQuery {
 
     pub tables: Vec<TableDecl>,
     pub relation: Relation,
+
+    /// Table that the main relation is written into, when the main pipeline
+    /// ends with `into schema.table`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub into: Option<Ident>,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
@@ -98,4 +105,8 @@ pub struct TableRef {
 
     /// Name hint for relation within this pipeline (table alias)
     pub name: Option<String>,
+
+    /// Span of the PRQL expression that referenced the table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
 }
@@ -27,8 +27,27 @@ pub enum Transform {
         with: TableRef,
         filter: Expr,
     },
-    Append(TableRef),
+    Append {
+        bottom: TableRef,
+        /// When set, columns are matched by name instead of by position.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        by_name: bool,
+    },
+    Except {
+        bottom: TableRef,
+        /// When set, duplicate rows are retained (`EXCEPT ALL`).
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        all: bool,
+    },
+    Intersect {
+        bottom: TableRef,
+        /// When set, duplicate rows are retained (`INTERSECT ALL`).
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        all: bool,
+    },
     Loop(Vec<Transform>),
+    Pivot(Pivot),
+    Unpivot(Unpivot),
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
@@ -36,6 +55,46 @@ pub struct Take {
     pub range: Range,
     pub partition: Vec<CId>,
     pub sort: Vec<ColumnSort<CId>>,
+
+    /// When set, approximately this percentage of rows is taken instead of
+    /// the `range`, which is unbounded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent: Option<i64>,
+}
+
+/// Turns values of one column into new columns.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Pivot {
+    /// Columns that are retained and identify the rows of the result.
+    pub partition: Vec<CId>,
+
+    /// Column whose values determine the new columns.
+    pub names_from: CId,
+
+    /// Column that is aggregated into the new columns.
+    pub values_from: CId,
+
+    /// Name of the aggregation function (i.e. `std.sum`).
+    pub func: String,
+
+    /// New columns: the value they are created for, their name and their id.
+    pub columns: Vec<(lr::Literal, String, CId)>,
+}
+
+/// Turns columns into rows of name-value pairs.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Unpivot {
+    /// Columns that are retained as they are.
+    pub retained: Vec<CId>,
+
+    /// Columns that are turned into rows.
+    pub columns: Vec<CId>,
+
+    /// New column that contains names of the unpivoted columns.
+    pub names_to: (String, CId),
+
+    /// New column that contains values of the unpivoted columns.
+    pub values_to: (String, CId),
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
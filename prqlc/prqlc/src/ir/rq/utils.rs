@@ -1,5 +1,7 @@
-use super::Expr;
-use super::ExprKind;
+use itertools::Itertools;
+
+use super::{fold_expr_kind, Expr, ExprKind, RelationalQuery, RqFold};
+use crate::{Result, Span};
 
 pub fn new_binop(left: Expr, operator_name: &str, right: Expr) -> Expr {
     Expr {
@@ -23,3 +25,29 @@ pub fn maybe_binop(left: Option<Expr>, operator_name: &str, right: Option<Expr>)
         (left, right) => left.or(right),
     }
 }
+
+/// Names of the parameters of the query, such as `1` of `$1`, without
+/// duplicates and in the order of their first occurrence in the source.
+pub fn collect_params(query: RelationalQuery) -> Vec<String> {
+    let mut collector = ParamCollector::default();
+    collector.fold_query(query).unwrap();
+
+    let mut params = collector.params;
+    params.sort_by_key(|(_, span)| (span.is_none(), span.map(|s| (s.source_id, s.start))));
+    params.into_iter().map(|(name, _)| name).unique().collect()
+}
+
+#[derive(Default)]
+struct ParamCollector {
+    params: Vec<(String, Option<Span>)>,
+}
+
+impl RqFold for ParamCollector {
+    fn fold_expr(&mut self, mut expr: Expr) -> Result<Expr> {
+        if let ExprKind::Param(name) = &expr.kind {
+            self.params.push((name.clone(), expr.span));
+        }
+        expr.kind = fold_expr_kind(self, expr.kind)?;
+        Ok(expr)
+    }
+}
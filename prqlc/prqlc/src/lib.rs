@@ -104,7 +104,9 @@ use serde::{Deserialize, Serialize};
 use strum::VariantNames;
 
 pub use error_message::{ErrorMessage, ErrorMessages, SourceLocation};
-pub use prqlc_parser::error::{Error, ErrorSource, Errors, MessageKind, Reason, WithErrorInfo};
+pub use prqlc_parser::error::{
+    Error, ErrorCode, ErrorSource, Errors, MessageKind, Reason, WithErrorInfo,
+};
 pub use prqlc_parser::lexer::lr;
 pub use prqlc_parser::parser::pr;
 pub use prqlc_parser::span::Span;
@@ -196,39 +198,309 @@ pub fn compiler_version() -> Version {
 /// [`sql::Dialect`](sql/enum.Dialect.html) for options and supported SQL
 /// dialects.
 pub fn compile(prql: &str, options: &Options) -> Result<String, ErrorMessages> {
-    let sources = SourceTree::from(prql);
+    compile_with_warnings(prql, options).map(|res| res.output)
+}
+
+/// Output of [compile_with_warnings].
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    /// The compiled SQL.
+    pub output: String,
+
+    /// Non-fatal messages (warnings and lints) produced during compilation.
+    pub warnings: Vec<ErrorMessage>,
+
+    /// Names of the parameters of the query, such as `1` of `$1`, which are
+    /// left in the SQL as placeholders to be bound when executing it. They
+    /// are ordered by their first occurrence in the query.
+    pub params: Vec<String>,
+}
+
+/// Compile a PRQL string into a SQL string, returning the warnings that were
+/// produced along the way.
+///
+/// Same as [compile], but instead of discarding warnings, they are composed
+/// in the same way as errors and returned alongside the SQL.
+pub fn compile_with_warnings(
+    prql: &str,
+    options: &Options,
+) -> Result<CompileOutput, ErrorMessages> {
+    compile_with_std(prql, options, None)
+}
+
+/// Compiles with an already resolved std module, or resolves it from scratch.
+fn compile_with_std(
+    prql: &str,
+    options: &Options,
+    std: Option<&semantic::ResolvedStd>,
+) -> Result<CompileOutput, ErrorMessages> {
+    let sources = query_sources(prql, options);
+    let compose = |messages: ErrorMessages| compose_messages(messages, &sources, options);
+
+    if let Target::Prql = options.target {
+        let pl = parser::parse(&sources).map_err(|e| compose(ErrorMessages::from(e)))?;
+        return Ok(CompileOutput {
+            output: pl_to_prql(&pl)?,
+            warnings: Vec::new(),
+            params: Vec::new(),
+        });
+    }
+
+    let main_path = main_path(options);
 
     Ok(&sources)
         .and_then(parser::parse)
+        .map(|ast| merge_prelude(ast, options))
         .and_then(|ast| {
-            semantic::resolve_and_lower(ast, &[], None)
+            resolve(ast, options, std)
+                .and_then(|root_module| {
+                    semantic::lower_with_warnings(root_module, &main_path, None)
+                })
                 .map_err(|e| e.with_source(ErrorSource::NameResolver).into())
         })
-        .and_then(|rq| {
-            sql::compile(rq, options).map_err(|e| e.with_source(ErrorSource::SQL).into())
+        .and_then(|(rq, warnings)| {
+            let params = ir::rq::collect_params(rq.clone());
+            let output = sql::compile(rq, options)
+                .map_err(|e| Errors::from(e.with_source(ErrorSource::SQL)))?;
+            Ok(CompileOutput {
+                output,
+                warnings: compose(ErrorMessages::from(Errors(warnings))).inner,
+                params,
+            })
         })
-        .map_err(|e| {
-            let error_messages = ErrorMessages::from(e).composed(&sources);
-            match options.display {
-                DisplayOptions::AnsiColor => error_messages,
-                DisplayOptions::Plain => ErrorMessages {
-                    inner: error_messages
-                        .inner
-                        .into_iter()
-                        .map(|e| ErrorMessage {
-                            display: e.display.map(|s| strip_str(&s).to_string()),
-                            ..e
-                        })
-                        .collect(),
-                },
-            }
+        .map_err(|e| compose(ErrorMessages::from(e)))
+}
+
+/// Check a PRQL string for errors, without generating SQL, returning the
+/// warnings.
+///
+/// This parses the query and runs semantic analysis, so it's cheaper than
+/// [compile], which makes it suitable for checking queries while they are
+/// edited. Errors which are raised only when generating SQL, such as of a
+/// function which isn't supported by the dialect, are not reported.
+///
+/// ```
+/// use prqlc::{validate, Options};
+///
+/// let opts = Options::default();
+/// assert!(validate("from employees | select {name}", &opts).is_ok());
+/// assert!(validate("from employees | select {name} | filter age > 30", &opts).is_err());
+/// ```
+pub fn validate(prql: &str, options: &Options) -> Result<Vec<ErrorMessage>, ErrorMessages> {
+    validate_with_std(prql, options, None)
+}
+
+fn validate_with_std(
+    prql: &str,
+    options: &Options,
+    std: Option<&semantic::ResolvedStd>,
+) -> Result<Vec<ErrorMessage>, ErrorMessages> {
+    let sources = query_sources(prql, options);
+    let compose = |messages: ErrorMessages| compose_messages(messages, &sources, options);
+
+    Ok(&sources)
+        .and_then(parser::parse)
+        .map(|ast| merge_prelude(ast, options))
+        .and_then(|ast| {
+            resolve(ast, options, std)
+                .and_then(|root_module| {
+                    semantic::lower_with_warnings(root_module, &main_path(options), None)
+                })
+                .map(|(_, warnings)| warnings)
+                .map_err(|e| e.with_source(ErrorSource::NameResolver).into())
+        })
+        .map(|warnings| compose(ErrorMessages::from(Errors(warnings))).inner)
+        .map_err(|e| compose(ErrorMessages::from(e)))
+}
+
+/// Composes messages with the sources, stripping colors when they are not
+/// to be displayed.
+fn compose_messages(
+    messages: ErrorMessages,
+    sources: &SourceTree,
+    options: &Options,
+) -> ErrorMessages {
+    let messages = messages.composed(sources);
+    match options.display {
+        DisplayOptions::AnsiColor => messages,
+        DisplayOptions::Plain => ErrorMessages {
+            inner: messages
+                .inner
+                .into_iter()
+                .map(|e| ErrorMessage {
+                    display: e.display.map(|s| strip_str(&s).to_string()),
+                    ..e
+                })
+                .collect(),
+        },
+    }
+}
+
+/// Compile a PRQL string into a SQL string, also returning a source map from
+/// byte ranges of the SQL to spans of the PRQL that produced them.
+///
+/// This allows tooling to highlight which PRQL produced a selected part of the
+/// SQL, down to the pipeline stages which produced a CTE.
+///
+/// ```
+/// use prqlc::{compile_with_source_map, Options};
+///
+/// let prql = "from employees | filter age > 30";
+/// let opts = Options::default().no_format().no_signature();
+/// let (sql, source_map) = compile_with_source_map(prql, &opts).unwrap();
+/// assert_eq!(sql, "SELECT * FROM employees WHERE age > 30");
+///
+/// // the `>` of the SQL
+/// let span = source_map.lookup(34..35).unwrap();
+/// assert_eq!(&prql[span.start..span.end], "age > 30");
+/// ```
+pub fn compile_with_source_map(
+    prql: &str,
+    options: &Options,
+) -> Result<(String, sql::SourceMap), ErrorMessages> {
+    let sources = query_sources(prql, options);
+    let compose = |messages: ErrorMessages| compose_messages(messages, &sources, options);
+
+    Ok(&sources)
+        .and_then(parser::parse)
+        .map(|ast| merge_prelude(ast, options))
+        .and_then(|ast| {
+            resolve(ast, options, None)
+                .and_then(|root_module| {
+                    semantic::lower_with_warnings(root_module, &main_path(options), None)
+                })
+                .map(|(rq, _)| rq)
+                .map_err(|e| e.with_source(ErrorSource::NameResolver).into())
         })
+        .and_then(|rq| {
+            sql::compile_with_source_map(rq, options)
+                .map_err(|e| Errors::from(e.with_source(ErrorSource::SQL)))
+        })
+        .map_err(|e| compose(ErrorMessages::from(e)))
+}
+
+/// Sources of the query, including the custom std module of
+/// [Options::std_lib], which is declared by the `std.prql` file.
+fn query_sources(prql: &str, options: &Options) -> SourceTree {
+    let mut sources = SourceTree::from(prql);
+    if let (Target::Sql(_), StdLibMode::Custom(std_source)) = (&options.target, &options.std_lib) {
+        sources.insert(PathBuf::from("std.prql"), std_source.clone());
+    }
+    if let (Target::Sql(_), Some(prelude)) = (&options.target, &options.prelude) {
+        // the root of the prelude is its only file, or the one with an empty path
+        let is_root = |path: &PathBuf| prelude.sources.len() == 1 || path.as_os_str() == "";
+        for (path, source) in &prelude.sources {
+            let path = if is_root(path) {
+                PathBuf::from(format!("{PRELUDE}.prql"))
+            } else {
+                PathBuf::from(PRELUDE).join(path)
+            };
+            sources.insert(path, source.clone());
+        }
+    }
+    sources
+}
+
+const PRELUDE: &str = "prelude";
+
+/// Moves the declarations of [Options::prelude] from their module into the
+/// root module, so the query can use them without importing.
+fn merge_prelude(mut ast: pr::ModuleDef, options: &Options) -> pr::ModuleDef {
+    if options.prelude.is_none() {
+        return ast;
+    }
+    let position = (ast.stmts.iter()).position(|s| parser::is_mod_def_for(s, PRELUDE));
+    if let Some(position) = position {
+        let prelude = ast.stmts.remove(position).kind.into_module_def().unwrap();
+        ast.stmts.splice(0..0, prelude.stmts);
+    }
+    ast
+}
+
+/// Runs semantic analysis on the query, with the std module loaded as
+/// configured by [Options::std_lib].
+fn resolve(
+    ast: pr::ModuleDef,
+    options: &Options,
+    std: Option<&semantic::ResolvedStd>,
+) -> Result<ir::decl::RootModule, Error> {
+    match (&options.std_lib, std) {
+        (StdLibMode::Full, Some(std)) => std.resolve(ast),
+        (StdLibMode::Full, None) => semantic::resolve(ast),
+        // a custom std module is a part of the sources
+        (StdLibMode::None | StdLibMode::Custom(_), _) => semantic::resolve_without_std_lib(ast),
+    }
+}
+
+/// Path of the relational variable to compile, split from [Options::main].
+fn main_path(options: &Options) -> Vec<String> {
+    (options.main.iter())
+        .flat_map(|main| main.split('.'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// A compiler that resolves the standard library once and reuses it for every
+/// query, which makes compiling many queries (for example in a long-running
+/// service) faster than calling [compile] for each of them.
+///
+/// Compiling only borrows the compiler and it is `Send + Sync`, so a single
+/// instance can be shared between threads.
+///
+/// ```
+/// use prqlc::{Compiler, Options};
+///
+/// let compiler = Compiler::new(Options::default().no_format().no_signature()).unwrap();
+/// let sql = compiler.compile("from employees | take 5").unwrap();
+/// assert_eq!(sql, "SELECT * FROM employees LIMIT 5");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compiler {
+    options: Options,
+    /// `None` unless [Options::std_lib] is [StdLibMode::Full].
+    std: Option<semantic::ResolvedStd>,
+}
+
+impl Compiler {
+    /// Creates a compiler, resolving the standard library unless
+    /// [Options::std_lib] excludes it.
+    pub fn new(options: Options) -> Result<Self, ErrorMessages> {
+        let std = (options.std_lib == StdLibMode::Full)
+            .then(semantic::ResolvedStd::new)
+            .transpose()
+            .map_err(|e| ErrorMessages::from(e.with_source(ErrorSource::NameResolver)))?;
+        Ok(Compiler { options, std })
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Compile a PRQL string into a SQL string. Same as [compile].
+    pub fn compile(&self, prql: &str) -> Result<String, ErrorMessages> {
+        self.compile_with_warnings(prql).map(|res| res.output)
+    }
+
+    /// Compile a PRQL string into a SQL string, returning the warnings that
+    /// were produced along the way. Same as [compile_with_warnings].
+    pub fn compile_with_warnings(&self, prql: &str) -> Result<CompileOutput, ErrorMessages> {
+        compile_with_std(prql, &self.options, self.std.as_ref())
+    }
+
+    /// Check a PRQL string for errors, without generating SQL, returning the
+    /// warnings. Same as [validate].
+    pub fn validate(&self, prql: &str) -> Result<Vec<ErrorMessage>, ErrorMessages> {
+        validate_with_std(prql, &self.options, self.std.as_ref())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Target {
     /// If `None` is used, dialect is extracted from `target` query header.
     Sql(Option<sql::Dialect>),
+
+    /// Formats the query and emits it as PRQL, rather than compiling it.
+    Prql,
 }
 
 impl Default for Target {
@@ -244,6 +516,7 @@ impl Target {
         let dialects = sql::Dialect::VARIANTS;
         names.extend(dialects.iter().map(|d| format!("sql.{d}")));
 
+        names.push("prql".to_string());
         names
     }
 }
@@ -252,6 +525,10 @@ impl FromStr for Target {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Target, Self::Err> {
+        if s == "prql" {
+            return Ok(Target::Prql);
+        }
+
         if let Some(dialect) = s.strip_prefix("sql.") {
             if dialect == "any" {
                 return Ok(Target::Sql(None));
@@ -301,6 +578,136 @@ pub struct Options {
     /// - Strip colors from the output (possibly also with a library such as
     ///   `anstream`).
     pub display: DisplayOptions,
+
+    /// Encoding of string literals in the generated SQL. With
+    /// [OutputEncoding::Ascii], non-ASCII characters are escaped using the
+    /// dialect's unicode escape syntax, for clients that can't handle them.
+    ///
+    /// Defaults to [OutputEncoding::Utf8].
+    #[serde(default)]
+    pub output_encoding: OutputEncoding,
+
+    /// Emit relations which are referenced only once as subqueries, instead of
+    /// as common table expressions in a `WITH` clause. Relations referenced
+    /// multiple times are still emitted as CTEs.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub inline_ctes: bool,
+
+    /// Statement which writes the result of a query ending with
+    /// `into schema.table` into that table.
+    ///
+    /// Defaults to [WriteMode::Insert].
+    #[serde(default)]
+    pub write_mode: WriteMode,
+
+    /// Format of date literals in the generated SQL, using the
+    /// [chrono strftime syntax](https://docs.rs/chrono/latest/chrono/format/strftime/index.html),
+    /// such as `%d.%m.%Y`. Time and timestamp literals are not affected.
+    ///
+    /// Defaults to ISO-8601 (`%Y-%m-%d`).
+    #[serde(default)]
+    pub date_format: Option<String>,
+
+    /// Place commas separating items of lists, such as the columns of a
+    /// `SELECT`, at the start of lines rather than at the end. Only applies
+    /// when `format` is enabled.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub leading_comma: bool,
+
+    /// Path of the relational variable to compile, such as `monthly_report`
+    /// or `reports.monthly`, for sources which declare multiple of them.
+    ///
+    /// Defaults to `None`, which compiles the `main` variable, or the
+    /// trailing pipeline of the query.
+    #[serde(default)]
+    pub main: Option<String>,
+
+    /// Result of aggregates, such as `sum`, over groups where all inputs are
+    /// null. With [AggregateNullHandling::Null], they yield NULL, as they do in
+    /// SQL.
+    ///
+    /// Defaults to [AggregateNullHandling::ZeroDefault].
+    #[serde(default)]
+    pub aggregate_null_handling: AggregateNullHandling,
+
+    /// Emit joins on an equality of equally named columns, such as
+    /// `join b (==id)`, as `JOIN b USING (id)` rather than
+    /// `JOIN b ON a.id = b.id`, where the dialect supports it.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub join_using: bool,
+
+    /// Emit SQL keywords, such as `SELECT` and `FROM`, in uppercase. When
+    /// false, they are emitted in lowercase, while identifiers and string
+    /// literals keep their case. Only applies when `format` is enabled.
+    ///
+    /// Defaults to true.
+    #[serde(default = "default_uppercase_keywords")]
+    pub uppercase_keywords: bool,
+
+    /// Order of CTEs in the `WITH` clause. Regardless of the order, each CTE
+    /// follows the CTEs it references.
+    ///
+    /// Defaults to [CteOrder::Reference].
+    #[serde(default)]
+    pub cte_order: CteOrder,
+
+    /// Emit `null` as `CAST(NULL AS type)` where its type is known from the
+    /// other branches of a `case`, as some dialects and prepared statements
+    /// require to infer the type of the result.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub explicit_null_cast: bool,
+
+    /// The standard library, which declares the functions of the `std`
+    /// module, such as `from` and `sum`.
+    ///
+    /// Defaults to [StdLibMode::Full].
+    #[serde(default)]
+    pub std_lib: StdLibMode,
+
+    /// Placement of NULLs by `sort`. Unless it's [NullsOrder::Dialect],
+    /// `NULLS FIRST` or `NULLS LAST` is emitted where it differs from the
+    /// default of the dialect.
+    ///
+    /// Defaults to [NullsOrder::Dialect].
+    #[serde(default)]
+    pub nulls_order: NullsOrder,
+
+    /// Declarations in PRQL, such as of functions, which the query can use
+    /// without importing them, as if it declared them itself. The other files
+    /// of the tree are its submodules, as in a project. They are reported in
+    /// error messages within `prelude`, such as `prelude.prql` for the root.
+    ///
+    /// Defaults to `None`.
+    #[serde(default)]
+    pub prelude: Option<SourceTree>,
+
+    /// Terminate the generated SQL with a semicolon, which some tools
+    /// require. It precedes the signature comment.
+    ///
+    /// Defaults to false.
+    #[serde(default)]
+    pub trailing_semicolon: bool,
+
+    /// Text of the signature comment, such as `Generated for ticket 123`,
+    /// emitted instead of the compiler version. Each line of the text is
+    /// prefixed with `-- `. An empty text omits the signature comment. Only
+    /// applies when `signature_comment` is enabled.
+    ///
+    /// Defaults to `None`, which emits the compiler version.
+    #[serde(default)]
+    pub signature_text: Option<String>,
+}
+
+fn default_uppercase_keywords() -> bool {
+    true
 }
 
 impl Default for Options {
@@ -311,6 +718,22 @@ impl Default for Options {
             signature_comment: true,
             color: true,
             display: DisplayOptions::AnsiColor,
+            output_encoding: OutputEncoding::Utf8,
+            inline_ctes: false,
+            write_mode: WriteMode::Insert,
+            date_format: None,
+            leading_comma: false,
+            main: None,
+            aggregate_null_handling: AggregateNullHandling::ZeroDefault,
+            join_using: false,
+            uppercase_keywords: true,
+            cte_order: CteOrder::Reference,
+            explicit_null_cast: false,
+            std_lib: StdLibMode::Full,
+            nulls_order: NullsOrder::Dialect,
+            prelude: None,
+            trailing_semicolon: false,
+            signature_text: None,
         }
     }
 }
@@ -349,6 +772,89 @@ impl Options {
         self.display = display;
         self
     }
+
+    pub fn with_output_encoding(mut self, output_encoding: OutputEncoding) -> Self {
+        self.output_encoding = output_encoding;
+        self
+    }
+
+    pub fn with_inline_ctes(mut self, inline_ctes: bool) -> Self {
+        self.inline_ctes = inline_ctes;
+        self
+    }
+
+    pub fn with_write_mode(mut self, write_mode: WriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
+
+    pub fn with_date_format(mut self, date_format: impl Into<String>) -> Self {
+        self.date_format = Some(date_format.into());
+        self
+    }
+
+    pub fn with_leading_comma(mut self, leading_comma: bool) -> Self {
+        self.leading_comma = leading_comma;
+        self
+    }
+
+    pub fn with_main(mut self, main: impl Into<String>) -> Self {
+        self.main = Some(main.into());
+        self
+    }
+
+    pub fn with_aggregate_null_handling(
+        mut self,
+        aggregate_null_handling: AggregateNullHandling,
+    ) -> Self {
+        self.aggregate_null_handling = aggregate_null_handling;
+        self
+    }
+
+    pub fn with_join_using(mut self, join_using: bool) -> Self {
+        self.join_using = join_using;
+        self
+    }
+
+    pub fn with_uppercase_keywords(mut self, uppercase_keywords: bool) -> Self {
+        self.uppercase_keywords = uppercase_keywords;
+        self
+    }
+
+    pub fn with_cte_order(mut self, cte_order: CteOrder) -> Self {
+        self.cte_order = cte_order;
+        self
+    }
+
+    pub fn with_explicit_null_cast(mut self, explicit_null_cast: bool) -> Self {
+        self.explicit_null_cast = explicit_null_cast;
+        self
+    }
+
+    pub fn with_std_lib(mut self, std_lib: StdLibMode) -> Self {
+        self.std_lib = std_lib;
+        self
+    }
+
+    pub fn with_nulls_order(mut self, nulls_order: NullsOrder) -> Self {
+        self.nulls_order = nulls_order;
+        self
+    }
+
+    pub fn with_prelude(mut self, prelude: impl Into<SourceTree>) -> Self {
+        self.prelude = Some(prelude.into());
+        self
+    }
+
+    pub fn with_trailing_semicolon(mut self, trailing_semicolon: bool) -> Self {
+        self.trailing_semicolon = trailing_semicolon;
+        self
+    }
+
+    pub fn with_signature_text(mut self, signature_text: impl Into<String>) -> Self {
+        self.signature_text = Some(signature_text.into());
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, strum::EnumString)]
@@ -361,6 +867,82 @@ pub enum DisplayOptions {
     AnsiColor,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[non_exhaustive]
+pub enum OutputEncoding {
+    /// String literals are emitted as they are
+    #[default]
+    Utf8,
+    /// Non-ASCII characters in string literals are escaped with the syntax of
+    /// the target dialect
+    Ascii,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[non_exhaustive]
+pub enum WriteMode {
+    /// `INSERT INTO table SELECT ...`
+    #[default]
+    Insert,
+    /// `CREATE TABLE table AS SELECT ...`
+    CreateTableAs,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[non_exhaustive]
+pub enum AggregateNullHandling {
+    /// Aggregates are wrapped in `COALESCE` to yield a default: 0 for `sum`,
+    /// true for `all`, false for `any` and an empty string for `concat_array`
+    #[default]
+    ZeroDefault,
+    /// Aggregates yield NULL
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[non_exhaustive]
+pub enum CteOrder {
+    /// In the order in which the main query references them, each preceded
+    /// by the CTEs it references
+    #[default]
+    Reference,
+    /// By depth of references: first the CTEs which don't reference other
+    /// CTEs, then the CTEs which only reference those, and so on
+    Topological,
+    /// In the order in which their relations appear in the source
+    Source,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[non_exhaustive]
+pub enum NullsOrder {
+    /// NULLs are placed where the dialect places them by default
+    #[default]
+    Dialect,
+    /// NULLs are placed first, regardless of the direction of the sort
+    First,
+    /// NULLs are placed last, regardless of the direction of the sort
+    Last,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum StdLibMode {
+    /// The standard library of the compiler
+    #[default]
+    Full,
+    /// No standard library, so only names declared by the query can be used
+    None,
+    /// The standard library from the given PRQL source, which is reported in
+    /// error messages as `std.prql`
+    Custom(String),
+}
+
 #[doc = include_str!("../README.md")]
 #[cfg(doctest)]
 pub struct ReadmeDoctests;
@@ -404,11 +986,32 @@ pub fn pl_to_rq_tree(
         .map_err(|e| e.with_source(ErrorSource::NameResolver).into())
 }
 
+/// Perform semantic analysis and return the columns of the main pipeline's
+/// final relation, with their names and inferred types.
+///
+/// A column's name or type is `None` when it can't be inferred. Columns which
+/// aren't known individually, such as those of a table without a declared
+/// type, are represented by [pr::TyTupleField::Wildcard].
+pub fn pl_to_types(pl: pr::ModuleDef) -> Result<Vec<pr::TyTupleField>, ErrorMessages> {
+    semantic::resolve_main_columns(pl, &[])
+        .map_err(|e| e.with_source(ErrorSource::NameResolver).into())
+}
+
 /// Generate SQL from RQ.
 pub fn rq_to_sql(rq: ir::rq::RelationalQuery, options: &Options) -> Result<String, ErrorMessages> {
     sql::compile(rq, options).map_err(|e| e.with_source(ErrorSource::SQL).into())
 }
 
+/// Generate SQL from RQ, also returning a source map: byte ranges of the SQL
+/// that were produced by PRQL expressions, paired with the spans of these
+/// expressions.
+pub fn rq_to_sql_with_source_map(
+    rq: ir::rq::RelationalQuery,
+    options: &Options,
+) -> Result<(String, sql::SourceMap), ErrorMessages> {
+    sql::compile_with_source_map(rq, options).map_err(|e| e.with_source(ErrorSource::SQL).into())
+}
+
 /// Generate PRQL code from PL AST
 pub fn pl_to_prql(pl: &pr::ModuleDef) -> Result<String, ErrorMessages> {
     Ok(codegen::WriteSource::write(&pl.stmts, codegen::WriteOpt::default()).unwrap())
@@ -443,6 +1046,52 @@ pub mod json {
     }
 }
 
+#[cfg(feature = "serde_yaml")]
+pub mod yaml {
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_yaml::with::singleton_map_recursive;
+
+    use super::*;
+
+    /// YAML serialization
+    pub fn from_pl(pl: &pr::ModuleDef) -> Result<String, ErrorMessages> {
+        to_string(pl)
+    }
+
+    /// YAML deserialization
+    pub fn to_pl(yaml: &str) -> Result<pr::ModuleDef, ErrorMessages> {
+        from_str(yaml)
+    }
+
+    /// YAML serialization
+    pub fn from_rq(rq: &ir::rq::RelationalQuery) -> Result<String, ErrorMessages> {
+        to_string(rq)
+    }
+
+    /// YAML deserialization
+    pub fn to_rq(yaml: &str) -> Result<ir::rq::RelationalQuery, ErrorMessages> {
+        from_str(yaml)
+    }
+
+    // YAML can't tag enums nested in other enums, so all enums are written
+    // as single-key maps, such as `String: foo` rather than `!String foo`.
+    fn to_string<T: Serialize>(value: &T) -> Result<String, ErrorMessages> {
+        let mut yaml = Vec::new();
+        let mut serializer = serde_yaml::Serializer::new(&mut yaml);
+        singleton_map_recursive::serialize(value, &mut serializer).map_err(convert_yaml_err)?;
+        Ok(String::from_utf8(yaml).unwrap())
+    }
+
+    fn from_str<T: DeserializeOwned>(yaml: &str) -> Result<T, ErrorMessages> {
+        singleton_map_recursive::deserialize(serde_yaml::Deserializer::from_str(yaml))
+            .map_err(convert_yaml_err)
+    }
+
+    fn convert_yaml_err(err: serde_yaml::Error) -> ErrorMessages {
+        ErrorMessages::from(Error::new_simple(err.to_string()))
+    }
+}
+
 /// All paths are relative to the project root.
 // We use `SourceTree` to represent both a single file (including a "file" piped
 // from stdin), and a collection of files. (Possibly this could be implemented
@@ -499,6 +1148,23 @@ impl SourceTree {
     }
 }
 
+impl<'de> Deserialize<'de> for SourceTree {
+    /// Deserializes the root and the sources, from which the source ids are
+    /// assigned again.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Sources {
+            root: Option<PathBuf>,
+            sources: HashMap<PathBuf, String>,
+        }
+        let Sources { root, sources } = Sources::deserialize(deserializer)?;
+        Ok(SourceTree::new(sources, root))
+    }
+}
+
 impl<S: ToString> From<S> for SourceTree {
     fn from(source: S) -> Self {
         SourceTree::single(PathBuf::from(""), source.to_string())
@@ -507,6 +1173,8 @@ impl<S: ToString> From<S> for SourceTree {
 
 /// Debugging and unstable API functions
 pub mod internal {
+    use itertools::Itertools;
+
     use super::*;
 
     /// Create column-level lineage graph
@@ -525,6 +1193,17 @@ pub mod internal {
         Ok(fc)
     }
 
+    /// Lex PRQL into LR, continuing past errors. Returns the tokens that
+    /// could be lexed, alongside the errors.
+    pub fn prql_to_tokens_recovery(prql: &str) -> (lr::Tokens, ErrorMessages) {
+        let sources = SourceTree::from(prql);
+        let source_id = *sources.source_ids.keys().exactly_one().unwrap();
+
+        let (tokens, errors) = prqlc_parser::lexer::lex_source_recovery(prql, source_id);
+        let errors = ErrorMessages::from(Errors(errors)).composed(&sources);
+        (lr::Tokens(tokens.unwrap_or_default()), errors)
+    }
+
     pub mod json {
         use super::*;
 
@@ -545,7 +1224,8 @@ pub mod internal {
 mod tests {
     use std::str::FromStr;
 
-    use insta::assert_debug_snapshot;
+    use insta::{assert_debug_snapshot, assert_snapshot};
+    use itertools::Itertools;
 
     use crate::pr::Ident;
     use crate::Target;
@@ -584,6 +1264,12 @@ mod tests {
         )
         ");
 
+        assert_debug_snapshot!(Target::from_str("prql"), @r"
+        Ok(
+            Prql,
+        )
+        ");
+
         assert_debug_snapshot!(Target::from_str("sql.poostgres"), @r#"
         Err(
             Error {
@@ -615,6 +1301,22 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn test_compile_target_prql() {
+        let opts = super::Options::default().with_target(Target::Prql);
+        let prql = "from x | select {a, b} | take 10";
+        assert_snapshot!(super::compile(prql, &opts).unwrap(), @r"
+        from x
+        select {a, b}
+        take 10
+        ");
+
+        let rq = super::prql_to_pl(prql).and_then(super::pl_to_rq).unwrap();
+        assert_snapshot!(super::rq_to_sql(rq, &opts).unwrap_err(), @r"
+        Error: target `prql` formats PRQL, so it cannot be used to compile RQ
+        ");
+    }
+
     /// Confirm that all target names can be parsed.
     #[test]
     fn test_target_names() {
@@ -623,4 +1325,283 @@ mod tests {
             .map(|name| Target::from_str(&name))
             .collect();
     }
+
+    #[cfg(feature = "serde_yaml")]
+    #[test]
+    fn test_yaml_roundtrip() {
+        let prql = r#"
+        let add_tax = func x <float> -> <float> x * 1.2
+        from employees
+        filter salary > 1000 && !(is_contractor)
+        derive {gross = add_tax salary, name = f"{first_name} {last_name}"}
+        group {department} (aggregate {total = sum gross})
+        sort {-total}
+        take 10
+        "#;
+
+        let pl = super::prql_to_pl(prql).unwrap();
+        let yaml = super::yaml::from_pl(&pl).unwrap();
+        assert_eq!(super::yaml::to_pl(&yaml).unwrap(), pl);
+
+        let rq = super::pl_to_rq(pl).unwrap();
+        let yaml = super::yaml::from_rq(&rq).unwrap();
+        assert_eq!(super::yaml::to_rq(&yaml).unwrap(), rq);
+    }
+
+    #[test]
+    fn test_pl_to_types() {
+        use crate::codegen::{WriteOpt, WriteSource};
+
+        let pl = super::prql_to_pl("from x | select {a, b = a + 1, c = 'hello'}").unwrap();
+        let columns: Vec<_> = super::pl_to_types(pl)
+            .unwrap()
+            .iter()
+            .map(|field| field.write(WriteOpt::default()).unwrap())
+            .collect();
+        assert_debug_snapshot!(columns, @r#"
+        [
+            "a = ?",
+            "b = ?",
+            "c = text",
+        ]
+        "#);
+
+        // types come from literals and from the return types of std functions
+        let pl = super::prql_to_pl("from x | select {a, b = a > 1, c = 42, d = math.pi}").unwrap();
+        let columns: Vec<_> = super::pl_to_types(pl)
+            .unwrap()
+            .iter()
+            .map(|field| field.write(WriteOpt::default()).unwrap())
+            .collect();
+        assert_debug_snapshot!(columns, @r#"
+        [
+            "a = ?",
+            "b = bool",
+            "c = int",
+            "d = float",
+        ]
+        "#);
+    }
+
+    #[test]
+    fn test_compile_with_pass_log() {
+        let (_, passes) = crate::debug::compile_with_pass_log(
+            "from x | select {a, b} | select {a}",
+            &super::Options::default().no_signature(),
+        )
+        .unwrap();
+        let fired: Vec<_> = passes.iter().filter(|p| p.changed).collect();
+        assert_debug_snapshot!(fired, @r#"
+        [
+            PassEvent {
+                name: "prune_inputs",
+                changed: true,
+            },
+        ]
+        "#);
+    }
+
+    #[test]
+    fn test_stage_sql() {
+        let stages = crate::debug::stage_sql(
+            r#"
+            from employees
+            filter country == "USA"
+            join departments (==dept_id)
+            group {departments.title} (aggregate {n = count this})
+            filter n > 2
+            sort {-n}
+            take 10
+            "#,
+            &super::Options::default(),
+        )
+        .unwrap();
+        let stages = stages
+            .into_iter()
+            .map(|(kind, sql)| format!("{kind}: {sql}"))
+            .join("\n");
+        assert_snapshot!(stages, @r"
+        From: FROM employees
+        Filter: WHERE country = 'USA'
+        Select: SELECT dept_id
+        From: FROM employees_0
+        Join: JOIN departments ON employees_0.dept_id = departments.dept_id
+        Aggregate: COUNT(*) AS n
+        Aggregate: GROUP BY departments.title
+        Filter: HAVING COUNT(*) > 2
+        Select: SELECT departments.title, COUNT(*) AS n
+        Sort: ORDER BY n DESC
+        Take: LIMIT 10
+        ");
+    }
+
+    #[test]
+    fn test_rq_to_debug_string() {
+        let rq = crate::prql_to_pl(
+            r#"
+            from e = employees
+            join d = departments (==dept_id)
+            filter e.salary > 1000
+            group {d.title} (aggregate {n = count this})
+            sort {-n}
+            take 10
+            "#,
+        )
+        .and_then(crate::pl_to_rq)
+        .unwrap();
+        assert_snapshot!(crate::debug::rq_to_debug_string(&rq), @r#"
+        table-0:
+          columns: [dept_id, title, *]
+          extern departments
+        table-1:
+          columns: [dept_id, salary, *]
+          extern employees
+        main:
+          columns: [title, n]
+          from table-1 as e [column-0 = dept_id, column-1 = salary, column-2 = *]
+          join inner table-0 as d [column-3 = dept_id, column-4 = title, column-5 = *] on std.eq(column-0, column-3)
+          filter std.gt(column-1, 1000)
+          compute column-6 = std.count(s"*") (aggregation)
+          aggregate [column-6] by [column-4]
+          sort [-column-6]
+          take ..10 sort [-column-6]
+          select [column-4, column-6]
+        "#);
+    }
+
+    #[test]
+    fn test_rq_to_sql_explained() {
+        let sources = crate::SourceTree::from(
+            r#"
+            from products
+            filter price > 10
+            take 20
+            derive {discounted = price * 0.9}
+            filter discounted < 50
+            take 10
+            filter discounted > 15
+            "#,
+        );
+        let opts = crate::Options::default().no_signature();
+        let sql = crate::prql_to_pl_tree(&sources)
+            .and_then(crate::pl_to_rq)
+            .and_then(|rq| crate::debug::rq_to_sql_explained(rq, &opts, &sources))
+            .unwrap();
+        assert_snapshot!(sql, @r"
+        -- from: from products filter price > 10 take 20 derive {discounted = price * 0.9}
+        WITH products_0 AS (
+          SELECT
+            *,
+            price * 0.9 AS discounted
+          FROM
+            products
+          WHERE
+            price > 10
+          LIMIT
+            20
+        ),
+        -- from: filter discounted < 50 take 10
+        products_1 AS (
+          SELECT
+            *
+          FROM
+            products_0
+          WHERE
+            discounted < 50
+          LIMIT
+            10
+        )
+        SELECT
+          *
+        FROM
+          products_1
+        WHERE
+          discounted > 15
+        ");
+    }
+
+    #[test]
+    fn test_resolution_trace() {
+        let trace = crate::debug::resolution_trace(
+            r#"
+            from e = employees
+            join d = departments (==dept_id)
+            select {e.name, d.title}
+            filter title != null
+            "#,
+        );
+        assert_snapshot!(trace, @r"
+        filter -> std.filter (function)
+        select -> std.select (function)
+        join -> std.join (function)
+        departments -> default_db.departments (table)
+        from -> std.from (function)
+        employees -> default_db.employees (table)
+        source -> _param.source (variable)
+        std.eq -> std.eq (function)
+        this.dept_id -> this.e.dept_id (column)
+        that.dept_id -> that.d.dept_id (column)
+        e.name -> this.e.name (column)
+        d.title -> this.d.title (column)
+        std.ne -> std.ne (function)
+        title -> this.d.title (column)
+        ");
+
+        let trace = crate::debug::resolution_trace(
+            r#"
+            from e = employees
+            join d = departments (==dept_id)
+            select {name}
+            "#,
+        );
+        assert_snapshot!(trace, @r"
+        select -> std.select (function)
+        join -> std.join (function)
+        departments -> default_db.departments (table)
+        from -> std.from (function)
+        employees -> default_db.employees (table)
+        source -> _param.source (variable)
+        std.eq -> std.eq (function)
+        this.dept_id -> this.e.dept_id (column)
+        that.dept_id -> that.d.dept_id (column)
+        error: Ambiguous name
+        hint: did you mean `d.name` or `e.name`?
+        ");
+    }
+
+    #[test]
+    fn test_rq_to_sql_with_source_map() {
+        let prql = "from albums\nfilter artist_id == 3\nderive {n = album_id + 1}";
+        let rq = crate::prql_to_pl(prql).and_then(crate::pl_to_rq).unwrap();
+        let options = super::Options::default().no_signature();
+        let (sql, source_map) = crate::rq_to_sql_with_source_map(rq, &options).unwrap();
+
+        let mapped = (source_map.mappings.into_iter())
+            .map(|(range, span)| format!("{:?} <- {:?}", &sql[range], &prql[span.start..span.end]))
+            .join("\n");
+        assert_snapshot!(mapped, @r#"
+        "album_id + 1" <- "album_id + 1"
+        "album_id" <- "album_id"
+        "1" <- "1"
+        "FROM\n  albums" <- "from albums"
+        "artist_id = 3" <- "artist_id == 3"
+        "artist_id" <- "artist_id"
+        "3" <- "3"
+        "#);
+    }
+
+    #[test]
+    fn test_compile_with_source_map() {
+        let prql = "from tracks\ntake 10\nfilter milliseconds > 1000";
+        let options = super::Options::default().no_signature();
+        let (sql, source_map) = crate::compile_with_source_map(prql, &options).unwrap();
+
+        // select the name of the CTE
+        let start = sql.find("tracks_0").unwrap();
+        let span = source_map.lookup(start..start + 8).unwrap();
+        assert_snapshot!(&prql[span.start..span.end], @r"
+        from tracks
+        take 10
+        ");
+    }
 }
@@ -6,7 +6,7 @@ use itertools::Itertools;
 use crate::debug;
 use crate::lr;
 use crate::pr;
-use crate::{Error, Errors, Result, SourceTree, WithErrorInfo};
+use crate::{Error, ErrorCode, Errors, Result, SourceTree, WithErrorInfo};
 
 pub fn parse(file_tree: &SourceTree) -> Result<pr::ModuleDef, Errors> {
     // register a new stage of the compiler
@@ -116,7 +116,7 @@ fn linearize_tree(tree: &SourceTree) -> Result<Vec<SourceFile>> {
             "Cannot find the root module within the following files:\n{file_names}"
         ))
         .push_hint("add a file that starts with uppercase letter to the root directory")
-        .with_code("E0002"));
+        .with_code(ErrorCode::E0002));
     }
 
     let mut sources: Vec<_> = Vec::with_capacity(tree.sources.len());
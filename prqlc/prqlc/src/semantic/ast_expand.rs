@@ -19,7 +19,7 @@ pub fn expand_expr(expr: pr::Expr) -> Result<pl::Expr> {
             e.alias = expr.alias.or(e.alias);
             return Ok(e);
         }
-        pr::ExprKind::Tuple(v) => pl::ExprKind::Tuple(expand_exprs(v)?),
+        pr::ExprKind::Tuple(v) => pl::ExprKind::Tuple(expand_tuple_fields(v)?),
         pr::ExprKind::Array(v) => pl::ExprKind::Array(expand_exprs(v)?),
 
         pr::ExprKind::Range(v) => expands_range(v)?,
@@ -68,6 +68,18 @@ pub fn expand_expr(expr: pr::Expr) -> Result<pl::Expr> {
                 })
                 .try_collect()?,
         ),
+        pr::ExprKind::Switch(v) => expand_switch(v)?,
+        pr::ExprKind::DeclaredColumns(v) => {
+            let columns = (v.columns.into_iter())
+                .map(|name| pr::TyTupleField::Single(Some(name), None))
+                .collect();
+
+            let mut e = expand_expr(*v.relation)?;
+            e.ty = Some(pr::Ty::relation(columns));
+            e.span = expr.span.or(e.span);
+            e.alias = expr.alias.or(e.alias);
+            return Ok(e);
+        }
         pr::ExprKind::Param(v) => pl::ExprKind::Param(v),
         pr::ExprKind::Internal(v) => pl::ExprKind::Internal(v),
     };
@@ -85,7 +97,34 @@ pub fn expand_expr(expr: pr::Expr) -> Result<pl::Expr> {
     })
 }
 
+/// De-sugars `case x [1 => a, _ => b]` into `case [x == 1 => a, true => b]`.
+fn expand_switch(v: pr::Switch) -> Result<pl::ExprKind> {
+    let cases = (v.cases.into_iter())
+        .map(|case| -> Result<_> {
+            let condition = if is_wildcard(&case.condition) {
+                pl::Expr::new(pl::Literal::Boolean(true))
+            } else {
+                new_binop(
+                    expand_expr(*v.value.clone())?,
+                    &["std", "eq"],
+                    expand_expr(*case.condition)?,
+                )
+            };
+            Ok(pl::SwitchCase {
+                condition: Box::new(condition),
+                value: expand_expr_box(case.value)?,
+            })
+        })
+        .try_collect()?;
+    Ok(pl::ExprKind::Case(cases))
+}
+
+fn is_wildcard(expr: &pr::Expr) -> bool {
+    matches!(&expr.kind, pr::ExprKind::Ident(ident) if ident.len() == 1 && ident.name == "_")
+}
+
 /// De-sugars range `a..b` into `{start=a, end=b}`. Open bounds are mapped into `null`.
+/// An exclusive end `a..<b` is de-sugared into `{start=a, end_exclusive=b}`.
 fn expands_range(v: generic::Range<Box<pr::Expr>>) -> Result<pl::ExprKind> {
     let mut start = v
         .start
@@ -98,7 +137,14 @@ fn expands_range(v: generic::Range<Box<pr::Expr>>) -> Result<pl::ExprKind> {
         .map(|e| expand_expr(*e))
         .transpose()?
         .unwrap_or_else(|| pl::Expr::new(pr::Literal::Null));
-    end.alias = Some("end".into());
+    end.alias = Some(
+        if v.end_exclusive {
+            "end_exclusive"
+        } else {
+            "end"
+        }
+        .into(),
+    );
     Ok(pl::ExprKind::Tuple(vec![start, end]))
 }
 
@@ -129,6 +175,24 @@ fn desugar_pipeline(mut pipeline: pr::Pipeline) -> Result<pl::Expr> {
     Ok(value)
 }
 
+/// Expands fields of a tuple, marking the spread ones `...x` to be flattened
+/// into the tuple.
+fn expand_tuple_fields(fields: Vec<pr::Expr>) -> Result<Vec<pl::Expr>> {
+    (fields.into_iter())
+        .map(|field| match field.kind {
+            pr::ExprKind::Unary(pr::UnaryExpr {
+                op: pr::UnOp::Spread,
+                expr,
+            }) => {
+                let mut expr = expand_expr(*expr)?;
+                expr.flatten = true;
+                Ok(expr)
+            }
+            _ => expand_expr(field),
+        })
+        .collect()
+}
+
 /// Desugar unary operators into function calls.
 fn expand_unary(pr::UnaryExpr { op, expr }: pr::UnaryExpr) -> Result<pl::ExprKind> {
     use pr::UnOp::*;
@@ -139,6 +203,11 @@ fn expand_unary(pr::UnaryExpr { op, expr }: pr::UnaryExpr) -> Result<pl::ExprKin
         Neg => ["std", "neg"],
         Not => ["std", "not"],
         Add => return Ok(expr.kind),
+        Spread => {
+            return Err(Error::new_simple(
+                "you can only use the spread operator `...` within a tuple",
+            ))
+        }
         EqSelf => {
             let pl::ExprKind::Ident(ident) = expr.kind else {
                 return Err(Error::new_simple(
@@ -194,6 +263,7 @@ fn expand_binary(pr::BinaryExpr { op, left, right }: pr::BinaryExpr) -> Result<p
         pr::BinOp::Gte => vec!["std", "gte"],
         pr::BinOp::Lte => vec!["std", "lte"],
         pr::BinOp::RegexSearch => vec!["std", "regex_search"],
+        pr::BinOp::NullSafeEq => vec!["std", "null_safe_eq"],
         pr::BinOp::And => vec!["std", "and"],
         pr::BinOp::Or => vec!["std", "or"],
         pr::BinOp::Coalesce => vec!["std", "coalesce"],
@@ -371,24 +441,28 @@ fn restrict_func_param(value: pl::FuncParam) -> pr::FuncParam {
 }
 
 /// Restricts a tuple of form `{start=a, end=b}` into a range `a..b`.
-pub fn try_restrict_range(expr: pl::Expr) -> Result<(pl::Expr, pl::Expr), pl::Expr> {
+///
+/// The returned flag is set when the end is exclusive (`{start=a, end_exclusive=b}`).
+pub fn try_restrict_range(expr: pl::Expr) -> Result<(pl::Expr, pl::Expr, bool), pl::Expr> {
     let pl::ExprKind::Tuple(fields) = expr.kind else {
         return Err(expr);
     };
 
+    let end_alias = fields.get(1).and_then(|f| f.alias.as_deref());
     if fields.len() != 2
         || fields[0].alias.as_deref() != Some("start")
-        || fields[1].alias.as_deref() != Some("end")
+        || !matches!(end_alias, Some("end" | "end_exclusive"))
     {
         return Err(pl::Expr {
             kind: pl::ExprKind::Tuple(fields),
             ..expr
         });
     }
+    let end_exclusive = end_alias == Some("end_exclusive");
 
     let [start, end]: [pl::Expr; 2] = fields.try_into().unwrap();
 
-    Ok((start, end))
+    Ok((start, end, end_exclusive))
 }
 
 /// Returns None if the Expr is a null literal and Some(expr) otherwise.
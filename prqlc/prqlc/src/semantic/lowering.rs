@@ -4,7 +4,7 @@ use std::iter::zip;
 
 use enum_as_inner::EnumAsInner;
 use itertools::Itertools;
-use prqlc_parser::generic::{InterpolateItem, Range, SwitchCase};
+use prqlc_parser::generic::{InterpolateItem, Range, SwitchCase, FORMAT_IDENT};
 use prqlc_parser::lexer::lr::Literal;
 use semver::{Prerelease, Version};
 
@@ -16,10 +16,10 @@ use crate::ir::pl::{self, Ident, Lineage, LineageColumn, PlFold, QueryDef};
 use crate::ir::rq::{
     self, CId, RelationColumn, RelationLiteral, RelationalQuery, TId, TableDecl, Transform,
 };
-use crate::pr::TyTupleField;
+use crate::pr::{self, TyTupleField};
 use crate::semantic::write_pl;
 use crate::utils::{toposort, IdGenerator};
-use crate::{Error, Reason, Result, Span, WithErrorInfo};
+use crate::{Error, ErrorCode, Reason, Result, Span, WithErrorInfo};
 
 /// Convert a resolved expression at path `main_path` relative to `root_mod`
 /// into RQ and make sure that:
@@ -42,7 +42,7 @@ pub fn lower_to_ir(
     log::debug!("lookup for main pipeline in {main_path:?}");
     let (_, main_ident) = root_mod.find_main_rel(main_path).map_err(|(hint, span)| {
         Error::new_simple("Missing main pipeline")
-            .with_code("E0001")
+            .with_code(ErrorCode::E0001)
             .with_hints(hint)
             .with_span(span)
     })?;
@@ -52,6 +52,9 @@ pub fn lower_to_ir(
     let def = def.cloned().unwrap_or_default();
     validate_query_def(&def)?;
 
+    // find the table that main is written into
+    let into = root_mod.module.get(&main_ident).and_then(find_into_target);
+
     // find all tables in the root module
     let tables = TableExtractor::extract(&root_mod.module);
 
@@ -77,10 +80,22 @@ pub fn lower_to_ir(
         def,
         tables: l.table_buffer,
         relation: main_relation.unwrap(),
+        into,
     };
     Ok((query, l.root_mod))
 }
 
+/// Finds the table named by an `@{into = schema.table}` annotation.
+fn find_into_target(decl: &decl::Decl) -> Option<Ident> {
+    decl.annotations.iter().find_map(|annotation| {
+        let items = annotation.expr.kind.as_tuple()?;
+        items
+            .iter()
+            .find(|item| item.alias.as_deref() == Some(pr::INTO_ANNOTATION))
+            .and_then(|item| item.kind.as_ident().cloned())
+    })
+}
+
 fn extern_ref_to_relation(
     mut columns: Vec<TyTupleField>,
     fq_ident: &Ident,
@@ -234,8 +249,9 @@ impl Lowerer {
             // make sure that type of this expr has been inferred to be a table
             expr.lineage = Some(Lineage::default());
         }
+        let span = expr.span;
 
-        Ok(match expr.kind {
+        let table_ref = match expr.kind {
             pl::ExprKind::Ident(fq_table_name) => {
                 // ident that refer to table: create an instance of the table
                 let id = expr.id.unwrap();
@@ -417,7 +433,8 @@ impl Lowerer {
                 .push_hint("are you missing `from` statement?")
                 .with_span(expr.span))
             }
-        })
+        };
+        Ok(rq::TableRef { span, ..table_ref })
     }
 
     fn redirect_mappings(&mut self, redirects: HashMap<CId, CId>) {
@@ -468,6 +485,7 @@ impl Lowerer {
             source: tid,
             name,
             columns,
+            span: None,
         }
     }
 
@@ -490,6 +508,31 @@ impl Lowerer {
         Ok(relation)
     }
 
+    /// Lower a relation that is used as an expression into a new table.
+    fn lower_sub_query(&mut self, expr: pl::Expr) -> Result<TId> {
+        let span = expr.span;
+
+        let window = self.window.take();
+        let relation = self.lower_relation(expr)?;
+        self.window = window;
+
+        if relation.columns.len() != 1 {
+            return Err(Error::new_simple(
+                "a relation used as an expression must have exactly one column",
+            )
+            .with_span(span));
+        }
+
+        let tid = self.tid.gen();
+        log::debug!("lowering sub-query table, columns = {:?}", relation.columns);
+        self.table_buffer.push(TableDecl {
+            id: tid,
+            name: None,
+            relation,
+        });
+        Ok(tid)
+    }
+
     // Result is stored in self.pipeline
     fn lower_pipeline(&mut self, ast: pl::Expr, closure_param: Option<usize>) -> Result<()> {
         let transform_call = match ast.kind {
@@ -558,16 +601,24 @@ impl Lowerer {
                 let sorts = self.lower_sorts(by)?;
                 self.pipeline.push(Transform::Sort(sorts));
             }
-            pl::TransformKind::Take { range, .. } => {
+            pl::TransformKind::Take { range, percent } => {
                 let window = self.window.take().unwrap_or_default();
                 let range = self.lower_range(range)?;
 
                 validate_take_range(&range, ast.span)?;
 
+                if percent.is_some() && !window.partition.is_empty() {
+                    return Err(Error::new_simple(
+                        "`take` with a percentage cannot be used within `group`",
+                    )
+                    .with_span(ast.span));
+                }
+
                 self.pipeline.push(Transform::Take(rq::Take {
                     range,
                     partition: window.partition,
                     sort: window.sort,
+                    percent,
                 }));
             }
             pl::TransformKind::Join {
@@ -582,10 +633,20 @@ impl Lowerer {
                 };
                 self.pipeline.push(transform);
             }
-            pl::TransformKind::Append(bottom) => {
+            pl::TransformKind::Append { bottom, by_name } => {
+                let bottom = self.lower_table_ref(*bottom)?;
+
+                self.pipeline.push(Transform::Append { bottom, by_name });
+            }
+            pl::TransformKind::Except { bottom, all } => {
+                let bottom = self.lower_table_ref(*bottom)?;
+
+                self.pipeline.push(Transform::Except { bottom, all });
+            }
+            pl::TransformKind::Intersect { bottom, all } => {
                 let bottom = self.lower_table_ref(*bottom)?;
 
-                self.pipeline.push(Transform::Append(bottom));
+                self.pipeline.push(Transform::Intersect { bottom, all });
             }
             pl::TransformKind::Loop(pipeline) => {
                 let relation = self.lower_relation(*pipeline)?;
@@ -596,6 +657,96 @@ impl Lowerer {
 
                 self.pipeline.push(Transform::Loop(pipeline));
             }
+            pl::TransformKind::Pivot {
+                by,
+                names_from,
+                values_from,
+                values,
+            } => {
+                let window = self.window.take();
+                if window.map_or(false, |w| !w.partition.is_empty()) {
+                    return Err(Error::new_simple("`pivot` cannot be used within `group`")
+                        .with_span(ast.span));
+                }
+
+                let partition = self.declare_as_columns(*by, false)?;
+                let names_from = self.declare_as_column(*names_from, false)?;
+
+                let aggregation = match values_from.kind {
+                    pl::ExprKind::RqOperator { name, args } => {
+                        args.into_iter().exactly_one().ok().map(|arg| (name, arg))
+                    }
+                    _ => None,
+                };
+                let Some((func, arg)) = aggregation else {
+                    return Err(Error::new_simple(
+                        "`pivot` expects `values_from` to be an aggregation of a column",
+                    )
+                    .with_span(values_from.span));
+                };
+                let values_from = self.declare_as_column(arg, false)?;
+
+                let values_span = values.span;
+                let values = values.kind.into_tuple().map_err(|_| {
+                    Error::new_simple("`pivot` expects `values` to be a tuple")
+                        .with_span(values_span)
+                })?;
+                let columns = (values.into_iter())
+                    .map(|value| {
+                        let cid = self.declare_new_column(&value);
+                        let (pl::ExprKind::Literal(literal), Some(alias)) =
+                            (value.kind, value.alias)
+                        else {
+                            return Err(Error::new_simple(
+                                "`pivot` expects `values` to be named literals",
+                            )
+                            .with_span(value.span));
+                        };
+                        Ok((literal, alias, cid))
+                    })
+                    .try_collect()?;
+
+                self.pipeline.push(Transform::Pivot(rq::Pivot {
+                    partition,
+                    names_from,
+                    values_from,
+                    func,
+                    columns,
+                }));
+            }
+            pl::TransformKind::Unpivot {
+                retained,
+                columns,
+                names_to,
+                values_to,
+            } => {
+                let window = self.window.take();
+                if window.map_or(false, |w| !w.partition.is_empty()) {
+                    return Err(Error::new_simple("`unpivot` cannot be used within `group`")
+                        .with_span(ast.span));
+                }
+
+                let retained = self.declare_as_columns(*retained, false)?;
+                let columns = self.declare_as_columns(*columns, false)?;
+
+                let names_to_name = names_to.alias.clone().ok_or_else(|| {
+                    Error::new_simple("`unpivot` expects `names_to` to be a column name")
+                        .with_span(names_to.span)
+                })?;
+                let names_to = (names_to_name, self.declare_new_column(&names_to));
+                let values_to_name = values_to.alias.clone().ok_or_else(|| {
+                    Error::new_simple("`unpivot` expects `values_to` to be a column name")
+                        .with_span(values_to.span)
+                })?;
+                let values_to = (values_to_name, self.declare_new_column(&values_to));
+
+                self.pipeline.push(Transform::Unpivot(rq::Unpivot {
+                    retained,
+                    columns,
+                    names_to,
+                    values_to,
+                }));
+            }
             pl::TransformKind::Group { .. } | pl::TransformKind::Window { .. } => unreachable!(
                 "transform `{}` cannot be lowered.",
                 (*transform_call.kind).as_ref()
@@ -611,6 +762,7 @@ impl Lowerer {
         Ok(Range {
             start: range.start.map(|x| self.lower_expr(*x)).transpose()?,
             end: range.end.map(|x| self.lower_expr(*x)).transpose()?,
+            end_exclusive: range.end_exclusive,
         })
     }
 
@@ -820,6 +972,14 @@ impl Lowerer {
         Ok(cid)
     }
 
+    /// Declares a column that is not computed by an expression, but produced by a transform.
+    fn declare_new_column(&mut self, expr_ast: &pl::Expr) -> CId {
+        let cid = self.cid.gen();
+        self.node_mapping
+            .insert(expr_ast.id.unwrap(), LoweredTarget::Compute(cid));
+        cid
+    }
+
     fn lower_expr(&mut self, expr: pl::Expr) -> Result<rq::Expr> {
         let span = expr.span;
 
@@ -904,7 +1064,7 @@ impl Lowerer {
                     .try_collect()?,
             ),
             pl::ExprKind::RqOperator { name, args } => {
-                let args = args.into_iter().map(|x| self.lower_expr(x)).try_collect()?;
+                let args = args.into_iter().map(|x| self.lower_arg(x)).try_collect()?;
 
                 rq::ExprKind::Operator { name, args }
             }
@@ -954,15 +1114,30 @@ impl Lowerer {
             .map(|i| {
                 Ok(match i {
                     InterpolateItem::String(s) => InterpolateItem::String(s),
-                    InterpolateItem::Expr { expr, .. } => InterpolateItem::Expr {
+                    InterpolateItem::Expr { expr, format } => InterpolateItem::Expr {
                         expr: Box::new(self.lower_expr(*expr)?),
-                        format: None,
+                        format: format.filter(|f| f == FORMAT_IDENT),
                     },
                 })
             })
             .try_collect()
     }
 
+    /// Lowers an arg of an operator, which may be a relation (e.g. `x | in (from y)`).
+    fn lower_arg(&mut self, expr: pl::Expr) -> Result<rq::Expr> {
+        if expr.ty.as_ref().is_some_and(|ty| ty.is_relation())
+            && matches!(
+                expr.kind,
+                pl::ExprKind::Ident(_) | pl::ExprKind::TransformCall(_)
+            )
+        {
+            let span = expr.span;
+            let kind = rq::ExprKind::SubQuery(self.lower_sub_query(expr)?);
+            return Ok(rq::Expr { kind, span });
+        }
+        self.lower_expr(expr)
+    }
+
     fn lookup_cid(&mut self, id: usize, name: Option<&String>) -> Result<CId> {
         let cid = match self.node_mapping.get(&id) {
             Some(LoweredTarget::Compute(cid)) => *cid,
@@ -1136,7 +1311,15 @@ impl PlFold for TableDepsCollector {
             }
             pl::ExprKind::Func(func) => pl::ExprKind::Func(Box::new(self.fold_func(*func)?)),
 
-            // optimization: don't recurse into anything else than TransformCalls and Func
+            // relations can be used as args to operators, such as `in`
+            pl::ExprKind::RqOperator { name, args } => pl::ExprKind::RqOperator {
+                name,
+                args: self.fold_exprs(args)?,
+            },
+            pl::ExprKind::Tuple(fields) => pl::ExprKind::Tuple(self.fold_exprs(fields)?),
+
+            // optimization: don't recurse into anything else than TransformCalls, Func,
+            // RqOperators and Tuples
             _ => expr.kind,
         };
         Ok(expr)
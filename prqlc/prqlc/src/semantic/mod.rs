@@ -15,9 +15,10 @@ use crate::ir::pl::{self, ImportDef, ModuleDef, Stmt, StmtKind, TypeDef, VarDef}
 use crate::ir::rq::RelationalQuery;
 use crate::parser::is_mod_def_for;
 use crate::pr;
+use crate::utils::IdGenerator;
 use crate::WithErrorInfo;
 use crate::{debug, parser};
-use crate::{Error, Reason, Result};
+use crate::{Error, ErrorCode, Reason, Result};
 
 /// Runs semantic analysis on the query and lowers PL to RQ.
 pub fn resolve_and_lower(
@@ -25,7 +26,28 @@ pub fn resolve_and_lower(
     main_path: &[String],
     database_module_path: Option<&[String]>,
 ) -> Result<RelationalQuery> {
-    let root_mod = resolve(file_tree)?;
+    resolve_and_lower_with_warnings(file_tree, main_path, database_module_path)
+        .map(|(query, _)| query)
+}
+
+/// Runs semantic analysis on the query and lowers PL to RQ.
+/// Also returns warnings that were emitted during resolution.
+pub fn resolve_and_lower_with_warnings(
+    file_tree: pr::ModuleDef,
+    main_path: &[String],
+    database_module_path: Option<&[String]>,
+) -> Result<(RelationalQuery, Vec<Error>)> {
+    lower_with_warnings(resolve(file_tree)?, main_path, database_module_path)
+}
+
+/// Lowers a resolved module to RQ.
+/// Also returns warnings that were emitted during resolution.
+pub fn lower_with_warnings(
+    mut root_mod: RootModule,
+    main_path: &[String],
+    database_module_path: Option<&[String]>,
+) -> Result<(RelationalQuery, Vec<Error>)> {
+    let warnings = std::mem::take(&mut root_mod.warnings);
 
     debug::log_stage(debug::Stage::Semantic(debug::StageSemantic::Lowering));
     let default_db = [NS_DEFAULT_DB.to_string()];
@@ -33,31 +55,141 @@ pub fn resolve_and_lower(
     let (query, _) = lowering::lower_to_ir(root_mod, main_path, database_module_path)?;
 
     debug::log_entry(|| debug::DebugEntryKind::ReprRq(query.clone()));
-    Ok(query)
+    Ok((query, warnings))
 }
 
 /// Runs semantic analysis on the query.
 pub fn resolve(mut module_tree: pr::ModuleDef) -> Result<RootModule> {
     load_std_lib(&mut module_tree);
+    resolve_without_std_lib(module_tree)
+}
 
+/// Runs semantic analysis on the query, without loading the std module, so
+/// only names declared by the query itself can be used.
+pub fn resolve_without_std_lib(module_tree: pr::ModuleDef) -> Result<RootModule> {
+    // init new root module
+    let root_module = RootModule {
+        module: Module::new_root(),
+        ..Default::default()
+    };
+    let (root_module, _) = resolve_into(root_module, IdGenerator::new(), module_tree)?;
+    Ok(root_module)
+}
+
+/// Resolves the module tree into an existing root module, continuing with ids
+/// of the given generator.
+fn resolve_into(
+    mut root_module: RootModule,
+    id: IdGenerator<usize>,
+    module_tree: pr::ModuleDef,
+) -> Result<(RootModule, IdGenerator<usize>)> {
     // expand AST into PL
     debug::log_stage(debug::Stage::Semantic(debug::StageSemantic::AstExpand));
     let root_module_def = ast_expand::expand_module_def(module_tree)?;
     debug::log_entry(|| debug::DebugEntryKind::ReprPl(root_module_def.clone()));
 
-    // init new root module
-    let mut root_module = RootModule {
-        module: Module::new_root(),
-        ..Default::default()
-    };
     let mut resolver = Resolver::new(&mut root_module);
+    resolver.id = id;
 
     // resolve the module def into the root module
     debug::log_stage(debug::Stage::Semantic(debug::StageSemantic::Resolver));
     resolver.fold_statements(root_module_def.stmts)?;
+    let id = resolver.id;
     debug::log_entry(|| debug::DebugEntryKind::ReprDecl(root_module.clone()));
 
-    Ok(root_module)
+    Ok((root_module, id))
+}
+
+/// The std module, resolved once so it can be reused for resolving many
+/// queries.
+#[derive(Debug, Clone)]
+pub struct ResolvedStd {
+    root_module: RootModule,
+    id: IdGenerator<usize>,
+}
+
+impl ResolvedStd {
+    pub fn new() -> Result<Self> {
+        let _suppressed = debug::log_suppress();
+
+        let mut module_tree = pr::ModuleDef {
+            name: String::new(),
+            stmts: Vec::new(),
+        };
+        load_std_lib(&mut module_tree);
+
+        let root_module = RootModule {
+            module: Module::new_root(),
+            ..Default::default()
+        };
+        let (root_module, id) = resolve_into(root_module, IdGenerator::new(), module_tree)?;
+        Ok(ResolvedStd { root_module, id })
+    }
+
+    /// Runs semantic analysis on the query, starting from the resolved std
+    /// module. Equivalent to [resolve].
+    pub fn resolve(&self, module_tree: pr::ModuleDef) -> Result<RootModule> {
+        if module_tree.stmts.iter().any(|s| is_mod_def_for(s, NS_STD)) {
+            // the query brings its own std module
+            return resolve(module_tree);
+        }
+
+        let (root_module, _) =
+            resolve_into(self.root_module.clone(), self.id.clone(), module_tree)?;
+        Ok(root_module)
+    }
+}
+
+/// Runs semantic analysis on the query, also returning the declaration that
+/// each identifier resolved to, up to the first error.
+///
+/// Names of the std module are not traced, unless the query brings its own.
+pub(crate) fn resolve_with_trace(
+    module_tree: pr::ModuleDef,
+) -> (Vec<debug::ResolvedName>, Result<RootModule>) {
+    let (mut root_module, id) = if module_tree.stmts.iter().any(|s| is_mod_def_for(s, NS_STD)) {
+        let root_module = RootModule {
+            module: Module::new_root(),
+            ..Default::default()
+        };
+        (root_module, IdGenerator::new())
+    } else {
+        match ResolvedStd::new() {
+            Ok(std) => (std.root_module, std.id),
+            Err(e) => return (Vec::new(), Err(e)),
+        }
+    };
+
+    let mut resolver = Resolver::new(&mut root_module);
+    resolver.id = id;
+    resolver.trace = Some(Vec::new());
+
+    let res = ast_expand::expand_module_def(module_tree)
+        .and_then(|module_def| resolver.fold_statements(module_def.stmts));
+    let trace = resolver.trace.take().unwrap_or_default();
+    (trace, res.map(|_| root_module))
+}
+
+/// Runs semantic analysis on the query and returns the columns of the main
+/// relation, as inferred by the resolver.
+pub fn resolve_main_columns(
+    file_tree: pr::ModuleDef,
+    main_path: &[String],
+) -> Result<Vec<pr::TyTupleField>> {
+    let root_mod = resolve(file_tree)?;
+
+    let (main, _) = root_mod.find_main_rel(main_path).map_err(|(hint, span)| {
+        Error::new_simple("Missing main pipeline")
+            .with_code(ErrorCode::E0001)
+            .with_hints(hint)
+            .with_span(span)
+    })?;
+    let main = main.clone().into_relation_var().unwrap();
+
+    Ok(main
+        .ty
+        .and_then(|ty| ty.into_relation())
+        .unwrap_or_default())
 }
 
 /// Preferred way of injecting std module.
@@ -229,7 +361,7 @@ pub mod test {
         prql target:sql.mssql version:"0"
 
         from employees
-        "#).unwrap(), @r"
+        "#).unwrap(), @r#"
         def:
           version: ^0
           other:
@@ -253,11 +385,12 @@ pub mod test {
                     - - Wildcard
                       - 0
                   name: employees
+                  span: "1:52-66"
               - Select:
                   - 0
           columns:
             - Wildcard
-        " );
+        "# );
 
         assert!(parse_resolve_and_lower(
             r###"
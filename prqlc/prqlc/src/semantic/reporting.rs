@@ -257,7 +257,9 @@ impl PlFold for FrameCollector {
                         pl::TransformKind::Derive { assigns: ref e }
                         | pl::TransformKind::Select { assigns: ref e }
                         | pl::TransformKind::Filter { filter: ref e }
-                        | pl::TransformKind::Append(ref e)
+                        | pl::TransformKind::Append { bottom: ref e, .. }
+                        | pl::TransformKind::Except { bottom: ref e, .. }
+                        | pl::TransformKind::Intersect { bottom: ref e, .. }
                         | pl::TransformKind::Loop(ref e)
                         | pl::TransformKind::Group {
                             pipeline: ref e, ..
@@ -281,7 +283,7 @@ impl PlFold for FrameCollector {
                             tcc.push(with.id.unwrap());
                             tcc.push(filter.id.unwrap());
                         }
-                        pl::TransformKind::Take { ref range } => {
+                        pl::TransformKind::Take { ref range, .. } => {
                             if let Some(e) = &range.start {
                                 tcc.push(e.id.unwrap());
                             }
@@ -294,6 +296,19 @@ impl PlFold for FrameCollector {
                                 tcc.push(c.column.id.unwrap());
                             }
                         }
+                        pl::TransformKind::Pivot {
+                            ref names_from,
+                            ref values_from,
+                            ref values,
+                            ..
+                        } => {
+                            tcc.push(names_from.id.unwrap());
+                            tcc.push(values_from.id.unwrap());
+                            tcc.push(values.id.unwrap());
+                        }
+                        pl::TransformKind::Unpivot { ref columns, .. } => {
+                            tcc.push(columns.id.unwrap());
+                        }
                     };
 
                     tcc
@@ -1,14 +1,15 @@
 use itertools::Itertools;
 
-use crate::ir::decl::{DeclKind, Module};
+use crate::debug;
+use crate::ir::decl::{Decl, DeclKind, Module};
 use crate::ir::pl;
 use crate::ir::pl::PlFold;
-use crate::pr::{Ty, TyKind, TyTupleField};
+use crate::pr::{self, Ty, TyKind, TyTupleField};
 use crate::semantic::resolver::{flatten, types, Resolver};
 use crate::semantic::{NS_INFER, NS_SELF, NS_THAT, NS_THIS};
 use crate::utils::IdGenerator;
 use crate::Result;
-use crate::{Error, Reason, Span, WithErrorInfo};
+use crate::{Error, MessageKind, Reason, Span, WithErrorInfo};
 
 impl pl::PlFold for Resolver<'_> {
     fn fold_stmts(&mut self, _: Vec<pl::Stmt>) -> Result<Vec<pl::Stmt>> {
@@ -80,6 +81,15 @@ impl pl::PlFold for Resolver<'_> {
                 let entry = self.root_mod.module.get(&fq_ident).unwrap();
                 log::debug!("... which is {entry}");
 
+                if let Some(trace) = &mut self.trace {
+                    trace.push(debug::ResolvedName::new(&ident, &fq_ident, &entry.kind));
+                }
+
+                if let Some(message) = find_deprecation(entry) {
+                    let warning = deprecation_warning(&ident, message).with_span(node.span);
+                    self.root_mod.warnings.push(warning);
+                }
+
                 match &entry.kind {
                     DeclKind::Infer(_) => pl::Expr {
                         kind: pl::ExprKind::Ident(fq_ident),
@@ -179,13 +189,15 @@ impl pl::PlFold for Resolver<'_> {
             pl::ExprKind::Func(closure) => self.fold_function(closure, *span)?,
 
             pl::ExprKind::Tuple(exprs) => {
+                // resolving a function call doesn't retain the flag of a
+                // spread field, so it's taken before
+                let flatten = exprs.iter().map(|e| e.flatten).collect_vec();
                 let exprs = self.fold_exprs(exprs)?;
 
                 // flatten
-                let exprs = exprs
-                    .into_iter()
-                    .flat_map(|e| match e.kind {
-                        pl::ExprKind::Tuple(items) if e.flatten => items,
+                let exprs = (exprs.into_iter().zip(flatten))
+                    .flat_map(|(e, flatten)| match e.kind {
+                        pl::ExprKind::Tuple(items) if e.flatten || flatten => items,
                         _ => vec![e],
                     })
                     .collect_vec();
@@ -311,6 +323,37 @@ impl Resolver<'_> {
     }
 }
 
+/// Finds the deprecation annotation of a declaration, returning its message
+/// (if it has one).
+fn find_deprecation(decl: &Decl) -> Option<Option<&str>> {
+    decl.annotations
+        .iter()
+        .find_map(|annotation| match &annotation.expr.kind {
+            pl::ExprKind::Ident(ident) => {
+                (ident.path.is_empty() && ident.name == pr::DEPRECATED_ANNOTATION).then_some(None)
+            }
+            pl::ExprKind::Tuple(items) => items
+                .iter()
+                .find(|item| item.alias.as_deref() == Some(pr::DEPRECATED_ANNOTATION))
+                .map(|item| match &item.kind {
+                    pl::ExprKind::Literal(pl::Literal::String(message)) => Some(message.as_str()),
+                    _ => None,
+                }),
+            _ => None,
+        })
+}
+
+fn deprecation_warning(ident: &pl::Ident, message: Option<&str>) -> Error {
+    let reason = match message {
+        Some(message) => format!("`{ident}` is deprecated: {message}"),
+        None => format!("`{ident}` is deprecated"),
+    };
+    Error {
+        kind: MessageKind::Warning,
+        ..Error::new_simple(reason)
+    }
+}
+
 fn ty_of_lineage(lineage: &pl::Lineage) -> Ty {
     Ty::relation(
         lineage
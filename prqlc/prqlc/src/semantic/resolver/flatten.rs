@@ -128,7 +128,17 @@ impl PlFold for Flattener {
                             ..pipeline
                         });
                     }
-                    kind => (self.fold_expr(*t.input)?, fold_transform_kind(self, kind)?),
+                    kind => {
+                        let input = self.fold_expr(*t.input)?;
+
+                        // relations within args (e.g. `filter (x | in (from y | sort z))`)
+                        // must not affect the sort of this pipeline
+                        let sort = self.sort.clone();
+                        let kind = fold_transform_kind(self, kind)?;
+                        self.sort = sort;
+
+                        (input, kind)
+                    }
                 };
 
                 // In case we're appending or joining another pipeline, we do not want to apply the
@@ -143,8 +153,13 @@ impl PlFold for Flattener {
                 //   derive {`album_name` = `name`}
                 //   select {`artist_id`, `album_name`}
                 // ) (this.id == that.artist_id)
-                let sort = if matches!(kind, TransformKind::Join { .. } | TransformKind::Append(_))
-                {
+                let sort = if matches!(
+                    kind,
+                    TransformKind::Join { .. }
+                        | TransformKind::Append { .. }
+                        | TransformKind::Except { .. }
+                        | TransformKind::Intersect { .. }
+                ) {
                     vec![]
                 } else {
                     self.sort.clone()
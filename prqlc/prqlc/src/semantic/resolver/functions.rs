@@ -6,7 +6,7 @@ use itertools::{Itertools, Position};
 use super::Resolver;
 use crate::ir::decl::{Decl, DeclKind, Module};
 use crate::ir::pl::*;
-use crate::pr::{Ty, TyFunc};
+use crate::pr::{Ty, TyFunc, TyKind};
 use crate::semantic::resolver::types;
 use crate::semantic::{NS_PARAM, NS_THAT, NS_THIS};
 use crate::Result;
@@ -243,10 +243,14 @@ impl Resolver<'_> {
 
                 // add relation frame into scope
                 if partial_application_position.is_none() {
-                    let frame = arg
-                        .lineage
-                        .as_ref()
-                        .ok_or_else(|| Error::new_bug(4317).with_span(closure.body.span))?;
+                    let frame = arg.lineage.as_ref().ok_or_else(|| {
+                        if matches!(&arg.kind, ExprKind::Array(items) if items.is_empty()) {
+                            Error::new_simple("relation literal must contain at least one row")
+                                .with_span(arg.span)
+                        } else {
+                            Error::new_bug(4317).with_span(closure.body.span)
+                        }
+                    })?;
                     if is_last {
                         self.root_mod.module.insert_frame(frame, NS_THIS);
                     } else {
@@ -346,7 +350,9 @@ impl Resolver<'_> {
     fn fold_within_namespace(&mut self, expr: Expr, param_name: &str) -> Result<Expr> {
         let prev_namespace = self.default_namespace.take();
 
-        if param_name.starts_with("noresolve.") {
+        if param_name == "noresolve.type" {
+            return Ok(self.resolve_type_alias(expr));
+        } else if param_name.starts_with("noresolve.") {
             return Ok(expr);
         } else if let Some((ns, _)) = param_name.split_once('.') {
             self.default_namespace = Some(ns.to_string());
@@ -358,6 +364,34 @@ impl Resolver<'_> {
         self.default_namespace = prev_namespace;
         res
     }
+
+    /// Replaces a type name with the primitive type it is declared as, so that
+    /// a cast to a user-defined type alias compiles to the underlying type.
+    /// Names which aren't declared as types are passed through to SQL as-is.
+    fn resolve_type_alias(&mut self, expr: Expr) -> Expr {
+        let ExprKind::Ident(ident) = &expr.kind else {
+            return expr;
+        };
+
+        self.root_mod.module.shadow(NS_THIS);
+        self.root_mod.module.shadow(NS_THAT);
+        let decls = self.root_mod.module.lookup(ident);
+        self.root_mod.module.unshadow(NS_THIS);
+        self.root_mod.module.unshadow(NS_THAT);
+
+        let Ok(fq_ident) = decls.into_iter().exactly_one() else {
+            return expr;
+        };
+        let decl = self.root_mod.module.get(&fq_ident).unwrap();
+        let Some(TyKind::Primitive(primitive)) = decl.kind.as_ty().map(|ty| &ty.kind) else {
+            return expr;
+        };
+
+        Expr {
+            kind: ExprKind::Ident(Ident::from_name(primitive.to_string())),
+            ..expr
+        }
+    }
 }
 
 fn extract_partial_application(mut func: Box<Func>, position: usize) -> Box<Func> {
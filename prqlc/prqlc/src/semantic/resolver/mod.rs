@@ -1,3 +1,4 @@
+use crate::debug;
 use crate::ir::decl::RootModule;
 use crate::utils::IdGenerator;
 
@@ -23,6 +24,9 @@ pub struct Resolver<'a> {
     in_func_call_name: bool,
 
     pub id: IdGenerator<usize>,
+
+    /// When set, records the declaration that each identifier resolved to.
+    pub trace: Option<Vec<debug::ResolvedName>>,
 }
 
 #[derive(Default, Clone)]
@@ -36,6 +40,7 @@ impl Resolver<'_> {
             default_namespace: None,
             in_func_call_name: false,
             id: IdGenerator::new(),
+            trace: None,
         }
     }
 }
@@ -256,9 +256,15 @@ fn ambiguous_error(idents: HashSet<Ident>, replace_name: Option<&String>) -> Err
         if let Some(name) = replace_name {
             ident.name.clone_from(name);
         }
-        chunks.push(ident.to_string());
+        chunks.push(format!("`{ident}`"));
     }
     chunks.sort();
-    let hint = format!("could be any of: {}", chunks.join(", "));
+
+    // `a`, `b` or `c`
+    let candidates = match chunks.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {last}", rest.join(", ")),
+        _ => chunks.join(""),
+    };
+    let hint = format!("did you mean {candidates}?");
     Error::new_simple("Ambiguous name").push_hint(hint)
 }
@@ -28,7 +28,18 @@ impl super::Resolver<'_> {
                         .with_span(stmt.span)?;
                     continue;
                 }
-                StmtKind::VarDef(var_def) => self.fold_var_def(var_def)?,
+                StmtKind::VarDef(mut var_def) => {
+                    // a tuple of main is a relation of a single row
+                    if var_def.name == "main"
+                        && var_def.value.as_ref().is_some_and(|v| v.kind.is_tuple())
+                    {
+                        let tuple = var_def.value.take().unwrap();
+                        let span = tuple.span;
+                        let relation = Expr::new(ExprKind::Array(vec![*tuple]));
+                        var_def.value = Some(Box::new(Expr { span, ..relation }));
+                    }
+                    self.fold_var_def(var_def)?
+                }
                 StmtKind::TypeDef(ty_def) => {
                     let mut ty = self.fold_type(ty_def.value)?;
                     ty.name = Some(ident.name.clone());
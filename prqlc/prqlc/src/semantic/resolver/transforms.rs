@@ -9,10 +9,11 @@ use super::Resolver;
 use crate::ir::decl::{Decl, DeclKind, Module};
 use crate::ir::generic::{SortDirection, WindowKind};
 use crate::ir::pl::*;
-use crate::pr::{Ty, TyKind, TyTupleField};
+use crate::pr::{PrimitiveSet, Ty, TyKind, TyTupleField};
 use crate::semantic::ast_expand::{restrict_null_literal, try_restrict_range};
 use crate::semantic::resolver::functions::expr_of_func;
 use crate::semantic::{write_pl, NS_PARAM, NS_THIS};
+use crate::utils::IdGenerator;
 use crate::{compiler_version, Error, Reason, Result, WithErrorInfo};
 
 impl Resolver<'_> {
@@ -71,13 +72,32 @@ impl Resolver<'_> {
             "take" => {
                 let [expr, tbl] = unpack::<2>(func.args);
 
+                let mut percent = None;
                 let range = if let ExprKind::Literal(Literal::Integer(n)) = expr.kind {
-                    range_from_ints(None, Some(n))
+                    let mut range = range_from_ints(None, Some(n));
+                    // so the SQL of the range can be traced back to the `take`
+                    range.end.as_mut().unwrap().span = expr.span;
+                    range
+                } else if let Some(n) = as_percentage(&expr) {
+                    if !(0..=100).contains(&n) {
+                        return Err(Error::new(Reason::Expected {
+                            who: Some("`take`".to_string()),
+                            expected: "a percentage between 0% and 100%".to_string(),
+                            found: write_pl(expr.clone()),
+                        })
+                        .with_span(expr.span));
+                    }
+                    percent = Some(n);
+                    Range::unbounded()
                 } else {
                     match try_restrict_range(expr) {
-                        Ok((start, end)) => Range {
+                        Ok((start, end, end_exclusive)) => Range {
                             start: restrict_null_literal(start).map(Box::new),
-                            end: restrict_null_literal(end).map(Box::new),
+                            end: restrict_null_literal(end)
+                                .map(|end| inclusive_end(end, end_exclusive))
+                                .transpose()?
+                                .map(Box::new),
+                            end_exclusive: false,
                         },
                         Err(expr) => {
                             return Err(Error::new(Reason::Expected {
@@ -92,7 +112,7 @@ impl Resolver<'_> {
                     }
                 };
 
-                (TransformKind::Take { range }, tbl)
+                (TransformKind::Take { range, percent }, tbl)
             }
             "join" => {
                 let [side, with, filter, tbl] = unpack::<4>(func.args);
@@ -165,7 +185,20 @@ impl Resolver<'_> {
                     // fold, so lineage and types are inferred
                     self.fold_expr(partition)?
                 };
+                // group keys are not a part of the partition, but can still
+                // be referenced by name within an aggregate, where they are
+                // valid in the SELECT of the GROUP BY
+                let is_aggregate = (pipeline.kind.as_func())
+                    .and_then(|f| f.name_hint.as_ref())
+                    .is_some_and(|n| n.to_string() == "std.aggregate");
+                let keys = if is_aggregate {
+                    group_keys_env(&by, &partition)
+                } else {
+                    Module::default()
+                };
+                self.root_mod.module.stack_push(NS_PARAM, keys);
                 let pipeline = self.fold_by_simulating_eval(pipeline, &partition)?;
+                self.root_mod.module.stack_pop(NS_PARAM).unwrap();
 
                 // unpack tbl back out
                 let tbl = *partition.kind.into_transform_call().unwrap().input;
@@ -222,6 +255,7 @@ impl Resolver<'_> {
                 let range = Range {
                     start: start.map(Literal::Integer).map(Expr::new).map(Box::new),
                     end: end.map(Literal::Integer).map(Expr::new).map(Box::new),
+                    end_exclusive: false,
                 };
 
                 let pipeline = self.fold_by_simulating_eval(pipeline, &tbl)?;
@@ -234,9 +268,46 @@ impl Resolver<'_> {
                 (transform_kind, tbl)
             }
             "append" => {
-                let [bottom, top] = unpack::<2>(func.args);
+                let [by_name, bottom, top] = unpack::<3>(func.args);
+
+                let by_name = {
+                    let as_bool = by_name.kind.as_literal().and_then(|l| l.as_boolean());
+
+                    *as_bool.ok_or_else(|| {
+                        Error::new(Reason::Expected {
+                            who: Some("parameter `by_name`".to_string()),
+                            expected: "a boolean".to_string(),
+                            found: write_pl(by_name.clone()),
+                        })
+                        .with_span(by_name.span)
+                    })?
+                };
 
-                (TransformKind::Append(Box::new(bottom)), top)
+                let bottom = Box::new(bottom);
+                (TransformKind::Append { bottom, by_name }, top)
+            }
+            "except" | "intersect" => {
+                let [all, bottom, top] = unpack::<3>(func.args);
+
+                let all = {
+                    let as_bool = all.kind.as_literal().and_then(|l| l.as_boolean());
+
+                    *as_bool.ok_or_else(|| {
+                        Error::new(Reason::Expected {
+                            who: Some("parameter `all`".to_string()),
+                            expected: "a boolean".to_string(),
+                            found: write_pl(all.clone()),
+                        })
+                        .with_span(all.span)
+                    })?
+                };
+
+                let bottom = Box::new(bottom);
+                if internal_name == "except" {
+                    (TransformKind::Except { bottom, all }, top)
+                } else {
+                    (TransformKind::Intersect { bottom, all }, top)
+                }
             }
             "loop" => {
                 let [pipeline, tbl] = unpack::<2>(func.args);
@@ -245,6 +316,76 @@ impl Resolver<'_> {
 
                 (TransformKind::Loop(Box::new(pipeline)), tbl)
             }
+            "pivot" => {
+                let [names_from, values_from, values, tbl] = unpack::<4>(func.args);
+
+                if !names_from.kind.is_ident() {
+                    return Err(Error::new(Reason::Expected {
+                        who: Some("`pivot`".to_string()),
+                        expected: "a column name".to_string(),
+                        found: write_pl(names_from.clone()),
+                    })
+                    .with_span(names_from.span));
+                }
+
+                // values_from must be an aggregation of a single column
+                let aggregated = match &values_from.kind {
+                    ExprKind::RqOperator { args, .. }
+                        if args.len() == 1 && args[0].kind.is_ident() =>
+                    {
+                        args[0].clone()
+                    }
+                    _ => {
+                        return Err(Error::new(Reason::Expected {
+                            who: Some("`pivot`".to_string()),
+                            expected: "an aggregation of a column, i.e. `sum amount`".to_string(),
+                            found: write_pl(values_from.clone()),
+                        })
+                        .with_span(values_from.span));
+                    }
+                };
+
+                let values = self.resolve_pivot_values(values)?;
+
+                let except = Expr::new(ExprKind::Tuple(vec![names_from.clone(), aggregated]));
+                let (by, tbl) = self.resolve_all_except(except, tbl)?;
+
+                let transform_kind = TransformKind::Pivot {
+                    by,
+                    names_from: Box::new(names_from),
+                    values_from: Box::new(values_from),
+                    values: Box::new(values),
+                };
+                (transform_kind, tbl)
+            }
+            "unpivot" => {
+                let [names_to, values_to, columns, tbl] = unpack::<4>(func.args);
+
+                let columns = self.coerce_into_tuple(columns)?;
+                for column in columns.kind.as_tuple().unwrap() {
+                    if !column.kind.is_ident() {
+                        return Err(Error::new(Reason::Expected {
+                            who: Some("`unpivot`".to_string()),
+                            expected: "a column name".to_string(),
+                            found: write_pl(column.clone()),
+                        })
+                        .with_span(column.span));
+                    }
+                }
+
+                let names_to = self.new_column_of_ident(names_to, "names_to")?;
+                let values_to = self.new_column_of_ident(values_to, "values_to")?;
+
+                let (retained, tbl) = self.resolve_all_except(columns.clone(), tbl)?;
+
+                let transform_kind = TransformKind::Unpivot {
+                    retained,
+                    columns: Box::new(columns),
+                    names_to: Box::new(names_to),
+                    values_to: Box::new(values_to),
+                };
+                (transform_kind, tbl)
+            }
 
             "in" => {
                 // yes, this is not a transform, but this is the most appropriate place for it
@@ -259,12 +400,13 @@ impl Resolver<'_> {
                 }
 
                 let pattern = match try_restrict_range(pattern) {
-                    Ok((start, end)) => {
+                    Ok((start, end, end_exclusive)) => {
                         let start = restrict_null_literal(start);
                         let end = restrict_null_literal(end);
 
+                        let end_op = if end_exclusive { "lt" } else { "lte" };
                         let start = start.map(|s| new_binop(value.clone(), &["std", "gte"], s));
-                        let end = end.map(|e| new_binop(value, &["std", "lte"], e));
+                        let end = end.map(|e| new_binop(value, &["std", end_op], e));
 
                         let res = maybe_binop(start, &["std", "and"], end);
                         let res = res.unwrap_or_else(|| {
@@ -283,6 +425,18 @@ impl Resolver<'_> {
                 .with_span(pattern.span));
             }
 
+            "greatest" | "least" => {
+                // yes, this is not a transform, but this is the most appropriate place for it
+
+                let [values] = unpack::<1>(func.args);
+                let values = self.coerce_into_tuple(values)?;
+
+                return Ok(Expr::new(ExprKind::RqOperator {
+                    name: format!("std.{internal_name}"),
+                    args: values.kind.into_tuple().unwrap(),
+                }));
+            }
+
             "tuple_every" => {
                 // yes, this is not a transform, but this is the most appropriate place for it
 
@@ -430,11 +584,31 @@ impl Resolver<'_> {
                 // throws an error during lowering.
                 // But because these functions don't *really* need an arg, we can just pass
                 // a null instead.
+                // `count` of a column is an exception, since it counts only the non-null
+                // values of the column, while `count this` counts all rows, with `*`.
+                // Other tuples have no SQL equivalent.
+                let [arg] = unpack::<1>(func.args);
+                let arg = match arg.kind {
+                    _ if internal_name == "row_number" => Expr::new(Literal::Null),
+                    ExprKind::Tuple(_) if self.is_all_columns(&arg) => Expr::new(
+                        ExprKind::SString(vec![InterpolateItem::String("*".to_string())]),
+                    ),
+                    ExprKind::Tuple(_) => {
+                        return Err(Error::new(Reason::Expected {
+                            who: Some("`count`".to_string()),
+                            expected: "a column or `this`".to_string(),
+                            found: write_pl(arg.clone()),
+                        })
+                        .push_hint("use `count this` to count all rows")
+                        .with_span(arg.span));
+                    }
+                    _ => arg,
+                };
                 return Ok(Expr {
                     needs_window,
                     ..Expr::new(ExprKind::RqOperator {
                         name: format!("std.{internal_name}"),
-                        args: vec![Expr::new(Literal::Null)],
+                        args: vec![arg],
                     })
                 });
             }
@@ -462,6 +636,112 @@ impl Resolver<'_> {
         })
     }
 
+    /// Resolves all columns of a relation, except the given ones.
+    /// Returns the resolved tuple and the relation.
+    fn resolve_all_except(&mut self, except: Expr, tbl: Expr) -> Result<(Box<Expr>, Expr)> {
+        let all = Expr::new(ExprKind::All {
+            within: Box::new(Expr::new(Ident::from_name(NS_THIS))),
+            except: Box::new(except),
+        });
+        // wrap into select, so the names are resolved correctly
+        let select = FuncCall {
+            name: Box::new(Expr::new(Ident::from_path(vec!["std", "select"]))),
+            args: vec![all, tbl],
+            named_args: Default::default(),
+        };
+        let select = self.fold_expr(Expr::new(ExprKind::FuncCall(select)))?;
+
+        // unpack tbl back out
+        let select = select.kind.into_transform_call().unwrap();
+        let assigns = select.kind.into_select().unwrap();
+        Ok((assigns, *select.input))
+    }
+
+    /// Converts an array of literals into a tuple of new columns, named after the literals.
+    fn resolve_pivot_values(&mut self, values: Expr) -> Result<Expr> {
+        let span = values.span;
+        let ExprKind::Array(items) = values.kind else {
+            return Err(Error::new(Reason::Expected {
+                who: Some("`pivot`".to_string()),
+                expected: "an array of values".to_string(),
+                found: write_pl(values),
+            })
+            .with_span(span));
+        };
+
+        let mut fields = Vec::with_capacity(items.len());
+        let mut columns = Vec::with_capacity(items.len());
+        for item in items {
+            let ExprKind::Literal(literal) = item.kind else {
+                return Err(Error::new(Reason::Expected {
+                    who: Some("`pivot`".to_string()),
+                    expected: "a literal value".to_string(),
+                    found: write_pl(item.clone()),
+                })
+                .with_span(item.span));
+            };
+            let name = match &literal {
+                Literal::String(s) => s.clone(),
+                _ => literal.to_string(),
+            };
+
+            fields.push(TyTupleField::Single(Some(name.clone()), None));
+            columns.push(Expr {
+                id: Some(self.id.gen()),
+                alias: Some(name),
+                span: item.span,
+                ..Expr::new(ExprKind::Literal(literal))
+            });
+        }
+
+        Ok(Expr {
+            id: Some(self.id.gen()),
+            ty: Some(Ty::new(TyKind::Tuple(fields))),
+            span,
+            ..Expr::new(ExprKind::Tuple(columns))
+        })
+    }
+
+    /// Declares a new column, named after an unresolved ident.
+    fn new_column_of_ident(&mut self, ident: Expr, param_name: &str) -> Result<Expr> {
+        let span = ident.span;
+        let ident = ident.try_cast(ExprKind::into_ident, Some(param_name), "ident")?;
+
+        Ok(Expr {
+            id: Some(self.id.gen()),
+            alias: Some(ident.name),
+            span,
+            ..Expr::new(ExprKind::Literal(Literal::Null))
+        })
+    }
+
+    /// Checks whether a resolved tuple is `this` or one of its relations, which
+    /// stand for all of their columns.
+    fn is_all_columns(&self, expr: &Expr) -> bool {
+        let this = NS_THIS.to_string();
+        let Some(DeclKind::Module(module)) = (self.root_mod.module)
+            .get(&Ident::from_name(&this))
+            .map(|d| &d.kind)
+        else {
+            return false;
+        };
+
+        // ids of the expanded tuples are not used, so they don't need to be unique
+        let mut id = IdGenerator::new();
+        let mut expanded = vec![Self::construct_tuple_from_module(&mut id, &[&this], module)];
+        for (name, decl) in &module.names {
+            if let DeclKind::Module(relation) = &decl.kind {
+                let prefix = [&this, name];
+                expanded.push(Self::construct_tuple_from_module(
+                    &mut id, &prefix, relation,
+                ));
+            }
+        }
+
+        let written = write_pl(expr.clone());
+        (expanded.into_iter()).any(|fields| write_pl(Expr::new(ExprKind::Tuple(fields))) == written)
+    }
+
     /// Wraps non-tuple Exprs into a singleton Tuple.
     pub(super) fn coerce_into_tuple(&mut self, expr: Expr) -> Result<Expr> {
         let is_tuple_ty =
@@ -550,11 +830,43 @@ impl Resolver<'_> {
                 let pipeline = pipeline.kind.into_function().unwrap().unwrap();
                 pipeline.return_ty.map(|x| *x)
             }
-            TransformKind::Append(bottom) => {
+            TransformKind::Append { by_name: true, .. } => transform_call.input.ty.clone(),
+            TransformKind::Append { bottom, .. }
+            | TransformKind::Except { bottom, .. }
+            | TransformKind::Intersect { bottom, .. } => {
                 let top = transform_call.input.ty.clone().unwrap();
+                let bottom_span = bottom.span;
                 let bottom = bottom.ty.clone().unwrap();
 
-                Some(type_intersection(top, bottom))
+                Some(type_intersection(top, bottom).with_span(bottom_span)?)
+            }
+            TransformKind::Pivot { by, values, .. } => {
+                let by = by.ty.clone().unwrap();
+                let by = by.kind.into_tuple().unwrap();
+
+                let values = values.ty.clone().unwrap();
+                let values = values.kind.into_tuple().unwrap();
+
+                Some(Ty::new(TyKind::Array(Some(Box::new(Ty::new(
+                    ty_tuple_kind([by, values].concat()),
+                ))))))
+            }
+            TransformKind::Unpivot {
+                retained,
+                names_to,
+                values_to,
+                ..
+            } => {
+                let retained = retained.ty.clone().unwrap();
+                let retained = retained.kind.into_tuple().unwrap();
+
+                let names_to =
+                    TyTupleField::Single(names_to.alias.clone(), Some(Ty::new(PrimitiveSet::Text)));
+                let values_to = TyTupleField::Single(values_to.alias.clone(), None);
+
+                Some(Ty::new(TyKind::Array(Some(Box::new(Ty::new(
+                    ty_tuple_kind([retained, vec![names_to, values_to]].concat()),
+                ))))))
             }
         })
     }
@@ -570,10 +882,41 @@ fn range_is_empty(range: &(Option<i64>, Option<i64>)) -> bool {
 fn range_from_ints(start: Option<i64>, end: Option<i64>) -> Range {
     let start = start.map(|x| Box::new(Expr::new(ExprKind::Literal(Literal::Integer(x)))));
     let end = end.map(|x| Box::new(Expr::new(ExprKind::Literal(Literal::Integer(x)))));
-    Range { start, end }
+    Range {
+        start,
+        end,
+        end_exclusive: false,
+    }
+}
+
+/// Returns the value of a percentage literal, such as `10%`.
+fn as_percentage(expr: &Expr) -> Option<i64> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::ValueAndUnit(v)) if v.unit == "%" => Some(v.n),
+        _ => None,
+    }
+}
+
+/// Converts the exclusive end of an integer range into an inclusive one.
+fn inclusive_end(end: Expr, end_exclusive: bool) -> Result<Expr> {
+    if !end_exclusive {
+        return Ok(end);
+    }
+    match end.kind {
+        ExprKind::Literal(Literal::Integer(n)) => Ok(Expr {
+            kind: ExprKind::Literal(Literal::Integer(n - 1)),
+            ..end
+        }),
+        _ => Err(
+            Error::new_simple("expected an int literal as the exclusive end of a range")
+                .with_span(end.span),
+        ),
+    }
 }
 
-fn into_literal_range(range: (Expr, Expr)) -> Result<(Option<i64>, Option<i64>)> {
+fn into_literal_range(
+    (start, end, end_exclusive): (Expr, Expr, bool),
+) -> Result<(Option<i64>, Option<i64>)> {
     fn into_int(bound: Expr) -> Result<Option<i64>> {
         match bound.kind {
             ExprKind::Literal(Literal::Null) => Ok(None),
@@ -581,7 +924,8 @@ fn into_literal_range(range: (Expr, Expr)) -> Result<(Option<i64>, Option<i64>)>
             _ => Err(Error::new_simple("expected an int literal").with_span(bound.span)),
         }
     }
-    Ok((into_int(range.0)?, into_int(range.1)?))
+    let end = inclusive_end(end, end_exclusive)?;
+    Ok((into_int(start)?, into_int(end)?))
 }
 
 impl Resolver<'_> {
@@ -651,6 +995,29 @@ impl Resolver<'_> {
     }
 }
 
+/// Declares the group keys by their names, except those which are also columns
+/// of the partition.
+fn group_keys_env(by: &Expr, partition: &Expr) -> Module {
+    let partition_names: Vec<&String> = (partition.lineage.iter())
+        .flat_map(|l| &l.columns)
+        .filter_map(|c| match c {
+            LineageColumn::Single { name, .. } => name.as_ref().map(|n| &n.name),
+            LineageColumn::All { .. } => None,
+        })
+        .collect();
+
+    let mut env = Module::default();
+    for key in by.kind.as_tuple().into_iter().flatten() {
+        let name = (key.alias.as_ref()).or_else(|| key.kind.as_ident().map(|i| &i.name));
+        let Some(name) = name.filter(|n| !partition_names.contains(n)) else {
+            continue;
+        };
+        let decl = Decl::from(DeclKind::Column(key.id.unwrap()));
+        env.names.insert(name.clone(), decl);
+    }
+    env
+}
+
 impl TransformCall {
     pub fn infer_lineage(&self) -> Result<Lineage> {
         use TransformKind::*;
@@ -707,12 +1074,34 @@ impl TransformCall {
                 let right = lineage_or_default(with)?;
                 join(left, right)
             }
-            Append(bottom) => {
+            // columns of the bottom relation are matched to the columns of top
+            Append { by_name: true, .. } => lineage_or_default(&self.input)?,
+            Append { bottom, .. } | Except { bottom, .. } | Intersect { bottom, .. } => {
                 let top = lineage_or_default(&self.input)?;
                 let bottom = lineage_or_default(bottom)?;
                 append(top, bottom)?
             }
             Loop(_) => lineage_or_default(&self.input)?,
+            Pivot { by, values, .. } => {
+                let mut lineage = lineage_or_default(&self.input)?;
+                lineage.clear();
+                lineage.apply_assigns(by, false);
+                lineage.apply_assigns(values, false);
+                lineage
+            }
+            Unpivot {
+                retained,
+                names_to,
+                values_to,
+                ..
+            } => {
+                let mut lineage = lineage_or_default(&self.input)?;
+                lineage.clear();
+                lineage.apply_assigns(retained, false);
+                lineage.apply_assign(names_to, false);
+                lineage.apply_assign(values_to, false);
+                lineage
+            }
             Sort { .. } | Filter { .. } | Take { .. } => lineage_or_default(&self.input)?,
         })
     }
@@ -1107,7 +1496,7 @@ mod tests {
         group invoice_no (
             take 1
         )
-        ").unwrap(), @r"
+        ").unwrap(), @r#"
         def:
           version: ~
           other: {}
@@ -1133,6 +1522,7 @@ mod tests {
                     - - Wildcard
                       - 1
                   name: c_invoice
+                  span: "1:9-23"
               - Select:
                   - 0
               - Take:
@@ -1142,7 +1532,7 @@ mod tests {
                       kind:
                         Literal:
                           Integer: 1
-                      span: ~
+                      span: "1:94-95"
                   partition:
                     - 0
                   sort: []
@@ -1150,7 +1540,7 @@ mod tests {
                   - 0
           columns:
             - Single: invoice_no
-        ");
+        "#);
 
         // oops, two arguments #339
         let result = parse_resolve_and_lower(
@@ -1195,7 +1585,7 @@ mod tests {
         sort (-issued_at)
         sort {issued_at}
         sort {-issued_at}
-        ").unwrap(), @r"
+        ").unwrap(), @r#"
         def:
           version: ~
           other: {}
@@ -1227,6 +1617,7 @@ mod tests {
                     - - Wildcard
                       - 3
                   name: invoices
+                  span: "1:9-22"
               - Sort:
                   - direction: Asc
                     column: 0
@@ -1256,6 +1647,6 @@ mod tests {
             - Single: amount
             - Single: num_of_articles
             - Wildcard
-        ");
+        "#);
     }
 }
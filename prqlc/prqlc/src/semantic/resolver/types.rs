@@ -33,9 +33,15 @@ impl Resolver<'_> {
             ExprKind::TransformCall(_) => return Ok(None), // TODO
             ExprKind::Tuple(fields) => {
                 let mut ty_fields: Vec<TyTupleField> = Vec::with_capacity(fields.len());
-                let has_other = false;
+                let mut has_other = false;
 
                 for field in fields {
+                    // `this.* except {..}` stands for columns that aren't known individually
+                    if field.kind.is_all() {
+                        has_other = true;
+                        continue;
+                    }
+
                     let ty = Resolver::infer_type(field)?;
 
                     if field.flatten {
@@ -374,35 +380,43 @@ fn is_not_super_type_of(sup: Option<&Ty>, sub: Option<&Ty>) -> bool {
     false
 }
 
-fn maybe_type_intersection(a: Option<Ty>, b: Option<Ty>) -> Option<Ty> {
-    match (a, b) {
-        (Some(a), Some(b)) => Some(type_intersection(a, b)),
+fn maybe_type_intersection(a: Option<Ty>, b: Option<Ty>) -> Result<Option<Ty>> {
+    Ok(match (a, b) {
+        (Some(a), Some(b)) => Some(type_intersection(a, b)?),
         (x, None) | (None, x) => x,
-    }
+    })
 }
 
-pub fn type_intersection(a: Ty, b: Ty) -> Ty {
-    match (a.kind, b.kind) {
+pub fn type_intersection(a: Ty, b: Ty) -> Result<Ty> {
+    Ok(match (a.kind, b.kind) {
         (a_kind, b_kind) if a_kind == b_kind => Ty { kind: a_kind, ..a },
 
         // tuple
         (TyKind::Tuple(a_fields), TyKind::Tuple(b_fields)) => {
-            type_intersection_of_tuples(a_fields, b_fields)
+            type_intersection_of_tuples(a_fields, b_fields)?
         }
 
         // array
         (TyKind::Array(Some(a)), TyKind::Array(Some(b))) => {
-            Ty::new(TyKind::Array(Some(Box::new(type_intersection(*a, *b)))))
+            Ty::new(TyKind::Array(Some(Box::new(type_intersection(*a, *b)?))))
         }
 
         _ => todo!(),
-    }
+    })
 }
 
-fn type_intersection_of_tuples(a: Vec<TyTupleField>, b: Vec<TyTupleField>) -> Ty {
+fn type_intersection_of_tuples(a: Vec<TyTupleField>, b: Vec<TyTupleField>) -> Result<Ty> {
     let a_has_other = a.iter().any(|f| f.is_wildcard());
     let b_has_other = b.iter().any(|f| f.is_wildcard());
 
+    let a_len = a.len();
+    let b_len = b.len();
+    let mismatch = || {
+        Error::new_simple(format!(
+            "relations have different numbers of columns: {a_len} and {b_len}"
+        ))
+    };
+
     let mut a_fields = a.into_iter().filter_map(|f| f.into_single().ok());
     let mut b_fields = b.into_iter().filter_map(|f| f.into_single().ok());
 
@@ -413,14 +427,14 @@ fn type_intersection_of_tuples(a: Vec<TyTupleField>, b: Vec<TyTupleField>) -> Ty
             (None, None) => break,
             (None, Some(b_field)) => {
                 if !a_has_other {
-                    todo!();
+                    return Err(mismatch());
                 }
                 has_other = true;
                 fields.push(TyTupleField::Single(b_field.0, b_field.1));
             }
             (Some(a_field), None) => {
                 if !b_has_other {
-                    todo!();
+                    return Err(mismatch());
                 }
                 has_other = true;
                 fields.push(TyTupleField::Single(a_field.0, a_field.1));
@@ -431,7 +445,7 @@ fn type_intersection_of_tuples(a: Vec<TyTupleField>, b: Vec<TyTupleField>) -> Ty
                     (None, None) | (Some(_), Some(_)) => None,
                     (None, Some(n)) | (Some(n), None) => Some(n),
                 };
-                let ty = maybe_type_intersection(a_ty, b_ty);
+                let ty = maybe_type_intersection(a_ty, b_ty)?;
 
                 fields.push(TyTupleField::Single(name, ty));
             }
@@ -441,5 +455,5 @@ fn type_intersection_of_tuples(a: Vec<TyTupleField>, b: Vec<TyTupleField>) -> Ty
         fields.push(TyTupleField::Wildcard(None));
     }
 
-    Ty::new(TyKind::Tuple(fields))
+    Ok(Ty::new(TyKind::Tuple(fields)))
 }
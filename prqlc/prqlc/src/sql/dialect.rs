@@ -16,8 +16,10 @@ use std::any::{Any, TypeId};
 
 use chrono::format::{Fixed, Item, Numeric, Pad, StrftimeItems};
 use serde::{Deserialize, Serialize};
+use sqlparser::ast::SetOperator;
 use strum::VariantNames;
 
+use crate::ir::generic::SortDirection;
 use crate::{Error, Result};
 
 /// SQL dialect.
@@ -53,6 +55,7 @@ pub enum Dialect {
     GlareDb,
     MsSql,
     MySql,
+    Oracle,
     Postgres,
     SQLite,
     Snowflake,
@@ -67,6 +70,7 @@ impl Dialect {
         match self {
             Dialect::MsSql => Box::new(MsSqlDialect),
             Dialect::MySql => Box::new(MySqlDialect),
+            Dialect::Oracle => Box::new(OracleDialect),
             Dialect::BigQuery => Box::new(BigQueryDialect),
             Dialect::SQLite => Box::new(SQLiteDialect),
             Dialect::ClickHouse => Box::new(ClickHouseDialect),
@@ -87,9 +91,11 @@ impl Dialect {
             | Dialect::Generic
             | Dialect::GlareDb
             | Dialect::ClickHouse => SupportLevel::Supported,
-            Dialect::MsSql | Dialect::Ansi | Dialect::BigQuery | Dialect::Snowflake => {
-                SupportLevel::Unsupported
-            }
+            Dialect::MsSql
+            | Dialect::Ansi
+            | Dialect::BigQuery
+            | Dialect::Snowflake
+            | Dialect::Oracle => SupportLevel::Unsupported,
         }
     }
 
@@ -125,17 +131,97 @@ pub struct DuckDbDialect;
 pub struct PostgresDialect;
 #[derive(Debug)]
 pub struct GlareDbDialect;
+#[derive(Debug)]
+pub struct OracleDialect;
 
 pub(super) enum ColumnExclude {
     Exclude,
     Except,
 }
 
+pub(super) enum TakePercent {
+    /// `FROM t TABLESAMPLE BERNOULLI (10)`, which only applies to tables, so
+    /// other relations fall back to [TakePercent::LimitOfCount]
+    TableSample,
+    /// `LIMIT (SELECT COUNT(*) FROM t) * 10 / 100`
+    LimitOfCount,
+    /// `FETCH FIRST 10 PERCENT ROWS ONLY`
+    FetchPercent,
+}
+
+/// Placement of NULLs by an `ORDER BY` which doesn't specify `NULLS FIRST` or
+/// `NULLS LAST`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum NullOrdering {
+    /// Not known, so the placement is always specified
+    Unknown,
+    /// NULLs are greater than other values: last when ascending and first
+    /// when descending
+    High,
+    /// NULLs are less than other values: first when ascending and last when
+    /// descending
+    Low,
+    /// NULLs are last, regardless of the direction
+    Last,
+}
+
+impl NullOrdering {
+    /// Whether NULLs are placed first when sorting in the given direction.
+    pub(super) fn nulls_first(self, direction: &SortDirection) -> Option<bool> {
+        let asc = matches!(direction, SortDirection::Asc);
+        match self {
+            NullOrdering::Unknown => None,
+            NullOrdering::High => Some(!asc),
+            NullOrdering::Low => Some(asc),
+            NullOrdering::Last => Some(false),
+        }
+    }
+}
+
+pub(super) enum UnicodeEscape {
+    /// `U&'caf\00E9'`
+    UnicodeString,
+    /// `'caf\u00E9'`
+    Backslash,
+    /// `'caf\xC3\xA9'`, with the bytes of UTF-8
+    HexBytes,
+    /// `UNISTR('caf\00E9')`, with the code units of UTF-16
+    Unistr,
+    /// `('caf' || CHR(233))`, concatenating a function of each code point
+    CodePoint { function: &'static str },
+    /// `('caf' + NCHAR(233))`, with the code units of UTF-16
+    Nchar,
+    /// `CONCAT('caf', CONVERT(UNHEX('C3A9') USING utf8mb4))`
+    ConvertUtf8,
+    /// Non-ASCII characters can't be escaped
+    None,
+}
+
 pub(super) trait DialectHandler: Any + Debug {
     fn use_fetch(&self) -> bool {
         false
     }
 
+    /// Whether `FETCH` must be accompanied by `OFFSET`.
+    /// When required, we fallback to `OFFSET 0 ROWS`.
+    fn fetch_requires_offset(&self) -> bool {
+        false
+    }
+
+    /// Whether `OFFSET` must be accompanied by `ORDER BY`.
+    /// When required, we fallback to `ORDER BY (SELECT NULL)`.
+    fn offset_requires_sort(&self) -> bool {
+        false
+    }
+
+    /// Whether a `take` within a subquery or CTE must be accompanied by `ORDER BY`.
+    /// When required, we sort by all of the projected columns, so the selected rows
+    /// are deterministic. When those columns aren't known, we rely on the fallback
+    /// of [DialectHandler::offset_requires_sort].
+    fn take_in_subquery_requires_sort(&self) -> bool {
+        false
+    }
+
     fn ident_quote(&self) -> char {
         '"'
     }
@@ -150,6 +236,11 @@ pub(super) trait DialectHandler: Any + Debug {
         true
     }
 
+    /// Support for UNION BY NAME, which matches columns by name.
+    fn set_ops_by_name(&self) -> bool {
+        false
+    }
+
     /// Support or EXCEPT ALL.
     /// When not supported, fallback to anti join.
     fn except_all(&self) -> bool {
@@ -160,18 +251,83 @@ pub(super) trait DialectHandler: Any + Debug {
         self.except_all()
     }
 
+    /// Whether the alias of a relation in FROM can be preceded by `AS`.
+    fn table_alias_as(&self) -> bool {
+        true
+    }
+
+    /// Set operation that removes the rows of another relation.
+    fn except_operator(&self) -> SetOperator {
+        SetOperator::Except
+    }
+
     /// Support for CONCAT function.
     /// When not supported we fallback to use `||` as concat operator.
     fn has_concat_function(&self) -> bool {
         true
     }
 
+    /// Support for multi-argument GREATEST and LEAST functions.
+    /// When not supported we fallback to the scalar forms of MAX and MIN.
+    fn has_greatest_least_functions(&self) -> bool {
+        true
+    }
+
     /// Whether or not intervals such as `INTERVAL 1 HOUR` require quotes like
     /// `INTERVAL '1 HOUR'`
     fn requires_quotes_intervals(&self) -> bool {
         false
     }
 
+    /// Type to which integer branches of a `CASE` are cast, when other
+    /// branches are floats. When `None`, the database finds a common type.
+    fn case_float_type(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether a boolean column can be used as a condition by itself, as in
+    /// `WHERE is_active`. Otherwise it is compared with `1`.
+    fn supports_bare_bool_conditions(&self) -> bool {
+        true
+    }
+
+    /// Placement of NULLs when sorting, unless it's specified with
+    /// `NULLS FIRST` or `NULLS LAST`.
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::Unknown
+    }
+
+    /// Support for `SELECT` without `FROM`. When not supported, constants are
+    /// selected from the `DUAL` table.
+    fn supports_select_without_from(&self) -> bool {
+        true
+    }
+
+    /// Support for `NULLS FIRST` and `NULLS LAST`.
+    /// When not supported, we fallback to sorting by whether the value is NULL first.
+    fn supports_nulls_first_last(&self) -> bool {
+        true
+    }
+
+    /// Type to which values are cast by `as bool`. When `None`, the dialect
+    /// has no boolean type, and values are compared with zero instead.
+    fn bool_cast_type(&self) -> Option<&'static str> {
+        Some("BOOLEAN")
+    }
+
+    /// Types of integers and floats, to which `NULL` is cast where its type is
+    /// known.
+    fn numeric_types(&self) -> (&'static str, &'static str) {
+        ("INTEGER", "DOUBLE PRECISION")
+    }
+
+    /// Statement which limits the execution time of the following query to
+    /// the given number of milliseconds.
+    /// When not supported, it results in an error.
+    fn statement_timeout(&self, _milliseconds: i64) -> Option<String> {
+        None
+    }
+
     /// Support for GROUP BY *
     fn stars_in_group(&self) -> bool {
         true
@@ -181,6 +337,41 @@ pub(super) trait DialectHandler: Any + Debug {
         false
     }
 
+    /// How a `take` of a percentage of rows is expressed.
+    /// When not supported, it results in an error.
+    fn take_percent(&self) -> Option<TakePercent> {
+        Some(TakePercent::LimitOfCount)
+    }
+
+    /// Support for a subquery in `LIMIT`, as used by [TakePercent::LimitOfCount]
+    fn supports_limit_subquery(&self) -> bool {
+        true
+    }
+
+    /// Support for `JOIN ... USING (col)`, with the joined column still
+    /// referenced qualified by either relation
+    fn supports_join_using(&self) -> bool {
+        true
+    }
+
+    /// Support for `PIVOT` and `UNPIVOT` in FROM clause
+    fn supports_pivot(&self) -> bool {
+        false
+    }
+
+    /// Support for table value constructors with column aliases, such as
+    /// `(VALUES (1, 2)) AS t(a, b)`
+    fn supports_values(&self) -> bool {
+        false
+    }
+
+    /// Whether a relation computing window functions that a following filter
+    /// refers to is emitted as a subquery of the filtering SELECT.
+    /// When false, it is emitted as a CTE, like other split relations.
+    fn window_filter_in_subquery(&self) -> bool {
+        false
+    }
+
     /// Get the date format for the given dialect
     /// PRQL uses the same format as `chrono` crate
     /// (see https://docs.rs/chrono/latest/chrono/format/strftime/index.html)
@@ -197,9 +388,29 @@ pub(super) trait DialectHandler: Any + Debug {
         ))
     }
 
+    /// Get the argument of the dialect's date truncation for the given unit,
+    /// such as `month`
+    fn translate_date_trunc_unit(&self, unit: &str) -> Result<String> {
+        Ok(unit.to_string())
+    }
+
     fn supports_zero_columns(&self) -> bool {
         false
     }
+
+    /// Maximum length of an identifier, in bytes.
+    /// Longer identifiers are truncated and suffixed with a hash of the full
+    /// name, so they remain unique. Names of existing tables and columns can't
+    /// exceed the limit, so only aliases from the query are ever truncated.
+    fn max_ident_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Syntax for escaping non-ASCII characters in string literals,
+    /// when [crate::OutputEncoding::Ascii] is requested.
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::None
+    }
 }
 
 impl dyn DialectHandler {
@@ -213,9 +424,23 @@ impl DialectHandler for GenericDialect {
     fn translate_chrono_item(&self, _item: Item) -> Result<String> {
         Err(Error::new_simple("Date formatting requires a dialect"))
     }
+
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::UnicodeString
+    }
 }
 
 impl DialectHandler for PostgresDialect {
+    // https://www.postgresql.org/docs/current/queries-order.html
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::High
+    }
+
+    fn statement_timeout(&self, milliseconds: i64) -> Option<String> {
+        // https://www.postgresql.org/docs/current/runtime-config-client.html#GUC-STATEMENT-TIMEOUT
+        Some(format!("SET statement_timeout = {milliseconds}"))
+    }
+
     fn requires_quotes_intervals(&self) -> bool {
         true
     }
@@ -224,6 +449,15 @@ impl DialectHandler for PostgresDialect {
         true
     }
 
+    // https://www.postgresql.org/docs/current/sql-select.html#SQL-FROM
+    fn take_percent(&self) -> Option<TakePercent> {
+        Some(TakePercent::TableSample)
+    }
+
+    fn supports_values(&self) -> bool {
+        true
+    }
+
     // https://www.postgresql.org/docs/current/functions-formatting.html
     fn translate_chrono_item<'a>(&self, item: Item) -> Result<String> {
         Ok(match item {
@@ -269,15 +503,40 @@ impl DialectHandler for PostgresDialect {
     fn supports_zero_columns(&self) -> bool {
         true
     }
+
+    // longer identifiers are silently truncated, so distinct aliases could collide
+    // https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS
+    fn max_ident_len(&self) -> Option<usize> {
+        Some(63)
+    }
+
+    // https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-STRINGS-UESCAPE
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::UnicodeString
+    }
 }
 
 impl DialectHandler for GlareDbDialect {
     fn requires_quotes_intervals(&self) -> bool {
         true
     }
+
+    // LIMIT only accepts constants
+    fn take_percent(&self) -> Option<TakePercent> {
+        None
+    }
+
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::UnicodeString
+    }
 }
 
 impl DialectHandler for SQLiteDialect {
+    // https://www.sqlite.org/lang_select.html#the_order_by_clause
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::Low
+    }
+
     fn set_ops_distinct(&self) -> bool {
         false
     }
@@ -290,16 +549,87 @@ impl DialectHandler for SQLiteDialect {
         false
     }
 
+    // https://www.sqlite.org/lang_corefunc.html#max_scalar
+    fn has_greatest_least_functions(&self) -> bool {
+        false
+    }
+
+    // `strftime` formats which zero the fields smaller than the unit
+    fn translate_date_trunc_unit(&self, unit: &str) -> Result<String> {
+        Ok(match unit {
+            "year" => "%Y-01-01",
+            "month" => "%Y-%m-01",
+            "day" => "%Y-%m-%d",
+            "hour" => "%Y-%m-%d %H:00:00",
+            "minute" => "%Y-%m-%d %H:%M:00",
+            "second" => "%Y-%m-%d %H:%M:%S",
+            _ => {
+                return Err(Error::new_simple(format!(
+                    "Truncating dates to a {unit} is not supported for SQLite"
+                )))
+            }
+        }
+        .to_string())
+    }
+
     fn stars_in_group(&self) -> bool {
         false
     }
+
+    // https://www.sqlite.org/lang_corefunc.html#char
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::CodePoint { function: "CHAR" }
+    }
 }
 
 impl DialectHandler for MsSqlDialect {
+    // https://learn.microsoft.com/en-us/sql/t-sql/queries/select-order-by-clause-transact-sql#arguments
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::Low
+    }
+
+    // there is no NULLS FIRST or NULLS LAST
+    fn supports_nulls_first_last(&self) -> bool {
+        false
+    }
+
+    // there is no boolean type, only BIT, which can't be used as a condition
+    // https://learn.microsoft.com/en-us/sql/t-sql/data-types/bit-transact-sql
+    fn bool_cast_type(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn supports_bare_bool_conditions(&self) -> bool {
+        false
+    }
+
+    // there is no USING, only ON
+    fn supports_join_using(&self) -> bool {
+        false
+    }
+
     fn use_fetch(&self) -> bool {
         true
     }
 
+    fn fetch_requires_offset(&self) -> bool {
+        true
+    }
+
+    fn offset_requires_sort(&self) -> bool {
+        true
+    }
+
+    // https://learn.microsoft.com/en-us/sql/t-sql/queries/select-order-by-clause-transact-sql#limitations-and-restrictions
+    fn take_in_subquery_requires_sort(&self) -> bool {
+        true
+    }
+
+    // FETCH does not accept PERCENT
+    fn take_percent(&self) -> Option<TakePercent> {
+        None
+    }
+
     // https://learn.microsoft.com/en-us/sql/t-sql/language-elements/set-operators-except-and-intersect-transact-sql?view=sql-server-ver16
     fn except_all(&self) -> bool {
         false
@@ -309,6 +639,11 @@ impl DialectHandler for MsSqlDialect {
         false
     }
 
+    // https://learn.microsoft.com/en-us/sql/t-sql/queries/table-value-constructor-transact-sql
+    fn supports_values(&self) -> bool {
+        true
+    }
+
     // https://learn.microsoft.com/en-us/dotnet/standard/base-types/custom-date-and-time-format-strings
     fn translate_chrono_item<'a>(&self, item: Item) -> Result<String> {
         Ok(match item {
@@ -353,13 +688,59 @@ impl DialectHandler for MsSqlDialect {
             }
         })
     }
+
+    // https://learn.microsoft.com/en-us/sql/relational-databases/databases/database-identifiers
+    fn max_ident_len(&self) -> Option<usize> {
+        Some(128)
+    }
+
+    // https://learn.microsoft.com/en-us/sql/t-sql/functions/nchar-transact-sql
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::Nchar
+    }
 }
 
 impl DialectHandler for MySqlDialect {
+    // https://dev.mysql.com/doc/refman/8.0/en/working-with-null.html
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::Low
+    }
+
+    // there is no NULLS FIRST or NULLS LAST
+    fn supports_nulls_first_last(&self) -> bool {
+        false
+    }
+
+    // CAST does not accept BOOLEAN, which is an alias of TINYINT(1)
+    // https://dev.mysql.com/doc/refman/8.0/en/cast-functions.html#function_cast
+    fn bool_cast_type(&self) -> Option<&'static str> {
+        None
+    }
+
+    // CAST does not accept INTEGER, only SIGNED and UNSIGNED
+    fn numeric_types(&self) -> (&'static str, &'static str) {
+        ("SIGNED", "DOUBLE")
+    }
+
+    fn statement_timeout(&self, milliseconds: i64) -> Option<String> {
+        // https://dev.mysql.com/doc/refman/8.0/en/server-system-variables.html#sysvar_max_execution_time
+        Some(format!("SET max_execution_time = {milliseconds}"))
+    }
+
     fn ident_quote(&self) -> char {
         '`'
     }
 
+    // https://dev.mysql.com/doc/refman/8.0/en/identifier-length.html
+    fn max_ident_len(&self) -> Option<usize> {
+        Some(64)
+    }
+
+    // LIMIT only accepts constants
+    fn take_percent(&self) -> Option<TakePercent> {
+        None
+    }
+
     fn set_ops_distinct(&self) -> bool {
         // https://dev.mysql.com/doc/refman/8.0/en/set-operations.html
         true
@@ -395,9 +776,25 @@ impl DialectHandler for MySqlDialect {
             }
         })
     }
+
+    // https://dev.mysql.com/doc/refman/8.0/en/cast-functions.html#function_convert
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::ConvertUtf8
+    }
 }
 
 impl DialectHandler for ClickHouseDialect {
+    // https://clickhouse.com/docs/en/sql-reference/statements/select/order-by
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::Last
+    }
+
+    // LIMIT only accepts constants
+    // https://clickhouse.com/docs/en/sql-reference/statements/select/limit
+    fn take_percent(&self) -> Option<TakePercent> {
+        None
+    }
+
     fn ident_quote(&self) -> char {
         '`'
     }
@@ -406,6 +803,22 @@ impl DialectHandler for ClickHouseDialect {
         true
     }
 
+    // There is no `QUALIFY`, so windows are filtered in the enclosing SELECT
+    fn window_filter_in_subquery(&self) -> bool {
+        true
+    }
+
+    // Literals get the smallest fitting type, such as `UInt8`, which may not
+    // have a supertype in common with the other branches
+    fn case_float_type(&self) -> Option<&'static str> {
+        Some("Float64")
+    }
+
+    // only nullable types can hold NULL
+    fn numeric_types(&self) -> (&'static str, &'static str) {
+        ("Nullable(Int64)", "Nullable(Float64)")
+    }
+
     // https://clickhouse.com/docs/en/sql-reference/functions/date-time-functions#formatDateTimeInJodaSyntax
     fn translate_chrono_item<'a>(&self, item: Item) -> Result<String> {
         Ok(match item {
@@ -446,9 +859,19 @@ impl DialectHandler for ClickHouseDialect {
             }
         })
     }
+
+    // https://clickhouse.com/docs/en/sql-reference/syntax#string
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::HexBytes
+    }
 }
 
 impl DialectHandler for BigQueryDialect {
+    // https://cloud.google.com/bigquery/docs/reference/standard-sql/query-syntax#order_by_clause
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::Low
+    }
+
     fn ident_quote(&self) -> char {
         '`'
     }
@@ -461,9 +884,34 @@ impl DialectHandler for BigQueryDialect {
         // https://cloud.google.com/bigquery/docs/reference/standard-sql/query-syntax#set_operators
         true
     }
+
+    fn take_percent(&self) -> Option<TakePercent> {
+        // LIMIT only accepts constants
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/query-syntax#limit_and_offset_clause
+        None
+    }
+
+    fn bool_cast_type(&self) -> Option<&'static str> {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/data-types#boolean_type
+        Some("BOOL")
+    }
+
+    fn numeric_types(&self) -> (&'static str, &'static str) {
+        ("INT64", "FLOAT64")
+    }
+
+    // https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#string_and_bytes_literals
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::Backslash
+    }
 }
 
 impl DialectHandler for SnowflakeDialect {
+    // https://docs.snowflake.com/en/sql-reference/constructs/order-by
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::High
+    }
+
     fn column_exclude(&self) -> Option<ColumnExclude> {
         // https://docs.snowflake.com/en/sql-reference/sql/select.html
         Some(ColumnExclude::Exclude)
@@ -473,9 +921,39 @@ impl DialectHandler for SnowflakeDialect {
         // https://docs.snowflake.com/en/sql-reference/operators-query.html
         false
     }
+
+    fn take_percent(&self) -> Option<TakePercent> {
+        // https://docs.snowflake.com/en/sql-reference/constructs/sample
+        Some(TakePercent::TableSample)
+    }
+
+    fn supports_limit_subquery(&self) -> bool {
+        // https://docs.snowflake.com/en/sql-reference/constructs/limit
+        false
+    }
+
+    fn supports_pivot(&self) -> bool {
+        // https://docs.snowflake.com/en/sql-reference/constructs/pivot
+        true
+    }
+
+    fn supports_values(&self) -> bool {
+        // https://docs.snowflake.com/en/sql-reference/constructs/values
+        true
+    }
+
+    // https://docs.snowflake.com/en/sql-reference/functions/chr
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::CodePoint { function: "CHR" }
+    }
 }
 
 impl DialectHandler for DuckDbDialect {
+    // https://duckdb.org/docs/sql/query_syntax/orderby
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::Last
+    }
+
     fn column_exclude(&self) -> Option<ColumnExclude> {
         // https://duckdb.org/2022/05/04/friendlier-sql.html#select--exclude
         Some(ColumnExclude::Exclude)
@@ -486,10 +964,30 @@ impl DialectHandler for DuckDbDialect {
         false
     }
 
+    fn set_ops_by_name(&self) -> bool {
+        // https://duckdb.org/docs/sql/query_syntax/setops.html#union-all-by-name
+        true
+    }
+
     fn supports_distinct_on(&self) -> bool {
         true
     }
 
+    fn take_percent(&self) -> Option<TakePercent> {
+        // https://duckdb.org/docs/sql/samples
+        Some(TakePercent::TableSample)
+    }
+
+    fn supports_pivot(&self) -> bool {
+        // https://duckdb.org/docs/sql/statements/pivot
+        true
+    }
+
+    fn supports_values(&self) -> bool {
+        // https://duckdb.org/docs/sql/query_syntax/values
+        true
+    }
+
     // https://duckdb.org/docs/sql/functions/dateformat
     fn translate_chrono_item<'a>(&self, item: Item) -> Result<String> {
         Ok(match item {
@@ -520,6 +1018,79 @@ impl DialectHandler for DuckDbDialect {
             }
         })
     }
+
+    // https://duckdb.org/docs/sql/functions/char#chrcode_point
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::CodePoint { function: "CHR" }
+    }
+}
+
+impl DialectHandler for OracleDialect {
+    // NULLs are sorted as if they were greater than any other value
+    fn default_null_ordering(&self) -> NullOrdering {
+        NullOrdering::High
+    }
+
+    // There is no LIMIT, but FETCH FIRST is supported since 12c
+    fn use_fetch(&self) -> bool {
+        true
+    }
+
+    // AS is only allowed before column aliases
+    fn table_alias_as(&self) -> bool {
+        false
+    }
+
+    // columns of USING cannot be qualified (ORA-25154)
+    fn supports_join_using(&self) -> bool {
+        false
+    }
+
+    // https://docs.oracle.com/en/database/oracle/oracle-database/23/sqlrf/Database-Object-Names-and-Qualifiers.html
+    fn max_ident_len(&self) -> Option<usize> {
+        Some(128)
+    }
+
+    fn take_percent(&self) -> Option<TakePercent> {
+        Some(TakePercent::FetchPercent)
+    }
+
+    fn set_ops_distinct(&self) -> bool {
+        false
+    }
+
+    fn except_all(&self) -> bool {
+        false
+    }
+
+    // EXCEPT is only a synonym since 21c
+    fn except_operator(&self) -> SetOperator {
+        SetOperator::Minus
+    }
+
+    // CONCAT only accepts two arguments
+    fn has_concat_function(&self) -> bool {
+        false
+    }
+
+    fn stars_in_group(&self) -> bool {
+        false
+    }
+
+    fn supports_pivot(&self) -> bool {
+        // supported since 11g
+        true
+    }
+
+    // supported since 23ai
+    fn supports_select_without_from(&self) -> bool {
+        false
+    }
+
+    // https://docs.oracle.com/en/database/oracle/oracle-database/23/sqlrf/UNISTR.html
+    fn unicode_escape(&self) -> UnicodeEscape {
+        UnicodeEscape::Unistr
+    }
 }
 
 #[cfg(test)]
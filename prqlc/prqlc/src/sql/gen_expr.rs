@@ -1,9 +1,11 @@
 //! Contains functions that compile [crate::pr::pl] nodes into [sqlparser] nodes.
 
 use std::cmp::Ordering;
+use std::fmt::Write;
 
+use chrono::NaiveDate;
 use itertools::Itertools;
-use prqlc_parser::generic::{InterpolateItem, Range};
+use prqlc_parser::generic::{InterpolateItem, Range, SwitchCase, FORMAT_IDENT};
 use regex::Regex;
 use sqlparser::ast::{
     self as sql_ast, BinaryOperator, DateTimeField, Fetch, Function, FunctionArg, FunctionArgExpr,
@@ -11,16 +13,24 @@ use sqlparser::ast::{
     WindowFrameBound, WindowSpec,
 };
 
+use super::dialect::UnicodeEscape;
 use super::gen_projection::try_into_exprs;
 use super::{keywords, Context};
 use crate::ir::generic::{ColumnSort, SortDirection, WindowFrame, WindowKind};
 use crate::ir::pl::{self, Ident, Literal};
 use crate::ir::rq;
 use crate::sql::pq::context::ColumnDecl;
-use crate::utils::{valid_ident, OrMap};
-use crate::{Error, Reason, Result, Span, WithErrorInfo};
+use crate::utils::{fnv1a_64, valid_ident, OrMap};
+use crate::{Error, NullsOrder, OutputEncoding, Reason, Result, Span, WithErrorInfo};
 
 pub(super) fn translate_expr(expr: rq::Expr, ctx: &mut Context) -> Result<ExprOrSource> {
+    let span = expr.span;
+    let res = translate_expr_kind(expr, ctx)?;
+    ctx.map_source(span, || res.clone().into_source());
+    Ok(res)
+}
+
+fn translate_expr_kind(expr: rq::Expr, ctx: &mut Context) -> Result<ExprOrSource> {
     Ok(match expr.kind {
         rq::ExprKind::ColumnRef(cid) => translate_cid(cid, ctx)?,
 
@@ -43,6 +53,19 @@ pub(super) fn translate_expr(expr: rq::Expr, ctx: &mut Context) -> Result<ExprOr
         }),
         rq::ExprKind::Literal(l) => translate_literal(l, ctx)?.into(),
         rq::ExprKind::Case(mut cases) => {
+            let float_type = common_float_type(&cases, ctx);
+            let null_type = null_cast_type(&cases, ctx);
+            let translate_value = |value: rq::Expr, ctx: &mut Context| -> Result<_> {
+                let is_int = infer_numeric_type(&value, ctx) == Some(NumericType::Int);
+                let is_null = matches!(value.kind, rq::ExprKind::Literal(Literal::Null));
+                let value = translate_expr(value, ctx)?.into_ast();
+                Ok(match (float_type, null_type) {
+                    (Some(ty), _) if is_int => cast_to(value, ty),
+                    (_, Some(ty)) if is_null => cast_to(value, ty),
+                    _ => value,
+                })
+            };
+
             let default = cases
                 .last()
                 .filter(|last| {
@@ -51,23 +74,28 @@ pub(super) fn translate_expr(expr: rq::Expr, ctx: &mut Context) -> Result<ExprOr
                         rq::ExprKind::Literal(Literal::Boolean(true))
                     )
                 })
-                .map(|def| translate_expr(def.value.clone(), ctx))
-                .transpose()?
-                .map(|x| x.into_ast());
+                .map(|def| translate_value(def.value.clone(), ctx))
+                .transpose()?;
 
             if default.is_some() {
                 cases.pop();
             }
 
             let else_result = default
-                .or(Some(sql_ast::Expr::Value(Value::Null)))
+                .or_else(|| {
+                    let null = sql_ast::Expr::Value(Value::Null);
+                    Some(match null_type {
+                        Some(ty) => cast_to(null, ty),
+                        None => null,
+                    })
+                })
                 .map(Box::new);
 
             let cases: Vec<_> = cases
                 .into_iter()
                 .map(|case| -> Result<_> {
-                    let cond = translate_expr(case.condition, ctx)?.into_ast();
-                    let value = translate_expr(case.value, ctx)?.into_ast();
+                    let cond = translate_condition(case.condition, ctx)?;
+                    let value = translate_value(case.value, ctx)?;
                     Ok((cond, value))
                 })
                 .try_collect()?;
@@ -103,10 +131,27 @@ pub(super) fn translate_expr(expr: rq::Expr, ctx: &mut Context) -> Result<ExprOr
                     }
                 }
                 "std.concat" => return Ok(process_concat(&expr, ctx)?.into()),
+                "std.coalesce" => return Ok(process_coalesce(&expr, ctx)?.into()),
+                "std.as" => {
+                    if let Some(cast) = try_into_bool_cast(args, ctx)? {
+                        return Ok(cast.into());
+                    }
+                }
+                "std.greatest" | "std.least" => {
+                    return Ok(process_greatest_least(&expr, name, args, ctx)?.into())
+                }
                 "std.array_in" => return Ok(process_array_in(&expr, args, ctx)?.into()),
                 "std.date.to_text" => {
                     return Ok(process_date_to_text(&expr, name, args, ctx)?.into())
                 }
+                "std.date.date_trunc" => {
+                    return Ok(process_date_trunc(&expr, name, args, ctx)?.into())
+                }
+                "std.math.floor" | "std.math.ceil" => {
+                    if let Some(scaled) = process_floor_ceil(&expr, name, args, ctx)? {
+                        return Ok(scaled.into());
+                    }
+                }
                 _ => match try_into_between(expr.clone(), ctx)? {
                     Some(between_expr) => return Ok(between_expr.into()),
                     None => {
@@ -126,9 +171,22 @@ pub(super) fn translate_expr(expr: rq::Expr, ctx: &mut Context) -> Result<ExprOr
             })
             .with_span(expr.span));
         }
+        rq::ExprKind::SubQuery(tid) => {
+            sql_ast::Expr::Subquery(Box::new(translate_sub_query(tid, ctx)?)).into()
+        }
     })
 }
 
+fn translate_sub_query(tid: rq::TId, ctx: &mut Context) -> Result<sql_ast::Query> {
+    let relation = ctx.sub_queries.remove(&tid).unwrap();
+
+    ctx.push_query();
+    ctx.query.is_subquery = true;
+    let query = super::gen_query::translate_relation(relation, ctx)?;
+    ctx.pop_query();
+    Ok(query)
+}
+
 /// Translates into IS NULL if possible
 fn process_null(name: &str, args: &[rq::Expr], ctx: &mut Context) -> Result<sql_ast::Expr> {
     let (a, b) = (&args[0], &args[1]);
@@ -191,6 +249,14 @@ fn process_array_in(
                 })
             }
         }
+        [col_expr, rq::Expr {
+            kind: rq::ExprKind::SubQuery(tid),
+            ..
+        }] => Ok(sql_ast::Expr::InSubquery {
+            expr: Box::new(translate_expr(col_expr.clone(), ctx)?.into_ast()),
+            subquery: Box::new(translate_sub_query(*tid, ctx)?),
+            negated: false,
+        }),
         _ => Err(
             Error::new_simple("args to `std.array_in` must be an expression and an array")
                 .with_span(expr.span),
@@ -237,9 +303,257 @@ fn process_date_to_text(
     }
 }
 
+/// Units which `std.date.date_trunc` can truncate to
+const DATE_TRUNC_UNITS: [&str; 8] = [
+    "year", "quarter", "month", "week", "day", "hour", "minute", "second",
+];
+
+/// Checks the unit of `std.date.date_trunc` and translates it for the dialect
+/// For now only unit as string literal is supported
+fn process_date_trunc(
+    expr: &rq::Expr,
+    op_name: &str,
+    args: &[rq::Expr],
+    ctx: &mut Context,
+) -> Result<sql_ast::Expr> {
+    let [unit_exp @ rq::Expr {
+        kind: rq::ExprKind::Literal(Literal::String(unit)),
+        ..
+    }, col_expr] = args
+    else {
+        return Err(Error::new_simple(
+            "`std.date.date_trunc` only supports a string literal as unit",
+        )
+        .with_span(expr.span));
+    };
+
+    if !DATE_TRUNC_UNITS.contains(&unit.as_str()) {
+        return Err(Error::new_simple(format!("unknown date unit `{unit}`"))
+            .push_hint(format!("expected one of {}", DATE_TRUNC_UNITS.join(", ")))
+            .with_span(unit_exp.span));
+    }
+    let unit =
+        (ctx.dialect.translate_date_trunc_unit(unit)).map_err(|e| e.with_span(unit_exp.span))?;
+
+    let expr = rq::Expr {
+        kind: rq::ExprKind::Operator {
+            name: op_name.to_string(),
+            args: vec![
+                rq::Expr {
+                    kind: rq::ExprKind::Literal(Literal::String(unit)),
+                    span: unit_exp.span,
+                },
+                col_expr.clone(),
+            ],
+        },
+        ..expr.clone()
+    };
+    Ok(super::operators::translate_operator_expr(expr, ctx)?.into_ast())
+}
+
+/// Translates `std.math.floor` & `std.math.ceil` with a precision of
+/// `n_digits` into `FLOOR(column * 10^n) / 10^n`, since most dialects don't
+/// accept a precision for these functions. Returns `None` without a precision.
+fn process_floor_ceil(
+    expr: &rq::Expr,
+    op_name: &str,
+    args: &[rq::Expr],
+    ctx: &mut Context,
+) -> Result<Option<sql_ast::Expr>> {
+    let [n_digits, column] = args else {
+        return Ok(None);
+    };
+
+    let factor = match n_digits.kind {
+        rq::ExprKind::Literal(Literal::Integer(0)) => return Ok(None),
+        rq::ExprKind::Literal(Literal::Integer(n)) if n > 0 => {
+            let factor = u32::try_from(n)
+                .ok()
+                .and_then(|n| 10_i64.checked_pow(n))
+                .ok_or_else(|| {
+                    Error::new_simple(format!("precision of {n} digits is out of range"))
+                        .with_span(n_digits.span)
+                })?;
+            rq::ExprKind::Literal(Literal::Integer(factor))
+        }
+        // parsed rather than computed, so `-2` gives exactly `0.01`
+        rq::ExprKind::Literal(Literal::Integer(n)) => {
+            rq::ExprKind::Literal(Literal::Float(format!("1e{n}").parse().unwrap()))
+        }
+        _ => rq::ExprKind::Operator {
+            name: "std.math.pow".to_string(),
+            args: vec![
+                n_digits.clone(),
+                rq::Expr {
+                    kind: rq::ExprKind::Literal(Literal::Integer(10)),
+                    span: None,
+                },
+            ],
+        },
+    };
+    let factor = rq::Expr {
+        kind: factor,
+        span: n_digits.span,
+    };
+
+    let scaled = rq::Expr {
+        kind: rq::ExprKind::Operator {
+            name: "std.mul".to_string(),
+            args: vec![column.clone(), factor.clone()],
+        },
+        span: column.span,
+    };
+    let rounded = rq::Expr {
+        kind: rq::ExprKind::Operator {
+            name: op_name.to_string(),
+            args: vec![
+                rq::Expr {
+                    kind: rq::ExprKind::Literal(Literal::Integer(0)),
+                    span: n_digits.span,
+                },
+                scaled,
+            ],
+        },
+        span: expr.span,
+    };
+
+    translate_binary_operator(&rounded, &factor, BinaryOperator::Divide, ctx).map(Some)
+}
+
+/// Translates an expression used as a condition, as in `WHERE`, `ON` or
+/// `CASE WHEN`.
+pub(super) fn translate_condition(expr: rq::Expr, ctx: &mut Context) -> Result<sql_ast::Expr> {
+    let expr = if ctx.dialect.supports_bare_bool_conditions() {
+        expr
+    } else {
+        compare_bare_columns(expr, ctx)
+    };
+    Ok(translate_expr(expr, ctx)?.into_ast())
+}
+
+/// Compares columns that are used as conditions by themselves with `1`,
+/// so `is_active && !is_deleted` becomes `is_active = 1 AND NOT is_deleted = 1`.
+fn compare_bare_columns(expr: rq::Expr, ctx: &Context) -> rq::Expr {
+    match expr.kind {
+        rq::ExprKind::Operator { name, args }
+            if matches!(name.as_str(), "std.and" | "std.or" | "std.not") =>
+        {
+            let args = args
+                .into_iter()
+                .map(|arg| compare_bare_columns(arg, ctx))
+                .collect();
+            rq::Expr {
+                kind: rq::ExprKind::Operator { name, args },
+                ..expr
+            }
+        }
+        rq::ExprKind::ColumnRef(cid) => match &ctx.anchor.column_decls[&cid] {
+            // computed columns are inlined before the projection
+            ColumnDecl::Compute(compute) if ctx.query.pre_projection => {
+                let inlined = compare_bare_columns(compute.expr.clone(), ctx);
+                if compute.window.is_none() && inlined != compute.expr {
+                    inlined
+                } else {
+                    expr
+                }
+            }
+            _ => rq::Expr {
+                kind: rq::ExprKind::Operator {
+                    name: "std.eq".to_string(),
+                    args: vec![expr, rq::Expr::from(1)],
+                },
+                span: None,
+            },
+        },
+        kind => rq::Expr { kind, ..expr },
+    }
+}
+
+/// Translates `as bool` into the dialect's cast to boolean
+fn try_into_bool_cast(args: &[rq::Expr], ctx: &mut Context) -> Result<Option<sql_ast::Expr>> {
+    let [ty, value] = args else {
+        return Ok(None);
+    };
+    let rq::ExprKind::SString(items) = &ty.kind else {
+        return Ok(None);
+    };
+    let [InterpolateItem::String(ty)] = items.as_slice() else {
+        return Ok(None);
+    };
+    if !ty.eq_ignore_ascii_case("bool") && !ty.eq_ignore_ascii_case("boolean") {
+        return Ok(None);
+    }
+
+    Ok(Some(match ctx.dialect.bool_cast_type() {
+        Some(data_type) => sql_ast::Expr::Cast {
+            kind: sql_ast::CastKind::Cast,
+            expr: Box::new(translate_expr(value.clone(), ctx)?.into_ast()),
+            data_type: sql_ast::DataType::Custom(
+                ObjectName(vec![sql_ast::Ident::new(data_type)]),
+                vec![],
+            ),
+            format: None,
+        },
+        None => {
+            let zero = rq::Expr {
+                kind: rq::ExprKind::Literal(Literal::Integer(0)),
+                span: None,
+            };
+            translate_binary_operator(value, &zero, BinaryOperator::NotEq, ctx)?
+        }
+    }))
+}
+
+/// Translates into GREATEST / LEAST, or their scalar MAX / MIN emulation
+fn process_greatest_least(
+    expr: &rq::Expr,
+    name: &str,
+    args: &[rq::Expr],
+    ctx: &mut Context,
+) -> Result<sql_ast::Expr> {
+    match args {
+        [] => Err(
+            Error::new_simple(format!("`{name}` requires at least one value")).with_span(expr.span),
+        ),
+        // scalar MAX / MIN with a single argument would be an aggregation
+        [value] => Ok(translate_expr(value.clone(), ctx)?.into_ast()),
+        _ => {
+            let func_name = match (name, ctx.dialect.has_greatest_least_functions()) {
+                ("std.greatest", true) => "GREATEST",
+                ("std.greatest", false) => "MAX",
+                (_, true) => "LEAST",
+                (_, false) => "MIN",
+            };
+
+            let args_list = args
+                .iter()
+                .map(|a| {
+                    translate_expr(a.clone(), ctx)
+                        .map(|x| FunctionArg::Unnamed(FunctionArgExpr::Expr(x.into_ast())))
+                })
+                .try_collect()?;
+
+            Ok(sql_ast::Expr::Function(Function {
+                name: ObjectName(vec![sql_ast::Ident::new(func_name)]),
+                args: sql_ast::FunctionArguments::List(FunctionArgumentList {
+                    args: args_list,
+                    clauses: vec![],
+                    duplicate_treatment: None,
+                }),
+                over: None,
+                filter: None,
+                null_treatment: None,
+                within_group: vec![],
+                parameters: sql_ast::FunctionArguments::None,
+                uses_odbc_syntax: false,
+            }))
+        }
+    }
+}
+
 fn process_concat(expr: &rq::Expr, ctx: &mut Context) -> Result<sql_ast::Expr> {
     if ctx.dialect.has_concat_function() {
-        let concat_args = collect_concat_args(expr);
+        let concat_args = collect_nested_args(expr, "std.concat");
 
         let args_list = concat_args
             .iter()
@@ -266,7 +580,7 @@ fn process_concat(expr: &rq::Expr, ctx: &mut Context) -> Result<sql_ast::Expr> {
             uses_odbc_syntax: false,
         }))
     } else {
-        let concat_args = collect_concat_args(expr);
+        let concat_args = collect_nested_args(expr, "std.concat");
 
         let mut iter = concat_args.into_iter();
         let first_expr = iter.next().unwrap();
@@ -285,6 +599,17 @@ fn process_concat(expr: &rq::Expr, ctx: &mut Context) -> Result<sql_ast::Expr> {
     }
 }
 
+/// Translates nested coalescing, such as `a ?? b ?? c`, into a single
+/// `COALESCE(a, b, c)`.
+fn process_coalesce(expr: &rq::Expr, ctx: &mut Context) -> Result<sql_ast::Expr> {
+    let args = collect_nested_args(expr, "std.coalesce")
+        .into_iter()
+        .map(|a| translate_expr(a.clone(), ctx).map(|x| x.into_ast()))
+        .try_collect()?;
+
+    Ok(function_call("COALESCE", args))
+}
+
 fn translate_binary_operator(
     left: &rq::Expr,
     right: &rq::Expr,
@@ -302,11 +627,14 @@ fn translate_binary_operator(
     Ok(sql_ast::Expr::BinaryOp { left, op, right })
 }
 
-fn collect_concat_args(expr: &rq::Expr) -> Vec<&rq::Expr> {
+/// Collects the args of nested calls of an operator, so `(a ?? b) ?? c`
+/// produces `[a, b, c]`.
+fn collect_nested_args<'a>(expr: &'a rq::Expr, op_name: &str) -> Vec<&'a rq::Expr> {
     match &expr.kind {
-        rq::ExprKind::Operator { name, args } if name == "std.concat" => {
-            args.iter().flat_map(collect_concat_args).collect()
-        }
+        rq::ExprKind::Operator { name, args } if name == op_name => args
+            .iter()
+            .flat_map(|a| collect_nested_args(a, op_name))
+            .collect(),
         _ => vec![expr],
     }
 }
@@ -378,16 +706,169 @@ fn operator_from_name(name: &str) -> Option<BinaryOperator> {
     }
 }
 
+fn translate_string_literal(s: String, ctx: &Context) -> Result<sql_ast::Expr> {
+    if ctx.output_encoding != OutputEncoding::Ascii || s.is_ascii() {
+        return Ok(sql_ast::Expr::Value(Value::SingleQuotedString(s)));
+    }
+
+    // escapes the backslash and non-ASCII characters within a string
+    let escape = |escape_char: &dyn Fn(char, &mut String)| {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            match c {
+                '\\' => escaped.push_str(r"\\"),
+                c if c.is_ascii() => escaped.push(c),
+                c => escape_char(c, &mut escaped),
+            }
+        }
+        Value::SingleQuotedString(escaped)
+    };
+
+    Ok(match ctx.dialect.unicode_escape() {
+        // escaping is done by sqlparser
+        UnicodeEscape::UnicodeString => sql_ast::Expr::Value(Value::UnicodeStringLiteral(s)),
+        UnicodeEscape::Backslash => sql_ast::Expr::Value(escape(&|c, escaped| {
+            if (c as u32) <= 0xFFFF {
+                write!(escaped, r"\u{:04X}", c as u32).unwrap()
+            } else {
+                write!(escaped, r"\U{:08X}", c as u32).unwrap()
+            }
+        })),
+        UnicodeEscape::HexBytes => sql_ast::Expr::Value(escape(&|c, escaped| {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                write!(escaped, r"\x{byte:02X}").unwrap();
+            }
+        })),
+        UnicodeEscape::Unistr => {
+            let escaped = escape(&|c, escaped| {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(escaped, r"\{unit:04X}").unwrap();
+                }
+            });
+            function_call("UNISTR", vec![sql_ast::Expr::Value(escaped)])
+        }
+        UnicodeEscape::CodePoint { function } => {
+            let parts = split_non_ascii(&s, |c| {
+                vec![function_call(function, vec![expr_of_i64(c as i64)])]
+            });
+            concat_parts(parts, BinaryOperator::StringConcat)
+        }
+        UnicodeEscape::Nchar => {
+            let parts = split_non_ascii(&s, |c| {
+                (c.encode_utf16(&mut [0; 2]).iter())
+                    .map(|unit| function_call("NCHAR", vec![expr_of_i64(*unit as i64)]))
+                    .collect()
+            });
+            concat_parts(parts, BinaryOperator::Plus)
+        }
+        UnicodeEscape::ConvertUtf8 => {
+            let mut parts = split_non_ascii(&s, |c| {
+                let mut hex = String::new();
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    write!(hex, "{byte:02X}").unwrap();
+                }
+                vec![sql_ast::Expr::Convert {
+                    is_try: false,
+                    expr: Box::new(function_call(
+                        "UNHEX",
+                        vec![sql_ast::Expr::Value(Value::SingleQuotedString(hex))],
+                    )),
+                    data_type: None,
+                    charset: Some(ObjectName(vec![sql_ast::Ident::new("utf8mb4")])),
+                    target_before_value: false,
+                    styles: vec![],
+                }]
+            });
+            if parts.len() == 1 {
+                parts.remove(0)
+            } else {
+                function_call("CONCAT", parts)
+            }
+        }
+        UnicodeEscape::None => {
+            return Err(Error::new_simple(format!(
+                "non-ASCII characters in strings can't be escaped for {}",
+                ctx.dialect_enum
+            ))
+            .push_hint("compile with the UTF-8 output encoding"))
+        }
+    })
+}
+
+/// Splits a string into literals of its ASCII runs and the expressions
+/// which produce each non-ASCII character.
+fn split_non_ascii(
+    s: &str,
+    translate_char: impl Fn(char) -> Vec<sql_ast::Expr>,
+) -> Vec<sql_ast::Expr> {
+    let mut parts = Vec::new();
+    let mut ascii = String::new();
+    for c in s.chars() {
+        if c.is_ascii() {
+            ascii.push(c);
+            continue;
+        }
+        if !ascii.is_empty() {
+            let literal = Value::SingleQuotedString(std::mem::take(&mut ascii));
+            parts.push(sql_ast::Expr::Value(literal));
+        }
+        parts.extend(translate_char(c));
+    }
+    if !ascii.is_empty() {
+        parts.push(sql_ast::Expr::Value(Value::SingleQuotedString(ascii)));
+    }
+    parts
+}
+
+/// Concatenates parts with an operator, in parentheses when there are many.
+fn concat_parts(parts: Vec<sql_ast::Expr>, op: BinaryOperator) -> sql_ast::Expr {
+    let many = parts.len() > 1;
+    let concat = (parts.into_iter())
+        .reduce(|left, right| sql_ast::Expr::BinaryOp {
+            left: Box::new(left),
+            op: op.clone(),
+            right: Box::new(right),
+        })
+        .unwrap();
+    if many {
+        sql_ast::Expr::Nested(Box::new(concat))
+    } else {
+        concat
+    }
+}
+
+fn function_call(name: &str, args: Vec<sql_ast::Expr>) -> sql_ast::Expr {
+    let args = (args.into_iter())
+        .map(|arg| FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)))
+        .collect();
+
+    sql_ast::Expr::Function(Function {
+        name: ObjectName(vec![sql_ast::Ident::new(name)]),
+        args: sql_ast::FunctionArguments::List(FunctionArgumentList {
+            args,
+            clauses: vec![],
+            duplicate_treatment: None,
+        }),
+        over: None,
+        filter: None,
+        null_treatment: None,
+        within_group: vec![],
+        parameters: sql_ast::FunctionArguments::None,
+        uses_odbc_syntax: false,
+    })
+}
+
 pub(super) fn translate_literal(l: Literal, ctx: &Context) -> Result<sql_ast::Expr> {
     Ok(match l {
         Literal::Null => sql_ast::Expr::Value(Value::Null),
-        Literal::String(s) | Literal::RawString(s) => {
-            sql_ast::Expr::Value(Value::SingleQuotedString(s))
-        }
+        Literal::String(s) | Literal::RawString(s) => translate_string_literal(s, ctx)?,
         Literal::Boolean(b) => sql_ast::Expr::Value(Value::Boolean(b)),
         Literal::Float(f) => sql_ast::Expr::Value(Value::Number(format!("{f:?}"), false)),
         Literal::Integer(i) => sql_ast::Expr::Value(Value::Number(format!("{i}"), false)),
-        Literal::Date(value) => translate_datetime_literal(sql_ast::DataType::Date, value, ctx),
+        Literal::Date(value) => {
+            let value = format_date(value, ctx)?;
+            translate_datetime_literal(sql_ast::DataType::Date, value, ctx)
+        }
         Literal::Time(value) => translate_datetime_literal(
             sql_ast::DataType::Time(None, sql_ast::TimezoneInfo::None),
             value,
@@ -409,6 +890,11 @@ pub(super) fn translate_literal(l: Literal, ctx: &Context) -> Result<sql_ast::Ex
                 "seconds" => DateTimeField::Second,
                 "milliseconds" => DateTimeField::Millisecond,
                 "microseconds" => DateTimeField::Microsecond,
+                "%" => {
+                    return Err(Error::new_simple(
+                        "percentages can only be used as the argument of `take`",
+                    ))
+                }
                 _ => {
                     return Err(Error::new_simple(format!(
                         "Unsupported interval unit: {}",
@@ -443,6 +929,20 @@ pub(super) fn translate_literal(l: Literal, ctx: &Context) -> Result<sql_ast::Ex
     })
 }
 
+/// Formats an ISO-8601 date with the date format of the options, if one is set.
+fn format_date(value: String, ctx: &Context) -> Result<String> {
+    let Some(format) = &ctx.date_format else {
+        return Ok(value);
+    };
+    let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+        .map_err(|_| Error::new_simple(format!("cannot parse date `{value}`")))?;
+
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format))
+        .map_err(|_| Error::new_simple(format!("invalid date format `{format}`")))?;
+    Ok(formatted)
+}
+
 fn translate_datetime_literal(
     data_type: sql_ast::DataType,
     value: String,
@@ -508,6 +1008,88 @@ fn translate_datetime_literal_with_sqlite_function(
     })
 }
 
+/// Type to cast integer branches of a `CASE` to, when the dialect requires
+/// branches mixing integers and floats to share a type.
+fn common_float_type(cases: &[SwitchCase<rq::Expr>], ctx: &Context) -> Option<&'static str> {
+    let float_type = ctx.dialect.case_float_type()?;
+
+    let types = cases
+        .iter()
+        .map(|case| infer_numeric_type(&case.value, ctx))
+        .collect_vec();
+    let has_int = types.contains(&Some(NumericType::Int));
+    let has_float = types.contains(&Some(NumericType::Float));
+    (has_int && has_float).then_some(float_type)
+}
+
+/// Type to cast NULL branches of a `CASE` to, when `explicit_null_cast` is set
+/// and the type of all other branches is known.
+fn null_cast_type(cases: &[SwitchCase<rq::Expr>], ctx: &Context) -> Option<&'static str> {
+    if !ctx.explicit_null_cast {
+        return None;
+    }
+
+    let types: Vec<_> = cases
+        .iter()
+        .filter(|case| !matches!(case.value.kind, rq::ExprKind::Literal(Literal::Null)))
+        .map(|case| infer_numeric_type(&case.value, ctx))
+        .collect::<Option<_>>()?;
+    let (int_type, float_type) = ctx.dialect.numeric_types();
+    if types.is_empty() {
+        None
+    } else if types.iter().all(|ty| *ty == NumericType::Int) {
+        Some(int_type)
+    } else {
+        Some(float_type)
+    }
+}
+
+fn cast_to(expr: sql_ast::Expr, ty: &str) -> sql_ast::Expr {
+    sql_ast::Expr::Cast {
+        kind: sql_ast::CastKind::Cast,
+        expr: Box::new(expr),
+        data_type: sql_ast::DataType::Custom(ObjectName(vec![sql_ast::Ident::new(ty)]), vec![]),
+        format: None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumericType {
+    Int,
+    Float,
+}
+
+/// Numeric type of an expression, as far as it can be inferred from literals,
+/// arithmetic and the expressions of computed columns.
+fn infer_numeric_type(expr: &rq::Expr, ctx: &Context) -> Option<NumericType> {
+    match &expr.kind {
+        rq::ExprKind::Literal(Literal::Integer(_)) => Some(NumericType::Int),
+        rq::ExprKind::Literal(Literal::Float(_)) => Some(NumericType::Float),
+        rq::ExprKind::ColumnRef(cid) => match ctx.anchor.column_decls.get(cid)? {
+            ColumnDecl::Compute(compute) if compute.window.is_none() => {
+                infer_numeric_type(&compute.expr, ctx)
+            }
+            _ => None,
+        },
+        rq::ExprKind::Operator { name, args } => match (name.as_str(), args.as_slice()) {
+            ("std.neg", [arg]) => infer_numeric_type(arg, ctx),
+            ("std.add" | "std.sub" | "std.mul" | "std.mod", [left, right]) => {
+                let left = infer_numeric_type(left, ctx)?;
+                let right = infer_numeric_type(right, ctx)?;
+                Some(if left == NumericType::Int && right == NumericType::Int {
+                    NumericType::Int
+                } else {
+                    NumericType::Float
+                })
+            }
+            ("std.div_f", [_, _]) => Some(NumericType::Float),
+            ("std.div_i", [_, _]) => Some(NumericType::Int),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 pub(super) fn translate_cid(cid: rq::CId, ctx: &mut Context) -> Result<ExprOrSource> {
     if ctx.query.pre_projection {
         log::debug!("translating {cid:?} pre projection");
@@ -591,6 +1173,9 @@ pub(super) fn translate_sstring(
         .into_iter()
         .map(|s_string_item| match s_string_item {
             InterpolateItem::String(string) => Ok(string),
+            InterpolateItem::Expr { expr, format } if format.as_deref() == Some(FORMAT_IDENT) => {
+                translate_sstring_ident(*expr, ctx)
+            }
             InterpolateItem::Expr { expr, .. } => {
                 translate_expr(*expr, ctx).map(|expr| expr.into_source())
             }
@@ -599,6 +1184,36 @@ pub(super) fn translate_sstring(
         .join(""))
 }
 
+/// Translates an `{ident:x}` interpolation into an identifier quoted for the
+/// dialect. The value must be a column, a text literal or an unresolved name.
+fn translate_sstring_ident(expr: rq::Expr, ctx: &mut Context) -> Result<String> {
+    let span = expr.span;
+    match expr.kind {
+        rq::ExprKind::Literal(Literal::String(name)) => {
+            return Ok(translate_ident_part(name, ctx).to_string());
+        }
+        // unresolved names are lowered to s-strings
+        rq::ExprKind::SString(ref items) => {
+            if let [InterpolateItem::String(name)] = items.as_slice() {
+                return Ok(translate_ident_part(name.clone(), ctx).to_string());
+            }
+        }
+        rq::ExprKind::ColumnRef(_) => {
+            if let ExprOrSource::Expr(
+                ident @ (sql_ast::Expr::Identifier(_) | sql_ast::Expr::CompoundIdentifier(_)),
+            ) = translate_expr(expr, ctx)?
+            {
+                return Ok(ident.to_string());
+            }
+        }
+        _ => {}
+    }
+    Err(
+        Error::new_simple("an `ident:` interpolation must refer to a column or a name")
+            .with_span(span),
+    )
+}
+
 /// Aggregate several ordered ranges into one, computing the intersection.
 ///
 /// Returns a tuple of `(start, end)`, where `end` is optional.
@@ -621,6 +1236,7 @@ pub(super) fn range_of_ranges(ranges: Vec<Range<rq::Expr>>) -> Result<Range<i64>
             return Ok(Range {
                 start: None,
                 end: Some(0),
+                end_exclusive: false,
             });
         }
     }
@@ -638,6 +1254,7 @@ fn try_range_into_int(range: Range<rq::Expr>) -> Result<Range<i64>> {
     Ok(Range {
         start: range.start.map(unpack_as_int_literal).transpose()?,
         end: range.end.map(unpack_as_int_literal).transpose()?,
+        end_exclusive: range.end_exclusive,
     })
 }
 
@@ -705,6 +1322,7 @@ fn translate_windowed(
                         kind: rq::ExprKind::Literal(Literal::Integer(0)),
                         span: None,
                     }),
+                    end_exclusive: false,
                 },
             )
         };
@@ -725,6 +1343,7 @@ fn translate_windowed(
         order_by: (window.sort)
             .into_iter()
             .map(|sort| translate_column_sort(&sort, ctx))
+            .flatten_ok()
             .try_collect()?,
         window_frame: if supports_frame && window.frame != default_frame {
             Some(try_into_window_frame(window.frame)?)
@@ -773,20 +1392,67 @@ fn try_into_window_frame(frame: WindowFrame<rq::Expr>) -> Result<sql_ast::Window
     })
 }
 
+/// Translates a sort into `ORDER BY` expressions. There may be two of them,
+/// when the placement of NULLs must be emulated.
 pub(super) fn translate_column_sort(
     sort: &ColumnSort<rq::CId>,
     ctx: &mut Context,
-) -> Result<OrderByExpr> {
-    Ok(OrderByExpr {
-        expr: translate_cid(sort.column, ctx)?.into_ast(),
-        asc: if matches!(sort.direction, SortDirection::Asc) {
-            None // default order is ASC, so there is no need to emit it
-        } else {
-            Some(false)
+) -> Result<Vec<OrderByExpr>> {
+    let expr = translate_cid(sort.column, ctx)?.into_ast();
+    let asc = if matches!(sort.direction, SortDirection::Asc) {
+        None // default order is ASC, so there is no need to emit it
+    } else {
+        Some(false)
+    };
+
+    let nulls_first = match ctx.nulls_order {
+        NullsOrder::Dialect => None,
+        NullsOrder::First => Some(true),
+        NullsOrder::Last => Some(false),
+    };
+    // omit the placement where it's the default of the dialect
+    let default_nulls_first = (ctx.dialect.default_null_ordering()).nulls_first(&sort.direction);
+    let nulls_first = nulls_first.filter(|n| default_nulls_first != Some(*n));
+
+    let Some(nulls_first) = nulls_first else {
+        return Ok(vec![OrderByExpr {
+            expr,
+            asc,
+            nulls_first: None,
+            with_fill: None,
+        }]);
+    };
+    if ctx.dialect.supports_nulls_first_last() {
+        return Ok(vec![OrderByExpr {
+            expr,
+            asc,
+            nulls_first: Some(nulls_first),
+            with_fill: None,
+        }]);
+    }
+
+    // emulated by first sorting by whether the value is NULL
+    let (when_null, otherwise) = if nulls_first { (0, 1) } else { (1, 0) };
+    let is_null = sql_ast::Expr::Case {
+        operand: None,
+        conditions: vec![sql_ast::Expr::IsNull(Box::new(expr.clone()))],
+        results: vec![expr_of_i64(when_null)],
+        else_result: Some(Box::new(expr_of_i64(otherwise))),
+    };
+    Ok(vec![
+        OrderByExpr {
+            expr: is_null,
+            asc: None,
+            nulls_first: None,
+            with_fill: None,
         },
-        nulls_first: None,
-        with_fill: None,
-    })
+        OrderByExpr {
+            expr,
+            asc,
+            nulls_first: None,
+            with_fill: None,
+        },
+    ])
 }
 
 /// Translate a PRQL Ident to a Vec of SQL Idents.
@@ -815,6 +1481,10 @@ pub(super) fn translate_ident(
 }
 
 pub(super) fn translate_ident_part(ident: String, ctx: &Context) -> sql_ast::Ident {
+    let ident = match ctx.dialect.max_ident_len() {
+        Some(max_len) if ident.len() > max_len => truncate_ident(&ident, max_len),
+        _ => ident,
+    };
     let is_bare = valid_ident().is_match(&ident);
 
     if is_bare && !keywords::is_keyword(&ident) {
@@ -824,6 +1494,20 @@ pub(super) fn translate_ident_part(ident: String, ctx: &Context) -> sql_ast::Ide
     }
 }
 
+/// Truncates an identifier to `max_len` bytes, replacing its end with a hash
+/// of the whole identifier, so different identifiers with a common prefix
+/// remain different.
+fn truncate_ident(ident: &str, max_len: usize) -> String {
+    let hash = fnv1a_64(ident.as_bytes()) as u32;
+    let suffix = format!("_{hash:08x}");
+
+    let mut prefix_len = max_len.saturating_sub(suffix.len());
+    while !ident.is_char_boundary(prefix_len) {
+        prefix_len -= 1;
+    }
+    format!("{}{suffix}", &ident[..prefix_len])
+}
+
 pub(super) fn translate_operand(
     expr: rq::Expr,
     is_left: bool,
@@ -1066,7 +1750,11 @@ mod test {
                 kind: rq::ExprKind::Literal(Literal::Integer(x)),
                 span: None,
             });
-            Range { start, end }
+            Range {
+                start,
+                end,
+                end_exclusive: false,
+            }
         }
 
         let range_1_10 = from_ints(Some(1), Some(10));
@@ -2,39 +2,60 @@
 //! then to a String. We use sqlparser because it's trivial to create the string
 //! once it's in their AST (it's just `.to_string()`). It also lets us support a
 //! few dialects of SQL immediately.
-use itertools::Itertools;
+use itertools::{Either, Itertools};
 use regex::Regex;
+use sqlparser::ast::helpers::stmt_create_table::CreateTableBuilder;
 use sqlparser::ast::{
     self as sql_ast, Join, JoinConstraint, JoinOperator, Select, SelectItem, SetExpr, TableAlias,
-    TableFactor, TableWithJoins,
+    TableFactor, TableSample, TableSampleKind, TableSampleMethod, TableSampleModifier,
+    TableSampleQuantity, TableWithJoins,
 };
 
+use super::dialect::TakePercent;
 use super::gen_expr::*;
 use super::gen_projection::*;
 use super::operators::translate_operator;
 use super::pq::ast::{Cte, CteKind, RelationExpr, RelationExprKind, SqlRelation, SqlTransform};
-use super::{Context, Dialect};
-use crate::debug;
-use crate::ir::pl::{JoinSide, Literal};
+use super::pq::context::ColumnDecl;
+use super::{Context, Instrumentation};
+use crate::debug::{self, TransformKind};
+use crate::ir::pl::{Ident, JoinSide, Literal};
 use crate::ir::rq::{CId, Expr, ExprKind, RelationLiteral, RelationalQuery};
 use crate::utils::{BreakUp, Pluck};
-use crate::{Error, Result, WithErrorInfo};
+use crate::{Error, Options, Result, WithErrorInfo, WriteMode};
 use prqlc_parser::generic::InterpolateItem;
 
 type Transform = SqlTransform<RelationExpr, ()>;
 
-pub fn translate_query(query: RelationalQuery, dialect: Option<Dialect>) -> Result<sql_ast::Query> {
+pub(super) fn translate_query(
+    query: RelationalQuery,
+    options: &Options,
+    instrumentation: Instrumentation,
+) -> Result<(sql_ast::Statement, Context)> {
+    let into = query.into.clone();
+
     // compile from RQ to PQ
-    let (pq_query, mut ctx) = super::pq::compile_query(query, dialect)?;
+    let (pq_query, mut ctx) = super::pq::compile_query(query, options, instrumentation)?;
+
+    // relations used as expressions are not CTEs, but are translated in place
+    let (ctes, sub_queries): (Vec<_>, Vec<_>) =
+        (pq_query.ctes.into_iter()).partition_map(|cte| match cte.kind {
+            CteKind::SubQuery(rel) => Either::Right((cte.tid, rel)),
+            kind => Either::Left(Cte { kind, ..cte }),
+        });
+    ctx.sub_queries.extend(sub_queries);
 
     debug::log_stage(debug::Stage::Sql(debug::StageSql::Main));
     let mut query = translate_relation(pq_query.main_relation, &mut ctx)?;
 
-    if !pq_query.ctes.is_empty() {
+    // the main relation is translated first, but its fragments follow the CTEs'
+    let main_stages = ctx.stage_log.as_mut().map(std::mem::take);
+
+    if !ctes.is_empty() {
         // attach CTEs
         let mut cte_tables = Vec::new();
         let mut recursive = false;
-        for cte in pq_query.ctes {
+        for cte in ctes {
             let (cte, rec) = translate_cte(cte, &mut ctx)?;
             cte_tables.push(cte);
             recursive = recursive || rec;
@@ -46,11 +67,59 @@ pub fn translate_query(query: RelationalQuery, dialect: Option<Dialect>) -> Resu
         });
     }
 
+    if let (Some(log), Some(main_stages)) = (&mut ctx.stage_log, main_stages) {
+        log.extend(main_stages);
+    }
+
     debug::log_entry(|| debug::DebugEntryKind::ReprSqlParser(query.clone()));
-    Ok(query)
+
+    let statement = match into {
+        Some(table) => translate_into(table, query, options.write_mode, &ctx),
+        None => sql_ast::Statement::Query(Box::new(query)),
+    };
+    Ok((statement, ctx))
 }
 
-fn translate_relation(relation: SqlRelation, ctx: &mut Context) -> Result<sql_ast::Query> {
+/// Wraps the query into a statement which writes its result into `table`.
+fn translate_into(
+    table: Ident,
+    query: sql_ast::Query,
+    write_mode: WriteMode,
+    ctx: &Context,
+) -> sql_ast::Statement {
+    let name = sql_ast::ObjectName(translate_ident(Some(table), None, ctx));
+    let query = Box::new(query);
+
+    match write_mode {
+        WriteMode::Insert => sql_ast::Statement::Insert(sql_ast::Insert {
+            or: None,
+            ignore: false,
+            into: true,
+            table: sql_ast::TableObject::TableName(name),
+            table_alias: None,
+            columns: Vec::new(),
+            overwrite: false,
+            source: Some(query),
+            assignments: Vec::new(),
+            partitioned: None,
+            after_columns: Vec::new(),
+            has_table_keyword: false,
+            on: None,
+            returning: None,
+            replace_into: false,
+            priority: None,
+            insert_alias: None,
+            settings: None,
+            format_clause: None,
+        }),
+        WriteMode::CreateTableAs => CreateTableBuilder::new(name).query(Some(query)).build(),
+    }
+}
+
+pub(super) fn translate_relation(
+    relation: SqlRelation,
+    ctx: &mut Context,
+) -> Result<sql_ast::Query> {
     match relation {
         SqlRelation::AtomicPipeline(pipeline) => translate_pipeline(pipeline, ctx),
         SqlRelation::Literal(data) => translate_relation_literal(data, ctx),
@@ -80,12 +149,32 @@ fn translate_select_pipeline(
     ctx.query.omit_ident_prefix = table_count == 1;
     ctx.query.pre_projection = true;
 
-    let mut from: Vec<_> = pipeline
-        .pluck(|t| t.into_from())
+    // PIVOT and UNPIVOT are applied to the relation in FROM
+    let mut reshapes = pipeline.pluck(|t| match t {
+        Transform::Pivot(_) | Transform::Unpivot(_) => Ok(t),
+        _ => Err(t),
+    });
+
+    let sources = pipeline.pluck(|t| t.into_from());
+
+    // TABLESAMPLE can only be applied to tables of the database
+    let from_extern_table = reshapes.is_empty()
+        && matches!(sources.as_slice(), [RelationExpr { kind: RelationExprKind::Ref(tid), .. }]
+            if ctx.anchor.lookup_table_decl(tid).is_some_and(|decl| decl.is_extern));
+
+    let mut from: Vec<_> = sources
         .into_iter()
         .map(|source| -> Result<TableWithJoins> {
+            let span =
+                (ctx.anchor.relation_instances.get(&source.riid)).and_then(|ri| ri.table_ref.span);
+            let mut relation = translate_relation_expr(source, ctx)?;
+            for reshape in reshapes.drain(..) {
+                relation = translate_reshape(relation, reshape, ctx)?;
+            }
+            ctx.map_source(span, || format!("FROM {relation}"));
+
             Ok(TableWithJoins {
-                relation: translate_relation_expr(source, ctx)?,
+                relation,
                 joins: vec![],
             })
         })
@@ -94,7 +183,13 @@ fn translate_select_pipeline(
     let joins = pipeline
         .pluck(|t| t.into_join())
         .into_iter()
-        .map(|j| translate_join(j, ctx))
+        .enumerate()
+        .map(|(i, j)| {
+            // following joins have multiple relations on their left, which
+            // could make a USING column ambiguous
+            let left = (i == 0).then(|| from.last().map(|f| &f.relation)).flatten();
+            translate_join(j, left, ctx)
+        })
         .collect::<Result<Vec<_>>>()?;
     if !joins.is_empty() {
         if let Some(from) = from.last_mut() {
@@ -109,16 +204,19 @@ fn translate_select_pipeline(
         .into_iter()
         .exactly_one()
         .unwrap();
+    let selected = projection.clone();
     let projection = translate_wildcards(&ctx.anchor, projection);
     let projection = translate_select_items(projection.0, projection.1, ctx)?;
 
     let order_by = pipeline.pluck(|t| t.into_sort());
     let takes = pipeline.pluck(|t| t.into_take());
+    let take_percent = takes.iter().find_map(|t| t.percent);
     let is_distinct = pipeline.iter().any(|t| matches!(t, SqlTransform::Distinct));
     let distinct_ons = pipeline.pluck(|t| t.into_distinct_on());
+    let is_distinct_on = !distinct_ons.is_empty();
     let distinct = if is_distinct {
         Some(sql_ast::Distinct::Distinct)
-    } else if !distinct_ons.is_empty() {
+    } else if is_distinct_on {
         Some(sql_ast::Distinct::On(
             distinct_ons
                 .into_iter()
@@ -142,6 +240,12 @@ fn translate_select_pipeline(
 
     // GROUP BY
     let aggregate = after_agg.pluck(|t| t.into_aggregate()).into_iter().next();
+    let aggregated = match &aggregate {
+        Some((_, compute)) if ctx.stage_log.is_some() => (compute.iter())
+            .map(|cid| translate_select_item(*cid, ctx))
+            .try_collect()?,
+        _ => Vec::new(),
+    };
     let group_by: Vec<CId> = aggregate.map(|(part, _)| part).unwrap_or_default();
     ctx.query.allow_stars = ctx.dialect.stars_in_group();
     let group_by = sql_ast::GroupByExpr::Expressions(try_into_exprs(group_by, ctx, None)?, vec![]);
@@ -149,7 +253,12 @@ fn translate_select_pipeline(
 
     ctx.query.pre_projection = false;
 
-    let ranges = takes.into_iter().map(|x| x.range).collect();
+    let ranges: Vec<_> = takes.into_iter().map(|x| x.range).collect();
+    let take_span = super::source_map::cover(
+        (ranges.iter())
+            .flat_map(|range| [&range.start, &range.end])
+            .filter_map(|bound| bound.as_ref()?.span),
+    );
     let take = range_of_ranges(ranges)?;
     let offset = take.start.map(|s| s - 1).unwrap_or(0);
     let limit = take.end.map(|e| e - offset);
@@ -175,45 +284,113 @@ fn translate_select_pipeline(
         .map(|sorts| {
             sorts
                 .iter()
-                .map(|s| translate_column_sort(s, ctx))
+                .map(|s| {
+                    // sorting of DISTINCT ON and plain expressions may use columns
+                    // that are not SELECTed, so they cannot be referenced by name
+                    let is_compute =
+                        matches!(ctx.anchor.column_decls[&s.column], ColumnDecl::Compute(_));
+                    ctx.query.pre_projection =
+                        (is_distinct_on || is_compute) && !selected.contains(&s.column);
+                    let sort = translate_column_sort(s, ctx);
+                    ctx.query.pre_projection = false;
+                    sort
+                })
+                .flatten_ok()
                 .try_collect()
         })
         .transpose()?
         .unwrap_or_default();
 
-    let (fetch, limit) = if ctx.dialect.use_fetch() {
+    let (mut fetch, mut limit) = if ctx.dialect.use_fetch() {
         (limit.map(|l| fetch_of_i64(l, ctx)), None)
     } else {
         (None, limit.map(expr_of_i64))
     };
 
-    // If we have a FETCH we need to make sure that:
-    // - we have an OFFSET (set to 0)
-    // - we have an ORDER BY (see https://stackoverflow.com/a/44919325)
-    if fetch.is_some() {
-        if offset.is_none() {
-            let kind = ExprKind::Literal(Literal::Integer(0));
-            let expr = Expr { kind, span: None };
-            offset = Some(sqlparser::ast::Offset {
-                value: translate_expr(expr, ctx)?.into_ast(),
-                rows: sqlparser::ast::OffsetRows::Rows,
-            })
-        }
-        if order_by.is_empty() {
-            order_by.push(sql_ast::OrderByExpr {
-                expr: sql_ast::Expr::Value(sql_ast::Value::Placeholder(
-                    "(SELECT NULL)".to_string(),
-                )),
-                asc: None,
-                nulls_first: None,
-                with_fill: None,
-            });
+    if let Some(percent) = take_percent {
+        match ctx.dialect.take_percent() {
+            Some(TakePercent::TableSample) if from_extern_table => {
+                let from = from.iter_mut().exactly_one().unwrap();
+                translate_table_sample(&mut from.relation, percent);
+            }
+            // CTEs and subqueries can't be sampled, so their rows are counted
+            Some(TakePercent::TableSample | TakePercent::LimitOfCount)
+                if ctx.dialect.supports_limit_subquery() =>
+            {
+                let count = count_of_relation(from.clone(), where_.clone());
+                let of_count = sql_ast::Expr::BinaryOp {
+                    left: Box::new(sql_ast::Expr::BinaryOp {
+                        left: Box::new(sql_ast::Expr::Subquery(Box::new(count))),
+                        op: sql_ast::BinaryOperator::Multiply,
+                        right: Box::new(expr_of_i64(percent)),
+                    }),
+                    op: sql_ast::BinaryOperator::Divide,
+                    right: Box::new(expr_of_i64(100)),
+                };
+                limit = Some(of_count);
+            }
+            Some(TakePercent::FetchPercent) => {
+                fetch = Some(sql_ast::Fetch {
+                    quantity: Some(expr_of_i64(percent)),
+                    with_ties: false,
+                    percent: true,
+                });
+            }
+            Some(TakePercent::TableSample) => {
+                return Err(Error::new_simple(format!(
+                    "`take` of a percentage of rows is only supported on tables for {}",
+                    ctx.dialect_enum
+                )))
+            }
+            Some(TakePercent::LimitOfCount) | None => {
+                return Err(Error::new_simple(format!(
+                    "`take` of a percentage of rows is not supported for {}",
+                    ctx.dialect_enum
+                )))
+            }
         }
     }
 
+    ctx.map_source(take_span, || match (&limit, &fetch) {
+        (Some(limit), _) => format!("LIMIT {limit}"),
+        (None, Some(fetch)) => fetch.to_string(),
+        (None, None) => String::new(),
+    });
+
+    // A take within a subquery may need to be sorted. We sort by all of the
+    // projected columns, so the rows it selects are deterministic. When they
+    // aren't known, the fallback for OFFSET below applies.
+    if (fetch.is_some() || limit.is_some())
+        && order_by.is_empty()
+        && ctx.query.is_subquery
+        && ctx.dialect.take_in_subquery_requires_sort()
+    {
+        order_by = order_by_of_projection(&projection).unwrap_or_default();
+    }
+
+    // If we have a FETCH we may need to make sure that we have an OFFSET (set to 0)
+    if fetch.is_some() && offset.is_none() && ctx.dialect.fetch_requires_offset() {
+        let kind = ExprKind::Literal(Literal::Integer(0));
+        let expr = Expr { kind, span: None };
+        offset = Some(sqlparser::ast::Offset {
+            value: translate_expr(expr, ctx)?.into_ast(),
+            rows: sqlparser::ast::OffsetRows::Rows,
+        })
+    }
+
+    // An OFFSET may need an ORDER BY (see https://stackoverflow.com/a/44919325)
+    if offset.is_some() && order_by.is_empty() && ctx.dialect.offset_requires_sort() {
+        order_by.push(sql_ast::OrderByExpr {
+            expr: sql_ast::Expr::Value(sql_ast::Value::Placeholder("(SELECT NULL)".to_string())),
+            asc: None,
+            nulls_first: None,
+            with_fill: None,
+        });
+    }
+
     ctx.pop_query();
 
-    Ok(sql_ast::Query {
+    let query = sql_ast::Query {
         order_by: if order_by.is_empty() {
             None
         } else {
@@ -234,7 +411,67 @@ fn translate_select_pipeline(
             having,
             ..default_select()
         })))
-    })
+    };
+    if ctx.stage_log.is_some() {
+        log_select_stages(&query, &aggregated, ctx);
+    }
+    Ok(query)
+}
+
+/// Records the SQL fragments of the clauses of a SELECT query. The items of
+/// its aggregation are passed separately, as they are mixed into the projection.
+fn log_select_stages(query: &sql_ast::Query, aggregated: &[SelectItem], ctx: &mut Context) {
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return;
+    };
+    let Select {
+        from,
+        selection: where_,
+        group_by,
+        having,
+        distinct,
+        projection,
+        ..
+    } = select.as_ref();
+
+    for table in from {
+        ctx.log_transform_sql(TransformKind::From, || format!("FROM {}", table.relation));
+        for join in &table.joins {
+            ctx.log_transform_sql(TransformKind::Join, || join.to_string().trim().to_string());
+        }
+    }
+    if let Some(where_) = where_ {
+        ctx.log_transform_sql(TransformKind::Filter, || format!("WHERE {where_}"));
+    }
+    if !aggregated.is_empty() {
+        ctx.log_transform_sql(TransformKind::Aggregate, || aggregated.iter().join(", "));
+    }
+    if matches!(group_by, sql_ast::GroupByExpr::Expressions(exprs, _) if !exprs.is_empty()) {
+        ctx.log_transform_sql(TransformKind::Aggregate, || group_by.to_string());
+    }
+    if let Some(having) = having {
+        ctx.log_transform_sql(TransformKind::Filter, || format!("HAVING {having}"));
+    }
+    if let Some(distinct) = distinct {
+        ctx.log_transform_sql(TransformKind::Distinct, || distinct.to_string());
+    }
+    ctx.log_transform_sql(TransformKind::Select, || {
+        format!("SELECT {}", projection.iter().join(", "))
+    });
+    if let Some(order_by) = &query.order_by {
+        ctx.log_transform_sql(TransformKind::Sort, || {
+            format!("ORDER BY {}", order_by.exprs.iter().join(", "))
+        });
+    }
+    let take = [
+        query.limit.as_ref().map(|l| format!("LIMIT {l}")),
+        query.offset.as_ref().map(|o| o.to_string()),
+        query.fetch.as_ref().map(|f| f.to_string()),
+    ];
+    let take = take.into_iter().flatten().join(" ");
+    if !take.is_empty() {
+        ctx.log_transform_sql(TransformKind::Take, || take);
+    }
 }
 
 fn translate_set_ops_pipeline(
@@ -250,43 +487,68 @@ fn translate_set_ops_pipeline(
 
         let op = match &transform {
             Union { .. } => sql_ast::SetOperator::Union,
-            Except { .. } => sql_ast::SetOperator::Except,
+            Except { .. } => context.dialect.except_operator(),
             Intersect { .. } => sql_ast::SetOperator::Intersect,
             Sort(_) => continue,
             _ => unreachable!(),
         };
 
-        let (distinct, bottom) = match transform {
-            Union { distinct, bottom }
-            | Except { distinct, bottom }
-            | Intersect { distinct, bottom } => (distinct, bottom),
+        let (distinct, by_name, bottom) = match transform {
+            Union {
+                distinct,
+                by_name,
+                bottom,
+            } => (distinct, by_name, bottom),
+            Except { distinct, bottom } | Intersect { distinct, bottom } => {
+                (distinct, false, bottom)
+            }
             _ => unreachable!(),
         };
+        if by_name && !context.dialect.set_ops_by_name() {
+            return Err(Error::new_simple(format!(
+                "`append by_name:true` is not supported for {}",
+                context.dialect_enum
+            )));
+        }
 
         // prepare top
         let left = query_to_set_expr(top, context);
 
+        let right = Box::new(SetExpr::Select(Box::new(sql_ast::Select {
+            projection: vec![SelectItem::Wildcard(
+                sql_ast::WildcardAdditionalOptions::default(),
+            )],
+            from: vec![TableWithJoins {
+                relation: translate_relation_expr(bottom, context)?,
+                joins: vec![],
+            }],
+            ..default_select()
+        })));
+        let set_quantifier = match (distinct, by_name) {
+            (true, false) if context.dialect.set_ops_distinct() => sql_ast::SetQuantifier::Distinct,
+            (true, false) => sql_ast::SetQuantifier::None,
+            (false, false) => sql_ast::SetQuantifier::All,
+            (true, true) if context.dialect.set_ops_distinct() => {
+                sql_ast::SetQuantifier::DistinctByName
+            }
+            (true, true) => sql_ast::SetQuantifier::ByName,
+            (false, true) => sql_ast::SetQuantifier::AllByName,
+        };
+
+        let kind = match op {
+            sql_ast::SetOperator::Union => TransformKind::Union,
+            sql_ast::SetOperator::Except | sql_ast::SetOperator::Minus => TransformKind::Except,
+            _ => TransformKind::Intersect,
+        };
+        context.log_transform_sql(kind, || match set_quantifier {
+            sql_ast::SetQuantifier::None => format!("{op} {right}"),
+            _ => format!("{op} {set_quantifier} {right}"),
+        });
+
         top = default_query(SetExpr::SetOperation {
             left,
-            right: Box::new(SetExpr::Select(Box::new(sql_ast::Select {
-                projection: vec![SelectItem::Wildcard(
-                    sql_ast::WildcardAdditionalOptions::default(),
-                )],
-                from: vec![TableWithJoins {
-                    relation: translate_relation_expr(bottom, context)?,
-                    joins: vec![],
-                }],
-                ..default_select()
-            }))),
-            set_quantifier: if distinct {
-                if context.dialect.set_ops_distinct() {
-                    sql_ast::SetQuantifier::Distinct
-                } else {
-                    sql_ast::SetQuantifier::None
-                }
-            } else {
-                sql_ast::SetQuantifier::All
-            },
+            right,
+            set_quantifier,
             op,
         });
     }
@@ -321,10 +583,14 @@ fn translate_relation_expr(relation_expr: RelationExpr, ctx: &mut Context) -> Re
                 version: None,
                 partitions: vec![],
                 json_path: None,
+                sample: None,
             }
         }
         RelationExprKind::SubQuery(query) => {
+            ctx.push_query();
+            ctx.query.is_subquery = true;
             let query = translate_relation(query, ctx)?;
+            ctx.pop_query();
 
             let alias = translate_table_alias(alias, ctx);
 
@@ -337,19 +603,175 @@ fn translate_relation_expr(relation_expr: RelationExpr, ctx: &mut Context) -> Re
     })
 }
 
+/// Sorts by each of the projected columns, or returns None when they aren't
+/// known because of a wildcard.
+fn order_by_of_projection(projection: &[SelectItem]) -> Option<Vec<sql_ast::OrderByExpr>> {
+    projection
+        .iter()
+        .map(|item| {
+            let expr = match item {
+                SelectItem::UnnamedExpr(expr) => expr.clone(),
+                SelectItem::ExprWithAlias { alias, .. } => sql_ast::Expr::Identifier(alias.clone()),
+                SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..) => return None,
+            };
+            Some(sql_ast::OrderByExpr {
+                expr,
+                asc: None,
+                nulls_first: None,
+                with_fill: None,
+            })
+        })
+        .collect()
+}
+
 fn translate_table_alias(alias: Option<String>, ctx: &mut Context) -> Option<TableAlias> {
     alias
         .map(|ident| translate_ident_part(ident, ctx))
+        .map(|ident| mark_table_alias(ident, ctx))
         .map(simple_table_alias)
 }
 
+/// Marks the name of a relation's alias in FROM, so `AS` is removed from
+/// before it, for dialects that don't allow it there.
+fn mark_table_alias(mut name: sql_ast::Ident, ctx: &Context) -> sql_ast::Ident {
+    if !ctx.dialect.table_alias_as() {
+        name.value.insert(0, super::TABLE_ALIAS_MARKER);
+    }
+    name
+}
+
+/// Appends `TABLESAMPLE` to a table in FROM, after its alias.
+fn translate_table_sample(relation: &mut TableFactor, percent: i64) {
+    if let TableFactor::Table { sample, .. } = relation {
+        *sample = Some(TableSampleKind::AfterTableAlias(Box::new(TableSample {
+            modifier: TableSampleModifier::TableSample,
+            name: Some(TableSampleMethod::Bernoulli),
+            quantity: Some(TableSampleQuantity {
+                parenthesized: true,
+                value: expr_of_i64(percent),
+                unit: None,
+            }),
+            seed: None,
+            bucket: None,
+            offset: None,
+        })));
+    }
+}
+
+/// A query counting the rows of a relation.
+fn count_of_relation(
+    from: Vec<TableWithJoins>,
+    selection: Option<sql_ast::Expr>,
+) -> sql_ast::Query {
+    let count = sql_ast::Expr::Function(sql_ast::Function {
+        name: sql_ast::ObjectName(vec![sql_ast::Ident::new("COUNT")]),
+        args: sql_ast::FunctionArguments::List(sql_ast::FunctionArgumentList {
+            args: vec![sql_ast::FunctionArg::Unnamed(
+                sql_ast::FunctionArgExpr::Wildcard,
+            )],
+            clauses: vec![],
+            duplicate_treatment: None,
+        }),
+        over: None,
+        filter: None,
+        null_treatment: None,
+        within_group: vec![],
+        parameters: sql_ast::FunctionArguments::None,
+        uses_odbc_syntax: false,
+    });
+
+    default_query(SetExpr::Select(Box::new(Select {
+        projection: vec![SelectItem::UnnamedExpr(count)],
+        from,
+        selection,
+        ..default_select()
+    })))
+}
+
+fn translate_reshape(
+    table: TableFactor,
+    reshape: Transform,
+    ctx: &mut Context,
+) -> Result<TableFactor> {
+    Ok(match reshape {
+        SqlTransform::Pivot(pivot) => {
+            let aggregate = Expr {
+                kind: ExprKind::Operator {
+                    name: pivot.func,
+                    args: vec![Expr {
+                        kind: ExprKind::ColumnRef(pivot.values_from),
+                        span: None,
+                    }],
+                },
+                span: None,
+            };
+            // PIVOT requires a plain aggregate function, so it cannot be wrapped into
+            // COALESCE (same as window functions)
+            let prev_wf = ctx.query.window_function;
+            ctx.query.window_function = true;
+            let aggregate = translate_expr(aggregate, ctx)?.into_ast();
+            ctx.query.window_function = prev_wf;
+
+            let aggregate = sql_ast::ExprWithAlias {
+                expr: aggregate,
+                alias: None,
+            };
+
+            let values = (pivot.columns.into_iter())
+                .map(|(value, name, _)| -> Result<_> {
+                    Ok(sql_ast::ExprWithAlias {
+                        expr: translate_literal(value, ctx)?,
+                        alias: Some(translate_ident_part(name, ctx)),
+                    })
+                })
+                .try_collect()?;
+
+            TableFactor::Pivot {
+                table: Box::new(table),
+                aggregate_functions: vec![aggregate],
+                value_column: vec![translate_column_name(pivot.names_from, ctx)?],
+                value_source: sql_ast::PivotValueSource::List(values),
+                default_on_null: None,
+                alias: None,
+            }
+        }
+        SqlTransform::Unpivot(unpivot) => TableFactor::Unpivot {
+            table: Box::new(table),
+            value: translate_ident_part(unpivot.values_to.0, ctx),
+            name: translate_ident_part(unpivot.names_to.0, ctx),
+            columns: (unpivot.columns.into_iter())
+                .map(|cid| translate_column_name(cid, ctx))
+                .try_collect()?,
+            alias: None,
+        },
+        _ => unreachable!(),
+    })
+}
+
+fn translate_column_name(cid: CId, ctx: &mut Context) -> Result<sql_ast::Ident> {
+    let Some(name) = ctx.anchor.ensure_column_name(cid).cloned() else {
+        return Err(Error::new_simple(
+            "`pivot` and `unpivot` can only refer to columns with names",
+        ));
+    };
+    Ok(translate_ident_part(name, ctx))
+}
+
 fn translate_join(
     (side, with, filter): (JoinSide, RelationExpr, Expr),
+    left: Option<&TableFactor>,
     ctx: &mut Context,
 ) -> Result<Join> {
     let relation = translate_relation_expr(with, ctx)?;
 
-    let constraint = JoinConstraint::On(translate_expr(filter, ctx)?.into_ast());
+    let on = translate_condition(filter, ctx)?;
+    let using = match left {
+        Some(left) if ctx.join_using && ctx.dialect.supports_join_using() => {
+            try_into_using(&on, left, &relation)
+        }
+        _ => None,
+    };
+    let constraint = using.unwrap_or(JoinConstraint::On(on));
 
     Ok(Join {
         relation,
@@ -363,15 +785,69 @@ fn translate_join(
     })
 }
 
+/// Translates `ON a.col = b.col` into `USING (col)`, when `a` and `b` are the
+/// two joined relations.
+fn try_into_using(
+    on: &sql_ast::Expr,
+    left: &TableFactor,
+    right: &TableFactor,
+) -> Option<JoinConstraint> {
+    let sql_ast::Expr::BinaryOp {
+        left: a,
+        op: sql_ast::BinaryOperator::Eq,
+        right: b,
+    } = on
+    else {
+        return None;
+    };
+    let (sql_ast::Expr::CompoundIdentifier(a), sql_ast::Expr::CompoundIdentifier(b)) =
+        (a.as_ref(), b.as_ref())
+    else {
+        return None;
+    };
+    let ([a_relation @ .., a_col], [b_relation @ .., b_col]) = (a.as_slice(), b.as_slice()) else {
+        return None;
+    };
+    if a_col != b_col {
+        return None;
+    }
+
+    let (left, right) = (relation_name(left)?, relation_name(right)?);
+    let is_between_sides = left != right
+        && ((a_relation == left && b_relation == right)
+            || (a_relation == right && b_relation == left));
+    is_between_sides.then(|| JoinConstraint::Using(vec![sql_ast::ObjectName(vec![a_col.clone()])]))
+}
+
+/// Name by which columns of a relation in FROM are qualified.
+fn relation_name(relation: &TableFactor) -> Option<&[sql_ast::Ident]> {
+    match relation {
+        TableFactor::Table {
+            alias: Some(alias), ..
+        }
+        | TableFactor::Derived {
+            alias: Some(alias), ..
+        } => Some(std::slice::from_ref(&alias.name)),
+        TableFactor::Table {
+            name, alias: None, ..
+        } => Some(&name.0),
+        _ => None,
+    }
+}
+
 fn translate_cte(cte: Cte, ctx: &mut Context) -> Result<(sql_ast::Cte, bool)> {
     let decl = ctx.anchor.lookup_table_decl(&cte.tid).unwrap();
     let cte_name = decl.name.clone().unwrap();
 
     let cte_name = translate_ident(Some(cte_name), None, ctx).pop().unwrap();
+    let mapped_before = ctx.source_map.as_ref().map_or(0, Vec::len);
 
+    ctx.push_query();
+    ctx.query.is_subquery = true;
     let (query, recursive) = match cte.kind {
         // base case
         CteKind::Normal(rel) => (translate_relation(rel, ctx)?, false),
+        CteKind::SubQuery(_) => unreachable!(),
 
         // special: WITH RECURSIVE
         CteKind::Loop { initial, step } => {
@@ -424,6 +900,7 @@ fn translate_cte(cte: Cte, ctx: &mut Context) -> Result<(sql_ast::Cte, bool)> {
             // (outer_query, false)
         }
     };
+    ctx.pop_query();
 
     let cte = sql_ast::Cte {
         alias: simple_table_alias(cte_name),
@@ -432,17 +909,19 @@ fn translate_cte(cte: Cte, ctx: &mut Context) -> Result<(sql_ast::Cte, bool)> {
         materialized: None,
         closing_paren_token: sqlparser::ast::helpers::attached_token::AttachedToken::empty(),
     };
+
+    // the CTE was produced by the pipeline stages which produced its contents
+    let span = (ctx.source_map.as_ref())
+        .and_then(|mapped| super::source_map::cover(mapped[mapped_before..].iter().map(|m| m.1)));
+    if let (Some(cte_map), Some(span)) = (&mut ctx.cte_map, span) {
+        cte_map.push((cte.to_string(), span));
+    }
+    ctx.map_source(span, || cte.to_string());
+
     Ok((cte, recursive))
 }
 
-fn translate_relation_literal(data: RelationLiteral, ctx: &Context) -> Result<sql_ast::Query> {
-    // TODO: this could be made to use VALUES instead of SELECT UNION ALL SELECT
-    //       I'm not sure about compatibility though.
-    // edit: probably not, because VALUES has no way of setting names of the columns
-    //       Postgres will just name them column1, column2 as so on.
-    //       Which means we can use VALUES, but only if this is not the top-level statement,
-    //       where they really matter.
-
+fn translate_relation_literal(data: RelationLiteral, ctx: &mut Context) -> Result<sql_ast::Query> {
     if data.rows.is_empty() {
         let mut nulls: Vec<_> = (data.columns.iter())
             .map(|col_name| SelectItem::ExprWithAlias {
@@ -460,11 +939,19 @@ fn translate_relation_literal(data: RelationLiteral, ctx: &Context) -> Result<sq
 
         return Ok(default_query(sql_ast::SetExpr::Select(Box::new(Select {
             projection: nulls,
+            from: from_of_constants(ctx),
             selection: Some(sql_ast::Expr::Value(sql_ast::Value::Boolean(false))),
             ..default_select()
         }))));
     }
 
+    // a single row is a SELECT of constants, which doesn't need names from an alias
+    if ctx.dialect.supports_values() && data.rows.len() > 1 {
+        return translate_relation_literal_values(data, ctx);
+    }
+
+    // VALUES has no way of setting names of the columns on its own, so dialects
+    // without column aliases on derived tables get SELECT UNION ALL SELECT
     let mut selects = Vec::with_capacity(data.rows.len());
 
     for row in data.rows {
@@ -477,6 +964,7 @@ fn translate_relation_literal(data: RelationLiteral, ctx: &Context) -> Result<sq
                     })
                 })
                 .try_collect()?,
+            from: from_of_constants(ctx),
             ..default_select()
         }));
 
@@ -496,6 +984,72 @@ fn translate_relation_literal(data: RelationLiteral, ctx: &Context) -> Result<sq
     Ok(default_query(body))
 }
 
+/// `FROM` of a `SELECT` of constants, which reads from `DUAL` in dialects
+/// that require a `FROM`.
+fn from_of_constants(ctx: &Context) -> Vec<TableWithJoins> {
+    if ctx.dialect.supports_select_without_from() {
+        return vec![];
+    }
+    vec![TableWithJoins {
+        relation: TableFactor::Table {
+            name: sql_ast::ObjectName(vec![sql_ast::Ident::new("DUAL")]),
+            alias: None,
+            args: None,
+            with_hints: vec![],
+            with_ordinality: false,
+            version: None,
+            partitions: vec![],
+            json_path: None,
+            sample: None,
+        },
+        joins: vec![],
+    }]
+}
+
+/// Translates a relation literal into `SELECT * FROM (VALUES ...) AS table_0(a, b)`,
+/// which names the columns via the alias of the derived table.
+fn translate_relation_literal_values(
+    data: RelationLiteral,
+    ctx: &mut Context,
+) -> Result<sql_ast::Query> {
+    let rows = (data.rows.into_iter())
+        .map(|row| {
+            row.into_iter()
+                .map(|v| translate_literal(v, ctx))
+                .try_collect()
+        })
+        .try_collect()?;
+    let values = default_query(SetExpr::Values(sql_ast::Values {
+        explicit_row: false,
+        rows,
+    }));
+
+    let alias = TableAlias {
+        name: mark_table_alias(sql_ast::Ident::new(ctx.anchor.table_name.gen()), ctx),
+        columns: (data.columns.into_iter())
+            .map(|col| sql_ast::TableAliasColumnDef {
+                name: translate_ident_part(col, ctx),
+                data_type: None,
+            })
+            .collect(),
+    };
+
+    Ok(default_query(SetExpr::Select(Box::new(Select {
+        projection: vec![SelectItem::Wildcard(
+            sql_ast::WildcardAdditionalOptions::default(),
+        )],
+        from: vec![TableWithJoins {
+            relation: TableFactor::Derived {
+                lateral: false,
+                subquery: Box::new(values),
+                alias: Some(alias),
+            },
+            joins: vec![],
+        }],
+        ..default_select()
+    }))))
+}
+
 pub(super) fn translate_query_sstring(
     items: Vec<InterpolateItem<Expr>>,
     ctx: &mut Context,
@@ -545,7 +1099,7 @@ pub(super) fn translate_query_operator(
 
 fn filter_of_conditions(exprs: Vec<Expr>, context: &mut Context) -> Result<Option<sql_ast::Expr>> {
     Ok(if let Some(cond) = all(exprs) {
-        Some(translate_expr(cond, context)?.into_ast())
+        Some(translate_condition(cond, context)?)
     } else {
         None
     })
@@ -635,8 +1189,9 @@ fn query_to_set_expr(query: sql_ast::Query, context: &mut Context) -> Box<SetExp
             relation: TableFactor::Derived {
                 lateral: false,
                 subquery: Box::new(query),
-                alias: Some(simple_table_alias(sql_ast::Ident::new(
-                    context.anchor.table_name.gen(),
+                alias: Some(simple_table_alias(mark_table_alias(
+                    sql_ast::Ident::new(context.anchor.table_name.gen()),
+                    context,
                 ))),
             },
             joins: vec![],
@@ -674,7 +1229,7 @@ mod test {
         let sql_ast = crate::tests::compile(query).unwrap();
 
         assert_snapshot!(sql_ast, @r"
-        WITH table_0 AS (
+        WITH employees_0 AS (
           SELECT
             title,
             AVG(salary) AS _expr_0
@@ -688,7 +1243,7 @@ mod test {
           title,
           AVG(_expr_0) AS avg_salary
         FROM
-          table_0
+          employees_0
         GROUP BY
           title
         ");
@@ -715,7 +1270,7 @@ mod test {
         let sql_ast = crate::tests::compile(query).unwrap();
 
         assert_snapshot!(sql_ast, @r"
-        WITH table_0 AS (
+        WITH employees_0 AS (
           SELECT
             *,
             RANK() OVER () AS global_rank
@@ -726,7 +1281,7 @@ mod test {
           *,
           RANK() OVER () AS rank
         FROM
-          table_0
+          employees_0
         WHERE
           country = 'USA'
         ");
@@ -741,7 +1296,7 @@ mod test {
         "#;
 
         assert_snapshot!(crate::tests::compile(query).unwrap(), @r"
-        WITH table_0 AS (
+        WITH tbl1_0 AS (
           SELECT
             *,
             AVG(bar) OVER () AS _expr_0
@@ -751,7 +1306,7 @@ mod test {
         SELECT
           *
         FROM
-          table_0
+          tbl1_0
         WHERE
           _expr_0 > 3
         ");
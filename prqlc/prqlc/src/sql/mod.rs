@@ -7,58 +7,289 @@ mod gen_query;
 mod keywords;
 mod operators;
 mod pq;
+mod source_map;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use regex::Regex;
 
 pub use dialect::{Dialect, SupportLevel};
 pub use pq::ast as pq_ast;
+pub use source_map::SourceMap;
 
 use self::dialect::DialectHandler;
-use self::pq::ast::Cte;
+use self::pq::ast::{Cte, SqlRelation};
 use self::pq::context::AnchorContext;
 use crate::debug;
 use crate::ir::rq;
-use crate::Result;
-use crate::{compiler_version, Options};
+use crate::{
+    compiler_version, AggregateNullHandling, CteOrder, NullsOrder, Options, OutputEncoding, Span,
+    Target,
+};
+use crate::{Error, Result};
 
 /// Translate a PRQL AST into a SQL string.
 pub fn compile(query: rq::RelationalQuery, options: &Options) -> Result<String> {
-    let crate::Target::Sql(dialect) = options.target;
-    let sql_ast = gen_query::translate_query(query, dialect)?;
+    compile_inner(query, options, Instrumentation::default()).map(|(sql, _)| sql)
+}
+
+/// Resolves the dialect which the query is compiled to: the dialect of
+/// [Options::target], or otherwise the `target` of the query header.
+pub fn resolve_dialect(query: &rq::RelationalQuery, options: &Options) -> Result<Dialect> {
+    let target = match &options.target {
+        Target::Sql(None) => {
+            let target = query.def.other.get("target");
+            target
+                .map(|s| Target::from_str(s))
+                .transpose()?
+                .unwrap_or_default()
+        }
+        target => target.clone(),
+    };
+    match target {
+        Target::Sql(dialect) => Ok(dialect.unwrap_or_default()),
+        Target::Prql => Err(Error::new_simple(
+            "target `prql` formats PRQL, so it cannot be used to compile RQ",
+        )),
+    }
+}
 
-    let sql = sql_ast.to_string();
+/// Same as [compile], but also returns the passes that were applied to the query.
+pub(crate) fn compile_with_pass_log(
+    query: rq::RelationalQuery,
+    options: &Options,
+) -> Result<(String, Vec<debug::PassEvent>)> {
+    let instrumentation = Instrumentation {
+        passes: true,
+        ..Default::default()
+    };
+    let (sql, ctx) = compile_inner(query, options, instrumentation)?;
+    Ok((sql, ctx.pass_log.unwrap_or_default()))
+}
+
+/// Same as [compile], but also returns the byte ranges of the SQL that were
+/// produced by PRQL expressions, along with spans of these expressions.
+pub(crate) fn compile_with_source_map(
+    query: rq::RelationalQuery,
+    options: &Options,
+) -> Result<(String, SourceMap)> {
+    let instrumentation = Instrumentation {
+        source_map: true,
+        ..Default::default()
+    };
+    let (sql, ctx) = compile_inner(query, options, instrumentation)?;
+    let fragments = (ctx.source_map.iter().flatten())
+        .map(|(fragment, span)| (ctx.respell(fragment.clone()), *span))
+        .collect();
+    let source_map = source_map::locate(&sql, fragments);
+    Ok((sql, source_map))
+}
+
+/// Same as [compile], but also returns the byte ranges of the CTEs within the
+/// SQL, along with spans of the PRQL which produced them.
+pub(crate) fn compile_with_cte_map(
+    query: rq::RelationalQuery,
+    options: &Options,
+) -> Result<(String, SourceMap)> {
+    let instrumentation = Instrumentation {
+        source_map: true,
+        ..Default::default()
+    };
+    let (sql, ctx) = compile_inner(query, options, instrumentation)?;
+    let fragments = (ctx.cte_map.iter().flatten())
+        .map(|(fragment, span)| (ctx.respell(fragment.clone()), *span))
+        .collect();
+    let cte_map = source_map::locate(&sql, fragments);
+    Ok((sql, cte_map))
+}
+
+/// Translates the query, returning the SQL fragment produced by each transform.
+pub(crate) fn compile_stages(
+    query: rq::RelationalQuery,
+    options: &Options,
+) -> Result<Vec<(debug::TransformKind, String)>> {
+    let instrumentation = Instrumentation {
+        stages: true,
+        ..Default::default()
+    };
+    let (_, ctx) = gen_query::translate_query(query, options, instrumentation)?;
+    Ok(ctx.stage_log.unwrap_or_default())
+}
+
+/// What the translation records besides the SQL, for debugging and source maps.
+#[derive(Debug, Clone, Copy, Default)]
+struct Instrumentation {
+    /// Records the pipelines before and after each pass, in [Context::pass_log].
+    passes: bool,
+    /// Records the SQL fragment of each transform, in [Context::stage_log].
+    stages: bool,
+    /// Records the SQL fragments of expressions and CTEs, in
+    /// [Context::source_map] and [Context::cte_map].
+    source_map: bool,
+}
+
+fn compile_inner(
+    query: rq::RelationalQuery,
+    options: &Options,
+    instrumentation: Instrumentation,
+) -> Result<(String, Context)> {
+    let statement_timeout = query.def.other.get("statement_timeout").cloned();
+    let has_header_target = query.def.other.contains_key("target");
+    let (sql_ast, ctx) = gen_query::translate_query(query, options, instrumentation)?;
+
+    let sql = ctx.respell(sql_ast.to_string());
 
     // formatting
     let sql = if options.format {
-        let formatted = sqlformat::format(
-            &sql,
-            &sqlformat::QueryParams::default(),
-            &sqlformat::FormatOptions::default(),
-        );
+        // keywords are already uppercase; sqlformat only recognizes keywords
+        // and never changes the case of quoted identifiers or strings
+        let format_options = sqlformat::FormatOptions {
+            uppercase: (!options.uppercase_keywords).then_some(false),
+            ..Default::default()
+        };
+        // sqlformat takes the FROM of `IS [NOT] DISTINCT FROM` for a clause,
+        // so it's tied to DISTINCT into a single word while formatting. Quoted
+        // strings and identifiers are skipped.
+        let sql = Regex::new(r#"'(?:[^']|'')*'|"(?:[^"]|"")*"|`[^`]*`|(?i)\bDISTINCT\s+FROM\b"#)
+            .unwrap()
+            .replace_all(&sql, |caps: &regex::Captures| {
+                let matched = &caps[0];
+                if matched.starts_with(['\'', '"', '`']) {
+                    matched.to_string()
+                } else if options.uppercase_keywords {
+                    format!("DISTINCT{DISTINCT_FROM_TIE}FROM")
+                } else {
+                    format!("distinct{DISTINCT_FROM_TIE}from")
+                }
+            });
+        let formatted =
+            sqlformat::format(&sql, &sqlformat::QueryParams::default(), &format_options)
+                .replace(DISTINCT_FROM_TIE, " ");
+
+        // sqlformat does not recognize `U&'...'` strings and splits them apart
+        let formatted = if options.output_encoding == OutputEncoding::Ascii {
+            formatted.replace("U & '", "U&'")
+        } else {
+            formatted
+        };
+
+        let formatted = if options.leading_comma {
+            move_commas_to_line_starts(&formatted)
+        } else {
+            formatted
+        };
 
         formatted + "\n"
     } else {
         sql
     };
 
+    // the timeout is set by a statement preceding the query
+    let sql = if let Some(timeout) = statement_timeout {
+        let timeout = timeout.parse().unwrap();
+        let Some(preamble) = ctx.dialect.statement_timeout(timeout) else {
+            return Err(Error::new_simple(format!(
+                "`statement_timeout` is not supported for {}",
+                ctx.dialect_enum
+            )));
+        };
+        let separator = if options.format { "\n" } else { " " };
+        format!("{preamble};{separator}{sql}")
+    } else {
+        sql
+    };
+
+    // the semicolon terminates the last statement, before any trailing newline
+    let sql = if options.trailing_semicolon {
+        match sql.strip_suffix('\n') {
+            Some(sql) => format!("{sql};\n"),
+            None => sql + ";",
+        }
+    } else {
+        sql
+    };
+
     debug::log_entry(|| debug::DebugEntryKind::ReprSql(sql.clone()));
 
     // signature
-    let sql = if options.signature_comment {
+    let signature = match &options.signature_text {
+        _ if !options.signature_comment => None,
+        Some(text) if text.is_empty() => None,
+        Some(text) => Some(text.lines().map(|line| format!("-- {line}")).join("\n")),
+        None => {
+            // the resolved dialect, unless the target was left to the default
+            let has_target = matches!(options.target, Target::Sql(Some(_))) || has_header_target;
+            let target = if has_target {
+                format!("target:sql.{} ", ctx.dialect_enum)
+            } else {
+                String::new()
+            };
+            Some(format!(
+                "-- Generated by PRQL compiler version:{} {}(https://prql-lang.org)",
+                compiler_version(),
+                target,
+            ))
+        }
+    };
+    let sql = if let Some(signature) = signature {
         let pre = if options.format { "\n" } else { " " };
         let post = if options.format { "\n" } else { "" };
-        let target = dialect
-            .map(|d| format!("target:sql.{d} "))
-            .unwrap_or_default();
-        let signature = format!(
-            "{pre}-- Generated by PRQL compiler version:{} {}(https://prql-lang.org){post}",
-            compiler_version(),
-            target,
-        );
-        sql + &signature
+        format!("{sql}{pre}{signature}{post}")
     } else {
         sql
     };
 
-    Ok(sql)
+    Ok((sql, ctx))
+}
+
+/// Moves commas that end a line to the start of the following line, after its
+/// indentation. Commas within quoted strings and identifiers are left as they are.
+fn move_commas_to_line_starts(sql: &str) -> String {
+    let mut res = String::with_capacity(sql.len());
+    let mut quote = None;
+
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if matches!(c, '\'' | '"' | '`') => quote = Some(c),
+            None if c == ',' && chars.peek() == Some(&'\n') => {
+                res.push(chars.next().unwrap());
+                while let Some(indent) = chars.next_if(|c| *c == ' ') {
+                    res.push(indent);
+                }
+                res.push_str(", ");
+                continue;
+            }
+            None => {}
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Joins the words of `DISTINCT FROM`, which sqlformat then reads as one word.
+/// It's a connector punctuation, like `_`.
+const DISTINCT_FROM_TIE: char = '\u{2040}';
+
+/// Precedes the names of table aliases, which are emitted without `AS` for
+/// dialects that don't allow it there (see [DialectHandler::table_alias_as]).
+const TABLE_ALIAS_MARKER: char = '\u{1}';
+
+/// Removes `AS` preceding the table aliases marked with [TABLE_ALIAS_MARKER],
+/// and the markers themselves.
+fn remove_table_alias_as(sql: &str) -> String {
+    let mut sql = sql.to_string();
+    for quote in ["", "\"", "`"] {
+        sql = sql.replace(
+            &format!(" AS {quote}{TABLE_ALIAS_MARKER}"),
+            &format!(" {quote}"),
+        );
+    }
+    sql.replace(TABLE_ALIAS_MARKER, "")
 }
 
 #[derive(Debug)]
@@ -66,6 +297,34 @@ struct Context {
     pub dialect: Box<dyn DialectHandler>,
     pub dialect_enum: Dialect,
 
+    pub output_encoding: OutputEncoding,
+
+    /// Format of date literals, in chrono strftime syntax. ISO-8601 when unset.
+    pub date_format: Option<String>,
+
+    /// When true, relations that are referenced only once are emitted as
+    /// subqueries instead of CTEs.
+    pub inline_ctes: bool,
+
+    /// When null, aggregates are not wrapped to yield their default.
+    pub aggregate_null_handling: AggregateNullHandling,
+
+    /// When true, joins on equally named columns are emitted with USING.
+    pub join_using: bool,
+
+    /// Order of CTEs in the `WITH` clause.
+    pub cte_order: CteOrder,
+
+    /// When true, NULL branches of a `CASE` are cast to the type of the others.
+    pub explicit_null_cast: bool,
+
+    /// Placement of NULLs by `ORDER BY`.
+    pub nulls_order: NullsOrder,
+
+    /// Number of references to each table of the query. Tables not in the map
+    /// are created while splitting pipelines and are referenced once.
+    pub table_ref_counts: HashMap<rq::TId, usize>,
+
     pub anchor: AnchorContext,
 
     // stuff regarding current query
@@ -75,6 +334,23 @@ struct Context {
     query_stack: Vec<QueryOpts>,
 
     pub ctes: Vec<Cte>,
+
+    /// Relations used as expressions, which are translated in place.
+    pub sub_queries: HashMap<rq::TId, SqlRelation>,
+
+    /// When set, preprocessing passes record whether they changed the query.
+    pub pass_log: Option<Vec<debug::PassEvent>>,
+
+    /// When set, translation records the SQL fragment of each transform.
+    pub stage_log: Option<Vec<(debug::TransformKind, String)>>,
+
+    /// When set, translation records the SQL of each expression that has a
+    /// span, so it can be located in the output.
+    pub source_map: Option<Vec<(String, Span)>>,
+
+    /// When set, translation records the SQL of each CTE, with the span of
+    /// the PRQL which produced it.
+    pub cte_map: Option<Vec<(String, Span)>>,
 }
 
 #[derive(Clone, Debug)]
@@ -96,6 +372,9 @@ struct QueryOpts {
 
     /// True when translating function that will have an OVER clause.
     pub window_function: bool,
+
+    /// True when translating a query nested within another (a subquery or a CTE).
+    pub is_subquery: bool,
 }
 
 impl Default for QueryOpts {
@@ -106,6 +385,7 @@ impl Default for QueryOpts {
             allow_ctes: true,
             allow_stars: true,
             window_function: false,
+            is_subquery: false,
         }
     }
 }
@@ -115,10 +395,48 @@ impl Context {
         Context {
             dialect: dialect.handler(),
             dialect_enum: dialect,
+            output_encoding: OutputEncoding::default(),
+            date_format: None,
+            inline_ctes: false,
+            aggregate_null_handling: AggregateNullHandling::default(),
+            join_using: false,
+            cte_order: CteOrder::default(),
+            explicit_null_cast: false,
+            nulls_order: NullsOrder::default(),
+            table_ref_counts: HashMap::new(),
             anchor,
             query: QueryOpts::default(),
             query_stack: Vec::new(),
             ctes: Vec::new(),
+            sub_queries: HashMap::new(),
+            pass_log: None,
+            stage_log: None,
+            source_map: None,
+            cte_map: None,
+        }
+    }
+
+    fn log_transform_sql(&mut self, kind: debug::TransformKind, sql: impl FnOnce() -> String) {
+        if self.stage_log.is_some() {
+            let sql = self.respell(sql());
+            self.stage_log.as_mut().unwrap().push((kind, sql));
+        }
+    }
+
+    /// Applies the spellings of the dialect that sqlparser's AST cannot
+    /// represent to the SQL rendered from it.
+    fn respell(&self, sql: String) -> String {
+        // sqlparser always emits AS before table aliases
+        if self.dialect.table_alias_as() {
+            sql
+        } else {
+            remove_table_alias_as(&sql)
+        }
+    }
+
+    fn map_source(&mut self, span: Option<Span>, sql: impl FnOnce() -> String) {
+        if let (Some(source_map), Some(span)) = (&mut self.source_map, span) {
+            source_map.push((sql(), span));
         }
     }
 
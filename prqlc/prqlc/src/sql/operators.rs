@@ -9,8 +9,8 @@ use super::gen_expr::{translate_operand, ExprOrSource, SourceExpr};
 use super::{Context, Dialect};
 use crate::ir::{decl, pl, rq};
 use crate::utils::Pluck;
-use crate::Result;
 use crate::{debug, semantic};
+use crate::{AggregateNullHandling, Result};
 use crate::{Error, WithErrorInfo};
 
 fn std() -> &'static decl::Module {
@@ -106,7 +106,7 @@ pub(super) fn translate_operator(
 
     let mut binding_strength = parent_binding_strength;
 
-    if !ctx.query.window_function {
+    if !ctx.query.window_function && ctx.aggregate_null_handling != AggregateNullHandling::Null {
         if let Some(default) = coalesce {
             text = format!("COALESCE({text}, {default})");
             binding_strength = 100;
@@ -27,7 +27,15 @@ pub(super) fn extract_atomic(
             "pipeline split after {}",
             preceding.last().unwrap().as_str()
         );
-        anchor_split(ctx, preceding, atomic)
+        let filters_window = filters_window(&atomic, ctx);
+        let atomic = anchor_split(ctx, preceding, atomic);
+
+        if filters_window {
+            let from = atomic.first().unwrap().as_from().unwrap();
+            let tid = ctx.relation_instances[from].table_ref.source;
+            ctx.window_filter_splits.insert(tid);
+        }
+        atomic
     } else {
         atomic
     };
@@ -58,6 +66,18 @@ pub(super) fn extract_atomic(
     atomic
 }
 
+/// Whether any of the filters of a pipeline refers to a window function.
+fn filters_window(pipeline: &[SqlTransform], ctx: &AnchorContext) -> bool {
+    let filters = pipeline.iter().filter_map(|t| t.as_super()?.as_filter());
+
+    filters
+        .flat_map(|expr| CidCollector::collect(expr.clone()))
+        .any(|cid| {
+            let decl = ctx.column_decls.get(&cid);
+            matches!(decl, Some(ColumnDecl::Compute(compute)) if compute.window.is_some())
+        })
+}
+
 /// Splits pipeline into two parts, such that the second part contains
 /// maximum number of transforms while "fitting" into a SELECT query.
 ///
@@ -91,7 +111,7 @@ pub(super) fn split_off_back(
         }
 
         // anchor and record all requirements
-        let required = get_requirements(&transform, &following_transforms);
+        let required = get_requirements(&transform, &following_transforms, ctx);
         log::debug!("transform {} requires {:?}", transform.as_str(), required);
         inputs_required.extend(required.clone());
 
@@ -261,15 +281,18 @@ pub(super) fn anchor_split(
         .iter()
         .map(|_| RelationColumn::Single(None))
         .collect_vec();
+    let name_hint = ctx.source_name_hint(&preceding);
     ctx.table_decls.insert(
         new_tid,
         SqlTableDecl {
             id: new_tid,
             name: None,
+            name_hint,
             relation: RelationStatus::NotYetDefined(RelationAdapter::Preprocessed(
                 preceding, columns,
             )),
             redirect_to: None,
+            is_extern: false,
         },
     );
 
@@ -279,6 +302,7 @@ pub(super) fn anchor_split(
             source: new_tid,
             name: None,
             columns: new_columns,
+            span: None,
         },
         cid_redirects,
     );
@@ -297,6 +321,7 @@ pub(super) fn anchor_split(
 fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>) -> bool {
     // Pipeline must be split when there is a transform that is out of order:
     // - from (max 1x),
+    // - pivot/unpivot (max 1x, applied to from)
     // - join (no limit),
     // - filters (for WHERE)
     // - aggregate (max 1x)
@@ -304,6 +329,7 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
     // - compute (no limit)
     // - sort (no limit)
     // - take (no limit)
+    //   (take of a percentage applies to FROM, so only filters and computes may precede it)
     // - distinct
     // - append/except/intersect (no limit)
     // - loop (max 1x)
@@ -331,18 +357,40 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
 
     let split = match transform {
         SqlTransform::From(_) => contains_any(following, ["From"]),
-        SqlTransform::Join { .. } => contains_any(following, ["From"]),
-        Super(Aggregate { .. }) => {
-            contains_any(following, ["From", "Join", "Aggregate", "Compute"])
-        }
+        SqlTransform::Join { .. } => contains_any(following, ["From", "TakePercent"]),
+        Super(Aggregate { .. }) => contains_any(
+            following,
+            ["From", "Join", "Aggregate", "Compute", "TakePercent"],
+        ),
         Super(Filter(_)) => contains_any(following, ["From", "Join"]),
+        Super(Pivot(_) | Unpivot(_)) => contains_any(following, ["From", "Join", "TakePercent"]),
         Super(Compute(_)) => contains_any(following, ["From", "Join", /* "Aggregate" */ "Filter"]),
 
         // Sort will be pushed down the CTEs, so there is no point in splitting for it.
         // Super(Sort(_)) => contains_any(following, ["From", "Join", "Compute", "Aggregate"]),
+        Super(Take(take)) if take.percent.is_some() => contains_any(
+            following,
+            [
+                "From",
+                "Join",
+                "Compute",
+                "Filter",
+                "Aggregate",
+                "Sort",
+                "Take",
+            ],
+        ),
         Super(Take(_)) => contains_any(
             following,
-            ["From", "Join", "Compute", "Filter", "Aggregate", "Sort"],
+            [
+                "From",
+                "Join",
+                "Compute",
+                "Filter",
+                "Aggregate",
+                "Sort",
+                "TakePercent",
+            ],
         ),
         SqlTransform::DistinctOn(_) => contains_any(
             following,
@@ -357,6 +405,7 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
                 "DistinctOn",
             ],
         ),
+        // LIMIT applies after DISTINCT, so only take of a percentage requires a split
         SqlTransform::Distinct => contains_any(
             following,
             [
@@ -366,7 +415,7 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
                 "Filter",
                 "Aggregate",
                 "Sort",
-                "Take",
+                "TakePercent",
             ],
         ),
         SqlTransform::Union { .. }
@@ -390,6 +439,13 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
 
     if !split {
         following.insert(transform.as_str().to_string());
+
+        if let Super(Take(rq::Take {
+            percent: Some(_), ..
+        })) = transform
+        {
+            following.insert("TakePercent".to_string());
+        }
     }
     split
 }
@@ -431,10 +487,28 @@ impl std::fmt::Debug for Requirement {
 pub(super) fn get_requirements(
     transform: &SqlTransform,
     following: &HashSet<String>,
+    ctx: &AnchorContext,
 ) -> Vec<Requirement> {
     use SqlTransform::Super;
     use Transform::*;
 
+    // special case for Sort, which only needs to SELECT complex expressions:
+    // plain expressions can be inlined into ORDER BY
+    if let Super(Sort(sorts)) = transform {
+        return (sorts.iter())
+            .map(|sort| {
+                let decl = ctx.column_decls.get(&sort.column);
+                let can_inline = matches!(decl, Some(ColumnDecl::Compute(compute))
+                    if can_inline_into_order_by(compute));
+                Requirement {
+                    col: sort.column,
+                    max_complexity: Complexity::Aggregation,
+                    selected: !can_inline,
+                }
+            })
+            .collect();
+    }
+
     // special case for Aggregate, which contain two difference Complexity-ies
     if let Super(Aggregate { partition, compute }) = transform {
         let mut r = Vec::new();
@@ -467,6 +541,15 @@ pub(super) fn get_requirements(
         };
         let mut requirements = into_requirements(expr_cids, expr_max_complexity, false);
 
+        // non-deterministic columns must be computed only once, so they cannot be
+        // inlined into other expressions
+        for requirement in &mut requirements {
+            let decl = ctx.column_decls.get(&requirement.col);
+            if matches!(decl, Some(ColumnDecl::Compute(c)) if is_non_deterministic(&c.expr)) {
+                requirement.max_complexity = Complexity::Plain;
+            }
+        }
+
         // window
         if let Some(window) = &compute.window {
             // TODO: what kind of exprs can be in window frame?
@@ -487,7 +570,7 @@ pub(super) fn get_requirements(
         Super(Filter(expr)) | SqlTransform::Join { filter: expr, .. } => {
             CidCollector::collect(expr.clone())
         }
-        Super(Sort(sorts)) => sorts.iter().map(|s| s.column).collect(),
+        SqlTransform::Sort(sorts) => sorts.iter().map(|s| s.column).collect(),
         Super(Take(rq::Take { range, .. })) => {
             let mut cids = Vec::new();
             if let Some(e) = &range.start {
@@ -512,8 +595,8 @@ pub(super) fn get_requirements(
             },
             false,
         ),
-        // we only use SELECTed columns in ORDER BY, so the columns can have high complexity
-        Super(Sort(_)) => (Complexity::Aggregation, true),
+        // sorting of DISTINCT ON is not SELECTed, so its expressions are inlined into ORDER BY
+        SqlTransform::Sort(_) => (Complexity::Plain, false),
 
         // LIMIT and OFFSET can use constant expressions which don't need to be SELECTed
         Super(Take(_)) => (Complexity::Plain, false),
@@ -524,6 +607,18 @@ pub(super) fn get_requirements(
     into_requirements(cids, max_complexity, selected)
 }
 
+/// Whether a computed column can be sorted by without being SELECTed.
+///
+/// Constants are excluded, because an integer in ORDER BY refers to the
+/// position of a column, and some databases reject other constants.
+fn can_inline_into_order_by(compute: &Compute) -> bool {
+    let is_constant = matches!(
+        compute.expr.kind,
+        rq::ExprKind::Literal(_) | rq::ExprKind::Param(_)
+    );
+    !is_constant && infer_complexity(compute) < Complexity::Windowed
+}
+
 /// Complexity of a column expressions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Complexity {
@@ -556,6 +651,10 @@ pub fn infer_complexity(compute: &Compute) -> Complexity {
 }
 
 pub fn infer_complexity_expr(expr: &Expr) -> Complexity {
+    if is_non_deterministic(expr) {
+        return Complexity::NonGroup;
+    }
+
     match &expr.kind {
         rq::ExprKind::Case(_) => Complexity::NonGroup,
         rq::ExprKind::Operator { args, .. } => args
@@ -566,11 +665,29 @@ pub fn infer_complexity_expr(expr: &Expr) -> Complexity {
         rq::ExprKind::ColumnRef(_)
         | rq::ExprKind::Literal(_)
         | rq::ExprKind::SString(_)
-        | rq::ExprKind::Param(_) => Complexity::Plain,
+        | rq::ExprKind::Param(_)
+        | rq::ExprKind::SubQuery(_) => Complexity::Plain,
         rq::ExprKind::Array(_) => Complexity::highest(),
     }
 }
 
+/// Whether an expression may evaluate to a different value each time, such as
+/// `std.random` or `std.current_time`.
+fn is_non_deterministic(expr: &Expr) -> bool {
+    match &expr.kind {
+        rq::ExprKind::Operator { name, args } => {
+            NON_DETERMINISTIC_OPERATORS.contains(&name.as_str())
+                || args.iter().any(is_non_deterministic)
+        }
+        rq::ExprKind::Case(cases) => (cases.iter())
+            .any(|case| is_non_deterministic(&case.condition) || is_non_deterministic(&case.value)),
+        _ => false,
+    }
+}
+
+const NON_DETERMINISTIC_OPERATORS: &[&str] =
+    &["std.random", "std.current_date", "std.current_time"];
+
 #[derive(Default)]
 pub struct CidCollector {
     // we could use HashSet instead of Vec, but this caused nondeterministic
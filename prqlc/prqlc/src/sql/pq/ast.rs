@@ -11,7 +11,7 @@ use serde::Serialize;
 use super::context::RIId;
 use crate::ir::generic::ColumnSort;
 use crate::ir::pl::JoinSide;
-use crate::ir::rq::{self, fold_column_sorts, RelationLiteral, RqFold};
+use crate::ir::rq::{self, fold_column_sorts, fold_pivot, fold_unpivot, RelationLiteral, RqFold};
 use crate::Result;
 
 #[derive(Debug, Clone, Serialize)]
@@ -57,6 +57,9 @@ pub enum CteKind {
         initial: SqlRelation,
         step: SqlRelation,
     },
+    /// A relation used as an expression, which is emitted in place of the
+    /// expression instead of in `WITH`.
+    SubQuery(SqlRelation),
 }
 
 /// Similar to [rq::Transform], but closer to a SQL clause.
@@ -64,7 +67,7 @@ pub enum CteKind {
 /// Uses two generic args that allows the compiler to work in multiple stages:
 /// - the first converts RQ to [SqlTransform<RIId, rq::Transform>],
 /// - the second compiles that to [SqlTransform<RelationExpr, ()>].
-#[derive(Debug, Clone, EnumAsInner, strum::AsRefStr, Serialize)]
+#[derive(Debug, Clone, PartialEq, EnumAsInner, strum::AsRefStr, Serialize)]
 pub enum SqlTransform<Rel = RIId, Super = rq::Transform> {
     /// Contains [rq::Transform] during compilation. After finishing, this is emptied.
     ///
@@ -98,6 +101,11 @@ pub enum SqlTransform<Rel = RIId, Super = rq::Transform> {
         filter: rq::Expr,
     },
 
+    /// Applied to the preceding [SqlTransform::From].
+    Pivot(rq::Pivot),
+    /// Applied to the preceding [SqlTransform::From].
+    Unpivot(rq::Unpivot),
+
     Distinct,
     DistinctOn(Vec<rq::CId>),
     Except {
@@ -111,6 +119,8 @@ pub enum SqlTransform<Rel = RIId, Super = rq::Transform> {
     Union {
         bottom: Rel,
         distinct: bool,
+        /// Columns are matched by name instead of by position.
+        by_name: bool,
     },
 }
 
@@ -174,11 +184,19 @@ pub fn fold_sql_transform<
             filter: fold.fold_expr(filter)?,
         },
 
+        SqlTransform::Pivot(pivot) => SqlTransform::Pivot(fold_pivot(fold, pivot)?),
+        SqlTransform::Unpivot(unpivot) => SqlTransform::Unpivot(fold_unpivot(fold, unpivot)?),
+
         SqlTransform::Distinct => SqlTransform::Distinct,
         SqlTransform::DistinctOn(ids) => SqlTransform::DistinctOn(fold.fold_cids(ids)?),
-        SqlTransform::Union { bottom, distinct } => SqlTransform::Union {
+        SqlTransform::Union {
+            bottom,
+            distinct,
+            by_name,
+        } => SqlTransform::Union {
             bottom: fold.fold_rel(bottom)?,
             distinct,
+            by_name,
         },
         SqlTransform::Except { bottom, distinct } => SqlTransform::Except {
             bottom: fold.fold_rel(bottom)?,
@@ -199,6 +217,7 @@ pub fn fold_sql_transform<
             partition: fold.fold_cids(take.partition)?,
             sort: fold_column_sorts(fold, take.sort)?,
             range: take.range,
+            percent: take.percent,
         }),
     })
 }
@@ -233,6 +252,7 @@ pub trait PqFold: PqMapper<RelationExpr, RelationExpr, (), ()> {
                     initial: self.fold_sql_relation(initial)?,
                     step: self.fold_sql_relation(step)?,
                 },
+                CteKind::SubQuery(rel) => CteKind::SubQuery(self.fold_sql_relation(rel)?),
             },
         })
     }
@@ -1,7 +1,7 @@
 //! Transform the parsed AST into a "materialized" AST, by executing functions and
 //! replacing variables. The materialized AST is "flat", in the sense that it
 //! contains no query-specific logic.
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::iter::zip;
 
 use enum_as_inner::EnumAsInner;
@@ -27,6 +27,10 @@ pub struct AnchorContext {
 
     pub relation_instances: HashMap<RIId, RelationInstance>,
 
+    /// Tables which were split off a pipeline because a following filter
+    /// refers to their window functions.
+    pub window_filter_splits: HashSet<TId>,
+
     pub col_name: NameGenerator,
     pub table_name: NameGenerator,
 
@@ -44,6 +48,10 @@ pub struct SqlTableDecl {
     /// Generated in postprocessing.
     pub name: Option<Ident>,
 
+    /// Name of the table this relation reads from. Used to derive [SqlTableDecl::name] when it
+    /// is not set, so generated names stay stable when unrelated parts of the query change.
+    pub name_hint: Option<String>,
+
     /// When set, any references to this decl will be redirected to the set TId.
     pub redirect_to: Option<TId>,
 
@@ -51,6 +59,9 @@ pub struct SqlTableDecl {
     /// None means that it has already been defined, or was not needed to be defined in the
     /// first place.
     pub relation: RelationStatus,
+
+    /// Whether this is a table of the database, rather than a relation defined by the query.
+    pub is_extern: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -144,6 +155,19 @@ impl AnchorContext {
     //     id
     // }
 
+    /// Finds the name of the table that a pipeline reads from, following name hints of
+    /// unnamed tables.
+    pub fn source_name_hint(&self, pipeline: &[SqlTransform]) -> Option<String> {
+        let riid = pipeline.iter().find_map(|t| t.as_from())?;
+        let source = self.relation_instances.get(riid)?.table_ref.source;
+        self.table_hint(&source)
+    }
+
+    fn table_hint(&self, tid: &TId) -> Option<String> {
+        let decl = self.table_decls.get(tid)?;
+        (decl.name.as_ref().map(|n| n.name.clone())).or_else(|| decl.name_hint.clone())
+    }
+
     pub fn register_compute(&mut self, compute: Compute) {
         let id = compute.id;
         let decl = ColumnDecl::Compute(Box::new(compute));
@@ -305,10 +329,20 @@ impl QueryLoader {
             name = Some(table.clone());
         }
 
+        let name_hint = match &decl.relation.kind {
+            RelationKind::Pipeline(transforms) => transforms.iter().find_map(|t| match t {
+                Transform::From(table_ref) => self.context.table_hint(&table_ref.source),
+                _ => None,
+            }),
+            _ => None,
+        };
+
+        let is_extern = matches!(decl.relation.kind, RelationKind::ExternRef(_));
         let sql_decl = SqlTableDecl {
             id: decl.id,
             name,
-            relation: if matches!(decl.relation.kind, RelationKind::ExternRef(_)) {
+            name_hint,
+            relation: if is_extern {
                 // this relation can be materialized by just using table name as a reference
                 // ... i.e. it's already defined.
                 RelationStatus::Defined
@@ -317,6 +351,7 @@ impl QueryLoader {
                 RelationStatus::NotYetDefined(decl.relation.into())
             },
             redirect_to: None,
+            is_extern,
         };
 
         self.context.table_decls.insert(decl.id, sql_decl);
@@ -1,39 +1,65 @@
 //! This module is responsible for translating RQ to PQ.
 
-use std::str::FromStr;
+use std::collections::HashMap;
 
 use itertools::Itertools;
 
-use super::super::{Context, Dialect};
+use super::super::{Context, Instrumentation};
 use super::anchor::{self, anchor_split};
 use super::ast::{self as pq, fold_sql_transform, PqMapper};
-use super::context::{AnchorContext, RIId, RelationAdapter, RelationStatus};
+use super::context::{AnchorContext, ColumnDecl, RIId, RelationAdapter, RelationStatus};
 use super::{postprocess, preprocess};
 use crate::debug;
-use crate::ir::rq::{self, RqFold};
+use crate::ir::rq::{self, RelationColumn, RqFold};
 use crate::utils::BreakUp;
-use crate::{Result, Target};
+use crate::{Options, Result};
 
 pub(in super::super) fn compile_query(
     query: rq::RelationalQuery,
-    dialect: Option<Dialect>,
+    options: &Options,
+    instrumentation: Instrumentation,
 ) -> Result<(pq::SqlQuery, Context)> {
     debug::log_stage(debug::Stage::Sql(debug::StageSql::Anchor));
 
-    let dialect = if let Some(dialect) = dialect {
-        dialect
+    let dialect = super::super::resolve_dialect(&query, options)?;
+
+    let query = inline_literal_main(query);
+
+    let table_ref_counts = if options.inline_ctes {
+        TableRefCounter::count(&query)
     } else {
-        let target = query.def.other.get("target");
-        let Target::Sql(maybe_dialect) = target
-            .map(|s| Target::from_str(s))
-            .transpose()?
-            .unwrap_or_default();
-        maybe_dialect.unwrap_or_default()
+        HashMap::new()
     };
+    let sub_queries = SubQueryCollector::collect(&query);
 
     let (anchor, main_relation) = AnchorContext::of(query);
 
     let mut ctx = Context::new(dialect, anchor);
+    ctx.output_encoding = options.output_encoding;
+    ctx.date_format.clone_from(&options.date_format);
+    ctx.inline_ctes = options.inline_ctes;
+    ctx.aggregate_null_handling = options.aggregate_null_handling;
+    ctx.join_using = options.join_using;
+    ctx.cte_order = options.cte_order;
+    ctx.explicit_null_cast = options.explicit_null_cast;
+    ctx.nulls_order = options.nulls_order;
+    ctx.table_ref_counts = table_ref_counts;
+    ctx.pass_log = instrumentation.passes.then(Vec::new);
+    ctx.stage_log = instrumentation.stages.then(Vec::new);
+    ctx.source_map = instrumentation.source_map.then(Vec::new);
+    ctx.cte_map = instrumentation.source_map.then(Vec::new);
+
+    // compile relations used as expressions, so they can be translated in place
+    for tid in sub_queries {
+        let decl = ctx.anchor.table_decls.get_mut(&tid).unwrap();
+        if let RelationStatus::NotYetDefined(sql_relation) = decl.relation.take_to_define() {
+            let relation = compile_relation(sql_relation, &mut ctx)?;
+            ctx.ctes.push(pq::Cte {
+                tid,
+                kind: pq::CteKind::SubQuery(relation),
+            });
+        }
+    }
 
     // compile main relation that will recursively compile CTEs
     let main_relation = compile_relation(main_relation.into(), &mut ctx)?;
@@ -107,6 +133,11 @@ fn compile_pipeline(
         pipeline = compile_loop(pipeline, ctx)?;
     }
 
+    // special case: pivot & unpivot
+    if pipeline.iter().any(is_reshape) {
+        pipeline = compile_reshape(pipeline, ctx);
+    }
+
     // extract an atomic pipeline from back of the pipeline and stash preceding part into context
     let pipeline = anchor::extract_atomic(pipeline, &mut ctx.anchor);
 
@@ -160,8 +191,12 @@ impl PqMapper<RIId, pq::RelationExpr, rq::Transform, ()> for TransformCompiler<'
                             }
                             rq::Transform::Sort(v) => pq::SqlTransform::Sort(v),
                             rq::Transform::Take(v) => pq::SqlTransform::Take(v),
+                            rq::Transform::Pivot(v) => pq::SqlTransform::Pivot(v),
+                            rq::Transform::Unpivot(v) => pq::SqlTransform::Unpivot(v),
                             rq::Transform::Compute(_)
-                            | rq::Transform::Append(_)
+                            | rq::Transform::Append { .. }
+                            | rq::Transform::Except { .. }
+                            | rq::Transform::Intersect { .. }
                             | rq::Transform::Loop(_) => {
                                 // these are not used from here on
                                 return Ok(None);
@@ -182,10 +217,18 @@ pub(super) fn compile_relation_instance(riid: RIId, ctx: &mut Context) -> Result
     let source = table_ref.source;
     let decl = ctx.anchor.table_decls.get_mut(&table_ref.source).unwrap();
 
+    // inline relations which are referenced only once, when requested
+    let inline = ctx.inline_ctes && ctx.table_ref_counts.get(&source).map_or(true, |c| *c == 1);
+
+    // inline relations whose window functions are filtered, if the dialect prefers so
+    let inline = inline
+        || (ctx.dialect.window_filter_in_subquery()
+            && ctx.anchor.window_filter_splits.contains(&source));
+
     // ensure that the table is declared
     if let RelationStatus::NotYetDefined(sql_relation) = decl.relation.take_to_define() {
         // if we cannot use CTEs (probably because we are within RECURSIVE)
-        if !ctx.query.allow_ctes {
+        if !ctx.query.allow_ctes || inline {
             // restore relation for other references
             decl.relation = RelationStatus::NotYetDefined(sql_relation.clone());
 
@@ -210,6 +253,79 @@ pub(super) fn compile_relation_instance(riid: RIId, ctx: &mut Context) -> Result
     })
 }
 
+/// Counts the references to each table of a query.
+#[derive(Default)]
+struct TableRefCounter {
+    counts: HashMap<rq::TId, usize>,
+}
+
+impl TableRefCounter {
+    fn count(query: &rq::RelationalQuery) -> HashMap<rq::TId, usize> {
+        let mut counter = TableRefCounter::default();
+        counter.fold_query(query.clone()).unwrap();
+        counter.counts
+    }
+}
+
+impl RqFold for TableRefCounter {
+    fn fold_table_ref(&mut self, table_ref: rq::TableRef) -> Result<rq::TableRef> {
+        *self.counts.entry(table_ref.source).or_default() += 1;
+        Ok(table_ref)
+    }
+}
+
+/// When the main relation only selects all columns of a relation literal, the
+/// literal becomes the main relation, so it isn't emitted as a CTE.
+fn inline_literal_main(mut query: rq::RelationalQuery) -> rq::RelationalQuery {
+    let rq::RelationKind::Pipeline(pipeline) = &query.relation.kind else {
+        return query;
+    };
+    let [rq::Transform::From(table_ref), rq::Transform::Select(cids)] = pipeline.as_slice() else {
+        return query;
+    };
+    // without columns, the main SELECT is needed to select a NULL
+    if cids.is_empty() || !cids.iter().eq(table_ref.columns.iter().map(|(_, cid)| cid)) {
+        return query;
+    }
+
+    let tid = table_ref.source;
+    let Some(position) = (query.tables.iter())
+        .position(|t| t.id == tid && matches!(t.relation.kind, rq::RelationKind::Literal(_)))
+    else {
+        return query;
+    };
+    if TableRefCounter::count(&query)[&tid] > 1 {
+        return query;
+    }
+
+    let table = query.tables.remove(position);
+    query.relation.kind = table.relation.kind;
+    query
+}
+
+/// Collects the tables of a query which are used as expressions.
+#[derive(Default)]
+struct SubQueryCollector {
+    tids: Vec<rq::TId>,
+}
+
+impl SubQueryCollector {
+    fn collect(query: &rq::RelationalQuery) -> Vec<rq::TId> {
+        let mut collector = SubQueryCollector::default();
+        collector.fold_query(query.clone()).unwrap();
+        collector.tids
+    }
+}
+
+impl RqFold for SubQueryCollector {
+    fn fold_expr_kind(&mut self, kind: rq::ExprKind) -> Result<rq::ExprKind> {
+        if let rq::ExprKind::SubQuery(tid) = &kind {
+            self.tids.push(*tid);
+        }
+        rq::fold_expr_kind(self, kind)
+    }
+}
+
 fn compile_loop(
     pipeline: Vec<pq::SqlTransform>,
     ctx: &mut Context,
@@ -273,6 +389,84 @@ fn compile_loop(
     Ok(following)
 }
 
+fn is_reshape(transform: &pq::SqlTransform) -> bool {
+    matches!(
+        transform,
+        pq::SqlTransform::Super(rq::Transform::Pivot(_) | rq::Transform::Unpivot(_))
+    )
+}
+
+/// PIVOT and UNPIVOT are applied to a relation in the FROM clause,
+/// so the pipeline is split just before the (last) reshape transform,
+/// unless it is already applied directly to a FROM.
+fn compile_reshape(
+    mut pipeline: Vec<pq::SqlTransform>,
+    ctx: &mut Context,
+) -> Vec<pq::SqlTransform> {
+    let index = pipeline.iter().rposition(is_reshape).unwrap();
+    let reshape = pipeline[index].as_super().unwrap();
+
+    let pipeline = if index == 1 && pipeline[0].as_from().is_some() {
+        pipeline
+    } else {
+        // the input relation must contain only the columns that are used by the reshape,
+        // because all other columns are retained
+        let input_columns = match reshape {
+            rq::Transform::Pivot(pivot) => {
+                let mut cols = pivot.partition.clone();
+                cols.extend([pivot.names_from, pivot.values_from]);
+                cols
+            }
+            rq::Transform::Unpivot(unpivot) => {
+                [unpivot.retained.clone(), unpivot.columns.clone()].concat()
+            }
+            _ => unreachable!(),
+        };
+
+        let following = pipeline.split_off(index);
+        pipeline.push(pq::SqlTransform::Super(rq::Transform::Select(
+            input_columns,
+        )));
+        anchor_split(&mut ctx.anchor, pipeline, following)
+    };
+
+    // columns are consumed and produced by the relation in FROM
+    let riid = *pipeline[0].as_from().unwrap();
+    let (consumed, produced) = match pipeline[1].as_super().unwrap() {
+        rq::Transform::Pivot(pivot) => (
+            vec![pivot.names_from, pivot.values_from],
+            (pivot.columns.iter())
+                .map(|(_, name, cid)| (name.clone(), *cid))
+                .collect_vec(),
+        ),
+        rq::Transform::Unpivot(unpivot) => (
+            unpivot.columns.clone(),
+            vec![unpivot.names_to.clone(), unpivot.values_to.clone()],
+        ),
+        _ => unreachable!(),
+    };
+
+    // consumed columns are not included in a wildcard anymore
+    let instance = ctx.anchor.relation_instances.get_mut(&riid).unwrap();
+    instance.original_cids.retain(|c| !consumed.contains(c));
+
+    for (name, cid) in produced {
+        if ctx.anchor.column_decls.contains_key(&cid) {
+            // already declared, when this pipeline was split before
+            continue;
+        }
+        let col = RelationColumn::Single(Some(name));
+        let decl = ColumnDecl::RelationColumn(riid, cid, col.clone());
+        ctx.anchor.column_decls.insert(cid, decl);
+
+        let instance = ctx.anchor.relation_instances.get_mut(&riid).unwrap();
+        instance.table_ref.columns.push((col, cid));
+        instance.original_cids.push(cid);
+    }
+
+    pipeline
+}
+
 fn ensure_names(transforms: &[pq::SqlTransform], ctx: &mut AnchorContext) {
     for t in transforms {
         if let pq::SqlTransform::Super(rq::Transform::Sort(columns))
@@ -22,12 +22,13 @@ mod test {
     use super::ast::SqlQuery;
     use super::*;
     use crate::sql::Dialect;
-    use crate::{Errors, Result};
+    use crate::{Errors, Options, Result, Target};
 
     fn parse_and_resolve(source: &str) -> Result<SqlQuery, Errors> {
         let query = crate::semantic::test::parse_resolve_and_lower(source)?;
 
-        let (sql, _) = compile_query(query, Some(Dialect::Generic))?;
+        let options = Options::default().with_target(Target::Sql(Some(Dialect::Generic)));
+        let (sql, _) = compile_query(query, &options, Default::default())?;
         Ok(sql)
     }
 
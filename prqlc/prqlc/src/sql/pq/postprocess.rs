@@ -8,17 +8,21 @@ use itertools::Itertools;
 
 use super::anchor::CidRedirector;
 use super::ast::*;
+use super::context::{AnchorContext, ColumnDecl, RIId};
 use crate::ir::generic::ColumnSort;
 use crate::ir::pl::Ident;
-use crate::ir::rq::{CId, RqFold, TId};
+use crate::ir::rq::{CId, RelationColumn, RqFold, TId};
 use crate::sql::Context;
-use crate::Result;
+use crate::utils::NameGenerator;
+use crate::{CteOrder, Result};
 
 type Sorting = Vec<ColumnSort<CId>>;
 
 pub(super) fn postprocess(query: SqlQuery, ctx: &mut Context) -> SqlQuery {
     let query = infer_sorts(query, ctx);
 
+    let query = order_ctes(query, ctx);
+
     assign_names(query, ctx)
 }
 
@@ -52,6 +56,17 @@ impl PqFold for SortingInference<'_> {
         let mut ctes = Vec::with_capacity(query.ctes.len());
         for cte in query.ctes {
             log::debug!("infer_sorts: {0:?}", cte.tid);
+
+            // the order of rows of a sub-query does not matter, so it is
+            // not selected and not propagated
+            if let CteKind::SubQuery(_) = cte.kind {
+                self.main_relation = true;
+                ctes.push(self.fold_cte(cte)?);
+                self.main_relation = false;
+                self.last_sorting.clear();
+                continue;
+            }
+
             let cte = self.fold_cte(cte)?;
 
             // store sorting to be used later in From references
@@ -119,6 +134,7 @@ impl PqMapper<RelationExpr, RelationExpr, (), ()> for SortingInference<'_> {
                             expr.kind = RelationExprKind::SubQuery(rel);
                         }
                     }
+                    ensure_sort_columns(&sorting, &expr.riid, &mut self.ctx.anchor);
                     sorting =
                         CidRedirector::redirect_sorts(sorting, &expr.riid, &mut self.ctx.anchor);
                     transform = SqlTransform::From(expr);
@@ -171,14 +187,58 @@ impl PqMapper<RelationExpr, RelationExpr, (), ()> for SortingInference<'_> {
     }
 }
 
+/// Adds computed columns of a sorting to the relation instance, when missing.
+///
+/// Expressions that are inlined into ORDER BY are only SELECTed from a CTE
+/// when its sorting is pushed down, so instances of the CTE don't have them.
+fn ensure_sort_columns(sorting: &Sorting, riid: &RIId, ctx: &mut AnchorContext) {
+    for sort in sorting {
+        let cid = sort.column;
+        let is_compute = matches!(ctx.column_decls.get(&cid), Some(ColumnDecl::Compute(_)));
+        if !is_compute
+            || ctx.relation_instances[riid]
+                .cid_redirects
+                .contains_key(&cid)
+        {
+            continue;
+        }
+
+        let name = ctx.ensure_column_name(cid).cloned();
+        let new_cid = ctx.cid.gen();
+        if let Some(name) = &name {
+            ctx.column_names.insert(new_cid, name.clone());
+        }
+        let col = RelationColumn::Single(name);
+        let decl = ColumnDecl::RelationColumn(*riid, new_cid, col.clone());
+        ctx.column_decls.insert(new_cid, decl);
+
+        let instance = ctx.relation_instances.get_mut(riid).unwrap();
+        instance.table_ref.columns.push((col, new_cid));
+        instance.cid_redirects.insert(cid, new_cid);
+    }
+}
+
 /// Makes sure all relation instances have assigned names. Tries to infer from table references.
 fn assign_names(query: SqlQuery, ctx: &mut Context) -> SqlQuery {
     // generate CTE names, make sure they don't clash
+    // Named decls come first, so they keep their names. The rest are named in
+    // the order the CTEs are emitted, so inserting a CTE does not rename the
+    // ones before it, nor the ones with a different hint.
+    let emission_order: HashMap<TId, usize> = (query.ctes.iter().enumerate())
+        .map(|(index, cte)| (cte.tid, index))
+        .collect();
     let decls = ctx.anchor.table_decls.values_mut();
     let mut names = HashSet::new();
-    for decl in decls.sorted_by_key(|d| d.id.get()) {
-        while decl.name.is_none() || names.contains(decl.name.as_ref().unwrap()) {
-            decl.name = Some(Ident::from_name(ctx.anchor.table_name.gen()));
+    for decl in decls.sorted_by_key(|d| {
+        let position = emission_order.get(&d.id).copied().unwrap_or(usize::MAX);
+        (d.name.is_none(), position, d.id.get())
+    }) {
+        if decl.name.as_ref().map_or(true, |n| names.contains(n)) {
+            let hint = (decl.name.as_ref().map(|n| n.name.clone())).or(decl.name_hint.take());
+            let name = gen_table_name(&mut ctx.anchor.table_name, hint.as_deref(), |n| {
+                names.contains(&Ident::from_name(n))
+            });
+            decl.name = Some(Ident::from_name(name));
         }
         names.insert(decl.name.clone().unwrap());
     }
@@ -192,6 +252,31 @@ fn assign_names(query: SqlQuery, ctx: &mut Context) -> SqlQuery {
     .unwrap()
 }
 
+/// Generates a name that is not taken. Names are derived from the `hint` as `{hint}_0`,
+/// `{hint}_1`, ... so they only depend on the relations with the same hint. Falls back to
+/// `table_N`.
+fn gen_table_name(
+    generator: &mut NameGenerator,
+    hint: Option<&str>,
+    is_taken: impl Fn(&str) -> bool,
+) -> String {
+    if let Some(hint) = hint {
+        let hint = hint.replace(|c: char| !c.is_alphanumeric() && c != '_', "_");
+        if !hint.is_empty() {
+            return (0..)
+                .map(|i| format!("{hint}_{i}"))
+                .find(|n| !is_taken(n))
+                .unwrap();
+        }
+    }
+    loop {
+        let name = generator.gen();
+        if !is_taken(&name) {
+            return name;
+        }
+    }
+}
+
 struct RelVarNameAssigner<'a> {
     relation_instance_names: HashSet<String>,
 
@@ -248,11 +333,16 @@ impl PqMapper<RelationExpr, RelationExpr, (), ()> for RelVarNameAssigner<'_> {
         }
 
         // make sure it is not already present in current query
-        while name
+        if name
             .as_ref()
             .map_or(true, |n| self.relation_instance_names.contains(n))
         {
-            *name = Some(self.ctx.anchor.table_name.gen());
+            let names = &self.relation_instance_names;
+            *name = Some(gen_table_name(
+                &mut self.ctx.anchor.table_name,
+                name.as_deref(),
+                |n| names.contains(n),
+            ));
         }
 
         // mark name as used
@@ -265,3 +355,109 @@ impl PqMapper<RelationExpr, RelationExpr, (), ()> for RelVarNameAssigner<'_> {
         Ok(sup)
     }
 }
+
+/// Reorders CTEs, which are initially in the order of references, each
+/// preceded by the CTEs it references.
+fn order_ctes(mut query: SqlQuery, ctx: &Context) -> SqlQuery {
+    let references: HashMap<TId, CteReferences> = (query.ctes.iter())
+        .map(|cte| (cte.tid, CteReferences::collect(cte, &ctx.anchor)))
+        .collect();
+
+    match ctx.cte_order {
+        CteOrder::Reference => {}
+
+        CteOrder::Topological => {
+            let mut depths: HashMap<TId, usize> = HashMap::new();
+            for cte in &query.ctes {
+                let depth = (references[&cte.tid].tids.iter())
+                    .filter(|tid| **tid != cte.tid)
+                    .filter_map(|tid| depths.get(tid))
+                    .map(|depth| depth + 1)
+                    .max()
+                    .unwrap_or_default();
+                depths.insert(cte.tid, depth);
+            }
+            query.ctes.sort_by_key(|cte| depths[&cte.tid]);
+        }
+
+        CteOrder::Source => {
+            // CTEs which don't read any relation themselves, such as later
+            // parts of a split pipeline, are positioned after their references
+            let mut positions: HashMap<TId, Option<usize>> = HashMap::new();
+            for cte in &query.ctes {
+                let refs = &references[&cte.tid];
+                let position = refs.position.or_else(|| {
+                    (refs.tids.iter())
+                        .filter_map(|tid| positions.get(tid).copied().flatten())
+                        .max()
+                });
+                positions.insert(cte.tid, position);
+            }
+
+            // repeatedly take the first CTE whose references are all taken
+            let mut remaining = std::mem::take(&mut query.ctes);
+            while !remaining.is_empty() {
+                let is_ready = |cte: &Cte| {
+                    (references[&cte.tid].tids.iter()).all(|tid| {
+                        *tid == cte.tid || !remaining.iter().any(|other| other.tid == *tid)
+                    })
+                };
+                let next = (remaining.iter().enumerate())
+                    .filter(|(_, cte)| is_ready(cte))
+                    .min_by_key(|(_, cte)| positions[&cte.tid].unwrap_or(usize::MAX))
+                    .map(|(index, _)| index)
+                    .unwrap();
+                query.ctes.push(remaining.remove(next));
+            }
+        }
+    }
+    query
+}
+
+/// Relations referenced by a CTE and the position of the earliest one in the
+/// source.
+struct CteReferences<'a> {
+    tids: Vec<TId>,
+    position: Option<usize>,
+    anchor: &'a AnchorContext,
+}
+
+impl<'a> CteReferences<'a> {
+    fn collect(cte: &Cte, anchor: &'a AnchorContext) -> Self {
+        let mut collector = CteReferences {
+            tids: Vec::new(),
+            position: None,
+            anchor,
+        };
+        collector.fold_cte(cte.clone()).unwrap();
+        collector
+    }
+}
+
+impl RqFold for CteReferences<'_> {}
+
+impl PqFold for CteReferences<'_> {}
+
+impl PqMapper<RelationExpr, RelationExpr, (), ()> for CteReferences<'_> {
+    fn fold_rel(&mut self, mut rel: RelationExpr) -> Result<RelationExpr> {
+        let span = self.anchor.relation_instances[&rel.riid].table_ref.span;
+        if let Some(span) = span {
+            self.position = Some(self.position.map_or(span.start, |p| p.min(span.start)));
+        }
+
+        rel.kind = match rel.kind {
+            RelationExprKind::Ref(tid) => {
+                self.tids.push(tid);
+                RelationExprKind::Ref(tid)
+            }
+            RelationExprKind::SubQuery(sub) => {
+                RelationExprKind::SubQuery(self.fold_sql_relation(sub)?)
+            }
+        };
+        Ok(rel)
+    }
+
+    fn fold_super(&mut self, sup: ()) -> Result<()> {
+        Ok(sup)
+    }
+}
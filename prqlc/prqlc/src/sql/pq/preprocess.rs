@@ -13,7 +13,7 @@ use crate::ir::rq::{
 };
 use crate::sql::Context;
 use crate::{debug, Error, Result, WithErrorInfo};
-use prqlc_parser::generic::{InterpolateItem, Range};
+use prqlc_parser::generic::{InterpolateItem, Range, SwitchCase};
 
 /// Converts RQ AST into SqlRQ AST and applies a few preprocessing operations.
 ///
@@ -24,20 +24,49 @@ pub(in crate::sql) fn preprocess(
     ctx: &mut Context,
 ) -> Result<Vec<SqlTransform>> {
     Ok(pipeline)
-        .and_then(normalize)
+        .and_then(|p| run_pass("normalize", p, ctx, |p, _| normalize(p)))
         .and_then(|p| wrap(p, ctx))
-        .and_then(|p| prune_inputs(p, ctx))
-        .and_then(|p| distinct(p, ctx))
-        .and_then(|p| union(p, ctx))
-        .and_then(|p| except(p, ctx))
-        .and_then(|p| intersect(p, ctx))
-        .map(reorder)
+        .and_then(|p| run_pass("prune_inputs", p, ctx, prune_inputs))
+        .and_then(|p| run_pass("pivot", p, ctx, pivot))
+        .and_then(|p| run_pass("distinct", p, ctx, distinct))
+        .and_then(|p| run_pass("union", p, ctx, union))
+        .and_then(|p| run_pass("except", p, ctx, except))
+        .and_then(|p| run_pass("intersect", p, ctx, intersect))
+        .and_then(|p| run_pass("reorder", p, ctx, |p, _| Ok(reorder(p))))
         .map(|p| {
             debug::log_entry(|| debug::DebugEntryKind::ReprPqEarly(p.clone()));
             p
         })
 }
 
+/// Applies a pass to the pipeline. When [Context::pass_log] is set, also
+/// records whether the pass changed the pipeline or the columns of its inputs.
+fn run_pass<T: Clone + PartialEq>(
+    name: &'static str,
+    pipeline: Vec<T>,
+    ctx: &mut Context,
+    pass: impl FnOnce(Vec<T>, &mut Context) -> Result<Vec<T>>,
+) -> Result<Vec<T>> {
+    if ctx.pass_log.is_none() {
+        return pass(pipeline, ctx);
+    }
+
+    let count_input_columns = |ctx: &Context| -> usize {
+        (ctx.anchor.relation_instances.values())
+            .map(|r| r.table_ref.columns.len())
+            .sum()
+    };
+
+    let (pipeline_before, columns_before) = (pipeline.clone(), count_input_columns(ctx));
+    let pipeline = pass(pipeline, ctx)?;
+    let changed = pipeline != pipeline_before || count_input_columns(ctx) != columns_before;
+
+    if let Some(log) = &mut ctx.pass_log {
+        log.push(debug::PassEvent { name, changed });
+    }
+    Ok(pipeline)
+}
+
 // This function was disabled because it changes semantics of the pipeline in some cases.
 // /// Pushes all [Transform::Select]s to the back of the pipeline.
 // pub(in crate::sql) fn push_down_selects(pipeline: Vec<Transform>) -> Vec<Transform> {
@@ -142,6 +171,7 @@ pub(in crate::sql) fn distinct(
                 range,
                 partition,
                 sort,
+                ..
             })) => {
                 let range_int = range
                     .clone()
@@ -221,6 +251,7 @@ fn create_filter_by_row_number(
                 range: Range {
                     start: None,
                     end: Some(int_expr(0)),
+                    end_exclusive: false,
                 },
             }
         },
@@ -285,7 +316,7 @@ pub(in crate::sql) fn union(
     let mut res = Vec::with_capacity(pipeline.len());
     let mut pipeline = pipeline.into_iter().peekable();
     while let Some(t) = pipeline.next() {
-        let Super(Append(bottom)) = t else {
+        let Super(Append { bottom, by_name }) = t else {
             res.push(t);
             continue;
         };
@@ -298,12 +329,17 @@ pub(in crate::sql) fn union(
             false
         };
 
-        res.push(SqlTransform::Union { bottom, distinct });
+        res.push(SqlTransform::Union {
+            bottom,
+            distinct,
+            by_name,
+        });
     }
     Ok(res)
 }
 
-/// Creates [SqlTransform::Except] from [Transform::Join] and [Transform::Filter]
+/// Creates [SqlTransform::Except] from [Transform::Except], or from
+/// [Transform::Join] and [Transform::Filter]
 pub(in crate::sql) fn except(
     pipeline: Vec<SqlTransform>,
     ctx: &mut Context,
@@ -315,6 +351,23 @@ pub(in crate::sql) fn except(
 
     let mut res = Vec::with_capacity(pipeline.len());
     for t in pipeline {
+        if let Super(Transform::Except { bottom, all }) = t {
+            if all && !ctx.dialect.except_all() {
+                return Err(Error::new_simple(format!(
+                    "The dialect {:?} does not support EXCEPT ALL",
+                    ctx.dialect
+                ))
+                .push_hint("`remove` is translated to an anti-join, when the columns of both relations are known"));
+            }
+            let bottom = ctx.anchor.create_relation_instance(bottom, HashMap::new());
+
+            res.push(SqlTransform::Except {
+                bottom,
+                distinct: !all,
+            });
+            continue;
+        }
+
         res.push(t);
 
         if res.len() < 2 {
@@ -396,7 +449,8 @@ pub(in crate::sql) fn except(
     Ok(res)
 }
 
-/// Creates [SqlTransform::Intersect] from [Transform::Join]
+/// Creates [SqlTransform::Intersect] from [Transform::Intersect], or from
+/// [Transform::Join]
 pub(in crate::sql) fn intersect(
     pipeline: Vec<SqlTransform>,
     ctx: &mut Context,
@@ -409,6 +463,33 @@ pub(in crate::sql) fn intersect(
     let mut res = Vec::with_capacity(pipeline.len());
     let mut pipeline = pipeline.into_iter().peekable();
     while let Some(t) = pipeline.next() {
+        if let Super(Transform::Intersect { bottom, all }) = t {
+            // INTERSECT ALL is INTERSECT DISTINCT, if top or output is DISTINCT
+            let distinct = !all
+                || matches!(res.last(), Some(Distinct))
+                || matches!(pipeline.peek(), Some(Distinct));
+
+            if !distinct && !ctx.dialect.intersect_all() {
+                return Err(Error::new_simple(format!(
+                    "The dialect {:?} does not support INTERSECT ALL",
+                    ctx.dialect
+                ))
+                .push_hint("use `intersect` without `all:true` to keep only distinct rows"));
+            }
+            let bottom = ctx.anchor.create_relation_instance(bottom, HashMap::new());
+
+            if distinct {
+                if let Some(Distinct) = res.last() {
+                    res.pop();
+                }
+                if let Some(Distinct) = pipeline.peek() {
+                    pipeline.next();
+                }
+            }
+            res.push(SqlTransform::Intersect { bottom, distinct });
+            continue;
+        }
+
         res.push(t);
 
         if res.is_empty() {
@@ -498,6 +579,91 @@ fn all_in(cids: &[CId], exprs: Vec<&Expr>) -> bool {
     cids.iter().all(|c| exprs.contains(c))
 }
 
+/// Converts [Transform::Pivot] into an aggregation of CASE expressions,
+/// for dialects that don't support PIVOT.
+pub(in crate::sql) fn pivot(
+    pipeline: Vec<SqlTransform>,
+    ctx: &mut Context,
+) -> Result<Vec<SqlTransform>> {
+    use SqlTransform::Super;
+
+    if ctx.dialect.supports_pivot() {
+        return Ok(pipeline);
+    }
+
+    let mut res = Vec::with_capacity(pipeline.len());
+    for transform in pipeline {
+        match transform {
+            Super(Transform::Pivot(pivot)) => {
+                // without knowing all of the columns, we cannot group by them
+                if ctx.anchor.contains_wildcard(&pivot.partition) {
+                    return Err(Error::new_simple(format!(
+                        "The dialect {:?} does not support PIVOT and it cannot be emulated for relations with unknown columns",
+                        ctx.dialect
+                    ))
+                    .push_hint("select the columns explicitly before `pivot`"));
+                }
+
+                // convert each of the new columns into:
+                //   func (case [names_from == value => values_from])
+                let mut compute = Vec::with_capacity(pivot.columns.len());
+                for (value, _, cid) in pivot.columns {
+                    let condition = new_binop(
+                        Expr {
+                            kind: ExprKind::ColumnRef(pivot.names_from),
+                            span: None,
+                        },
+                        "std.eq",
+                        Expr {
+                            kind: ExprKind::Literal(value),
+                            span: None,
+                        },
+                    );
+                    let value = Expr {
+                        kind: ExprKind::ColumnRef(pivot.values_from),
+                        span: None,
+                    };
+                    let case = Expr {
+                        kind: ExprKind::Case(vec![SwitchCase { condition, value }]),
+                        span: None,
+                    };
+
+                    let column = Compute {
+                        id: cid,
+                        expr: Expr {
+                            kind: ExprKind::Operator {
+                                name: pivot.func.clone(),
+                                args: vec![case],
+                            },
+                            span: None,
+                        },
+                        window: None,
+                        is_aggregation: true,
+                    };
+                    ctx.anchor.register_compute(column.clone());
+
+                    res.push(Super(Transform::Compute(column)));
+                    compute.push(cid);
+                }
+
+                res.push(Super(Transform::Aggregate {
+                    partition: pivot.partition,
+                    compute,
+                }));
+            }
+            Super(Transform::Unpivot(_)) => {
+                return Err(Error::new_simple(format!(
+                    "The dialect {:?} does not support UNPIVOT",
+                    ctx.dialect
+                ))
+                .push_hint("UNPIVOT is supported by DuckDB and Snowflake dialects"));
+            }
+            _ => res.push(transform),
+        }
+    }
+    Ok(res)
+}
+
 fn all_null(exprs: Vec<&Expr>) -> bool {
     exprs
         .iter()
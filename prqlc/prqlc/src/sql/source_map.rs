@@ -0,0 +1,101 @@
+//! Locating SQL produced by PRQL expressions within the output.
+
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use crate::Span;
+
+/// Byte ranges of the SQL that were produced by PRQL expressions, paired with
+/// the spans of these expressions.
+///
+/// Ranges may be nested, for example an expression within a `WHERE` clause,
+/// which is within a CTE. They are sorted by their start.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    pub mappings: Vec<(Range<usize>, Span)>,
+}
+
+impl SourceMap {
+    /// Returns the span of the innermost PRQL that produced SQL containing
+    /// the given byte range.
+    pub fn lookup(&self, range: Range<usize>) -> Option<Span> {
+        (self.mappings.iter())
+            .filter(|(r, _)| r.start <= range.start && range.end <= r.end)
+            .min_by_key(|(r, _)| r.len())
+            .map(|(_, span)| *span)
+    }
+}
+
+/// Finds byte ranges of SQL fragments within the output query.
+///
+/// Formatting changes whitespace of the query, so fragments are matched
+/// ignoring whitespace. When a fragment occurs multiple times, the first
+/// occurrence that has not been claimed by an equal fragment is used.
+/// Fragments that cannot be found are omitted.
+pub(super) fn locate(sql: &str, fragments: Vec<(String, Span)>) -> SourceMap {
+    // non-whitespace chars of the query, with their byte ranges
+    let (chars, ranges): (String, Vec<_>) = sql
+        .char_indices()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, c)| (c, i..i + c.len_utf8()))
+        .unzip();
+    let char_starts = chars.char_indices().map(|(i, _)| i).collect_vec();
+
+    let mut source_map: Vec<(Range<usize>, Span)> = Vec::new();
+    for (fragment, span) in fragments {
+        let fragment: String = fragment.chars().filter(|c| !c.is_whitespace()).collect();
+        if fragment.is_empty() {
+            continue;
+        }
+
+        let found = chars.match_indices(&fragment).find_map(|(start, _)| {
+            let first = char_starts.binary_search(&start).ok()?;
+            let last = first + fragment.chars().count() - 1;
+            let range = ranges[first].start..ranges[last].end;
+
+            let is_claimed = source_map.iter().any(|(r, _)| r == &range);
+            (!is_claimed && is_at_word_boundary(sql, &range)).then_some(range)
+        });
+        if let Some(range) = found {
+            source_map.push((range, span));
+        }
+    }
+
+    source_map.sort_by_key(|(range, _)| (range.start, usize::MAX - range.end));
+    SourceMap {
+        mappings: source_map,
+    }
+}
+
+/// The smallest span containing all given spans of the first source.
+pub(super) fn cover(spans: impl IntoIterator<Item = Span>) -> Option<Span> {
+    spans.into_iter().reduce(|cover, span| {
+        if span.source_id != cover.source_id {
+            return cover;
+        }
+        Span {
+            start: cover.start.min(span.start),
+            end: cover.end.max(span.end),
+            ..cover
+        }
+    })
+}
+
+/// Whether the range does not start or end within an identifier.
+fn is_at_word_boundary(sql: &str, range: &Range<usize>) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let before = sql[..range.start].chars().next_back();
+    let first = sql[range.clone()].chars().next();
+    let last = sql[range.clone()].chars().next_back();
+    let after = sql[range.end..].chars().next();
+
+    let starts_in_word = before
+        .zip(first)
+        .is_some_and(|(b, f)| is_word(b) && is_word(f));
+    let ends_in_word = last
+        .zip(after)
+        .is_some_and(|(l, a)| is_word(l) && is_word(a));
+    !starts_in_word && !ends_in_word
+}
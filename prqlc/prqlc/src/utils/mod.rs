@@ -92,6 +92,14 @@ pub(crate) fn valid_ident() -> &'static Regex {
     })
 }
 
+/// 64-bit FNV-1a hash. Unlike the hashers of `std`, it's stable across
+/// platforms and Rust versions, so its output can be persisted.
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
 fn should_use_color() -> bool {
     match anstream::AutoStream::choice(&stderr()) {
         anstream::ColorChoice::Auto => true,
@@ -236,7 +236,15 @@ fn empty_tuple_from() {
 
     assert_snapshot!(compile(r###"
     from []
-    "###).unwrap_err(), @"Error: internal compiler error; tracked at https://github.com/PRQL/prql/issues/4317");
+    "###).unwrap_err(), @r"
+    Error:
+       ╭─[:2:10]
+       │
+     2 │     from []
+       │          ─┬
+       │           ╰── relation literal must contain at least one row
+    ───╯
+    ");
 
     assert_snapshot!(compile(r###"
     from {}
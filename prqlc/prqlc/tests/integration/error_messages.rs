@@ -206,7 +206,7 @@ fn test_ambiguous() {
        │            ──┬─
        │              ╰─── Ambiguous name
        │
-       │ Help: could be any of: std.date, this.date
+       │ Help: did you mean `std.date` or `this.date`?
        │
        │ Note: available columns: date
     ───╯
@@ -229,7 +229,7 @@ fn test_ambiguous_join() {
        │            ┬
        │            ╰── Ambiguous name
        │
-       │ Help: could be any of: a.x, b.x
+       │ Help: did you mean `a.x` or `b.x`?
        │
        │ Note: available columns: a.x, b.x
     ───╯
@@ -251,7 +251,27 @@ fn test_ambiguous_inference() {
        │            ┬
        │            ╰── Ambiguous name
        │
-       │ Help: could be any of: a.x, b.x
+       │ Help: did you mean `a.x` or `b.x`?
+    ───╯
+    ");
+}
+
+#[test]
+fn test_ambiguous_join_candidates() {
+    assert_snapshot!(compile(r#"
+    from customers
+    join orders (==customer_id)
+    select {id}
+    "#)
+    .unwrap_err(), @r"
+    Error:
+       ╭─[:4:13]
+       │
+     4 │     select {id}
+       │             ─┬
+       │              ╰── Ambiguous name
+       │
+       │ Help: did you mean `customers.id` or `orders.id`?
     ───╯
     ");
 }
@@ -309,6 +329,44 @@ fn date_to_text_with_column_format() {
     ");
 }
 
+#[test]
+fn date_trunc_unknown_unit() {
+    assert_snapshot!(compile(r#"
+  from orders
+  derive {
+    order_month = (order_date | date.date_trunc "monht")
+  }"#).unwrap_err(), @r#"
+    Error:
+       ╭─[:4:49]
+       │
+     4 │     order_month = (order_date | date.date_trunc "monht")
+       │                                                 ───┬───
+       │                                                    ╰───── unknown date unit `monht`
+       │
+       │ Help: expected one of year, quarter, month, week, day, hour, minute, second
+    ───╯
+    "#);
+}
+
+#[test]
+fn date_trunc_not_supported_unit() {
+    assert_snapshot!(compile(r#"
+  prql target:sql.sqlite
+
+  from orders
+  derive {
+    order_week = (order_date | date.date_trunc "week")
+  }"#).unwrap_err(), @r#"
+    Error:
+       ╭─[:6:48]
+       │
+     6 │     order_week = (order_date | date.date_trunc "week")
+       │                                                ───┬──
+       │                                                   ╰──── Truncating dates to a week is not supported for SQLite
+    ───╯
+    "#);
+}
+
 #[test]
 fn date_to_text_unsupported_chrono_item() {
     assert_snapshot!(compile(r#"
@@ -359,3 +417,151 @@ fn empty_interpolations() {
     ───╯
     "#);
 }
+
+#[test]
+fn deprecated_function() {
+    let res = prqlc::compile_with_warnings(
+        r#"
+    @{deprecated = "use `add_two` instead"}
+    let plus_two = x -> x + 2
+
+    @deprecated
+    let double = x -> x * 2
+
+    from numbers
+    derive {a = (plus_two n), b = (n | double)}
+    "#,
+        &prqlc::Options::default().no_signature(),
+    )
+    .unwrap();
+
+    // the query still compiles
+    assert_snapshot!(res.output, @r"
+    SELECT
+      *,
+      n + 2 AS a,
+      n * 2 AS b
+    FROM
+      numbers
+    ");
+
+    assert_eq!(res.warnings.len(), 2);
+    assert_snapshot!(res.warnings[0], @r"
+    Warning:
+       ╭─[:9:18]
+       │
+     9 │     derive {a = (plus_two n), b = (n | double)}
+       │                  ────┬───
+       │                      ╰───── `plus_two` is deprecated: use `add_two` instead
+    ───╯
+    ");
+    assert_snapshot!(res.warnings[1], @r"
+    Warning:
+       ╭─[:9:40]
+       │
+     9 │     derive {a = (plus_two n), b = (n | double)}
+       │                                        ───┬──
+       │                                           ╰──── `double` is deprecated
+    ───╯
+    ");
+}
+
+#[test]
+fn validate() {
+    let options = prqlc::Options::default();
+
+    let errors = prqlc::validate("from x | select {a} | filter b > 1", &options).unwrap_err();
+    assert_snapshot!(errors.inner[0].reason, @"Unknown name `b`");
+
+    // unsupported by the dialect, which is an error of SQL generation
+    let prql = r#"
+    prql target:sql.sqlite
+
+    from x
+    derive {parts = (a | text.split ",")}
+    "#;
+    assert!(prqlc::compile(prql, &options).is_err());
+    assert!(prqlc::validate(prql, &options).unwrap().is_empty());
+
+    let warnings = prqlc::validate(
+        r#"
+    @deprecated
+    let double = x -> x * 2
+
+    from numbers
+    derive {b = (n | double)}
+    "#,
+        &options,
+    )
+    .unwrap();
+    assert_snapshot!(warnings[0].reason, @"`double` is deprecated");
+}
+
+#[test]
+fn append_column_count_mismatch() {
+    assert_snapshot!(compile(r###"
+    from tracks
+    select {album_id, name}
+    append (from artists | select {name})
+    "###).unwrap_err(), @r"
+    Error:
+       ╭─[:4:28]
+       │
+     4 │     append (from artists | select {name})
+       │                            ──────┬──────
+       │                                  ╰──────── relations have different numbers of columns: 2 and 1
+    ───╯
+    ");
+}
+
+#[test]
+fn except_column_count_mismatch() {
+    assert_snapshot!(compile(r###"
+    from tracks
+    select {album_id, name}
+    except (from artists | select {name})
+    "###).unwrap_err(), @r"
+    Error:
+       ╭─[:4:28]
+       │
+     4 │     except (from artists | select {name})
+       │                            ──────┬──────
+       │                                  ╰──────── relations have different numbers of columns: 2 and 1
+    ───╯
+    ");
+}
+
+#[test]
+fn intersect_column_count_mismatch() {
+    assert_snapshot!(compile(r###"
+    from tracks
+    select {album_id, name}
+    intersect (from artists | select {name})
+    "###).unwrap_err(), @r"
+    Error:
+       ╭─[:4:31]
+       │
+     4 │     intersect (from artists | select {name})
+       │                               ──────┬──────
+       │                                     ╰──────── relations have different numbers of columns: 2 and 1
+    ───╯
+    ");
+}
+
+#[test]
+fn count_of_tuple() {
+    assert_snapshot!(compile(r###"
+    from tracks
+    aggregate {n = count {album_id, name}}
+    "###).unwrap_err(), @r"
+    Error:
+       ╭─[:3:26]
+       │
+     3 │     aggregate {n = count {album_id, name}}
+       │                          ────────┬───────
+       │                                  ╰───────── `count` expected a column or `this`, but found {this.tracks.album_id, this.tracks.name}
+       │
+       │ Help: use `count this` to count all rows
+    ───╯
+    ");
+}
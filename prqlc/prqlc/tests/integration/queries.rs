@@ -1,11 +1,12 @@
 #![cfg(not(target_family = "wasm"))]
 use std::path::Path;
+use std::sync::OnceLock;
 use std::{env, fs};
 
 use insta::assert_debug_snapshot;
 use insta::{assert_snapshot, with_settings};
 use prqlc::sql::Dialect;
-use prqlc::{Options, Target};
+use prqlc::{Compiler, Options, Target};
 use test_each_file::test_each_path;
 
 mod lex {
@@ -47,6 +48,11 @@ mod compile {
         with_settings!({ input_file => prql_path }, {
             assert_snapshot!(test_name, &sql, &prql)
         });
+
+        // a compiler shared between tests (and so threads) produces the same SQL
+        static COMPILER: OnceLock<Compiler> = OnceLock::new();
+        let compiler = COMPILER.get_or_init(|| Compiler::new(options).unwrap());
+        assert_eq!(compiler.compile(&prql).unwrap(), sql);
     }
 }
 
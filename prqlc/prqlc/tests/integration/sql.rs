@@ -1,6 +1,9 @@
 //! Simple tests for "this PRQL creates this SQL" go here.
 use insta::assert_snapshot;
-use prqlc::{sql, ErrorMessages, Options, SourceTree, Target};
+use prqlc::{
+    sql, AggregateNullHandling, CteOrder, ErrorCode, ErrorMessages, NullsOrder, Options,
+    OutputEncoding, SourceTree, StdLibMode, Target,
+};
 use rstest::rstest;
 
 pub(crate) fn compile(prql: &str) -> Result<String, ErrorMessages> {
@@ -157,6 +160,97 @@ fn test_stdlib_math_module_mssql() {
     );
 }
 
+#[test]
+fn test_stdlib_math_round() {
+    let query = r#"
+    from products
+    select {price_rounded = (price | math.round 2)}
+    "#;
+
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    SELECT
+      ROUND((price)::numeric, 2) AS price_rounded
+    FROM
+      products
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::SQLite).unwrap(), @r"
+    SELECT
+      ROUND(price, 2) AS price_rounded
+    FROM
+      products
+    ");
+}
+
+#[test]
+fn test_stdlib_math_floor_ceil() {
+    let query = r#"
+    from products
+    select {
+      price_floor = (price | math.floor),
+      price_ceil = (price | math.ceil n_digits:2),
+      price_hundreds = (price | math.floor n_digits:-2),
+      price_scaled = (price | math.ceil n_digits:precision),
+    }
+    "#;
+
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    SELECT
+      FLOOR(price) AS price_floor,
+      CEIL(price * 100) / 100 AS price_ceil,
+      FLOOR(price * 0.01) / 0.01 AS price_hundreds,
+      CEIL(price * POW(10, precision)) / POW(10, precision) AS price_scaled
+    FROM
+      products
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::SQLite).unwrap(), @r"
+    SELECT
+      FLOOR(price) AS price_floor,
+      CEIL(price * 100) / 100 AS price_ceil,
+      FLOOR(price * 0.01) / 0.01 AS price_hundreds,
+      CEIL(price * POW(10, precision)) / POW(10, precision) AS price_scaled
+    FROM
+      products
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::MsSql).unwrap(), @r"
+    SELECT
+      FLOOR(price) AS price_floor,
+      CEILING(price * 100) / 100 AS price_ceil,
+      FLOOR(price * 0.01) / 0.01 AS price_hundreds,
+      CEILING(price * POWER(10, precision)) / POWER(10, precision) AS price_scaled
+    FROM
+      products
+    ");
+}
+
+#[test]
+fn test_stdlib_greatest_least() {
+    let query = r#"
+    from products
+    select {
+      highest = (greatest {price, list_price, 0}),
+      lowest = (least {price, list_price}),
+      single = (greatest price),
+    }
+    "#;
+
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    SELECT
+      GREATEST(price, list_price, 0) AS highest,
+      LEAST(price, list_price) AS lowest,
+      price AS single
+    FROM
+      products
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::SQLite).unwrap(), @r"
+    SELECT
+      MAX(price, list_price, 0) AS highest,
+      MIN(price, list_price) AS lowest,
+      price AS single
+    FROM
+      products
+    ");
+}
+
 #[test]
 fn test_stdlib_text_module() {
     assert_snapshot!(compile(r#"
@@ -249,6 +343,202 @@ FROM
     )
 }
 
+#[rstest]
+#[case::generic(sql::Dialect::Generic, "SPLIT_PART(email, '@', 2)")]
+#[case::duckdb(sql::Dialect::DuckDb, "SPLIT_PART(email, '@', 2)")]
+#[case::postgres(sql::Dialect::Postgres, "SPLIT_PART(email, '@', 2)")]
+#[case::snowflake(sql::Dialect::Snowflake, "SPLIT_PART(email, '@', 2)")]
+#[case::bigquery(sql::Dialect::BigQuery, "SPLIT(email, '@') [OFFSET(2 - 1)]")] // arrays are indexed from 0
+#[case::clickhouse(sql::Dialect::ClickHouse, "splitByString('@', email) [2]")]
+fn split_part_operator(#[case] dialect: sql::Dialect, #[case] expected_split_part: &'static str) {
+    let query = r#"
+    from users
+    select {
+      domain = (email | text.split_part "@" 2)
+    }"#;
+    let expected = format!(
+        r#"
+SELECT
+  {expected_split_part} AS domain
+FROM
+  users
+"#
+    );
+    assert_eq!(
+        compile_with_sql_dialect(query, dialect).unwrap(),
+        expected.trim_start()
+    )
+}
+
+#[rstest]
+#[case::generic(sql::Dialect::Generic, "CURRENT_DATE")]
+#[case::postgres(sql::Dialect::Postgres, "CURRENT_DATE")]
+#[case::sqlite(sql::Dialect::SQLite, "DATE('now')")]
+#[case::mssql(sql::Dialect::MsSql, "CAST(GETDATE() AS DATE)")]
+#[case::clickhouse(sql::Dialect::ClickHouse, "today()")]
+#[case::oracle(sql::Dialect::Oracle, "TRUNC(CURRENT_DATE)")]
+fn current_date_operator(
+    #[case] dialect: sql::Dialect,
+    #[case] expected_current_date: &'static str,
+) {
+    let query = r#"
+    from orders
+    select {
+      today = current_date
+    }"#;
+    let expected = format!(
+        r#"
+SELECT
+  {expected_current_date} AS today
+FROM
+  orders
+"#
+    );
+    assert_eq!(
+        compile_with_sql_dialect(query, dialect).unwrap(),
+        expected.trim_start()
+    )
+}
+
+#[test]
+fn test_text_split() {
+    let query = r#"
+    from users
+    derive {
+      tags = (tag_list | text.split ","),
+      tag = (tag_list | text.split_part "," (tag_index + 1)),
+    }
+    "#;
+
+    assert_snapshot!(compile(&format!("prql target:sql.postgres\n{query}")).unwrap(), @r"
+    SELECT
+      *,
+      STRING_TO_ARRAY(tag_list, ',') AS tags,
+      SPLIT_PART(tag_list, ',', tag_index + 1) AS tag
+    FROM
+      users
+    ");
+    // the index is computed before converting it to an offset
+    assert_snapshot!(compile(&format!("prql target:sql.bigquery\n{query}")).unwrap(), @r"
+    SELECT
+      *,
+      SPLIT(tag_list, ',') AS tags,
+      SPLIT(tag_list, ',') [OFFSET((tag_index + 1) - 1)] AS tag
+    FROM
+      users
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.mssql\n{query}")).unwrap_err(), @r#"
+    Error:
+       ╭─[:5:26]
+       │
+     5 │       tags = (tag_list | text.split ","),
+       │                          ───────┬──────
+       │                                 ╰──────── operator std.text.split is not supported for dialect mssql
+    ───╯
+    "#);
+}
+
+#[test]
+fn test_hash() {
+    let query = r#"
+    from users
+    derive {
+      email_md5 = (email | hash.md5),
+      email_sha256 = (email | hash.sha256),
+    }
+    "#;
+
+    assert_snapshot!(compile(&format!("prql target:sql.postgres\n{query}")).unwrap(), @r"
+    SELECT
+      *,
+      MD5(email) AS email_md5,
+      ENCODE(SHA256(CONVERT_TO(email, 'UTF8')), 'hex') AS email_sha256
+    FROM
+      users
+    ");
+    // hashes are bytes, which are converted to hexadecimal text
+    assert_snapshot!(compile(&format!("prql target:sql.bigquery\n{query}")).unwrap(), @r"
+    SELECT
+      *,
+      TO_HEX(MD5(email)) AS email_md5,
+      TO_HEX(SHA256(email)) AS email_sha256
+    FROM
+      users
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.snowflake\n{query}")).unwrap(), @r"
+    SELECT
+      *,
+      MD5(email) AS email_md5,
+      SHA2(email, 256) AS email_sha256
+    FROM
+      users
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.sqlite\n{query}")).unwrap_err(), @r"
+    Error:
+       ╭─[:5:28]
+       │
+     5 │       email_md5 = (email | hash.md5),
+       │                            ────┬───
+       │                                ╰───── operator std.hash.md5 is not supported for dialect sqlite
+    ───╯
+    ");
+}
+
+#[rstest]
+#[case::generic(sql::Dialect::Generic, "DATE_TRUNC('month', order_date)")]
+#[case::clickhouse(sql::Dialect::ClickHouse, "DATE_TRUNC('month', order_date)")]
+#[case::duckdb(sql::Dialect::DuckDb, "DATE_TRUNC('month', order_date)")]
+#[case::postgres(sql::Dialect::Postgres, "DATE_TRUNC('month', order_date)")]
+#[case::snowflake(sql::Dialect::Snowflake, "DATE_TRUNC('month', order_date)")]
+#[case::sqlite(sql::Dialect::SQLite, "strftime('%Y-%m-01', order_date)")]
+fn date_trunc_operator(#[case] dialect: sql::Dialect, #[case] expected_date_trunc: &'static str) {
+    let query = r#"
+    from orders
+    select {
+      order_month = (order_date | date.date_trunc "month")
+    }"#;
+    let expected = format!(
+        r#"
+SELECT
+  {expected_date_trunc} AS order_month
+FROM
+  orders
+"#
+    );
+    assert_eq!(
+        compile_with_sql_dialect(query, dialect).unwrap(),
+        expected.trim_start()
+    )
+}
+
+#[rstest]
+#[case::generic(sql::Dialect::Generic, "RANDOM()")]
+#[case::bigquery(sql::Dialect::BigQuery, "RAND()")]
+#[case::clickhouse(sql::Dialect::ClickHouse, "rand()")]
+#[case::mysql(sql::Dialect::MySql, "RAND()")]
+#[case::postgres(sql::Dialect::Postgres, "RANDOM()")]
+#[case::sqlite(sql::Dialect::SQLite, "RANDOM()")]
+fn random_operator(#[case] dialect: sql::Dialect, #[case] expected_random: &'static str) {
+    let query = r#"
+    from tracks
+    sort random
+    "#;
+    let expected = format!(
+        r#"
+SELECT
+  *
+FROM
+  tracks
+ORDER BY
+  {expected_random}
+"#
+    );
+    assert_eq!(
+        compile_with_sql_dialect(query, dialect).unwrap(),
+        expected.trim_start()
+    )
+}
+
 #[test]
 fn json_of_test() {
     let pl = prqlc::prql_to_pl("from employees | take 10").unwrap();
@@ -462,7 +752,7 @@ fn test_append() {
         take 10
     )
     "###).unwrap(), @r"
-    WITH table_0 AS (
+    WITH employees_0 AS (
       SELECT
         name,
         salary + bonuses AS cost
@@ -482,13 +772,13 @@ fn test_append() {
           employees
         LIMIT
           3
-      ) AS table_1
+      ) AS table_0
     UNION
     ALL
     SELECT
       *
     FROM
-      table_0
+      employees_0
     ");
 
     assert_snapshot!(compile(r###"
@@ -537,6 +827,39 @@ fn test_append() {
     ");
 }
 
+#[test]
+fn test_append_by_name() {
+    let query = r#"
+    from employees
+    select {name, salary}
+    append by_name:true (from managers | select {salary, name})
+    "#;
+
+    assert_snapshot!(compile(&format!("prql target:sql.duckdb\n{query}")).unwrap(), @r"
+    WITH managers_0 AS (
+      SELECT
+        salary,
+        name
+      FROM
+        managers
+    )
+    SELECT
+      name,
+      salary
+    FROM
+      employees
+    UNION
+    ALL BY NAME
+    SELECT
+      *
+    FROM
+      managers_0
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.postgres\n{query}")).unwrap_err(), @r"
+    Error: `append by_name:true` is not supported for postgres
+    ");
+}
+
 #[test]
 fn test_remove_01() {
     assert_snapshot!(compile(r#"
@@ -568,7 +891,7 @@ fn test_remove_02() {
     )
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
+    WITH artist_0 AS (
       SELECT
         artist_id
       FROM
@@ -583,7 +906,7 @@ fn test_remove_02() {
     SELECT
       *
     FROM
-      table_0
+      artist_0
     "
     );
 }
@@ -598,7 +921,7 @@ fn test_remove_03() {
     )
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
+    WITH artist_0 AS (
       SELECT
         artist_id
       FROM
@@ -609,9 +932,9 @@ fn test_remove_03() {
       album.title
     FROM
       album
-      LEFT JOIN table_0 ON album.artist_id = table_0.artist_id
+      LEFT JOIN artist_0 ON album.artist_id = artist_0.artist_id
     WHERE
-      table_0.artist_id IS NULL
+      artist_0.artist_id IS NULL
     "
     );
 }
@@ -637,14 +960,14 @@ fn test_remove_05() {
     prql target:sql.sqlite
 
     let distinct = rel -> (from t = _param.rel | group {t.*} (take 1))
-    let except = `default_db.bottom` top -> (top | distinct | remove bottom)
+    let except_distinct = `default_db.bottom` top -> (top | distinct | remove bottom)
 
     from album
     select {artist_id, title}
-    except (from artist | select {artist_id, name})
+    except_distinct (from artist | select {artist_id, name})
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
+    WITH artist_0 AS (
       SELECT
         artist_id,
         name
@@ -660,7 +983,7 @@ fn test_remove_05() {
     SELECT
       *
     FROM
-      table_0
+      artist_0
     "
     );
 }
@@ -671,10 +994,10 @@ fn test_remove_06() {
     prql target:sql.sqlite
 
     let distinct = rel -> (from t = _param.rel | group {t.*} (take 1))
-    let except = func `default_db.bottom` top -> (top | distinct | remove bottom)
+    let except_distinct = func `default_db.bottom` top -> (top | distinct | remove bottom)
 
     from album
-    except artist
+    except_distinct artist
     "#).unwrap(),
         @r"
     SELECT
@@ -691,37 +1014,32 @@ fn test_remove_06() {
 }
 
 #[test]
-fn test_intersect_01() {
-    assert_snapshot!(compile(r#"
+fn test_except() {
+    let query = r#"
     from album
-    intersect artist
-    "#).unwrap(),
-        @r"
+    select {artist_id}
+    except (from artist | select {artist_id})
+    "#;
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    WITH artist_0 AS (
+      SELECT
+        artist_id
+      FROM
+        artist
+    )
     SELECT
-      *
+      artist_id
     FROM
-      album AS t
-    INTERSECT
-    ALL
+      album
+    EXCEPT
+      DISTINCT
     SELECT
       *
     FROM
-      artist AS b
-    "
-    );
-}
-
-#[test]
-fn test_intersect_02() {
-    assert_snapshot!(compile(r#"
-    from album
-    select artist_id
-    intersect (
-        from artist | select artist_id
-    )
-    "#).unwrap(),
-        @r"
-    WITH table_0 AS (
+      artist_0
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::SQLite).unwrap(), @r"
+    WITH artist_0 AS (
       SELECT
         artist_id
       FROM
@@ -731,91 +1049,242 @@ fn test_intersect_02() {
       artist_id
     FROM
       album
-    INTERSECT
-    ALL
+    EXCEPT
     SELECT
       *
     FROM
-      table_0
-    "
-    );
-}
-
-#[test]
-fn test_intersect_03() {
-    assert_snapshot!(compile(r#"
-    let distinct = rel -> (_param.rel | group this (take 1))
-
-    from album
-    select artist_id
-    distinct
-    intersect (
-        from artist | select artist_id
-    )
-    distinct
-    "#).unwrap(),
-        @r"
-    WITH table_0 AS (
+      artist_0
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Oracle).unwrap(), @r"
+    WITH artist_0 AS (
       SELECT
         artist_id
       FROM
         artist
-    ),
-    table_1 AS (
-      SELECT
-        artist_id
-      FROM
-        album
-      INTERSECT
-      DISTINCT
-      SELECT
-        *
-      FROM
-        table_0
     )
     SELECT
-      DISTINCT artist_id
+      artist_id
     FROM
-      table_1
-    "
-    );
-}
-
+      album
+    MINUS
+    SELECT
+      *
+    FROM
+      artist_0
+    ");
+
+    let query = r#"
+    from album
+    select {artist_id}
+    except all:true (from artist | select {artist_id})
+    "#;
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    WITH artist_0 AS (
+      SELECT
+        artist_id
+      FROM
+        artist
+    )
+    SELECT
+      artist_id
+    FROM
+      album
+    EXCEPT
+      ALL
+    SELECT
+      *
+    FROM
+      artist_0
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Oracle).unwrap_err(), @r"
+    Error: The dialect OracleDialect does not support EXCEPT ALL
+    ↳ Hint: `remove` is translated to an anti-join, when the columns of both relations are known
+    ");
+
+    // `remove` of a distinct relation is also spelled MINUS
+    let query = r#"
+    from album
+    select {artist_id}
+    group {artist_id} (take 1)
+    remove (from artist | select {artist_id})
+    "#;
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Oracle).unwrap(), @r"
+    WITH artist_0 AS (
+      SELECT
+        artist_id
+      FROM
+        artist
+    )
+    SELECT
+      artist_id
+    FROM
+      album
+    MINUS
+    SELECT
+      *
+    FROM
+      artist_0
+    ");
+}
+
 #[test]
-fn test_intersect_04() {
+fn test_intersect() {
+    let query = r#"
+    from album
+    select {artist_id}
+    intersect (from artist | select {artist_id})
+    "#;
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    WITH artist_0 AS (
+      SELECT
+        artist_id
+      FROM
+        artist
+    )
+    SELECT
+      artist_id
+    FROM
+      album
+    INTERSECT
+    DISTINCT
+    SELECT
+      *
+    FROM
+      artist_0
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::SQLite).unwrap(), @r"
+    WITH artist_0 AS (
+      SELECT
+        artist_id
+      FROM
+        artist
+    )
+    SELECT
+      artist_id
+    FROM
+      album
+    INTERSECT
+    SELECT
+      *
+    FROM
+      artist_0
+    ");
+}
+
+#[test]
+fn test_intersect_01() {
+    assert_snapshot!(compile(r#"
+    from album
+    intersect all:true artist
+    "#).unwrap(),
+        @r"
+    SELECT
+      *
+    FROM
+      album
+    INTERSECT
+    ALL
+    SELECT
+      *
+    FROM
+      artist
+    "
+    );
+}
+
+#[test]
+fn test_intersect_02() {
+    assert_snapshot!(compile(r#"
+    from album
+    select artist_id
+    intersect all:true (
+        from artist | select artist_id
+    )
+    "#).unwrap(),
+        @r"
+    WITH artist_0 AS (
+      SELECT
+        artist_id
+      FROM
+        artist
+    )
+    SELECT
+      artist_id
+    FROM
+      album
+    INTERSECT
+    ALL
+    SELECT
+      *
+    FROM
+      artist_0
+    "
+    );
+}
+
+#[test]
+fn test_intersect_03() {
     assert_snapshot!(compile(r#"
     let distinct = rel -> (_param.rel | group this (take 1))
 
     from album
     select artist_id
+    distinct
     intersect (
         from artist | select artist_id
     )
     distinct
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
+    WITH artist_0 AS (
       SELECT
         artist_id
       FROM
         artist
-    ),
-    table_1 AS (
+    )
+    SELECT
+      artist_id
+    FROM
+      album
+    INTERSECT
+    DISTINCT
+    SELECT
+      *
+    FROM
+      artist_0
+    "
+    );
+}
+
+#[test]
+fn test_intersect_04() {
+    assert_snapshot!(compile(r#"
+    let distinct = rel -> (_param.rel | group this (take 1))
+
+    from album
+    select artist_id
+    intersect all:true (
+        from artist | select artist_id
+    )
+    distinct
+    "#).unwrap(),
+        @r"
+    WITH artist_0 AS (
       SELECT
         artist_id
       FROM
-        album
-      INTERSECT
-      ALL
-      SELECT
-        *
-      FROM
-        table_0
+        artist
     )
     SELECT
-      DISTINCT artist_id
+      artist_id
+    FROM
+      album
+    INTERSECT
+    DISTINCT
+    SELECT
+      *
     FROM
-      table_1
+      artist_0
     "
     );
 }
@@ -833,7 +1302,7 @@ fn test_intersect_05() {
     )
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
+    WITH artist_0 AS (
       SELECT
         artist_id
       FROM
@@ -848,7 +1317,7 @@ fn test_intersect_05() {
     SELECT
       *
     FROM
-      table_0
+      artist_0
     "
     );
 }
@@ -859,11 +1328,11 @@ fn test_intersect_06() {
     prql target:sql.sqlite
 
     from album
-    intersect artist
+    intersect all:true artist
     "#).unwrap_err(),
         @r"
     Error: The dialect SQLiteDialect does not support INTERSECT ALL
-    ↳ Hint: providing more column information will allow the query to be translated to an anti-join.
+    ↳ Hint: use `intersect` without `all:true` to keep only distinct rows
     "
     );
 }
@@ -896,7 +1365,7 @@ fn test_sort_in_nested_join() {
     ) (this.artist_id == that.artist_id) | take 10
     "#).unwrap(),
         @r#"
-    WITH table_0 AS (
+    WITH artists_0 AS (
       SELECT
         *
       FROM
@@ -908,10 +1377,10 @@ fn test_sort_in_nested_join() {
     )
     SELECT
       albums.*,
-      table_0.*
+      artists_0.*
     FROM
       albums
-      LEFT JOIN table_0 ON albums.artist_id = table_0.artist_id
+      LEFT JOIN artists_0 ON albums.artist_id = artists_0.artist_id
     LIMIT
       10
     "#
@@ -933,7 +1402,7 @@ fn test_sort_in_nested_append() {
     )
     "#).unwrap(),
         @r#"
-        WITH table_0 AS (
+        WITH albums_0 AS (
           SELECT
             album_id,
             title
@@ -957,13 +1426,13 @@ fn test_sort_in_nested_append() {
               album_id
             LIMIT
               2
-          ) AS table_1
+          ) AS table_0
         UNION
         ALL
         SELECT
           *
         FROM
-          table_0
+          albums_0
     "#
     );
 }
@@ -980,26 +1449,26 @@ fn test_rn_ids_are_unique() {
         take 3
     )
     "###).unwrap()), @r"
-    WITH table_1 AS (
+    WITH y_orig_0 AS (
       SELECT
         *,
         ROW_NUMBER() OVER (PARTITION BY y_id) AS _expr_1
       FROM
         y_orig
     ),
-    table_0 AS (
+    y_orig_1 AS (
       SELECT
         *,
         ROW_NUMBER() OVER (PARTITION BY x_id) AS _expr_0
       FROM
-        table_1
+        y_orig_0
       WHERE
         _expr_1 <= 2
     )
     SELECT
       *
     FROM
-      table_0
+      y_orig_1
     WHERE
       _expr_0 <= 3
     ");
@@ -1116,7 +1585,7 @@ fn test_sorts_01() {
     select {renamed = somefield}
     "#
     ).unwrap()), @r"
-    WITH table_0 AS (
+    WITH x_0 AS (
       SELECT
         'something' AS renamed,
         'something' AS _expr_0
@@ -1126,7 +1595,7 @@ fn test_sorts_01() {
     SELECT
       renamed
     FROM
-      table_0
+      x_0
     ORDER BY
       _expr_0
     ");
@@ -1179,7 +1648,7 @@ fn test_sorts_03() {
     take 5
     "#
     ).unwrap()), @r"
-    WITH table_0 AS (
+    WITH a_0 AS (
       SELECT
         a.*,
         b.*,
@@ -1195,12 +1664,126 @@ fn test_sorts_03() {
     SELECT
       *
     FROM
-      table_0
+      a_0
     ORDER BY
       _expr_0
     ");
 }
 
+#[test]
+fn test_sort_by_expression() {
+    // plain expressions are inlined into ORDER BY, without a CTE
+    assert_snapshot!(compile(r#"
+    from tracks
+    select {album_id, milliseconds, bytes}
+    sort {-(milliseconds + bytes), album_id}
+    take 3
+    "#).unwrap(), @r"
+    SELECT
+      album_id,
+      milliseconds,
+      bytes
+    FROM
+      tracks
+    ORDER BY
+      milliseconds + bytes DESC,
+      album_id
+    LIMIT
+      3
+    ");
+
+    // the sorting of a CTE is kept by the main query
+    assert_snapshot!(compile(r#"
+    from tracks
+    sort {milliseconds + bytes}
+    take 3
+    join albums (==album_id)
+    "#).unwrap(), @r"
+    WITH tracks_0 AS (
+      SELECT
+        *,
+        milliseconds + bytes AS _expr_0
+      FROM
+        tracks
+      ORDER BY
+        _expr_0
+      LIMIT
+        3
+    )
+    SELECT
+      tracks_0.*,
+      albums.*
+    FROM
+      tracks_0
+      JOIN albums ON tracks_0.album_id = albums.album_id
+    ORDER BY
+      tracks_0._expr_0
+    ");
+
+    // negated aggregate
+    assert_snapshot!(compile(r#"
+    from tracks
+    group album_id (aggregate {total = sum milliseconds})
+    sort {-(total / 1000)}
+    "#).unwrap(), @r"
+    WITH tracks_0 AS (
+      SELECT
+        album_id,
+        COALESCE(SUM(milliseconds), 0) AS total
+      FROM
+        tracks
+      GROUP BY
+        album_id
+    )
+    SELECT
+      album_id,
+      total
+    FROM
+      tracks_0
+    ORDER BY
+      total / 1000 DESC
+    ");
+}
+
+#[test]
+fn test_sort_random() {
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from tracks
+    sort random
+    take 10
+    "#, sql::Dialect::MsSql).unwrap(), @r"
+    SELECT
+      *
+    FROM
+      tracks
+    ORDER BY
+      NEWID() OFFSET 0 ROWS
+    FETCH FIRST
+      10 ROWS ONLY
+    ");
+
+    // a random value is computed once, even when it's referenced multiple times
+    assert_snapshot!(compile(r#"
+    from tracks
+    derive r = random
+    select {track_id, r, r2 = r * 2}
+    "#).unwrap(), @r"
+    WITH tracks_0 AS (
+      SELECT
+        track_id,
+        RANDOM() AS r
+      FROM
+        tracks
+    )
+    SELECT
+      track_id,
+      r,
+      r * 2 AS r2
+    FROM
+      tracks_0
+    ");
+}
+
 #[test]
 fn test_numbers() {
     let query = r###"
@@ -1249,11 +1832,35 @@ fn test_ranges() {
 }
 
 #[test]
-fn test_in_values_01() {
-    assert_snapshot!((compile(r#"
+fn test_ranges_exclusive_end() {
+    assert_snapshot!((compile(r###"
     from employees
-    filter (title | in ["Sales Manager", "Sales Support Agent"])
-    filter (employee_id | in [1, 2, 5])
+    derive {
+      close = (distance | in ..<50),
+      middle = (distance | in 50..<100),
+      inclusive = (distance | in 50..100),
+    }
+    take 2..<5
+    "###).unwrap()), @r"
+    SELECT
+      *,
+      distance < 50 AS close,
+      distance >= 50
+      AND distance < 100 AS middle,
+      distance BETWEEN 50 AND 100 AS inclusive
+    FROM
+      employees
+    LIMIT
+      3 OFFSET 1
+    ");
+}
+
+#[test]
+fn test_in_values_01() {
+    assert_snapshot!((compile(r#"
+    from employees
+    filter (title | in ["Sales Manager", "Sales Support Agent"])
+    filter (employee_id | in [1, 2, 5])
     filter (f"{emp_group}.{role}" | in ["sales_ne.mgr", "sales_mw.mgr"])
     filter (s"{metadata} ->> '$.location'" | in ["Northeast", "Midwest"])
     "#).unwrap()), @r"
@@ -1309,6 +1916,99 @@ fn test_in_values_03() {
     "###);
 }
 
+#[test]
+fn test_in_subquery() {
+    assert_snapshot!((compile(r#"
+    from employees
+    filter (employee_id | in (from managers | select {employee_id}))
+    "#).unwrap()), @r"
+    SELECT
+      *
+    FROM
+      employees
+    WHERE
+      employee_id IN (
+        SELECT
+          employee_id
+        FROM
+          managers
+      )
+    ");
+
+    assert_snapshot!((compile(r#"
+    prql target:sql.mssql
+
+    let top_customers = (
+      from invoices
+      group {customer_id} (aggregate {total = sum amount})
+      sort {-total}
+      take 10
+      select {customer_id}
+    )
+
+    from customers
+    filter (customer_id | in top_customers)
+    select {customer_id, name}
+    "#).unwrap()), @r"
+    WITH invoices_0 AS (
+      SELECT
+        customer_id,
+        COALESCE(SUM(amount), 0) AS _expr_0
+      FROM
+        invoices
+      GROUP BY
+        customer_id
+      ORDER BY
+        _expr_0 DESC OFFSET 0 ROWS
+      FETCH FIRST
+        10 ROWS ONLY
+    ),
+    top_customers AS (
+      SELECT
+        customer_id,
+        _expr_0
+      FROM
+        invoices_0
+    )
+    SELECT
+      customer_id,
+      name
+    FROM
+      customers
+    WHERE
+      customer_id IN (
+        SELECT
+          customer_id
+        FROM
+          top_customers
+      )
+    ");
+
+    assert_snapshot!((compile(r#"
+    from employees
+    derive {is_manager = (employee_id | in (from managers | select {employee_id}))}
+    "#).unwrap()), @r"
+    SELECT
+      *,
+      employee_id IN (
+        SELECT
+          employee_id
+        FROM
+          managers
+      ) AS is_manager
+    FROM
+      employees
+    ");
+
+    assert!(compile(
+        r#"
+    from employees
+    filter (employee_id | in (from managers | select {employee_id, name}))
+    "#
+    )
+    .is_err());
+}
+
 #[test]
 fn test_not_in_values() {
     assert_snapshot!((compile(r#"
@@ -1430,7 +2130,7 @@ fn test_window_functions_00() {
     "###).unwrap()), @r"
     SELECT
       *,
-      COUNT(*) OVER (PARTITION BY last_name)
+      COUNT(first_name) OVER (PARTITION BY last_name)
     FROM
       employees
     ");
@@ -1463,12 +2163,12 @@ fn test_window_functions_02() {
     "#;
 
     assert_snapshot!((compile(query).unwrap()), @r"
-    WITH table_0 AS (
+    WITH cust_order_0 AS (
       SELECT
         TO_CHAR(co.order_date, '%Y-%m') AS order_month,
         TO_CHAR(co.order_date, '%Y-%m-%d') AS order_day,
         COUNT(DISTINCT co.order_id) AS num_orders,
-        COUNT(*) AS num_books,
+        COUNT(ol.book_id) AS num_books,
         COALESCE(SUM(ol.price), 0) AS total_price
       FROM
         cust_order AS co
@@ -1493,7 +2193,7 @@ fn test_window_functions_02() {
           order_day
       ) AS num_books_last_week
     FROM
-      table_0
+      cust_order_0
     ORDER BY
       order_day
     ");
@@ -1692,7 +2392,7 @@ fn test_window_functions_12() {
       derive {c = lag 1 a}
     )
     "###).unwrap()), @r"
-    WITH table_0 AS (
+    WITH x_0 AS (
       SELECT
         *,
         LAG(a, 1) OVER () AS b
@@ -1706,7 +2406,7 @@ fn test_window_functions_12() {
           b
       ) AS c
     FROM
-      table_0
+      x_0
     ORDER BY
       b
     ");
@@ -1718,7 +2418,7 @@ fn test_window_functions_12() {
       derive {c = lag 1 a}
     )
     "###).unwrap()), @r"
-    WITH table_0 AS (
+    WITH x_0 AS (
       SELECT
         LAG(a, 1) OVER () AS b,
         *
@@ -1729,7 +2429,7 @@ fn test_window_functions_12() {
       *,
       LAG(a, 1) OVER (PARTITION BY b) AS c
     FROM
-      table_0
+      x_0
     ");
 }
 
@@ -1746,7 +2446,7 @@ fn test_window_functions_13() {
       window (derive {count = row_number this})
     )
     "###).unwrap()), @r"
-    WITH table_0 AS (
+    WITH tracks_0 AS (
       SELECT
         *,
         ROW_NUMBER() OVER (PARTITION BY album_id) AS _expr_0
@@ -1758,7 +2458,29 @@ fn test_window_functions_13() {
       *,
       ROW_NUMBER() OVER (PARTITION BY milliseconds - _expr_0) AS count
     FROM
-      table_0
+      tracks_0
+    ");
+}
+
+#[test]
+fn test_window_functions_14() {
+    assert_snapshot!((compile(r###"
+    from prices
+    sort day
+    window range:..0 (
+        derive {running_max = max price}
+    )
+    "###).unwrap()), @r"
+    SELECT
+      *,
+      MAX(price) OVER (
+        ORDER BY
+          day
+      ) AS running_max
+    FROM
+      prices
+    ORDER BY
+      day
     ");
 }
 
@@ -1786,6 +2508,86 @@ fn test_window_single_item_range() {
     ");
 }
 
+#[test]
+fn test_window_multiple_keys() {
+    assert_snapshot!(compile(r###"
+    from employees
+    group {department, level + 1} (
+      sort {hired_at, -salary}
+      window rows:-2..0 (
+        derive {r = row_number this, s = sum salary}
+      )
+    )
+    "###).unwrap(), @r"
+    SELECT
+      department,
+      level + 1,
+      *,
+      ROW_NUMBER() OVER (
+        PARTITION BY department,
+        level + 1
+        ORDER BY
+          hired_at,
+          salary DESC
+      ) AS r,
+      SUM(salary) OVER (
+        PARTITION BY department,
+        level + 1
+        ORDER BY
+          hired_at,
+          salary DESC ROWS BETWEEN 2 PRECEDING AND CURRENT ROW
+      ) AS s
+    FROM
+      employees
+    ");
+}
+
+#[test]
+fn test_window_filter_clickhouse() {
+    let query = r###"
+    from employees
+    derive {r = row_number this}
+    filter r <= 3
+    select {name, r}
+    "###;
+
+    // a filter on a window function is applied to a CTE by default...
+    assert_snapshot!(compile(query).unwrap(), @r"
+    WITH employees_0 AS (
+      SELECT
+        name,
+        ROW_NUMBER() OVER () AS r
+      FROM
+        employees
+    )
+    SELECT
+      name,
+      r
+    FROM
+      employees_0
+    WHERE
+      r <= 3
+    ");
+
+    // ...and to a subquery for ClickHouse
+    let query = format!("prql target:sql.clickhouse\n{query}");
+    assert_snapshot!(compile(&query).unwrap(), @r"
+    SELECT
+      name,
+      r
+    FROM
+      (
+        SELECT
+          name,
+          ROW_NUMBER() OVER () AS r
+        FROM
+          employees
+      ) AS table_0
+    WHERE
+      r <= 3
+    ");
+}
+
 #[test]
 fn test_name_resolving() {
     let query = r###"
@@ -2020,7 +2822,7 @@ fn test_take_06() {
     sort name
     take 1..5
     "###).unwrap()), @r"
-    WITH table_0 AS (
+    WITH employees_0 AS (
       SELECT
         *
       FROM
@@ -2031,7 +2833,7 @@ fn test_take_06() {
     SELECT
       *
     FROM
-      table_0
+      employees_0
     ORDER BY
       name
     LIMIT
@@ -2153,1651 +2955,2843 @@ fn test_take_mssql() {
     SELECT
       *
     FROM
-      tracks OFFSET 2 ROWS
+      tracks
+    ORDER BY
+      (
+        SELECT
+          NULL
+      ) OFFSET 2 ROWS
     ");
-}
 
-#[test]
-fn test_distinct_01() {
-    // window functions cannot materialize into where statement: CTE is needed
-    assert_snapshot!((compile(r###"
-    from employees
-    derive {rn = row_number id}
-    filter rn > 2
-    "###).unwrap()), @r"
-    WITH table_0 AS (
-      SELECT
-        *,
-        ROW_NUMBER() OVER () AS rn
-      FROM
-        employees
-    )
+    assert_snapshot!((compile(r#"
+    prql target:sql.mssql
+
+    from tracks
+    sort name
+    take 10..20
+    "#).unwrap()), @r"
     SELECT
       *
     FROM
-      table_0
-    WHERE
-      rn > 2
+      tracks
+    ORDER BY
+      name OFFSET 9 ROWS
+    FETCH FIRST
+      11 ROWS ONLY
     ");
 }
 
 #[test]
-fn test_distinct_02() {
-    // basic distinct
-    assert_snapshot!((compile(r###"
-    from employees
-    select first_name
-    group first_name (take 1)
-    "###).unwrap()), @r"
+fn test_take_mssql_subquery() {
+    // a take within a CTE is sorted by the projected columns
+    assert_snapshot!((compile(r#"
+    prql target:sql.mssql
+
+    from tracks
+    select {track_id, name}
+    take 3
+    join albums (==track_id)
+    "#).unwrap()), @r"
+    WITH tracks_0 AS (
+      SELECT
+        track_id,
+        name
+      FROM
+        tracks
+      ORDER BY
+        track_id,
+        name OFFSET 0 ROWS
+      FETCH FIRST
+        3 ROWS ONLY
+    )
     SELECT
-      DISTINCT first_name
+      tracks_0.track_id,
+      tracks_0.name,
+      albums.*
     FROM
-      employees
+      tracks_0
+      JOIN albums ON tracks_0.track_id = albums.track_id
     ");
-}
 
-#[test]
-fn test_distinct_03() {
-    // distinct on two columns
-    assert_snapshot!((compile(r###"
-    from employees
-    select {first_name, last_name}
-    group {first_name, last_name} (take 1)
-    "###).unwrap()), @r"
+    // an existing sort is kept
+    assert_snapshot!((compile(r#"
+    prql target:sql.mssql
+
+    from tracks
+    select {track_id, name}
+    sort name
+    take 3
+    join albums (==track_id)
+    "#).unwrap()), @r"
+    WITH tracks_0 AS (
+      SELECT
+        track_id,
+        name
+      FROM
+        tracks
+      ORDER BY
+        name OFFSET 0 ROWS
+      FETCH FIRST
+        3 ROWS ONLY
+    )
     SELECT
-      DISTINCT first_name,
-      last_name
+      tracks_0.track_id,
+      tracks_0.name,
+      albums.*
     FROM
-      employees
+      tracks_0
+      JOIN albums ON tracks_0.track_id = albums.track_id
+    ORDER BY
+      tracks_0.name
     ");
-}
-#[test]
-fn test_distinct_04() {
-    // We want distinct only over first_name and last_name, so we can't use a
-    // `DISTINCT *` here.
-    assert_snapshot!((compile(r###"
-    from employees
-    group {first_name, last_name} (take 1)
-    "###).unwrap()), @r"
-    WITH table_0 AS (
+
+    // without known columns, it's sorted by nothing in particular
+    assert_snapshot!((compile(r#"
+    prql target:sql.mssql
+
+    from tracks
+    take 3
+    join albums (==track_id)
+    "#).unwrap()), @r"
+    WITH tracks_0 AS (
       SELECT
-        *,
-        ROW_NUMBER() OVER (PARTITION BY first_name, last_name) AS _expr_0
+        *
       FROM
-        employees
+        tracks
+      ORDER BY
+        (
+          SELECT
+            NULL
+        ) OFFSET 0 ROWS
+      FETCH FIRST
+        3 ROWS ONLY
     )
     SELECT
-      *
+      tracks_0.*,
+      albums.*
     FROM
-      table_0
-    WHERE
-      _expr_0 <= 1
+      tracks_0
+      JOIN albums ON tracks_0.track_id = albums.track_id
     ");
-}
-#[test]
-fn test_distinct_05() {
-    // Check that a different order doesn't stop distinct from being used.
-    assert!(compile(
-        "from employees | select {first_name, last_name} | group {last_name, first_name} (take 1)"
-    )
-    .unwrap()
-    .contains("DISTINCT"));
-}
-#[test]
-fn test_distinct_06() {
-    // head
-    assert_snapshot!((compile(r###"
-    from employees
-    group department (take 3)
-    "###).unwrap()), @r"
-    WITH table_0 AS (
+
+    assert_snapshot!((compile(r#"
+    prql target:sql.mssql
+
+    from t
+    take 3
+    filter x > 1
+    "#).unwrap()), @r"
+    WITH t_0 AS (
       SELECT
-        *,
-        ROW_NUMBER() OVER (PARTITION BY department) AS _expr_0
+        *
       FROM
-        employees
+        t
+      ORDER BY
+        (
+          SELECT
+            NULL
+        ) OFFSET 0 ROWS
+      FETCH FIRST
+        3 ROWS ONLY
     )
     SELECT
       *
     FROM
-      table_0
+      t_0
     WHERE
-      _expr_0 <= 3
+      x > 1
     ");
 }
+
 #[test]
-fn test_distinct_07() {
-    assert_snapshot!((compile(r###"
-    from employees
-    group department (sort salary | take 2..3)
-    "###).unwrap()), @r"
-    WITH table_0 AS (
-      SELECT
-        *,
-        ROW_NUMBER() OVER (
-          PARTITION BY department
-          ORDER BY
-            salary
-        ) AS _expr_0
-      FROM
-        employees
-    )
+fn test_take_oracle() {
+    assert_snapshot!((compile(r#"
+    prql target:sql.oracle
+
+    from t
+    take 5
+    "#).unwrap()), @r"
     SELECT
       *
     FROM
-      table_0
-    WHERE
-      _expr_0 BETWEEN 2 AND 3
+      t
+    FETCH FIRST
+      5 ROWS ONLY
+    ");
+
+    assert_snapshot!((compile(r#"
+    prql target:sql.oracle
+
+    from tracks
+    sort {-milliseconds}
+    take 3..5
+    "#).unwrap()), @r"
+    SELECT
+      *
+    FROM
+      tracks
+    ORDER BY
+      milliseconds DESC OFFSET 2 ROWS
+    FETCH FIRST
+      3 ROWS ONLY
+    ");
+
+    assert_snapshot!((compile(r#"
+    prql target:sql.oracle
+
+    from tracks
+    take 3..
+    "#).unwrap()), @r"
+    SELECT
+      *
+    FROM
+      tracks OFFSET 2 ROWS
     ");
 }
+
 #[test]
-fn test_distinct_08() {
-    assert_snapshot!((compile(r###"
+fn test_take_percent() {
+    assert_snapshot!((compile(r#"
+    prql target:sql.postgres
+
+    from e = employees
+    filter country == "USA"
+    take 10%
+    select {e.name}
+    "#).unwrap()), @r"
+    SELECT
+      name
+    FROM
+      employees AS e TABLESAMPLE BERNOULLI (10)
+    WHERE
+      country = 'USA'
+    ");
+
+    // only tables of the database can be sampled, so the rows of a CTE are counted
+    assert_snapshot!((compile(r#"
+    prql target:sql.postgres
+
     from employees
-    group department (sort salary | take 4..4)
-    "###).unwrap()), @r"
-    WITH table_0 AS (
+    join departments (==dept_id)
+    take 10%
+    "#).unwrap()), @r"
+    WITH employees_0 AS (
       SELECT
-        *,
-        ROW_NUMBER() OVER (
-          PARTITION BY department
-          ORDER BY
-            salary
-        ) AS _expr_0
+        employees.*,
+        departments.*
       FROM
         employees
+        JOIN departments ON employees.dept_id = departments.dept_id
     )
     SELECT
       *
     FROM
-      table_0
-    WHERE
-      _expr_0 = 4
+      employees_0
+    LIMIT
+      (
+        SELECT
+          COUNT(*)
+        FROM
+          employees_0
+      ) * 10 / 100
     ");
-}
 
-#[test]
-fn test_distinct_09() {
-    assert_snapshot!(compile("
-    from invoices
-    select {billing_country, billing_city}
-    group {billing_city} (
-      take 1
-    )
-    sort billing_city
-    ").unwrap(), @r"
-    WITH table_0 AS (
-      SELECT
-        billing_city,
-        billing_country,
-        ROW_NUMBER() OVER (PARTITION BY billing_city) AS _expr_0
-      FROM
-        invoices
-    )
+    assert_snapshot!((compile(r#"
+    prql target:sql.sqlite
+
+    from employees
+    filter country == "USA"
+    take 10%
+    "#).unwrap()), @r"
     SELECT
-      billing_city,
-      billing_country
+      *
     FROM
-      table_0
+      employees
     WHERE
-      _expr_0 <= 1
-    ORDER BY
-      billing_city
+      country = 'USA'
+    LIMIT
+      (
+        SELECT
+          COUNT(*)
+        FROM
+          employees
+        WHERE
+          country = 'USA'
+      ) * 10 / 100
     ");
-}
 
-#[test]
-fn test_distinct_on_01() {
-    assert_snapshot!((compile(r###"
-    prql target:sql.postgres
+    assert_snapshot!((compile(r#"
+    prql target:sql.oracle
 
     from employees
-    group department (
-      sort age
-      take 1
-    )
-    "###).unwrap()), @r"
+    take 10%
+    "#).unwrap()), @r"
     SELECT
-      DISTINCT ON (department) *
+      *
     FROM
       employees
-    ORDER BY
-      department,
-      age
+    FETCH FIRST
+      10 PERCENT ROWS ONLY
     ");
-}
 
-#[test]
-fn test_distinct_on_02() {
-    assert_snapshot!((compile(r###"
-    prql target:sql.duckdb
+    assert_snapshot!((compile(r#"
+    prql target:sql.mysql
 
-    from x
-    select {class, begins}
-    group {begins} (take 1)
-    "###).unwrap()), @r"
-    SELECT
-      DISTINCT ON (begins) begins,
-      class
-    FROM
-      x
+    from employees
+    take 10%
+    "#).unwrap_err()), @r"
+    Error: `take` of a percentage of rows is not supported for mysql
     ");
-}
 
-#[test]
-fn test_distinct_on_03() {
-    assert_snapshot!((compile(r###"
+    // LIMIT only accepts constants
+    assert_snapshot!((compile(r#"
+    prql target:sql.clickhouse
+
+    from employees
+    take 10%
+    "#).unwrap_err()), @r"
+    Error: `take` of a percentage of rows is not supported for clickhouse
+    ");
+
+    assert_snapshot!((compile(r#"
     prql target:sql.duckdb
 
-    from tab1
-    group col1 (
-      take 1
-    )
-    derive foo = 1
-    select foo
-    "###).unwrap()), @r"
-    WITH table_0 AS (
+    from employees
+    join departments (==dept_id)
+    take 10%
+    "#).unwrap()), @r"
+    WITH employees_0 AS (
       SELECT
-        DISTINCT ON (col1) NULL
+        employees.*,
+        departments.*
       FROM
-        tab1
+        employees
+        JOIN departments ON employees.dept_id = departments.dept_id
     )
     SELECT
-      1 AS foo
+      *
     FROM
-      table_0
+      employees_0
+    LIMIT
+      (
+        SELECT
+          COUNT(*)
+        FROM
+          employees_0
+      ) * 10 / 100
     ");
-}
 
-#[test]
-fn test_distinct_on_04() {
-    assert_snapshot!((compile(r###"
-    prql target:sql.duckdb
+    assert_snapshot!((compile(r#"
+    prql target:sql.snowflake
 
-    from a
-    join b (b.a_id == a.id)
-    group {a.id} (
-      sort b.x
-      take 1
-    )
-    select {a.id, b.y}
-    "###).unwrap()), @r"
+    from employees
+    take 10%
+    "#).unwrap()), @r"
     SELECT
-      DISTINCT ON (a.id) a.id,
-      b.y
+      *
     FROM
-      a
-      JOIN b ON b.a_id = a.id
-    ORDER BY
-      a.id,
-      b.x
+      employees TABLESAMPLE BERNOULLI (10)
     ");
-}
 
-#[test]
-fn test_group_take_n_01() {
-    assert_snapshot!((compile(r###"
-    prql target:sql.postgres
+    // the rows of a CTE can't be counted in LIMIT either
+    assert_snapshot!((compile(r#"
+    prql target:sql.snowflake
 
     from employees
-    group department (
-      sort age
-      take 2
-    )
-    "###).unwrap()), @r"
-    WITH table_0 AS (
+    join departments (==dept_id)
+    take 10%
+    "#).unwrap_err()), @r"
+    Error: `take` of a percentage of rows is only supported on tables for snowflake
+    ");
+}
+
+#[test]
+fn test_oracle() {
+    assert_snapshot!((compile(r#"
+    prql target:sql.oracle
+
+    from tracks
+    filter (title | text.contains "mix")
+    select {
+      name = f"{composer} - {title}",
+      r = milliseconds % 1000,
+    }
+    "#).unwrap()), @r"
+    SELECT
+      composer || ' - ' || title AS name,
+      MOD(milliseconds, 1000) AS r
+    FROM
+      tracks
+    WHERE
+      title LIKE '%' || 'mix' || '%'
+    ");
+
+    // relations are aliased without AS
+    let query = r#"
+    prql target:sql.oracle
+
+    from e = employees
+    join d = (from departments | take 5) (==dept_id)
+    select {e.name, d.title}
+    "#;
+    assert_snapshot!(compile(query).unwrap(), @r"
+    WITH departments_0 AS (
       SELECT
-        *,
-        ROW_NUMBER() OVER (
-          PARTITION BY department
-          ORDER BY
-            age
-        ) AS _expr_0
+        *
       FROM
-        employees
+        departments
+      FETCH FIRST
+        5 ROWS ONLY
     )
     SELECT
-      *
+      e.name,
+      departments_0.title
     FROM
-      table_0
-    WHERE
-      _expr_0 <= 2
+      employees e
+      JOIN departments_0 ON e.dept_id = departments_0.dept_id
+    ");
+
+    let options = Options::default().no_signature().with_inline_ctes(true);
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      e.name,
+      table_0.title
+    FROM
+      employees e
+      JOIN (
+        SELECT
+          *
+        FROM
+          departments
+        FETCH FIRST
+          5 ROWS ONLY
+      ) table_0 ON e.dept_id = table_0.dept_id
     ");
 }
 
 #[test]
-fn test_group_take_n_02() {
-    assert_snapshot!((compile(r###"
-    prql target:sql.postgres
-
+fn test_cte_names_stable() {
+    // CTEs are named after their source, numbered in the order they are
+    // emitted, so inserting a stage only renames the CTEs that follow it
+    // and read from the same source
+    let before = compile(
+        r#"
     from employees
-    group department (
-      sort age
-      take 2..
+    take 10
+    filter salary > 100
+    join (from departments | take 5 | filter size > 3) (==dept_id)
+    "#,
     )
-    "###).unwrap()),  @r"
-    WITH table_0 AS (
+    .unwrap();
+    let after = compile(
+        r#"
+    from employees
+    take 10
+    filter salary > 100
+    take 5
+    filter salary > 200
+    join (from departments | take 5 | filter size > 3) (==dept_id)
+    "#,
+    )
+    .unwrap();
+
+    let employees_0 = &before[..before.find("), employees_1").unwrap()];
+    assert!(after.starts_with(employees_0));
+    let departments =
+        &before[before.find("departments_0 AS").unwrap()..before.find(")\nSELECT").unwrap()];
+    assert!(after.contains(departments));
+
+    assert_snapshot!(after, @r"
+    WITH employees_0 AS (
       SELECT
-        *,
-        ROW_NUMBER() OVER (
-          PARTITION BY department
-          ORDER BY
-            age
-        ) AS _expr_0
+        *
       FROM
         employees
+      LIMIT
+        10
+    ), employees_1 AS (
+      SELECT
+        *
+      FROM
+        employees_0
+      WHERE
+        salary > 100
+      LIMIT
+        5
+    ), employees_2 AS (
+      SELECT
+        *
+      FROM
+        employees_1
+      WHERE
+        salary > 200
+    ),
+    departments_0 AS (
+      SELECT
+        *
+      FROM
+        departments
+      LIMIT
+        5
+    ), departments_1 AS (
+      SELECT
+        *
+      FROM
+        departments_0
+      WHERE
+        size > 3
     )
     SELECT
-      *
+      employees_2.*,
+      departments_1.*
     FROM
-      table_0
-    WHERE
-      _expr_0 >= 2
+      employees_2
+      JOIN departments_1 ON employees_2.dept_id = departments_1.dept_id
     ");
 }
 
 #[test]
-fn test_join() {
-    assert_snapshot!((compile(r###"
-    from x
-    join y (==id)
-    "###).unwrap()), @r"
+fn test_long_identifiers() {
+    // identifiers longer than 128 bytes are truncated, but remain distinct
+    let prefix = "total_compensation_".repeat(7);
+    assert_snapshot!((compile(&format!(r#"
+    prql target:sql.oracle
+
+    from employees
+    derive {{
+      {prefix}bonus = salary + bonus,
+      {prefix}benefits = salary + benefits,
+    }}
+    sort {prefix}bonus
+    "#)).unwrap()), @r"
     SELECT
-      x.*,
-      y.*
+      *,
+      salary + bonus AS total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_74fb2a7c,
+      salary + benefits AS total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_a049ec5d
     FROM
-      x
-      JOIN y ON x.id = y.id
+      employees
+    ORDER BY
+      total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_74fb2a7c
     ");
 
-    compile("from x | join y {==x.id}").unwrap_err();
-}
+    // Postgres would silently truncate both aliases to the same 63 bytes
+    let prefix = "total_compensation_".repeat(4);
+    assert_snapshot!((compile(&format!(r#"
+    prql target:sql.postgres
 
-#[test]
-fn test_join_side_literal() {
-    assert_snapshot!((compile(r###"
-    let my_side = "right"
+    from employees
+    derive {{
+      {prefix}bonus = salary + bonus,
+      {prefix}benefits = salary + benefits,
+    }}
+    sort {prefix}bonus
+    "#)).unwrap()), @r"
+    SELECT
+      *,
+      salary + bonus AS total_compensation_total_compensation_total_compensati_77a63264,
+      salary + benefits AS total_compensation_total_compensation_total_compensati_41ed14f5
+    FROM
+      employees
+    ORDER BY
+      total_compensation_total_compensation_total_compensati_77a63264
+    ");
 
-    from x
-    join y (==id) side:my_side
-    "###).unwrap()), @r"
+    let prefix = "total_compensation_".repeat(7);
+    assert_snapshot!((compile(&format!(r#"
+    prql target:sql.mssql
+
+    from employees
+    derive {{
+      {prefix}bonus = salary + bonus,
+      {prefix}benefits = salary + benefits,
+    }}
+    "#)).unwrap()), @r"
     SELECT
-      x.*,
-      y.*
+      *,
+      salary + bonus AS total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_74fb2a7c,
+      salary + benefits AS total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_a049ec5d
     FROM
-      x
-      RIGHT JOIN y ON x.id = y.id
+      employees
+    ");
+
+    // SQLite has no limit
+    assert_snapshot!((compile(&format!(r#"
+    prql target:sql.sqlite
+
+    from employees
+    derive {{
+      {prefix}bonus = salary + bonus,
+    }}
+    "#)).unwrap()), @r"
+    SELECT
+      *,
+      salary + bonus AS total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_total_compensation_bonus
+    FROM
+      employees
     ");
 }
 
 #[test]
-fn test_join_side_literal_err() {
-    assert_snapshot!((compile(r###"
-    let my_side = 42
+fn test_count_if() {
+    let query = r#"
+    from employees
+    aggregate {active = count_if status == "active", total = count this}
+    "#;
+    assert_snapshot!(compile(query).unwrap(), @r"
+    SELECT
+      COUNT(*) FILTER (
+        WHERE
+          status = 'active'
+      ) AS active,
+      COUNT(*) AS total
+    FROM
+      employees
+    ");
 
-    from x
-    join y (==id) side:my_side
-    "###).unwrap_err()), @r"
-    Error:
-       ╭─[:5:24]
-       │
-     5 │     join y (==id) side:my_side
-       │                        ───┬───
-       │                           ╰───── `side` expected inner, left, right or full, but found 42
-    ───╯
+    assert_snapshot!(compile(&format!("prql target:sql.mysql\n{query}")).unwrap(), @r"
+    SELECT
+      COUNT(
+        CASE
+          WHEN status = 'active' THEN 1
+        END
+      ) AS active,
+      COUNT(*) AS total
+    FROM
+      employees
+    ");
+
+    assert_snapshot!(compile(&format!("prql target:sql.bigquery\n{query}")).unwrap(), @r"
+    SELECT
+      COUNTIF(status = 'active') AS active,
+      COUNT(*) AS total
+    FROM
+      employees
     ");
 }
 
 #[test]
-fn test_join_side_literal_via_func() {
+fn test_distinct_01() {
+    // window functions cannot materialize into where statement: CTE is needed
     assert_snapshot!((compile(r###"
-    let my_join = func m <relation> c s <text>:"right" tbl <relation> -> (
-        join side:_param.s m (c == that.k) tbl
+    from employees
+    derive {rn = row_number id}
+    filter rn > 2
+    "###).unwrap()), @r"
+    WITH employees_0 AS (
+      SELECT
+        *,
+        ROW_NUMBER() OVER () AS rn
+      FROM
+        employees
     )
+    SELECT
+      *
+    FROM
+      employees_0
+    WHERE
+      rn > 2
+    ");
+}
 
-    from x
-    my_join default_db.y this.id s:"left"
+#[test]
+fn test_distinct_02() {
+    // basic distinct
+    assert_snapshot!((compile(r###"
+    from employees
+    select first_name
+    group first_name (take 1)
     "###).unwrap()), @r"
     SELECT
-      x.*,
-      y.*
+      DISTINCT first_name
     FROM
-      x
-      LEFT JOIN y ON x.id = y.k
+      employees
     ");
 }
 
 #[test]
-fn test_join_side_literal_via_func_err() {
+fn test_distinct_03() {
+    // distinct on two columns
     assert_snapshot!((compile(r###"
-    let my_join = func m <relation> c s <text>:"right" tbl <relation> -> (
-        join side:_param.s m (c == that.k) tbl
-    )
-
-    from x
-    my_join default_db.y this.id s:"four"
-    "###).unwrap_err()), @r#"
-    Error:
-       ╭─[:3:19]
-       │
-     3 │         join side:_param.s m (c == that.k) tbl
-       │                   ────┬───
-       │                       ╰───── `side` expected inner, left, right or full, but found "four"
-    ───╯
-    "#);
+    from employees
+    select {first_name, last_name}
+    group {first_name, last_name} (take 1)
+    "###).unwrap()), @r"
+    SELECT
+      DISTINCT first_name,
+      last_name
+    FROM
+      employees
+    ");
 }
-
 #[test]
-fn test_from_json() {
-    // Test that the SQL generated from the JSON of the PRQL is the same as the raw PRQL
-    let original_prql = r#"
-    from e=employees
-    join salaries (==emp_no)
-    group {e.emp_no, e.gender} (
-      aggregate {
-        emp_salary = average salaries.salary
-      }
-    )
-    join de=dept_emp (==emp_no)
-    join dm=dept_manager (
-      (dm.dept_no == de.dept_no) && s"(de.from_date, de.to_date) OVERLAPS (dm.from_date, dm.to_date)"
-    )
-    group {dm.emp_no, gender} (
-      aggregate {
-        salary_avg = average emp_salary,
-        salary_sd = stddev emp_salary
-      }
-    )
-    derive mng_no = emp_no
-    join managers=employees (==emp_no)
-    derive mng_name = s"managers.first_name || ' ' || managers.last_name"
-    select {mng_name, managers.gender, salary_avg, salary_sd}
-    "#;
-
-    let source_tree = SourceTree::from(original_prql);
-
-    let sql_from_prql = Ok(prqlc::prql_to_pl_tree(&source_tree).unwrap())
-        .and_then(|ast| prqlc::semantic::resolve_and_lower(ast, &[], None))
-        .and_then(|rq| sql::compile(rq, &Options::default()))
-        .unwrap();
-
-    let sql_from_json = prqlc::prql_to_pl(original_prql)
-        .map(|x| prqlc::json::from_pl(&x).unwrap())
-        .map(|json| prqlc::json::to_pl(&json).unwrap())
-        .and_then(prqlc::pl_to_rq)
-        .and_then(|rq| prqlc::rq_to_sql(rq, &Options::default()))
-        .unwrap();
-
-    assert_eq!(sql_from_prql, sql_from_json);
-}
-
-#[test]
-fn test_f_string() {
-    let query = r#"
+fn test_distinct_04() {
+    // We want distinct only over first_name and last_name, so we can't use a
+    // `DISTINCT *` here.
+    assert_snapshot!((compile(r###"
     from employees
-    derive age = year_born - s'now()'
-    select {
-        f"Hello my name is {prefix}{first_name} {last_name}",
-        f"and I am {age} years old."
-    }
-    "#;
-
-    assert_snapshot!(
-      compile(query).unwrap(),
-        @r"
-    SELECT
-      CONCAT(
-        'Hello my name is ',
-        prefix,
-        first_name,
-        ' ',
-        last_name
-      ),
-      CONCAT('and I am ', year_born - now(), ' years old.')
-    FROM
-      employees
-    "
-    );
-
-    assert_snapshot!(
-        prqlc::compile(
-          query,
-          &Options::default()
-              .no_signature()
-              .with_target(Target::Sql(Some(sql::Dialect::SQLite)))
-
-      ).unwrap(),
-          @r"
+    group {first_name, last_name} (take 1)
+    "###).unwrap()), @r"
+    WITH employees_0 AS (
+      SELECT
+        *,
+        ROW_NUMBER() OVER (PARTITION BY first_name, last_name) AS _expr_0
+      FROM
+        employees
+    )
     SELECT
-      'Hello my name is ' || prefix || first_name || ' ' || last_name,
-      'and I am ' || year_born - now() || ' years old.'
+      *
     FROM
-      employees
-    "
-    );
+      employees_0
+    WHERE
+      _expr_0 <= 1
+    ");
 }
-
 #[test]
-fn test_sql_of_ast_1() {
-    let query = r#"
-    from employees
-    filter country == "USA"
-    group {title, country} (
-        aggregate {average salary}
+fn test_distinct_05() {
+    // Check that a different order doesn't stop distinct from being used.
+    assert!(compile(
+        "from employees | select {first_name, last_name} | group {last_name, first_name} (take 1)"
     )
-    sort title
-    take 20
-    "#;
-
-    let sql = compile(query).unwrap();
-    assert_snapshot!(sql,
-        @r"
-    SELECT
-      title,
-      country,
-      AVG(salary)
-    FROM
-      employees
-    WHERE
-      country = 'USA'
-    GROUP BY
-      title,
-      country
-    ORDER BY
-      title
-    LIMIT
-      20
-    "
-    );
+    .unwrap()
+    .contains("DISTINCT"));
 }
-
 #[test]
-fn test_sql_of_ast_02() {
-    assert_snapshot!(compile(r#"
+fn test_distinct_06() {
+    // head
+    assert_snapshot!((compile(r###"
     from employees
-    aggregate sum_salary = s"sum({salary})"
-    filter sum_salary > 100
-    "#).unwrap(), @r"
+    group department (take 3)
+    "###).unwrap()), @r"
+    WITH employees_0 AS (
+      SELECT
+        *,
+        ROW_NUMBER() OVER (PARTITION BY department) AS _expr_0
+      FROM
+        employees
+    )
     SELECT
-      sum(salary) AS sum_salary
+      *
     FROM
-      employees
-    HAVING
-      sum(salary) > 100
+      employees_0
+    WHERE
+      _expr_0 <= 3
     ");
 }
-
 #[test]
-fn test_bare_s_string() {
-    let query = r#"
-    let grouping = s"""
-        SELECT SUM(a)
-        FROM tbl
-        GROUP BY
-          GROUPING SETS
-          ((b, c, d), (d), (b, d))
-      """
-    from grouping
-    "#;
-
-    let sql = compile(query).unwrap();
-    assert_snapshot!(sql,
-        @r"
-    WITH table_0 AS (
+fn test_distinct_07() {
+    assert_snapshot!((compile(r###"
+    from employees
+    group department (sort salary | take 2..3)
+    "###).unwrap()), @r"
+    WITH employees_0 AS (
       SELECT
-        SUM(a)
+        *,
+        ROW_NUMBER() OVER (
+          PARTITION BY department
+          ORDER BY
+            salary
+        ) AS _expr_0
       FROM
-        tbl
-      GROUP BY
-        GROUPING SETS ((b, c, d), (d), (b, d))
+        employees
     )
     SELECT
       *
     FROM
-      table_0
-    "
-    );
+      employees_0
+    WHERE
+      _expr_0 BETWEEN 2 AND 3
+    ");
 }
-
 #[test]
-fn test_bare_s_string_01() {
-    // Test that case insensitive SELECT is accepted. We allow it as it is valid SQL.
-    assert_snapshot!(compile(r#"
-    let a = s"select insensitive from rude"
-    from a
-    "#).unwrap(),
-        @r"
-    WITH table_0 AS (
+fn test_distinct_08() {
+    assert_snapshot!((compile(r###"
+    from employees
+    group department (sort salary | take 4..4)
+    "###).unwrap()), @r"
+    WITH employees_0 AS (
       SELECT
-        insensitive
-      from
-        rude
+        *,
+        ROW_NUMBER() OVER (
+          PARTITION BY department
+          ORDER BY
+            salary
+        ) AS _expr_0
+      FROM
+        employees
     )
     SELECT
       *
     FROM
-      table_0
-    "
-    );
+      employees_0
+    WHERE
+      _expr_0 = 4
+    ");
 }
 
 #[test]
-fn test_bare_s_string_02() {
-    // Check a mixture of cases for good measure.
-    assert_snapshot!(compile(r#"
-    let a = s"sElEcT insensitive from rude"
-    from a
-    "#).unwrap(),
-        @r"
-    WITH table_0 AS (
+fn test_distinct_09() {
+    assert_snapshot!(compile("
+    from invoices
+    select {billing_country, billing_city}
+    group {billing_city} (
+      take 1
+    )
+    sort billing_city
+    ").unwrap(), @r"
+    WITH invoices_0 AS (
       SELECT
-        insensitive
-      from
-        rude
+        billing_city,
+        billing_country,
+        ROW_NUMBER() OVER (PARTITION BY billing_city) AS _expr_0
+      FROM
+        invoices
     )
     SELECT
-      *
+      billing_city,
+      billing_country
     FROM
-      table_0
-    "
-    );
+      invoices_0
+    WHERE
+      _expr_0 <= 1
+    ORDER BY
+      billing_city
+    ");
 }
 
 #[test]
-fn test_bare_s_string_03() {
-    // Check SELECT\n.
-    assert_snapshot!(compile(r#"
-    let a = s"
+fn test_distinct_10() {
+    // take doesn't require a subquery
+    assert_snapshot!(compile("
+    from invoices
+    select {billing_country, billing_city}
+    group {billing_country, billing_city} (
+      take 1
+    )
+    take 10
+    ").unwrap(), @r"
     SELECT
-      foo
+      DISTINCT billing_country,
+      billing_city
     FROM
-      bar"
+      invoices
+    LIMIT
+      10
+    ");
+}
 
-    from a
-    "#).unwrap(), @r"
-    WITH table_0 AS (
+#[test]
+fn test_distinct_on_01() {
+    assert_snapshot!((compile(r###"
+    prql target:sql.postgres
+
+    from employees
+    group department (
+      sort age
+      take 1
+    )
+    "###).unwrap()), @r"
+    SELECT
+      DISTINCT ON (department) *
+    FROM
+      employees
+    ORDER BY
+      department,
+      age
+    ");
+}
+
+#[test]
+fn test_distinct_on_02() {
+    assert_snapshot!((compile(r###"
+    prql target:sql.duckdb
+
+    from x
+    select {class, begins}
+    group {begins} (take 1)
+    "###).unwrap()), @r"
+    SELECT
+      DISTINCT ON (begins) begins,
+      class
+    FROM
+      x
+    ");
+}
+
+#[test]
+fn test_distinct_on_03() {
+    assert_snapshot!((compile(r###"
+    prql target:sql.duckdb
+
+    from tab1
+    group col1 (
+      take 1
+    )
+    derive foo = 1
+    select foo
+    "###).unwrap()), @r"
+    WITH tab1_0 AS (
       SELECT
-        foo
+        DISTINCT ON (col1) NULL
       FROM
-        bar
+        tab1
     )
     SELECT
-      *
+      1 AS foo
     FROM
-      table_0
+      tab1_0
     ");
 }
 
 #[test]
-fn test_bare_s_string_04() {
-    assert_snapshot!(compile(r#"
-    s"SELECTfoo"
-    "#).unwrap_err(), @r"
-    Error: s-strings representing a table must start with `SELECT `
-    ↳ Hint: this is a limitation by current compiler implementation
+fn test_distinct_on_04() {
+    assert_snapshot!((compile(r###"
+    prql target:sql.duckdb
+
+    from a
+    join b (b.a_id == a.id)
+    group {a.id} (
+      sort b.x
+      take 1
+    )
+    select {a.id, b.y}
+    "###).unwrap()), @r"
+    SELECT
+      DISTINCT ON (a.id) a.id,
+      b.y
+    FROM
+      a
+      JOIN b ON b.a_id = a.id
+    ORDER BY
+      a.id,
+      b.x
     ");
 }
 
 #[test]
-// Confirm that a regular expr_call in a table definition works as expected.
-fn test_table_definition_with_expr_call() {
+fn test_distinct_on_05() {
+    // expressions that are only used for sorting are inlined into ORDER BY
     let query = r###"
-    let e = take 4 (from employees)
-    from e
+    from events
+    select {user_id, kind, created_at}
+    group {user_id} (
+      sort {-(created_at + 1)}
+      take 1
+    )
     "###;
 
-    let sql = compile(query).unwrap();
-    assert_snapshot!(sql,
-        @r"
-    WITH e AS (
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    SELECT
+      DISTINCT ON (user_id) user_id,
+      kind,
+      created_at
+    FROM
+      events
+    ORDER BY
+      user_id,
+      created_at + 1 DESC
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Generic).unwrap(), @r"
+    WITH events_0 AS (
       SELECT
-        *
+        user_id,
+        kind,
+        created_at,
+        ROW_NUMBER() OVER (
+          PARTITION BY user_id
+          ORDER BY
+            created_at + 1 DESC
+        ) AS _expr_0
       FROM
-        employees
-      LIMIT
-        4
+        events
     )
     SELECT
-      *
+      user_id,
+      kind,
+      created_at
     FROM
-      e
-    "
-    );
+      events_0
+    WHERE
+      _expr_0 <= 1
+    ");
 }
 
 #[test]
-fn test_prql_to_sql_1() {
-    assert_snapshot!(compile(r#"
-    from employees
-    aggregate {
-        count salary,
-        sum salary,
-    }
-    "#).unwrap(), @r"
-    SELECT
-      COUNT(*),
-      COALESCE(SUM(salary), 0)
-    FROM
-      employees
-    "
-    );
-    assert_snapshot!(compile(r#"
+fn test_group_take_n_01() {
+    assert_snapshot!((compile(r###"
     prql target:sql.postgres
-    from developers
-    group team (
-        aggregate {
-            skill_width = count_distinct specialty,
-        }
+
+    from employees
+    group department (
+      sort age
+      take 2
+    )
+    "###).unwrap()), @r"
+    WITH employees_0 AS (
+      SELECT
+        *,
+        ROW_NUMBER() OVER (
+          PARTITION BY department
+          ORDER BY
+            age
+        ) AS _expr_0
+      FROM
+        employees
     )
-    "#).unwrap(), @r"
     SELECT
-      team,
-      COUNT(DISTINCT specialty) AS skill_width
+      *
     FROM
-      developers
-    GROUP BY
-      team
-    "
-    )
+      employees_0
+    WHERE
+      _expr_0 <= 2
+    ");
 }
 
 #[test]
-#[ignore]
-fn test_prql_to_sql_2() {
-    let query = r#"
-from employees
-filter country == "USA"                           # Each line transforms the previous result.
-derive {                                         # This adds columns / variables.
-gross_salary = salary + payroll_tax,
-gross_cost = gross_salary + benefits_cost      # Variables can use other variables.
-}
-filter gross_cost > 0
-group {title, country} (
-aggregate  {                                 # `by` are the columns to group by.
-    average salary,                          # These are aggregation calcs run on each group.
-    sum     salary,
-    average gross_salary,
-    sum     gross_salary,
-    average gross_cost,
-    sum_gross_cost = sum gross_cost,
-    ct = count salary,
-}
-)
-sort sum_gross_cost
-filter ct > 200
-take 20
-"#;
+fn test_group_take_n_02() {
+    assert_snapshot!((compile(r###"
+    prql target:sql.postgres
 
-    let sql = compile(query).unwrap();
-    assert_snapshot!(sql, @r###"
-    WITH table_0 AS (
+    from employees
+    group department (
+      sort age
+      take 2..
+    )
+    "###).unwrap()),  @r"
+    WITH employees_0 AS (
       SELECT
-        title,
-        country,
-        salary + payroll_tax + benefits_cost AS _expr_0,
-        salary + payroll_tax AS _expr_1,
-        salary
+        *,
+        ROW_NUMBER() OVER (
+          PARTITION BY department
+          ORDER BY
+            age
+        ) AS _expr_0
       FROM
         employees
-      WHERE
-        country = 'USA'
     )
     SELECT
-      title,
-      country,
-      AVG(salary),
-      COALESCE(SUM(salary), 0),
-      AVG(_expr_1),
-      COALESCE(SUM(_expr_1), 0),
-      AVG(_expr_0),
-      COALESCE(SUM(_expr_0), 0) AS sum_gross_cost,
-      COUNT(*) AS ct
+      *
     FROM
-      table_0
+      employees_0
     WHERE
-      _expr_0 > 0
-    GROUP BY
-      title,
-      country
-    HAVING
-      COUNT(*) > 200
-    ORDER BY
-      sum_gross_cost
-    LIMIT
-      20
-    "###);
+      _expr_0 >= 2
+    ");
 }
 
 #[test]
-fn test_prql_to_sql_table() {
-    // table
-    let query = r#"
-    let newest_employees = (
-        from employees
-        sort tenure
-        take 50
-    )
-    let average_salaries = (
-        from salaries
-        group country (
-            aggregate {
-                average_country_salary = average salary
-            }
-        )
-    )
-    from newest_employees
-    join average_salaries (==country)
-    select {name, salary, average_country_salary}
-    "#;
-    let sql = compile(query).unwrap();
-    assert_snapshot!(sql,
-        @r"
-    WITH newest_employees AS (
-      SELECT
-        *
-      FROM
-        employees
-      ORDER BY
-        tenure
-      LIMIT
-        50
-    ), average_salaries AS (
-      SELECT
-        country,
-        AVG(salary) AS average_country_salary
-      FROM
-        salaries
-      GROUP BY
-        country
-    )
+fn test_join() {
+    assert_snapshot!((compile(r###"
+    from x
+    join y (==id)
+    "###).unwrap()), @r"
     SELECT
-      newest_employees.name,
-      newest_employees.salary,
-      average_salaries.average_country_salary
+      x.*,
+      y.*
     FROM
-      newest_employees
-      JOIN average_salaries ON newest_employees.country = average_salaries.country
-    ORDER BY
-      employees.tenure
-    "
-    );
+      x
+      JOIN y ON x.id = y.id
+    ");
+
+    compile("from x | join y {==x.id}").unwrap_err();
 }
 
 #[test]
-fn test_nonatomic() {
-    // A take, then two aggregates
-    let query = r#"
-        from employees
-        take 20
-        filter country == "USA"
-        group {title, country} (
-            aggregate {
-                salary = average salary
-            }
-        )
-        group {title, country} (
-            aggregate {
-                sum_gross_cost = average salary
-            }
-        )
-        sort sum_gross_cost
-    "#;
+fn test_join_computed_keys() {
+    // computed join keys are only emitted in `ON`, not in the projection
+    assert_snapshot!((compile(r###"
+    from a
+    join b (s"LOWER({this.x})" == s"LOWER({that.y})")
+    select {a.x, b.z}
+    "###).unwrap()), @r"
+    SELECT
+      a.x,
+      b.z
+    FROM
+      a
+      JOIN b ON LOWER(a.x) = LOWER(b.y)
+    ");
 
-    assert_snapshot!((compile(query).unwrap()), @r"
-    WITH table_1 AS (
+    assert_snapshot!((compile(r###"
+    from a
+    select {x, z}
+    join side:left (from b | take 3) ((text.lower this.x) == (text.lower that.y))
+    group {x} (aggregate {c = count this})
+    "###).unwrap()), @r"
+    WITH b_0 AS (
       SELECT
-        title,
-        country,
-        salary
+        *
       FROM
-        employees
+        b
       LIMIT
-        20
-    ), table_0 AS (
-      SELECT
-        title,
-        country,
-        AVG(salary) AS _expr_0
-      FROM
-        table_1
-      WHERE
-        country = 'USA'
-      GROUP BY
-        title,
-        country
+        3
     )
     SELECT
-      title,
-      country,
-      AVG(_expr_0) AS sum_gross_cost
-    FROM
-      table_0
-    GROUP BY
-      title,
-      country
-    ORDER BY
-      sum_gross_cost
-    ");
-
-    // A aggregate, then sort and filter
-    let query = r###"
-        from employees
-        group {title, country} (
-            aggregate {
-                sum_gross_cost = average salary
-            }
-        )
-        sort sum_gross_cost
-        filter sum_gross_cost > 0
-    "###;
-
-    assert_snapshot!((compile(query).unwrap()), @r"
-    SELECT
-      title,
-      country,
-      AVG(salary) AS sum_gross_cost
+      a.x,
+      COUNT(*) AS c
     FROM
-      employees
+      a
+      LEFT JOIN b_0 ON LOWER(a.x) = LOWER(b_0.y)
     GROUP BY
-      title,
-      country
-    HAVING
-      AVG(salary) > 0
-    ORDER BY
-      sum_gross_cost
+      a.x
     ");
 }
 
 #[test]
-/// Confirm a nonatomic table works.
-fn test_nonatomic_table() {
-    // A take, then two aggregates
-    let query = r#"
-    let a = (
-        from employees
-        take 50
-        group country (aggregate {s"count(*)"})
-    )
-    from a
-    join b (==country)
-    select {name, salary, average_country_salary}
-"#;
-
-    assert_snapshot!((compile(query).unwrap()), @r"
-    WITH table_0 AS (
-      SELECT
-        country
-      FROM
-        employees
-      LIMIT
-        50
-    ), a AS (
+fn test_join_after_group() {
+    // the aggregation is done in a CTE, before joining
+    assert_snapshot!((compile(r###"
+    from tracks
+    group {album_id} (aggregate {n = count this})
+    filter n > 10
+    join side:left a = albums (==album_id)
+    select {album_id, n, a.title}
+    sort {-n}
+    "###).unwrap()), @r"
+    WITH tracks_0 AS (
       SELECT
-        country,
-        count(*)
+        album_id,
+        COUNT(*) AS n
       FROM
-        table_0
+        tracks
       GROUP BY
-        country
+        album_id
+      HAVING
+        COUNT(*) > 10
     )
     SELECT
-      b.name,
-      b.salary,
-      b.average_country_salary
+      tracks_0.album_id,
+      tracks_0.n,
+      a.title
     FROM
-      a
-      JOIN b ON a.country = b.country
+      tracks_0
+      LEFT JOIN albums AS a ON tracks_0.album_id = a.album_id
+    ORDER BY
+      tracks_0.n DESC
     ");
-}
 
-#[test]
-fn test_table_names_between_splits_01() {
-    assert_snapshot!(compile(r###"
-    from employees
-    join d = department (==dept_no)
-    take 10
-    derive emp_no = employees.emp_no
-    join s = salaries (==emp_no)
-    select {employees.emp_no, d.name, s.salary}
-    "###).unwrap(), @r"
-    WITH table_0 AS (
+    // joined columns can be aggregated again
+    assert_snapshot!((compile(r###"
+    from tracks
+    group {album_id} (aggregate {n = count this})
+    join albums (==album_id)
+    group {albums.artist_id} (aggregate {tracks = sum n})
+    "###).unwrap()), @r"
+    WITH tracks_0 AS (
       SELECT
-        employees.emp_no,
-        d.name
+        COUNT(*) AS _expr_0,
+        album_id
       FROM
-        employees
-        JOIN department AS d ON employees.dept_no = d.dept_no
-      LIMIT
-        10
+        tracks
+      GROUP BY
+        album_id
     )
     SELECT
-      table_0.emp_no,
-      table_0.name,
-      s.salary
+      albums.artist_id,
+      COALESCE(SUM(tracks_0._expr_0), 0) AS tracks
     FROM
-      table_0
-      JOIN salaries AS s ON table_0.emp_no = s.emp_no
+      tracks_0
+      JOIN albums ON tracks_0.album_id = albums.album_id
+    GROUP BY
+      albums.artist_id
     ");
-}
 
-#[test]
-fn test_table_names_between_splits_02() {
-    assert_snapshot!(compile(r###"
-    from e = employees
-    take 10
-    join salaries (==emp_no)
-    select {e.*, salaries.salary}
-    "###).unwrap(), @r"
-    WITH table_0 AS (
+    // both sides are aggregated
+    assert_snapshot!((compile(r###"
+    from tracks
+    group {album_id} (aggregate {n = count this})
+    join (from invoice_items | group {album_id} (aggregate {sold = sum quantity})) (==album_id)
+    derive ratio = sold / n
+    "###).unwrap()), @r"
+    WITH tracks_0 AS (
       SELECT
-        *
+        album_id,
+        COUNT(*) AS n
       FROM
-        employees AS e
-      LIMIT
-        10
+        tracks
+      GROUP BY
+        album_id
+    ),
+    invoice_items_0 AS (
+      SELECT
+        album_id,
+        COALESCE(SUM(quantity), 0) AS sold
+      FROM
+        invoice_items
+      GROUP BY
+        album_id
     )
     SELECT
-      table_0.*,
-      salaries.salary
+      tracks_0.album_id,
+      tracks_0.n,
+      invoice_items_0.album_id,
+      invoice_items_0.sold,
+      invoice_items_0.sold / tracks_0.n AS ratio
     FROM
-      table_0
-      JOIN salaries ON table_0.emp_no = salaries.emp_no
+      tracks_0
+      JOIN invoice_items_0 ON tracks_0.album_id = invoice_items_0.album_id
     ");
 }
 
 #[test]
-fn test_table_alias_01() {
+fn test_join_side_literal() {
     assert_snapshot!((compile(r###"
-    from e = employees
-    join salaries side:left (salaries.emp_no == e.emp_no)
-    group {e.emp_no} (
-        aggregate {
-            emp_salary = average salaries.salary
-        }
-    )
-    select {emp_no, emp_salary}
+    let my_side = "right"
+
+    from x
+    join y (==id) side:my_side
     "###).unwrap()), @r"
     SELECT
-      e.emp_no,
-      AVG(salaries.salary) AS emp_salary
+      x.*,
+      y.*
     FROM
-      employees AS e
-      LEFT JOIN salaries ON salaries.emp_no = e.emp_no
-    GROUP BY
-      e.emp_no
-    ");
+      x
+      RIGHT JOIN y ON x.id = y.id
+    ");
 }
 
 #[test]
-fn test_table_alias_02() {
-    assert_snapshot!((compile(r#"
-    from e = employees
-    select e.first_name
-    filter e.first_name == "Fred"
-    "#).unwrap()), @r"
-    SELECT
-      first_name
-    FROM
-      employees AS e
-    WHERE
-      first_name = 'Fred'
+fn test_join_side_literal_err() {
+    assert_snapshot!((compile(r###"
+    let my_side = 42
+
+    from x
+    join y (==id) side:my_side
+    "###).unwrap_err()), @r"
+    Error:
+       ╭─[:5:24]
+       │
+     5 │     join y (==id) side:my_side
+       │                        ───┬───
+       │                           ╰───── `side` expected inner, left, right or full, but found 42
+    ───╯
     ");
 }
 
 #[test]
-fn test_targets() {
-    // Generic
-    let query = r###"
-    prql target:sql.generic
-    from Employees
-    select {FirstName, `last name`}
-    take 3
-    "###;
+fn test_join_side_literal_via_func() {
+    assert_snapshot!((compile(r###"
+    let my_join = func m <relation> c s <text>:"right" tbl <relation> -> (
+        join side:_param.s m (c == that.k) tbl
+    )
 
-    assert_snapshot!((compile(query).unwrap()), @r#"
+    from x
+    my_join default_db.y this.id s:"left"
+    "###).unwrap()), @r"
     SELECT
-      "FirstName",
-      "last name"
+      x.*,
+      y.*
     FROM
-      "Employees"
-    LIMIT
-      3
-    "#);
+      x
+      LEFT JOIN y ON x.id = y.k
+    ");
+}
 
-    // SQL server
-    let query = r###"
-    prql target:sql.mssql
-    from Employees
-    select {FirstName, `last name`}
-    take 3
-    "###;
+#[test]
+fn test_join_side_literal_via_func_err() {
+    assert_snapshot!((compile(r###"
+    let my_join = func m <relation> c s <text>:"right" tbl <relation> -> (
+        join side:_param.s m (c == that.k) tbl
+    )
 
-    assert_snapshot!((compile(query).unwrap()), @r#"
-    SELECT
-      "FirstName",
-      "last name"
-    FROM
-      "Employees"
-    ORDER BY
-      (
-        SELECT
-          NULL
-      ) OFFSET 0 ROWS
-    FETCH FIRST
-      3 ROWS ONLY
+    from x
+    my_join default_db.y this.id s:"four"
+    "###).unwrap_err()), @r#"
+    Error:
+       ╭─[:3:19]
+       │
+     3 │         join side:_param.s m (c == that.k) tbl
+       │                   ────┬───
+       │                       ╰───── `side` expected inner, left, right or full, but found "four"
+    ───╯
     "#);
+}
 
-    // MySQL
-    let query = r###"
-    prql target:sql.mysql
-    from Employees
-    select {FirstName, `last name`}
-    take 3
-    "###;
+#[test]
+fn test_from_json() {
+    // Test that the SQL generated from the JSON of the PRQL is the same as the raw PRQL
+    let original_prql = r#"
+    from e=employees
+    join salaries (==emp_no)
+    group {e.emp_no, e.gender} (
+      aggregate {
+        emp_salary = average salaries.salary
+      }
+    )
+    join de=dept_emp (==emp_no)
+    join dm=dept_manager (
+      (dm.dept_no == de.dept_no) && s"(de.from_date, de.to_date) OVERLAPS (dm.from_date, dm.to_date)"
+    )
+    group {dm.emp_no, gender} (
+      aggregate {
+        salary_avg = average emp_salary,
+        salary_sd = stddev emp_salary
+      }
+    )
+    derive mng_no = emp_no
+    join managers=employees (==emp_no)
+    derive mng_name = s"managers.first_name || ' ' || managers.last_name"
+    select {mng_name, managers.gender, salary_avg, salary_sd}
+    "#;
 
-    assert_snapshot!((compile(query).unwrap()), @r"
-    SELECT
-      `FirstName`,
-      `last name`
-    FROM
-      `Employees`
-    LIMIT
-      3
-    ");
+    let source_tree = SourceTree::from(original_prql);
+
+    let sql_from_prql = Ok(prqlc::prql_to_pl_tree(&source_tree).unwrap())
+        .and_then(|ast| prqlc::semantic::resolve_and_lower(ast, &[], None))
+        .and_then(|rq| sql::compile(rq, &Options::default()))
+        .unwrap();
+
+    let sql_from_json = prqlc::prql_to_pl(original_prql)
+        .map(|x| prqlc::json::from_pl(&x).unwrap())
+        .map(|json| prqlc::json::to_pl(&json).unwrap())
+        .and_then(prqlc::pl_to_rq)
+        .and_then(|rq| prqlc::rq_to_sql(rq, &Options::default()))
+        .unwrap();
+
+    assert_eq!(sql_from_prql, sql_from_json);
 }
 
 #[test]
-fn test_target_clickhouse() {
-    let query = r###"
-    prql target:sql.clickhouse
-
-    from github_json
-    derive {event_type_dotted = `event.type`}
-    "###;
+fn test_percentile() {
+    let query = r#"
+    from employees
+    group department (
+      aggregate {p90 = (salary | percentile 0.9), med = median salary}
+    )
+    "#;
 
-    assert_snapshot!((compile(query).unwrap()), @r"
+    assert_snapshot!(compile(&format!("prql target:sql.postgres\n{query}")).unwrap(), @r"
     SELECT
-      *,
-      `event.type` AS event_type_dotted
+      department,
+      PERCENTILE_CONT(0.9) WITHIN GROUP (
+        ORDER BY
+          salary
+      ) AS p90,
+      PERCENTILE_CONT(0.5) WITHIN GROUP (
+        ORDER BY
+          salary
+      ) AS med
     FROM
-      github_json
+      employees
+    GROUP BY
+      department
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.duckdb\n{query}")).unwrap(), @r"
+    SELECT
+      department,
+      PERCENTILE_CONT(0.9) WITHIN GROUP (
+        ORDER BY
+          salary
+      ) AS p90,
+      MEDIAN(salary) AS med
+    FROM
+      employees
+    GROUP BY
+      department
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.sqlite\n{query}")).unwrap_err(), @r"
+    Error:
+       ╭─[:5:34]
+       │
+     5 │       aggregate {p90 = (salary | percentile 0.9), med = median salary}
+       │                                  ───────┬──────
+       │                                         ╰──────── operator std.percentile is not supported for dialect sqlite
+    ───╯
     ");
 }
 
 #[test]
-fn test_ident_escaping() {
-    // Generic
+fn test_array_agg() {
     let query = r#"
-    from `anim"ls`
-    derive {`čebela` = BeeName, medved = `bear's_name`}
+    from employees
+    group department (
+      aggregate {names = array_agg name, csv = string_agg name ", "}
+    )
     "#;
 
-    assert_snapshot!((compile(query).unwrap()), @r#"
+    assert_snapshot!(compile(&format!("prql target:sql.postgres\n{query}")).unwrap(), @r"
     SELECT
-      *,
-      "BeeName" AS "čebela",
-      "bear's_name" AS medved
+      department,
+      ARRAY_AGG(name) AS names,
+      STRING_AGG(name, ', ') AS csv
     FROM
-      "anim""ls"
+      employees
+    GROUP BY
+      department
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.snowflake\n{query}")).unwrap(), @r"
+    SELECT
+      department,
+      ARRAY_AGG(name) AS names,
+      LISTAGG(name, ', ') AS csv
+    FROM
+      employees
+    GROUP BY
+      department
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.sqlite\n{query}")).unwrap_err(), @r#"
+    Error:
+       ╭─[:5:26]
+       │
+     5 │       aggregate {names = array_agg name, csv = string_agg name ", "}
+       │                          ───────┬──────
+       │                                 ╰──────── operator std.array_agg is not supported for dialect sqlite
+    ───╯
     "#);
 
-    // MySQL
     let query = r#"
-    prql target:sql.mysql
-
-    from `anim"ls`
-    derive {`čebela` = BeeName, medved = `bear's_name`}
+    from employees
+    group department (
+      aggregate {csv = string_agg name ", "}
+    )
     "#;
-
-    assert_snapshot!((compile(query).unwrap()), @r#"
+    assert_snapshot!(compile(&format!("prql target:sql.sqlite\n{query}")).unwrap(), @r"
     SELECT
-      *,
-      `BeeName` AS `čebela`,
-      `bear's_name` AS medved
+      department,
+      GROUP_CONCAT(name, ', ') AS csv
     FROM
-      `anim"ls`
-    "#);
+      employees
+    GROUP BY
+      department
+    ");
+    assert_snapshot!(compile(&format!("prql target:sql.mysql\n{query}")).unwrap(), @r"
+    SELECT
+      department,
+      GROUP_CONCAT(name SEPARATOR ', ') AS csv
+    FROM
+      employees
+    GROUP BY
+      department
+    ");
 }
 
 #[test]
-fn test_literal() {
-    let query = r###"
+fn test_f_string() {
+    let query = r#"
     from employees
-    derive {always_true = true}
-    "###;
+    derive age = year_born - s'now()'
+    select {
+        f"Hello my name is {prefix}{first_name} {last_name}",
+        f"and I am {age} years old."
+    }
+    "#;
 
-    let sql = compile(query).unwrap();
-    assert_snapshot!(sql,
+    assert_snapshot!(
+      compile(query).unwrap(),
         @r"
     SELECT
-      *,
-      true AS always_true
-    FROM
-      employees
-    "
-    );
-}
-
-#[test]
-fn test_same_column_names() {
-    // #820
-    let query = r###"
-let x = (
-from x_table
-select only_in_x = foo
-)
-
-let y = (
-from y_table
-select foo
-)
-
-from x
-join y (foo == only_in_x)
-"###;
-
-    assert_snapshot!(compile(query).unwrap(),
-        @r"
-    WITH x AS (
-      SELECT
-        foo AS only_in_x
-      FROM
-        x_table
-    ),
-    y AS (
-      SELECT
-        foo
-      FROM
-        y_table
-    )
-    SELECT
-      x.only_in_x,
-      y.foo
+      CONCAT(
+        'Hello my name is ',
+        prefix,
+        first_name,
+        ' ',
+        last_name
+      ),
+      CONCAT('and I am ', year_born - now(), ' years old.')
     FROM
-      x
-      JOIN y ON y.foo = x.only_in_x
+      employees
     "
     );
-}
 
-#[test]
-fn test_double_aggregate() {
-    // #941
-    compile(
-        r###"
-    from numbers
-    group {type} (
-        aggregate {
-            total_amt = sum amount,
-        }
-        aggregate {
-            max amount
-        }
-    )
-    "###,
-    )
-    .unwrap_err();
+    assert_snapshot!(
+        prqlc::compile(
+          query,
+          &Options::default()
+              .no_signature()
+              .with_target(Target::Sql(Some(sql::Dialect::SQLite)))
 
-    assert_snapshot!(compile(r###"
-    from numbers
-    group {`type`} (
-        aggregate {
-            total_amt = sum amount,
-            max amount
-        }
-    )
-    "###).unwrap(),
-        @r"
+      ).unwrap(),
+          @r"
     SELECT
-      type,
-      COALESCE(SUM(amount), 0) AS total_amt,
-      MAX(amount)
+      'Hello my name is ' || prefix || first_name || ' ' || last_name,
+      'and I am ' || year_born - now() || ' years old.'
     FROM
-      numbers
-    GROUP BY
-      type
+      employees
     "
     );
 }
 
 #[test]
-fn test_window_function_coalesce() {
-    // #3587
-    assert_snapshot!(compile(r###"
-    from x
-    select {a, b=a}
-    window (
-      select {
-        cumsum_a=(sum a),
-        cumsum_b=(sum b)
-      }
+fn test_sql_of_ast_1() {
+    let query = r#"
+    from employees
+    filter country == "USA"
+    group {title, country} (
+        aggregate {average salary}
     )
-    "###).unwrap(),
+    sort title
+    take 20
+    "#;
+
+    let sql = compile(query).unwrap();
+    assert_snapshot!(sql,
         @r"
     SELECT
-      SUM(a) OVER () AS cumsum_a,
-      SUM(a) OVER () AS cumsum_b
+      title,
+      country,
+      AVG(salary)
     FROM
-      x
+      employees
+    WHERE
+      country = 'USA'
+    GROUP BY
+      title,
+      country
+    ORDER BY
+      title
+    LIMIT
+      20
     "
     );
 }
 
 #[test]
-fn test_casting() {
-    assert_snapshot!(compile(r###"
-    from x
-    select {a}
-    derive {
-        b = (a | as int) + 10,
-        c = (a | as int) - 10,
-        d = (a | as float) * 10,
-        e = (a | as float) / 10,
-    }
-    "###).unwrap(),
-        @r"
+fn test_sql_of_ast_02() {
+    assert_snapshot!(compile(r#"
+    from employees
+    aggregate sum_salary = s"sum({salary})"
+    filter sum_salary > 100
+    "#).unwrap(), @r"
     SELECT
-      a,
-      CAST(a AS int) + 10 AS b,
-      CAST(a AS int) - 10 AS c,
-      CAST(a AS float) * 10 AS d,
-      CAST(a AS float) / 10 AS e
+      sum(salary) AS sum_salary
     FROM
-      x
-    "
-    );
+      employees
+    HAVING
+      sum(salary) > 100
+    ");
 }
 
 #[test]
-fn test_toposort() {
-    // #1183
-
-    assert_snapshot!(compile(r###"
-    let b = (
-        from somesource
-    )
-
-    let a = (
-        from b
-    )
+fn test_bare_s_string() {
+    let query = r#"
+    let grouping = s"""
+        SELECT SUM(a)
+        FROM tbl
+        GROUP BY
+          GROUPING SETS
+          ((b, c, d), (d), (b, d))
+      """
+    from grouping
+    "#;
 
-    from b
-    "###).unwrap(),
+    let sql = compile(query).unwrap();
+    assert_snapshot!(sql,
         @r"
-    WITH b AS (
+    WITH table_0 AS (
       SELECT
-        *
+        SUM(a)
       FROM
-        somesource
+        tbl
+      GROUP BY
+        GROUPING SETS ((b, c, d), (d), (b, d))
     )
     SELECT
       *
     FROM
-      b
+      table_0
     "
     );
 }
 
 #[test]
-fn test_inline_tables() {
-    assert_snapshot!(compile(r###"
-    (
-        from employees
-        select {emp_id, name, surname, `type`, amount}
-    )
-    join s = (from salaries | select {emp_id, salary}) (==emp_id)
-    "###).unwrap(),
+fn test_bare_s_string_01() {
+    // Test that case insensitive SELECT is accepted. We allow it as it is valid SQL.
+    assert_snapshot!(compile(r#"
+    let a = s"select insensitive from rude"
+    from a
+    "#).unwrap(),
         @r"
     WITH table_0 AS (
       SELECT
-        emp_id,
-        salary
-      FROM
-        salaries
+        insensitive
+      from
+        rude
     )
     SELECT
-      employees.emp_id,
-      employees.name,
-      employees.surname,
-      employees.type,
-      employees.amount,
-      table_0.emp_id,
-      table_0.salary
+      *
     FROM
-      employees
-      JOIN table_0 ON employees.emp_id = table_0.emp_id
+      table_0
     "
     );
 }
 
 #[test]
-fn test_filter_and_select_unchanged_alias() {
-    // #1185
-
-    assert_snapshot!(compile(r###"
-    from account
-    filter account.name != null
-    select {name = account.name}
-    "###).unwrap(),
+fn test_bare_s_string_02() {
+    // Check a mixture of cases for good measure.
+    assert_snapshot!(compile(r#"
+    let a = s"sElEcT insensitive from rude"
+    from a
+    "#).unwrap(),
         @r"
+    WITH table_0 AS (
+      SELECT
+        insensitive
+      from
+        rude
+    )
     SELECT
-      name
+      *
     FROM
-      account
-    WHERE
-      name IS NOT NULL
+      table_0
     "
     );
 }
 
 #[test]
-fn test_filter_and_select_changed_alias() {
-    // #1185
-    assert_snapshot!(compile(r###"
-    from account
-    filter account.name != null
-    select {renamed_name = account.name}
-    "###).unwrap(),
-        @r"
-    SELECT
-      name AS renamed_name
-    FROM
-      account
-    WHERE
-      name IS NOT NULL
-    "
-    );
-
-    // #1207
+fn test_bare_s_string_03() {
+    // Check SELECT\n.
     assert_snapshot!(compile(r#"
-    from x
-    filter name != "Bob"
-    select name = name ?? "Default"
-    "#).unwrap(),
-        @r"
+    let a = s"
     SELECT
-      COALESCE(name, 'Default') AS name
+      foo
     FROM
-      x
-    WHERE
-      name <> 'Bob'
-    "
-    );
-}
-
-#[test]
-fn test_unused_alias() {
-    // #1308
-    assert_snapshot!(compile(r###"
-    from account
-    select n = {account.name}
-    "###).unwrap_err(), @r"
-    Error:
-       ╭─[:3:16]
-       │
-     3 │     select n = {account.name}
-       │                ───────┬──────
-       │                       ╰──────── unexpected assign to `n`
-       │
-       │ Help: move assign into the tuple: `[n = ...]`
-    ───╯
-    ")
-}
+      bar"
 
-#[test]
-fn test_table_s_string_01() {
-    assert_snapshot!(compile(r#"
-    let main <relation> = s"SELECT DISTINCT ON first_name, age FROM employees ORDER BY age ASC"
-    "#).unwrap(),
-        @r"
+    from a
+    "#).unwrap(), @r"
     WITH table_0 AS (
       SELECT
-        DISTINCT ON first_name,
-        age
+        foo
       FROM
-        employees
-      ORDER BY
-        age ASC
+        bar
     )
     SELECT
       *
     FROM
       table_0
-    "
-    );
+    ");
 }
+
 #[test]
-fn test_table_s_string_02() {
+fn test_bare_s_string_04() {
     assert_snapshot!(compile(r#"
-    s"""
-        SELECT DISTINCT ON first_name, id, age FROM employees ORDER BY age ASC
-    """
-    join s = s"SELECT * FROM salaries" (==id)
-    "#).unwrap(),
-        @r"
-    WITH table_0 AS (
-      SELECT
-        DISTINCT ON first_name,
-        id,
-        age
-      FROM
-        employees
-      ORDER BY
-        age ASC
-    ),
-    table_1 AS (
-      SELECT
-        *
-      FROM
-        salaries
-    )
-    SELECT
-      table_0.*,
-      table_1.*
-    FROM
-      table_0
-      JOIN table_1 ON table_0.id = table_1.id
-    "
-    );
+    s"SELECTfoo"
+    "#).unwrap_err(), @r"
+    Error: s-strings representing a table must start with `SELECT `
+    ↳ Hint: this is a limitation by current compiler implementation
+    ");
 }
+
 #[test]
-fn test_table_s_string_03() {
-    assert_snapshot!(compile(r#"
-    s"""SELECT * FROM employees"""
-    filter country == "USA"
-    "#).unwrap(),
+// Confirm that a regular expr_call in a table definition works as expected.
+fn test_table_definition_with_expr_call() {
+    let query = r###"
+    let e = take 4 (from employees)
+    from e
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_snapshot!(sql,
         @r"
-    WITH table_0 AS (
+    WITH e AS (
       SELECT
         *
       FROM
         employees
+      LIMIT
+        4
     )
     SELECT
       *
     FROM
-      table_0
-    WHERE
-      country = 'USA'
+      e
     "
     );
 }
+
 #[test]
-fn test_table_s_string_04() {
+fn test_prql_to_sql_1() {
     assert_snapshot!(compile(r#"
-    s"""SELECT * FROM employees"""
-    select {e = this}
-    filter e.country == "USA"
-    "#).unwrap(),
-        @r"
-    WITH table_0 AS (
-      SELECT
-        *
-      FROM
-        employees
-    )
+    from employees
+    aggregate {
+        count salary,
+        sum salary,
+    }
+    "#).unwrap(), @r"
     SELECT
-      *
+      COUNT(salary),
+      COALESCE(SUM(salary), 0)
     FROM
-      table_0
-    WHERE
-      country = 'USA'
+      employees
     "
     );
-}
-#[test]
-fn test_table_s_string_05() {
     assert_snapshot!(compile(r#"
-    let weeks_between = start end -> s"SELECT generate_series({start}, {end}, '1 week') as date"
-    let current_week = -> s"date(date_trunc('week', current_date))"
-
-    weeks_between @2022-06-03 (current_week + 4)
-    "#).unwrap(),
-        @r"
-    WITH table_0 AS (
-      SELECT
-        generate_series(
-          DATE '2022-06-03',
-          date(date_trunc('week', current_date)) + 4,
-          '1 week'
-        ) as date
+    prql target:sql.postgres
+    from developers
+    group team (
+        aggregate {
+            skill_width = count_distinct specialty,
+        }
     )
+    "#).unwrap(), @r"
     SELECT
-      *
+      team,
+      COUNT(DISTINCT specialty) AS skill_width
     FROM
-      table_0
+      developers
+    GROUP BY
+      team
     "
-    );
+    )
 }
+
 #[test]
-fn test_table_s_string_06() {
-    assert_snapshot!(compile(r#"
-    s"SELECT * FROM {default_db.x}"
-    "#).unwrap(),
-        @r"
+#[ignore]
+fn test_prql_to_sql_2() {
+    let query = r#"
+from employees
+filter country == "USA"                           # Each line transforms the previous result.
+derive {                                         # This adds columns / variables.
+gross_salary = salary + payroll_tax,
+gross_cost = gross_salary + benefits_cost      # Variables can use other variables.
+}
+filter gross_cost > 0
+group {title, country} (
+aggregate  {                                 # `by` are the columns to group by.
+    average salary,                          # These are aggregation calcs run on each group.
+    sum     salary,
+    average gross_salary,
+    sum     gross_salary,
+    average gross_cost,
+    sum_gross_cost = sum gross_cost,
+    ct = count salary,
+}
+)
+sort sum_gross_cost
+filter ct > 200
+take 20
+"#;
+
+    let sql = compile(query).unwrap();
+    assert_snapshot!(sql, @r###"
     WITH table_0 AS (
       SELECT
-        *
+        title,
+        country,
+        salary + payroll_tax + benefits_cost AS _expr_0,
+        salary + payroll_tax AS _expr_1,
+        salary
       FROM
-        x
+        employees
+      WHERE
+        country = 'USA'
     )
     SELECT
-      *
+      title,
+      country,
+      AVG(salary),
+      COALESCE(SUM(salary), 0),
+      AVG(_expr_1),
+      COALESCE(SUM(_expr_1), 0),
+      AVG(_expr_0),
+      COALESCE(SUM(_expr_0), 0) AS sum_gross_cost,
+      COUNT(*) AS ct
     FROM
       table_0
-    "
-    );
-}
-
-#[test]
-fn test_direct_table_references() {
-    assert_snapshot!(compile(
-        r#"
-    from x
-    select s"{x}.field"
-    "#,
-    )
-    .unwrap_err(), @r#"
+    WHERE
+      _expr_0 > 0
+    GROUP BY
+      title,
+      country
+    HAVING
+      COUNT(*) > 200
+    ORDER BY
+      sum_gross_cost
+    LIMIT
+      20
+    "###);
+}
+
+#[test]
+fn test_prql_to_sql_table() {
+    // table
+    let query = r#"
+    let newest_employees = (
+        from employees
+        sort tenure
+        take 50
+    )
+    let average_salaries = (
+        from salaries
+        group country (
+            aggregate {
+                average_country_salary = average salary
+            }
+        )
+    )
+    from newest_employees
+    join average_salaries (==country)
+    select {name, salary, average_country_salary}
+    "#;
+    let sql = compile(query).unwrap();
+    assert_snapshot!(sql,
+        @r"
+    WITH newest_employees AS (
+      SELECT
+        *
+      FROM
+        employees
+      ORDER BY
+        tenure
+      LIMIT
+        50
+    ), average_salaries AS (
+      SELECT
+        country,
+        AVG(salary) AS average_country_salary
+      FROM
+        salaries
+      GROUP BY
+        country
+    )
+    SELECT
+      newest_employees.name,
+      newest_employees.salary,
+      average_salaries.average_country_salary
+    FROM
+      newest_employees
+      JOIN average_salaries ON newest_employees.country = average_salaries.country
+    ORDER BY
+      employees.tenure
+    "
+    );
+}
+
+#[test]
+fn test_nonatomic() {
+    // A take, then two aggregates
+    let query = r#"
+        from employees
+        take 20
+        filter country == "USA"
+        group {title, country} (
+            aggregate {
+                salary = average salary
+            }
+        )
+        group {title, country} (
+            aggregate {
+                sum_gross_cost = average salary
+            }
+        )
+        sort sum_gross_cost
+    "#;
+
+    assert_snapshot!((compile(query).unwrap()), @r"
+    WITH employees_0 AS (
+      SELECT
+        title,
+        country,
+        salary
+      FROM
+        employees
+      LIMIT
+        20
+    ), employees_1 AS (
+      SELECT
+        title,
+        country,
+        AVG(salary) AS _expr_0
+      FROM
+        employees_0
+      WHERE
+        country = 'USA'
+      GROUP BY
+        title,
+        country
+    )
+    SELECT
+      title,
+      country,
+      AVG(_expr_0) AS sum_gross_cost
+    FROM
+      employees_1
+    GROUP BY
+      title,
+      country
+    ORDER BY
+      sum_gross_cost
+    ");
+
+    // A aggregate, then sort and filter
+    let query = r###"
+        from employees
+        group {title, country} (
+            aggregate {
+                sum_gross_cost = average salary
+            }
+        )
+        sort sum_gross_cost
+        filter sum_gross_cost > 0
+    "###;
+
+    assert_snapshot!((compile(query).unwrap()), @r"
+    SELECT
+      title,
+      country,
+      AVG(salary) AS sum_gross_cost
+    FROM
+      employees
+    GROUP BY
+      title,
+      country
+    HAVING
+      AVG(salary) > 0
+    ORDER BY
+      sum_gross_cost
+    ");
+}
+
+#[test]
+/// Confirm a nonatomic table works.
+fn test_nonatomic_table() {
+    // A take, then two aggregates
+    let query = r#"
+    let a = (
+        from employees
+        take 50
+        group country (aggregate {s"count(*)"})
+    )
+    from a
+    join b (==country)
+    select {name, salary, average_country_salary}
+"#;
+
+    assert_snapshot!((compile(query).unwrap()), @r"
+    WITH employees_0 AS (
+      SELECT
+        country
+      FROM
+        employees
+      LIMIT
+        50
+    ), a AS (
+      SELECT
+        country,
+        count(*)
+      FROM
+        employees_0
+      GROUP BY
+        country
+    )
+    SELECT
+      b.name,
+      b.salary,
+      b.average_country_salary
+    FROM
+      a
+      JOIN b ON a.country = b.country
+    ");
+}
+
+#[test]
+fn test_table_names_between_splits_01() {
+    assert_snapshot!(compile(r###"
+    from employees
+    join d = department (==dept_no)
+    take 10
+    derive emp_no = employees.emp_no
+    join s = salaries (==emp_no)
+    select {employees.emp_no, d.name, s.salary}
+    "###).unwrap(), @r"
+    WITH employees_0 AS (
+      SELECT
+        employees.emp_no,
+        d.name
+      FROM
+        employees
+        JOIN department AS d ON employees.dept_no = d.dept_no
+      LIMIT
+        10
+    )
+    SELECT
+      employees_0.emp_no,
+      employees_0.name,
+      s.salary
+    FROM
+      employees_0
+      JOIN salaries AS s ON employees_0.emp_no = s.emp_no
+    ");
+}
+
+#[test]
+fn test_table_names_between_splits_02() {
+    assert_snapshot!(compile(r###"
+    from e = employees
+    take 10
+    join salaries (==emp_no)
+    select {e.*, salaries.salary}
+    "###).unwrap(), @r"
+    WITH employees_0 AS (
+      SELECT
+        *
+      FROM
+        employees AS e
+      LIMIT
+        10
+    )
+    SELECT
+      employees_0.*,
+      salaries.salary
+    FROM
+      employees_0
+      JOIN salaries ON employees_0.emp_no = salaries.emp_no
+    ");
+}
+
+#[test]
+fn test_table_alias_01() {
+    assert_snapshot!((compile(r###"
+    from e = employees
+    join salaries side:left (salaries.emp_no == e.emp_no)
+    group {e.emp_no} (
+        aggregate {
+            emp_salary = average salaries.salary
+        }
+    )
+    select {emp_no, emp_salary}
+    "###).unwrap()), @r"
+    SELECT
+      e.emp_no,
+      AVG(salaries.salary) AS emp_salary
+    FROM
+      employees AS e
+      LEFT JOIN salaries ON salaries.emp_no = e.emp_no
+    GROUP BY
+      e.emp_no
+    ");
+}
+
+#[test]
+fn test_table_alias_02() {
+    assert_snapshot!((compile(r#"
+    from e = employees
+    select e.first_name
+    filter e.first_name == "Fred"
+    "#).unwrap()), @r"
+    SELECT
+      first_name
+    FROM
+      employees AS e
+    WHERE
+      first_name = 'Fred'
+    ");
+}
+
+#[test]
+fn test_targets() {
+    // Generic
+    let query = r###"
+    prql target:sql.generic
+    from Employees
+    select {FirstName, `last name`}
+    take 3
+    "###;
+
+    assert_snapshot!((compile(query).unwrap()), @r#"
+    SELECT
+      "FirstName",
+      "last name"
+    FROM
+      "Employees"
+    LIMIT
+      3
+    "#);
+
+    // SQL server
+    let query = r###"
+    prql target:sql.mssql
+    from Employees
+    select {FirstName, `last name`}
+    take 3
+    "###;
+
+    assert_snapshot!((compile(query).unwrap()), @r#"
+    SELECT
+      "FirstName",
+      "last name"
+    FROM
+      "Employees"
+    ORDER BY
+      (
+        SELECT
+          NULL
+      ) OFFSET 0 ROWS
+    FETCH FIRST
+      3 ROWS ONLY
+    "#);
+
+    // MySQL
+    let query = r###"
+    prql target:sql.mysql
+    from Employees
+    select {FirstName, `last name`}
+    take 3
+    "###;
+
+    assert_snapshot!((compile(query).unwrap()), @r"
+    SELECT
+      `FirstName`,
+      `last name`
+    FROM
+      `Employees`
+    LIMIT
+      3
+    ");
+}
+
+#[test]
+fn test_target_clickhouse() {
+    let query = r###"
+    prql target:sql.clickhouse
+
+    from github_json
+    derive {event_type_dotted = `event.type`}
+    "###;
+
+    assert_snapshot!((compile(query).unwrap()), @r"
+    SELECT
+      *,
+      `event.type` AS event_type_dotted
+    FROM
+      github_json
+    ");
+}
+
+#[test]
+fn test_ident_escaping() {
+    // Generic
+    let query = r#"
+    from `anim"ls`
+    derive {`čebela` = BeeName, medved = `bear's_name`}
+    "#;
+
+    assert_snapshot!((compile(query).unwrap()), @r#"
+    SELECT
+      *,
+      "BeeName" AS "čebela",
+      "bear's_name" AS medved
+    FROM
+      "anim""ls"
+    "#);
+
+    // MySQL
+    let query = r#"
+    prql target:sql.mysql
+
+    from `anim"ls`
+    derive {`čebela` = BeeName, medved = `bear's_name`}
+    "#;
+
+    assert_snapshot!((compile(query).unwrap()), @r#"
+    SELECT
+      *,
+      `BeeName` AS `čebela`,
+      `bear's_name` AS medved
+    FROM
+      `anim"ls`
+    "#);
+}
+
+#[test]
+fn test_literal() {
+    let query = r###"
+    from employees
+    derive {always_true = true}
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_snapshot!(sql,
+        @r"
+    SELECT
+      *,
+      true AS always_true
+    FROM
+      employees
+    "
+    );
+}
+
+#[test]
+fn test_same_column_names() {
+    // #820
+    let query = r###"
+let x = (
+from x_table
+select only_in_x = foo
+)
+
+let y = (
+from y_table
+select foo
+)
+
+from x
+join y (foo == only_in_x)
+"###;
+
+    assert_snapshot!(compile(query).unwrap(),
+        @r"
+    WITH x AS (
+      SELECT
+        foo AS only_in_x
+      FROM
+        x_table
+    ),
+    y AS (
+      SELECT
+        foo
+      FROM
+        y_table
+    )
+    SELECT
+      x.only_in_x,
+      y.foo
+    FROM
+      x
+      JOIN y ON y.foo = x.only_in_x
+    "
+    );
+}
+
+#[test]
+fn test_double_aggregate() {
+    // #941
+    compile(
+        r###"
+    from numbers
+    group {type} (
+        aggregate {
+            total_amt = sum amount,
+        }
+        aggregate {
+            max amount
+        }
+    )
+    "###,
+    )
+    .unwrap_err();
+
+    assert_snapshot!(compile(r###"
+    from numbers
+    group {`type`} (
+        aggregate {
+            total_amt = sum amount,
+            max amount
+        }
+    )
+    "###).unwrap(),
+        @r"
+    SELECT
+      type,
+      COALESCE(SUM(amount), 0) AS total_amt,
+      MAX(amount)
+    FROM
+      numbers
+    GROUP BY
+      type
+    "
+    );
+}
+
+#[test]
+fn test_window_function_coalesce() {
+    // #3587
+    assert_snapshot!(compile(r###"
+    from x
+    select {a, b=a}
+    window (
+      select {
+        cumsum_a=(sum a),
+        cumsum_b=(sum b)
+      }
+    )
+    "###).unwrap(),
+        @r"
+    SELECT
+      SUM(a) OVER () AS cumsum_a,
+      SUM(a) OVER () AS cumsum_b
+    FROM
+      x
+    "
+    );
+}
+
+#[test]
+fn test_coalesce_nested() {
+    // nested coalescing is flattened into a single COALESCE
+    assert_snapshot!(compile(r#"
+    from x
+    select {
+        a = mobile ?? home ?? work,
+        b = coalesce (coalesce mobile home) (work ?? ""),
+        c = (mobile ?? home) + (work ?? home),
+    }
+    "#).unwrap(), @r"
+    SELECT
+      COALESCE(mobile, home, work) AS a,
+      COALESCE(mobile, home, work, '') AS b,
+      COALESCE(mobile, home) + COALESCE(work, home) AS c
+    FROM
+      x
+    ");
+}
+
+#[test]
+fn test_casting() {
+    assert_snapshot!(compile(r###"
+    from x
+    select {a}
+    derive {
+        b = (a | as int) + 10,
+        c = (a | as int) - 10,
+        d = (a | as float) * 10,
+        e = (a | as float) / 10,
+    }
+    "###).unwrap(),
+        @r"
+    SELECT
+      a,
+      CAST(a AS int) + 10 AS b,
+      CAST(a AS int) - 10 AS c,
+      CAST(a AS float) * 10 AS d,
+      CAST(a AS float) / 10 AS e
+    FROM
+      x
+    "
+    );
+}
+
+#[test]
+fn test_cast_to_bool() {
+    let query = r#"
+    from t
+    filter (flag | as bool)
+    derive {is_active = (active | as bool)}
+    "#;
+
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    SELECT
+      *,
+      CAST(active AS BOOLEAN) AS is_active
+    FROM
+      t
+    WHERE
+      CAST(flag AS BOOLEAN)
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::BigQuery).unwrap(), @r"
+    SELECT
+      *,
+      CAST(active AS BOOL) AS is_active
+    FROM
+      t
+    WHERE
+      CAST(flag AS BOOL)
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::MsSql).unwrap(), @r"
+    SELECT
+      *,
+      active <> 0 AS is_active
+    FROM
+      t
+    WHERE
+      flag <> 0
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::MySql).unwrap(), @r"
+    SELECT
+      *,
+      active <> 0 AS is_active
+    FROM
+      t
+    WHERE
+      flag <> 0
+    ");
+}
+
+#[test]
+fn test_bare_bool_conditions() {
+    let query = r#"
+    from t
+    filter is_active
+    filter (!is_deleted || flag)
+    derive {rank = case [is_admin => 1, true => 2]}
+    join u (t.is_active && t.id == u.id)
+    "#;
+
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    WITH t_0 AS (
+      SELECT
+        *,
+        CASE
+          WHEN is_admin THEN 1
+          ELSE 2
+        END AS rank
+      FROM
+        t
+      WHERE
+        is_active
+        AND (
+          NOT is_deleted
+          OR flag
+        )
+    )
+    SELECT
+      t_0.*,
+      u.*
+    FROM
+      t_0
+      JOIN u ON t_0.is_active
+      AND t_0.id = u.id
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::MsSql).unwrap(), @r"
+    WITH t_0 AS (
+      SELECT
+        *,
+        CASE
+          WHEN is_admin = 1 THEN 1
+          ELSE 2
+        END AS rank
+      FROM
+        t
+      WHERE
+        is_active = 1
+        AND (
+          NOT is_deleted = 1
+          OR flag = 1
+        )
+    )
+    SELECT
+      t_0.*,
+      u.*
+    FROM
+      t_0
+      JOIN u ON t_0.is_active = 1
+      AND t_0.id = u.id
+    ");
+}
+
+#[test]
+fn test_cast_type_alias() {
+    assert_snapshot!(compile(r###"
+    type PositiveInt = int
+    type Id = PositiveInt
+
+    from x
+    select {
+        a = (a | as PositiveInt),
+        b = (b | as Id),
+        c = (c | as BIGINT),
+    }
+    "###).unwrap(),
+        @r"
+    SELECT
+      CAST(a AS int) AS a,
+      CAST(b AS int) AS b,
+      CAST(c AS BIGINT) AS c
+    FROM
+      x
+    "
+    );
+}
+
+#[test]
+fn test_sstring_ident() {
+    let query = r###"
+    let table_name = "my table"
+
+    from t
+    select {
+        a = s"UPPER({ident:`a b`})",
+        b = s"(SELECT COUNT(*) FROM {ident:table_name})",
+    }
+    "###;
+
+    assert_snapshot!(compile(query).unwrap(), @r#"
+    SELECT
+      UPPER("a b") AS a,
+      (
+        SELECT
+          COUNT(*)
+        FROM
+          "my table"
+      ) AS b
+    FROM
+      t
+    "#);
+
+    assert_snapshot!(compile(&format!("prql target:sql.mysql\n{query}")).unwrap(), @r"
+    SELECT
+      UPPER(`a b`) AS a,
+      (
+        SELECT
+          COUNT(*)
+        FROM
+          `my table`
+      ) AS b
+    FROM
+      t
+    ");
+
+    assert_snapshot!(compile(r###"
+    from t
+    derive {b = a + 1, c = s"{ident:b}"}
+    "###).unwrap_err(), @r#"
+    Error:
+       ╭─[:3:37]
+       │
+     3 │     derive {b = a + 1, c = s"{ident:b}"}
+       │                                     ┬
+       │                                     ╰── an `ident:` interpolation must refer to a column or a name
+    ───╯
+    "#);
+
+    // a variable named `ident` is still interpolated as a value
+    assert_snapshot!(compile(r###"
+    from t
+    select {ident, identity}
+    derive {b = s"{ident} + {identity}", c = s"{this.ident}"}
+    "###).unwrap(), @r"
+    SELECT
+      ident,
+      identity,
+      ident + identity AS b,
+      ident AS c
+    FROM
+      t
+    ");
+}
+
+#[test]
+fn test_sstring_declared_columns() {
+    assert_snapshot!(compile(r#"
+    from a
+    join (s"SELECT e, f, g FROM other"):{e, f, g} (==e)
+    select {a.x, f}
+    group f (aggregate {n = count this})
+    "#).unwrap(), @r"
+    WITH table_0 AS (
+      SELECT
+        e,
+        f,
+        g
+      FROM
+        other
+    )
+    SELECT
+      table_0.f,
+      COUNT(*) AS n
+    FROM
+      a
+      JOIN table_0 ON a.e = table_0.e
+    GROUP BY
+      table_0.f
+    ");
+
+    // only the declared columns are available
+    assert_snapshot!(compile(r#"
+    from o = (s"SELECT e, f FROM other"):{e, f}
+    select {e, g}
+    "#).unwrap_err(), @r"
+    Error:
+       ╭─[:3:16]
+       │
+     3 │     select {e, g}
+       │                ┬
+       │                ╰── Unknown name `g`
+       │
+       │ Help: available columns: o.e, o.f
+    ───╯
+    ");
+}
+
+#[test]
+fn test_toposort() {
+    // #1183
+
+    assert_snapshot!(compile(r###"
+    let b = (
+        from somesource
+    )
+
+    let a = (
+        from b
+    )
+
+    from b
+    "###).unwrap(),
+        @r"
+    WITH b AS (
+      SELECT
+        *
+      FROM
+        somesource
+    )
+    SELECT
+      *
+    FROM
+      b
+    "
+    );
+}
+
+#[test]
+fn test_inline_tables() {
+    assert_snapshot!(compile(r###"
+    (
+        from employees
+        select {emp_id, name, surname, `type`, amount}
+    )
+    join s = (from salaries | select {emp_id, salary}) (==emp_id)
+    "###).unwrap(),
+        @r"
+    WITH salaries_0 AS (
+      SELECT
+        emp_id,
+        salary
+      FROM
+        salaries
+    )
+    SELECT
+      employees.emp_id,
+      employees.name,
+      employees.surname,
+      employees.type,
+      employees.amount,
+      salaries_0.emp_id,
+      salaries_0.salary
+    FROM
+      employees
+      JOIN salaries_0 ON employees.emp_id = salaries_0.emp_id
+    "
+    );
+}
+
+#[test]
+fn test_filter_and_select_unchanged_alias() {
+    // #1185
+
+    assert_snapshot!(compile(r###"
+    from account
+    filter account.name != null
+    select {name = account.name}
+    "###).unwrap(),
+        @r"
+    SELECT
+      name
+    FROM
+      account
+    WHERE
+      name IS NOT NULL
+    "
+    );
+}
+
+#[test]
+fn test_filter_and_select_changed_alias() {
+    // #1185
+    assert_snapshot!(compile(r###"
+    from account
+    filter account.name != null
+    select {renamed_name = account.name}
+    "###).unwrap(),
+        @r"
+    SELECT
+      name AS renamed_name
+    FROM
+      account
+    WHERE
+      name IS NOT NULL
+    "
+    );
+
+    // #1207
+    assert_snapshot!(compile(r#"
+    from x
+    filter name != "Bob"
+    select name = name ?? "Default"
+    "#).unwrap(),
+        @r"
+    SELECT
+      COALESCE(name, 'Default') AS name
+    FROM
+      x
+    WHERE
+      name <> 'Bob'
+    "
+    );
+}
+
+#[test]
+fn test_unused_alias() {
+    // #1308
+    assert_snapshot!(compile(r###"
+    from account
+    select n = {account.name}
+    "###).unwrap_err(), @r"
+    Error:
+       ╭─[:3:16]
+       │
+     3 │     select n = {account.name}
+       │                ───────┬──────
+       │                       ╰──────── unexpected assign to `n`
+       │
+       │ Help: move assign into the tuple: `[n = ...]`
+    ───╯
+    ")
+}
+
+#[test]
+fn test_table_s_string_01() {
+    assert_snapshot!(compile(r#"
+    let main <relation> = s"SELECT DISTINCT ON first_name, age FROM employees ORDER BY age ASC"
+    "#).unwrap(),
+        @r"
+    WITH table_0 AS (
+      SELECT
+        DISTINCT ON first_name,
+        age
+      FROM
+        employees
+      ORDER BY
+        age ASC
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    "
+    );
+}
+#[test]
+fn test_table_s_string_02() {
+    assert_snapshot!(compile(r#"
+    s"""
+        SELECT DISTINCT ON first_name, id, age FROM employees ORDER BY age ASC
+    """
+    join s = s"SELECT * FROM salaries" (==id)
+    "#).unwrap(),
+        @r"
+    WITH table_0 AS (
+      SELECT
+        DISTINCT ON first_name,
+        id,
+        age
+      FROM
+        employees
+      ORDER BY
+        age ASC
+    ),
+    table_1 AS (
+      SELECT
+        *
+      FROM
+        salaries
+    )
+    SELECT
+      table_0.*,
+      table_1.*
+    FROM
+      table_0
+      JOIN table_1 ON table_0.id = table_1.id
+    "
+    );
+}
+#[test]
+fn test_table_s_string_03() {
+    assert_snapshot!(compile(r#"
+    s"""SELECT * FROM employees"""
+    filter country == "USA"
+    "#).unwrap(),
+        @r"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        employees
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    WHERE
+      country = 'USA'
+    "
+    );
+}
+#[test]
+fn test_table_s_string_04() {
+    assert_snapshot!(compile(r#"
+    s"""SELECT * FROM employees"""
+    select {e = this}
+    filter e.country == "USA"
+    "#).unwrap(),
+        @r"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        employees
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    WHERE
+      country = 'USA'
+    "
+    );
+}
+#[test]
+fn test_table_s_string_05() {
+    assert_snapshot!(compile(r#"
+    let weeks_between = start end -> s"SELECT generate_series({start}, {end}, '1 week') as date"
+    let current_week = -> s"date(date_trunc('week', current_date))"
+
+    weeks_between @2022-06-03 (current_week + 4)
+    "#).unwrap(),
+        @r"
+    WITH table_0 AS (
+      SELECT
+        generate_series(
+          DATE '2022-06-03',
+          date(date_trunc('week', current_date)) + 4,
+          '1 week'
+        ) as date
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    "
+    );
+}
+#[test]
+fn test_table_s_string_06() {
+    assert_snapshot!(compile(r#"
+    s"SELECT * FROM {default_db.x}"
+    "#).unwrap(),
+        @r"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        x
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    "
+    );
+}
+
+#[test]
+fn test_direct_table_references() {
+    assert_snapshot!(compile(
+        r#"
+    from x
+    select s"{x}.field"
+    "#,
+    )
+    .unwrap_err(), @r#"
     Error:
        ╭─[:3:15]
        │
@@ -3809,194 +5803,1253 @@ fn test_direct_table_references() {
     ───╯
     "#);
 
-    assert_snapshot!(compile(
-        r###"
+    assert_snapshot!(compile(
+        r###"
+    from x
+    select x
+    "###,
+    )
+    .unwrap(), @r"
+    SELECT
+      *
+    FROM
+      x
+    ");
+}
+
+#[test]
+fn test_name_shadowing() {
+    assert_snapshot!(compile(
+        r###"
+    from x
+    select {a, a, a = a + 1}
+    "###).unwrap(),
+        @r"
+    SELECT
+      a AS _expr_0,
+      a AS _expr_0,
+      a + 1 AS a
+    FROM
+      x
+    "
+    );
+
+    assert_snapshot!(compile(
+        r###"
+    from x
+    select a
+    derive a
+    derive a = a + 1
+    derive a = a + 2
+    "###).unwrap(),
+        @r"
+    SELECT
+      a AS _expr_0,
+      a AS _expr_0,
+      a + 1,
+      a + 1 + 2 AS a
+    FROM
+      x
+    "
+    );
+}
+
+#[test]
+fn test_group_all() {
+    assert_snapshot!(compile(
+        r###"
+    prql target:sql.sqlite
+
+    from a=albums
+    group a.* (aggregate {count this})
+        "###).unwrap_err(), @"Error: Target dialect does not support * in this position.");
+
+    assert_snapshot!(compile(
+        r###"
+    from e=albums
+    group !{genre_id} (aggregate {count this})
+        "###).unwrap_err(), @"Error: Excluding columns not supported as this position");
+}
+
+#[test]
+fn test_group_key_in_aggregate() {
+    assert_snapshot!(compile(r#"
+    from sales
+    select {year, amount}
+    group {year} (aggregate {label = f"{year}-total", n = count this})
+    "#).unwrap(), @r"
+    SELECT
+      year,
+      CONCAT(year, '-total') AS label,
+      COUNT(*) AS n
+    FROM
+      sales
+    GROUP BY
+      year
+    ");
+
+    // an aliased key is referenced by its expression, not by its alias
+    assert_snapshot!(compile(r#"
+    from sales
+    select {year, amount}
+    group {decade = year / 10} (aggregate {label = decade * 10, total = sum amount})
+    "#).unwrap(), @r"
+    SELECT
+      year / 10 AS decade,
+      year / 10 * 10 AS label,
+      COALESCE(SUM(amount), 0) AS total
+    FROM
+      sales
+    GROUP BY
+      year / 10
+    ");
+}
+
+#[test]
+fn test_output_column_deduplication() {
+    // #1249
+    assert_snapshot!(compile(
+        r#"
+    from report
+    derive r = s"RANK() OVER ()"
+    filter r == 1
+        "#).unwrap(),
+        @r"
+    WITH report_0 AS (
+      SELECT
+        *,
+        RANK() OVER () AS r
+      FROM
+        report
+    )
+    SELECT
+      *
+    FROM
+      report_0
+    WHERE
+      r = 1
+    "
+    );
+}
+
+#[test]
+fn test_case_01() {
+    assert_snapshot!(compile(
+        r###"
+    from employees
+    derive display_name = case [
+        nickname != null => nickname,
+        true => f'{first_name} {last_name}'
+    ]
+        "###).unwrap(),
+        @r"
+    SELECT
+      *,
+      CASE
+        WHEN nickname IS NOT NULL THEN nickname
+        ELSE CONCAT(first_name, ' ', last_name)
+      END AS display_name
+    FROM
+      employees
+    "
+    );
+}
+
+#[test]
+fn test_case_02() {
+    assert_snapshot!(compile(
+        r###"
+    from employees
+    derive display_name = case [
+        nickname != null => nickname,
+        first_name != null => f'{first_name} {last_name}'
+    ]
+        "###).unwrap(),
+        @r"
+    SELECT
+      *,
+      CASE
+        WHEN nickname IS NOT NULL THEN nickname
+        WHEN first_name IS NOT NULL THEN CONCAT(first_name, ' ', last_name)
+        ELSE NULL
+      END AS display_name
+    FROM
+      employees
+    "
+    );
+}
+
+#[test]
+fn test_case_03() {
+    assert_snapshot!(compile(
+        r###"
+    from tracks
+    select category = case [
+        length > avg_length => 'long'
+    ]
+    group category (aggregate {count this})
+        "###).unwrap(),
+        @r"
+    WITH tracks_0 AS (
+      SELECT
+        CASE
+          WHEN length > avg_length THEN 'long'
+          ELSE NULL
+        END AS category,
+        length,
+        avg_length
+      FROM
+        tracks
+    )
+    SELECT
+      category,
+      COUNT(*)
+    FROM
+      tracks_0
+    GROUP BY
+      category
+    "
+    );
+}
+
+#[test]
+fn test_case_value() {
+    assert_snapshot!(compile(r#"
+    from tracks
+    derive {
+        size = case bytes / 1000000 [
+            0 => "small",
+            1 => "medium",
+            null => "unknown",
+            _ => "large",
+        ],
+        first_genre = case genre_id [1 => "rock"],
+    }
+    "#).unwrap(), @r"
+    SELECT
+      *,
+      CASE
+        WHEN bytes / 1000000 = 0 THEN 'small'
+        WHEN bytes / 1000000 = 1 THEN 'medium'
+        WHEN bytes / 1000000 IS NULL THEN 'unknown'
+        ELSE 'large'
+      END AS size,
+      CASE
+        WHEN genre_id = 1 THEN 'rock'
+        ELSE NULL
+      END AS first_genre
+    FROM
+      tracks
+    ");
+}
+
+#[test]
+fn test_tuple_spread() {
+    assert_snapshot!(compile(r#"
+    let metrics = x -> {total = x * 2, half = x / 2}
+
+    from invoices
+    select {invoice_id, ...metrics amount}
+    derive {...{taxed = total * 1.2}}
+    "#).unwrap(), @r"
+    SELECT
+      invoice_id,
+      amount * 2 AS total,
+      amount / 2 AS half,
+      amount * 2 * 1.2 AS taxed
+    FROM
+      invoices
+    ");
+}
+
+#[test]
+fn test_case_mixed_numeric_types() {
+    let query = r#"
+    from tracks
+    derive half_length = length / 2
+    derive weight = case [
+        length > 300 => 1.5,
+        length > 200 => half_length,
+        length > 100 => milliseconds,
+        true => -1,
+    ]
+    "#;
+
+    assert_snapshot!(compile(&format!("prql target:sql.clickhouse\n{query}")).unwrap(), @r"
+    SELECT
+      *,
+      (length / 2) AS half_length,
+      CASE
+        WHEN length > 300 THEN 1.5
+        WHEN length > 200 THEN (length / 2)
+        WHEN length > 100 THEN milliseconds
+        ELSE CAST(-1 AS Float64)
+      END AS weight
+    FROM
+      tracks
+    ");
+
+    // most databases find a common type themselves
+    assert_snapshot!(compile(query).unwrap(), @r"
+    SELECT
+      *,
+      length / 2 AS half_length,
+      CASE
+        WHEN length > 300 THEN 1.5
+        WHEN length > 200 THEN length / 2
+        WHEN length > 100 THEN milliseconds
+        ELSE -1
+      END AS weight
+    FROM
+      tracks
+    ");
+}
+
+#[test]
+fn test_explicit_null_cast() {
+    let compile_null_cast = |target: &str, query: &str| {
+        let options = Options::default()
+            .no_signature()
+            .no_format()
+            .with_explicit_null_cast(true);
+        prqlc::compile(&format!("prql target:sql.{target}\n{query}"), &options).unwrap()
+    };
+
+    let query = "from tracks | derive {tier = case [length > 300 => 2, length > 100 => 1]}";
+    assert_snapshot!(compile_null_cast("generic", query), @r"
+    SELECT *, CASE WHEN length > 300 THEN 2 WHEN length > 100 THEN 1 ELSE CAST(NULL AS INTEGER) END AS tier FROM tracks
+    ");
+    assert_snapshot!(compile_null_cast("mysql", query), @r"
+    SELECT *, CASE WHEN length > 300 THEN 2 WHEN length > 100 THEN 1 ELSE CAST(NULL AS SIGNED) END AS tier FROM tracks
+    ");
+
+    let query = "from tracks | derive {rate = case [length > 0 => null, true => price / length]}";
+    assert_snapshot!(compile_null_cast("bigquery", query), @r"
+    SELECT *, CASE WHEN length > 0 THEN CAST(NULL AS FLOAT64) ELSE (price * 1.0 / length) END AS rate FROM tracks
+    ");
+
+    // the type of a column is not known
+    let query = "from tracks | derive {name = case [length > 0 => title]}";
+    assert_snapshot!(compile_null_cast("generic", query), @r"
+    SELECT *, CASE WHEN length > 0 THEN title ELSE NULL END AS name FROM tracks
+    ");
+}
+
+#[test]
+fn test_sql_options() {
+    let options = Options::default();
+    let sql = prqlc::compile("from x", &options).unwrap();
+
+    assert!(sql.contains('\n'));
+    assert!(sql.contains("-- Generated by"));
+
+    let options = Options::default().no_signature().no_format();
+    let sql = prqlc::compile("from x", &options).unwrap();
+
+    assert!(!sql.contains('\n'));
+    assert!(!sql.contains("-- Generated by"));
+}
+
+#[test]
+fn test_signature_comment_target() {
+    let version = prqlc::compiler_version();
+
+    // the target of the query header
+    let sql = prqlc::compile("prql target:sql.postgres\nfrom x", &Options::default()).unwrap();
+    assert!(sql.ends_with(&format!(
+        "-- Generated by PRQL compiler version:{version} target:sql.postgres (https://prql-lang.org)\n"
+    )));
+
+    // the target of options
+    let options = Options::default().with_target(Target::Sql(Some(sql::Dialect::DuckDb)));
+    let sql = prqlc::compile("from x", &options).unwrap();
+    assert!(sql.contains(&format!("version:{version} target:sql.duckdb ")));
+
+    // no target
+    let sql = prqlc::compile("from x", &Options::default()).unwrap();
+    assert!(sql.contains(&format!("version:{version} (https://prql-lang.org)")));
+
+    let options = Options::default().no_signature();
+    let sql = prqlc::compile("prql target:sql.postgres\nfrom x", &options).unwrap();
+    assert!(!sql.contains("-- Generated by"));
+}
+
+#[test]
+fn test_signature_text() {
+    let query = "from x | take 1";
+
+    let options = Options::default().with_signature_text("Generated for ticket ABC-123");
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      *
+    FROM
+      x
+    LIMIT
+      1
+
+    -- Generated for ticket ABC-123
+    ");
+
+    // each line is a comment
+    let options = Options::default()
+        .no_format()
+        .with_signature_text("Generated for ticket ABC-123\nat {timestamp}");
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT * FROM x LIMIT 1 -- Generated for ticket ABC-123
+    -- at {timestamp}
+    ");
+
+    let options = Options::default().with_signature_text("");
+    assert!(!prqlc::compile(query, &options).unwrap().contains("--"));
+
+    let options = Options::default()
+        .no_signature()
+        .with_signature_text("Generated for ticket ABC-123");
+    assert!(!prqlc::compile(query, &options).unwrap().contains("--"));
+
+    // without a text, the signature contains the compiler version
+    let sql = prqlc::compile(query, &Options::default()).unwrap();
+    assert!(sql.ends_with(&format!(
+        "-- Generated by PRQL compiler version:{} (https://prql-lang.org)\n",
+        prqlc::compiler_version()
+    )));
+}
+
+#[test]
+fn test_output_encoding_ascii() {
+    let compile_ascii = |prql: &str| {
+        let options = Options::default()
+            .no_signature()
+            .with_output_encoding(OutputEncoding::Ascii);
+        prqlc::compile(prql, &options).unwrap()
+    };
+
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.postgres
+    from x
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = U&'caf\00E9 \+01F600 ''it'' \\ x'
+    ");
+
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.bigquery
+    from x
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = 'caf\u00E9 \U0001F600 ''it'' \\ x'
+    ");
+
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.sqlite
+    from x
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = (
+        'caf' || CHAR(233) || ' ' || CHAR(128512) || ' ''it'' \ x'
+      )
+    ");
+
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.duckdb
+    from x
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = (
+        'caf' || CHR(233) || ' ' || CHR(128512) || ' ''it'' \ x'
+      )
+    ");
+
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.mysql
+    from x
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = CONCAT(
+        'caf',
+        CONVERT(UNHEX('C3A9') USING utf8mb4),
+        ' ',
+        CONVERT(UNHEX('F09F9880') USING utf8mb4),
+        ' ''it'' \ x'
+      )
+    ");
+
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.mssql
+    from x
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = (
+        'caf' + NCHAR(233) + ' ' + NCHAR(55357) + NCHAR(56832) + ' ''it'' \ x'
+      )
+    ");
+
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.oracle
     from x
-    select x
-    "###,
-    )
-    .unwrap(), @r"
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
     SELECT
       *
     FROM
       x
+    WHERE
+      name = UNISTR('caf\00E9 \D83D\DE00 ''it'' \\ x')
     ");
-}
 
-#[test]
-fn test_name_shadowing() {
-    assert_snapshot!(compile(
-        r###"
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.clickhouse
     from x
-    select {a, a, a = a + 1}
-    "###).unwrap(),
-        @r"
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
     SELECT
-      a AS _expr_0,
-      a AS _expr_0,
-      a + 1 AS a
+      *
     FROM
       x
-    "
-    );
+    WHERE
+      name = 'caf\xC3\xA9 \xF0\x9F\x98\x80 ''it'' \\ x'
+    ");
 
-    assert_snapshot!(compile(
-        r###"
+    assert_snapshot!(compile_ascii(r#"
+    prql target:sql.snowflake
     from x
-    select a
-    derive a
-    derive a = a + 1
-    derive a = a + 2
-    "###).unwrap(),
-        @r"
+    filter name == "caf\u{e9} \u{1F600} 'it' \\ x"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = (
+        'caf' || CHR(233) || ' ' || CHR(128512) || ' ''it'' \ x'
+      )
+    ");
+
+    // ASCII strings are not affected
+    assert_snapshot!(compile_ascii(r#"
+    from x
+    filter name == "cafe"
+    "#), @r"
+    SELECT
+      *
+    FROM
+      x
+    WHERE
+      name = 'cafe'
+    ");
+}
+
+#[test]
+fn test_date_format() {
+    let query = r#"
+    from events
+    filter (created_at | in @2024-01-01..@2024-12-31)
+    derive {since = @2024-01-01T10:30}
+    "#;
+
+    let options = Options::default()
+        .no_signature()
+        .with_date_format("%d.%m.%Y");
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      *,
+      TIMESTAMP '2024-01-01T10:30' AS since
+    FROM
+      events
+    WHERE
+      created_at BETWEEN DATE '01.01.2024' AND DATE '31.12.2024'
+    ");
+
+    // ISO-8601 by default
+    assert_snapshot!(compile(query).unwrap(), @r"
+    SELECT
+      *,
+      TIMESTAMP '2024-01-01T10:30' AS since
+    FROM
+      events
+    WHERE
+      created_at BETWEEN DATE '2024-01-01' AND DATE '2024-12-31'
+    ");
+
+    let options = Options::default().with_date_format("%H:%M");
+    assert_snapshot!(prqlc::compile(query, &options).unwrap_err(), @"Error: invalid date format `%H:%M`");
+}
+
+#[test]
+fn test_trailing_semicolon() {
+    let query = "from tracks | take 3";
+
+    let options = Options::default()
+        .no_signature()
+        .with_trailing_semicolon(true);
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      *
+    FROM
+      tracks
+    LIMIT
+      3;
+    ");
+
+    let options = options.no_format();
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT * FROM tracks LIMIT 3;
+    ");
+
+    // the semicolon precedes the signature comment
+    let version = prqlc::compiler_version().to_string();
+    let options = Options::default().with_trailing_semicolon(true);
+    let sql = prqlc::compile(query, &options).unwrap();
+    assert_snapshot!(sql.replace(&version, "<version>"), @r"
+    SELECT
+      *
+    FROM
+      tracks
+    LIMIT
+      3;
+
+    -- Generated by PRQL compiler version:<version> (https://prql-lang.org)
+    ");
+
+    let options = options.no_format();
+    let sql = prqlc::compile(query, &options).unwrap();
+    assert_snapshot!(sql.replace(&version, "<version>"), @r"
+    SELECT * FROM tracks LIMIT 3; -- Generated by PRQL compiler version:<version> (https://prql-lang.org)
+    ");
+}
+
+#[test]
+fn test_leading_comma() {
+    let query = r#"
+    from tracks
+    derive {label = "a,\nb"}
+    group {album_id, genre_id} (aggregate {total = sum milliseconds, label = min label})
+    take 10
+    select {album_id, genre_id, total, label}
+    sort {album_id, -total}
+    "#;
+
+    let options = Options::default().no_signature().with_leading_comma(true);
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    WITH tracks_0 AS (
+      SELECT
+        album_id
+        , genre_id
+        , COALESCE(SUM(milliseconds), 0) AS total
+        , MIN('a,
+    b') AS label
+      FROM
+        tracks
+      GROUP BY
+        album_id
+        , genre_id
+      LIMIT
+        10
+    )
+    SELECT
+      album_id
+      , genre_id
+      , total
+      , label
+    FROM
+      tracks_0
+    ORDER BY
+      album_id
+      , total DESC
+    ");
+}
+
+#[test]
+fn test_compile_named_main() {
+    let query = r#"
+    let monthly_report = (
+      from invoices
+      group {month = invoice_date} (aggregate {total = sum amount})
+    )
+
+    let customers_by_city = (from customers | select {city, name})
+
+    from monthly_report
+    take 5
+    "#;
+
+    let options = Options::default()
+        .no_signature()
+        .with_main("monthly_report");
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      invoice_date AS month,
+      COALESCE(SUM(amount), 0) AS total
+    FROM
+      invoices
+    GROUP BY
+      invoice_date
+    ");
+
+    let options = Options::default()
+        .no_signature()
+        .with_main("customers_by_city");
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      city,
+      name
+    FROM
+      customers
+    ");
+
+    let options = Options::default().no_signature().with_main("yearly_report");
+    assert_snapshot!(prqlc::compile(query, &options).unwrap_err(), @r"
+    [E0001] Error: Missing main pipeline
+    ↳ Hint: Expected a declaration at yearly_report or yearly_report.main
+    ");
+}
+
+#[test]
+fn test_aggregate_null_handling() {
+    let query = r#"
+    from invoices
+    group customer_id (
+      aggregate {total = sum amount, mean = average amount, paid = all is_paid}
+    )
+    "#;
+
+    let options = Options::default().no_signature();
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      customer_id,
+      COALESCE(SUM(amount), 0) AS total,
+      AVG(amount) AS mean,
+      COALESCE(BOOL_AND(is_paid), TRUE) AS paid
+    FROM
+      invoices
+    GROUP BY
+      customer_id
+    ");
+
+    let options = options.with_aggregate_null_handling(AggregateNullHandling::Null);
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    SELECT
+      customer_id,
+      SUM(amount) AS total,
+      AVG(amount) AS mean,
+      BOOL_AND(is_paid) AS paid
+    FROM
+      invoices
+    GROUP BY
+      customer_id
+    ");
+}
+
+#[test]
+fn test_join_using() {
+    let compile_using = |prql: &str| {
+        let options = Options::default().no_signature().with_join_using(true);
+        prqlc::compile(prql, &options).unwrap()
+    };
+
+    assert_snapshot!(compile_using(r#"
+    from employees
+    join side:left departments (==department_id)
+    select {employees.name, departments.title}
+    "#), @r"
+    SELECT
+      employees.name,
+      departments.title
+    FROM
+      employees
+      LEFT JOIN departments USING(department_id)
+    ");
+
+    // not a single equality of equally named columns
+    assert_snapshot!(compile_using(r#"
+    from e = employees
+    join d = departments (e.dept_id == d.department_id)
+    "#), @r"
+    SELECT
+      e.*,
+      d.*
+    FROM
+      employees AS e
+      JOIN departments AS d ON e.dept_id = d.department_id
+    ");
+    assert_snapshot!(compile_using(r#"
+    from employees
+    join departments (==department_id && departments.is_active)
+    "#), @r"
+    SELECT
+      employees.*,
+      departments.*
+    FROM
+      employees
+      JOIN departments ON employees.department_id = departments.department_id
+      AND departments.is_active
+    ");
+
+    // the second join has both preceding relations on its left
+    assert_snapshot!(compile_using(r#"
+    from employees
+    join departments (==department_id)
+    join locations (employees.location_id == locations.location_id)
+    "#), @r"
+    SELECT
+      employees.*,
+      departments.*,
+      locations.*
+    FROM
+      employees
+      JOIN departments USING(department_id)
+      JOIN locations ON employees.location_id = locations.location_id
+    ");
+
+    // MS SQL has no USING
+    assert_snapshot!(compile_using(r#"
+    prql target:sql.mssql
+    from employees
+    join departments (==department_id)
+    "#), @r"
+    SELECT
+      employees.*,
+      departments.*
+    FROM
+      employees
+      JOIN departments ON employees.department_id = departments.department_id
+    ");
+}
+
+#[test]
+fn test_uppercase_keywords() {
+    let options = Options::default()
+        .no_signature()
+        .with_uppercase_keywords(false);
+
+    // identifiers and string literals keep their case
+    assert_snapshot!(prqlc::compile(r#"
+    from Employees
+    filter status == 'Active Order FROM'
+    join side:left d = departments (==dept_id)
+    select {Employees.`Order`, Employees.Name, d.title}
+    sort {-Name}
+    take 5
+    "#, &options).unwrap(), @r#"
+    with "Employees_0" as (
+      select
+        "Order",
+        "Name",
+        dept_id
+      from
+        "Employees"
+      where
+        status = 'Active Order FROM'
+    )
+    select
+      "Employees_0"."Order",
+      "Employees_0"."Name",
+      d.title
+    from
+      "Employees_0"
+      left join departments as d on "Employees_0".dept_id = d.dept_id
+    order by
+      "Employees_0"."Name" desc
+    limit
+      5
+    "#);
+}
+
+#[test]
+fn test_cte_order() {
+    let compile_ordered = |cte_order: CteOrder| {
+        let options = Options::default().no_signature().with_cte_order(cte_order);
+        prqlc::compile(
+            r#"
+            let totals = (from invoices | group {customer_id} (aggregate {total = sum amount}))
+
+            from customers
+            sort id
+            take 100
+            filter is_active
+            take 10
+            join totals (==customer_id)
+            "#,
+            &options,
+        )
+        .unwrap()
+    };
+
+    assert_snapshot!(compile_ordered(CteOrder::Reference), @r"
+    WITH customers_0 AS (
+      SELECT
+        *
+      FROM
+        customers
+      ORDER BY
+        id
+      LIMIT
+        100
+    ), customers_1 AS (
+      SELECT
+        *
+      FROM
+        customers_0
+      WHERE
+        is_active
+      ORDER BY
+        id
+      LIMIT
+        10
+    ), totals AS (
+      SELECT
+        customer_id,
+        COALESCE(SUM(amount), 0) AS total
+      FROM
+        invoices
+      GROUP BY
+        customer_id
+    )
+    SELECT
+      customers_1.*,
+      totals.customer_id,
+      totals.total
+    FROM
+      customers_1
+      JOIN totals ON customers_1.customer_id = totals.customer_id
+    ORDER BY
+      customers_1.id
+    ");
+
+    // CTEs without references come first
+    assert_snapshot!(compile_ordered(CteOrder::Topological), @r"
+    WITH customers_0 AS (
+      SELECT
+        *
+      FROM
+        customers
+      ORDER BY
+        id
+      LIMIT
+        100
+    ), totals AS (
+      SELECT
+        customer_id,
+        COALESCE(SUM(amount), 0) AS total
+      FROM
+        invoices
+      GROUP BY
+        customer_id
+    ),
+    customers_1 AS (
+      SELECT
+        *
+      FROM
+        customers_0
+      WHERE
+        is_active
+      ORDER BY
+        id
+      LIMIT
+        10
+    )
+    SELECT
+      customers_1.*,
+      totals.customer_id,
+      totals.total
+    FROM
+      customers_1
+      JOIN totals ON customers_1.customer_id = totals.customer_id
+    ORDER BY
+      customers_1.id
+    ");
+
+    // `totals` is declared before the main pipeline
+    assert_snapshot!(compile_ordered(CteOrder::Source), @r"
+    WITH totals AS (
+      SELECT
+        customer_id,
+        COALESCE(SUM(amount), 0) AS total
+      FROM
+        invoices
+      GROUP BY
+        customer_id
+    ),
+    customers_0 AS (
+      SELECT
+        *
+      FROM
+        customers
+      ORDER BY
+        id
+      LIMIT
+        100
+    ), customers_1 AS (
+      SELECT
+        *
+      FROM
+        customers_0
+      WHERE
+        is_active
+      ORDER BY
+        id
+      LIMIT
+        10
+    )
     SELECT
-      a AS _expr_0,
-      a AS _expr_0,
-      a + 1,
-      a + 1 + 2 AS a
+      customers_1.*,
+      totals.customer_id,
+      totals.total
     FROM
-      x
-    "
-    );
+      customers_1
+      JOIN totals ON customers_1.customer_id = totals.customer_id
+    ORDER BY
+      customers_1.id
+    ");
 }
 
 #[test]
-fn test_group_all() {
-    assert_snapshot!(compile(
-        r###"
-    prql target:sql.sqlite
+fn test_std_lib_mode() {
+    let std_lib = r#"
+    type relation = [{..}]
+    let from = func `default_db.source` <relation> -> <relation> source
+    let select = func columns tbl <relation> -> <relation> internal select
+    "#;
 
-    from a=albums
-    group a.* (aggregate {count this})
-        "###).unwrap_err(), @"Error: Target dialect does not support * in this position.");
+    // the query has to declare the functions it uses
+    let options = Options::default()
+        .no_signature()
+        .with_std_lib(StdLibMode::None);
+    let query = format!("module std {{{std_lib}}}\nfrom employees | select {{name}}");
+    assert_snapshot!(prqlc::compile(&query, &options).unwrap(), @r"
+    SELECT
+      name
+    FROM
+      employees
+    ");
+    assert_snapshot!(prqlc::compile("from employees | select {name}", &options).unwrap_err(), @r"
+    Error:
+       ╭─[:1:18]
+       │
+     1 │ from employees | select {name}
+       │                  ───┬──
+       │                     ╰──── Unknown name `select`
+    ───╯
+    ");
 
-    assert_snapshot!(compile(
-        r###"
-    from e=albums
-    group !{genre_id} (aggregate {count this})
-        "###).unwrap_err(), @"Error: Excluding columns not supported as this position");
+    let options = Options::default()
+        .no_signature()
+        .with_std_lib(StdLibMode::Custom(std_lib.to_string()));
+    assert_snapshot!(prqlc::compile("from employees | select {name}", &options).unwrap(), @r"
+    SELECT
+      name
+    FROM
+      employees
+    ");
+    assert_snapshot!(prqlc::compile("from employees | select {name} | select {total = sum name}", &options).unwrap_err(), @r"
+    Error:
+       ╭─[:1:50]
+       │
+     1 │ from employees | select {name} | select {total = sum name}
+       │                                                  ─┬─
+       │                                                   ╰─── Unknown name `sum`
+       │
+       │ Help: available columns: employees.name
+    ───╯
+    ");
 }
 
 #[test]
-fn test_output_column_deduplication() {
-    // #1249
-    assert_snapshot!(compile(
+fn test_prelude() {
+    let options = Options::default().no_signature().with_prelude(
         r#"
-    from report
-    derive r = s"RANK() OVER ()"
-    filter r == 1
-        "#).unwrap(),
-        @r"
-    WITH table_0 AS (
-      SELECT
-        *,
-        RANK() OVER () AS r
-      FROM
-        report
-    )
+    let with_tax = func rate amount -> amount * (1 + rate)
+    let vat = 0.2
+    let large = rel -> (rel | filter price > 100)
+    "#,
+    );
+    assert_snapshot!(prqlc::compile("from orders | large | derive {total = with_tax vat price}", &options).unwrap(), @r"
     SELECT
-      *
+      *,
+      price * (1 + 0.2) AS total
     FROM
-      table_0
+      orders
     WHERE
-      r = 1
-    "
-    );
-}
+      price > 100
+    ");
 
-#[test]
-fn test_case_01() {
-    assert_snapshot!(compile(
-        r###"
-    from employees
-    derive display_name = case [
-        nickname != null => nickname,
-        true => f'{first_name} {last_name}'
-    ]
-        "###).unwrap(),
-        @r"
+    let compiler = prqlc::Compiler::new(options).unwrap();
+    assert_snapshot!(compiler.compile("from orders | select {total = with_tax 0.1 price}").unwrap(), @r"
     SELECT
-      *,
-      CASE
-        WHEN nickname IS NOT NULL THEN nickname
-        ELSE CONCAT(first_name, ' ', last_name)
-      END AS display_name
+      price * (1 + 0.1) AS total
     FROM
-      employees
-    "
+      orders
+    ");
+
+    // other files of a prelude are its submodules
+    let prelude = SourceTree::new(
+        [
+            ("".into(), "let vat = 0.2".to_string()),
+            (
+                "tax.prql".into(),
+                "let with_tax = func amount -> amount * (1 + vat)".to_string(),
+            ),
+        ],
+        None,
     );
+    let options = Options::default().no_signature().with_prelude(prelude);
+    assert_snapshot!(prqlc::compile("from orders | select {total = tax.with_tax price}", &options).unwrap(), @r"
+    SELECT
+      price * (1 + 0.2) AS total
+    FROM
+      orders
+    ");
+
+    let (sql, _) = prqlc::compile_with_source_map("from orders | select {vat}", &options).unwrap();
+    assert_snapshot!(sql, @r"
+    SELECT
+      0.2
+    FROM
+      orders
+    ");
+
+    // errors within the prelude point to it
+    let options = Options::default().with_prelude("let broken = func x -> (x +)");
+    assert_snapshot!(prqlc::compile("from orders", &options).unwrap_err(), @r"
+    Error:
+       ╭─[prelude.prql:1:28]
+       │
+     1 │ let broken = func x -> (x +)
+       │                            ┬
+       │                            ╰── unexpected ) while parsing function call
+    ───╯
+    ");
 }
 
 #[test]
-fn test_case_02() {
-    assert_snapshot!(compile(
-        r###"
+fn test_inline_ctes() {
+    let compile_inline = |prql: &str| {
+        let options = Options::default().no_signature().with_inline_ctes(true);
+        prqlc::compile(prql, &options).unwrap()
+    };
+
+    let query = r#"
     from employees
-    derive display_name = case [
-        nickname != null => nickname,
-        first_name != null => f'{first_name} {last_name}'
-    ]
-        "###).unwrap(),
-        @r"
+    sort salary
+    take 10
+    aggregate {total = sum salary}
+    "#;
+
+    assert_snapshot!(compile(query).unwrap(), @r"
+    WITH employees_0 AS (
+      SELECT
+        salary
+      FROM
+        employees
+      ORDER BY
+        salary
+      LIMIT
+        10
+    )
     SELECT
-      *,
-      CASE
-        WHEN nickname IS NOT NULL THEN nickname
-        WHEN first_name IS NOT NULL THEN CONCAT(first_name, ' ', last_name)
-        ELSE NULL
-      END AS display_name
+      COALESCE(SUM(salary), 0) AS total
     FROM
-      employees
-    "
-    );
-}
+      employees_0
+    ");
+    assert_snapshot!(compile_inline(query), @r"
+    SELECT
+      COALESCE(SUM(salary), 0) AS total
+    FROM
+      (
+        SELECT
+          salary
+        FROM
+          employees
+        ORDER BY
+          salary
+        LIMIT
+          10
+      ) AS table_0
+    ");
 
-#[test]
-fn test_case_03() {
-    assert_snapshot!(compile(
-        r###"
-    from tracks
-    select category = case [
-        length > avg_length => 'long'
-    ]
-    group category (aggregate {count this})
-        "###).unwrap(),
-        @r"
-    WITH table_0 AS (
+    // relations referenced multiple times stay as CTEs
+    assert_snapshot!(compile_inline(r#"
+    let top = (from employees | take 10)
+    let managers = (from top | select {id = manager_id})
+    from top
+    join managers (==id)
+    "#), @r#"
+    WITH "top" AS (
       SELECT
-        CASE
-          WHEN length > avg_length THEN 'long'
-          ELSE NULL
-        END AS category,
-        length,
-        avg_length
+        *
       FROM
-        tracks
+        employees
+      LIMIT
+        10
     )
     SELECT
-      category,
-      COUNT(*)
+      "top".*,
+      managers.id
     FROM
-      table_0
-    GROUP BY
-      category
-    "
-    );
+      "top"
+      JOIN (
+        SELECT
+          manager_id AS id
+        FROM
+          "top"
+      ) AS managers ON "top".id = managers.id
+    "#);
 }
 
 #[test]
-fn test_sql_options() {
-    let options = Options::default();
-    let sql = prqlc::compile("from x", &options).unwrap();
-
-    assert!(sql.contains('\n'));
-    assert!(sql.contains("-- Generated by"));
-
-    let options = Options::default().no_signature().no_format();
-    let sql = prqlc::compile("from x", &options).unwrap();
-
-    assert!(!sql.contains('\n'));
-    assert!(!sql.contains("-- Generated by"));
+fn test_let_referenced_twice() {
+    // the pipeline of `base` is emitted once, as a CTE referenced twice
+    assert_snapshot!(compile(r#"
+    let base = (from tasks | filter is_open)
+    from parents = base
+    join children = base (parents.id == children.parent_id)
+    select {parents.name, child = children.name}
+    "#).unwrap(), @r"
+    WITH base AS (
+      SELECT
+        *
+      FROM
+        tasks
+      WHERE
+        is_open
+    )
+    SELECT
+      parents.name,
+      children.name AS child
+    FROM
+      base AS parents
+      JOIN base AS children ON parents.id = children.parent_id
+    ");
 }
 
 #[test]
@@ -4068,7 +7121,7 @@ fn test_basic_agg() {
     "#).unwrap(),
         @r"
     SELECT
-      COUNT(*),
+      COUNT(salary),
       COUNT(*)
     FROM
       employees
@@ -4372,18 +7425,11 @@ fn test_from_text_04() {
     std.from_text 'a,b'
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
-      SELECT
-        NULL AS a,
-        NULL AS b
-      WHERE
-        false
-    )
     SELECT
-      a,
-      b
-    FROM
-      table_0
+      NULL AS a,
+      NULL AS b
+    WHERE
+      false
     "
     );
 }
@@ -4394,20 +7440,12 @@ fn test_from_text_05() {
     std.from_text format:json '''{"columns": ["a", "b", "c"], "data": []}'''
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
-      SELECT
-        NULL AS a,
-        NULL AS b,
-        NULL AS c
-      WHERE
-        false
-    )
     SELECT
-      a,
-      b,
-      c
-    FROM
-      table_0
+      NULL AS a,
+      NULL AS b,
+      NULL AS c
+    WHERE
+      false
     "
     );
 }
@@ -4516,20 +7554,53 @@ fn test_header_target_error() {
     ");
 }
 
+#[test]
+fn test_header_statement_timeout() {
+    let query = "from a\ntake 5";
+
+    assert_snapshot!(compile(&format!("prql target:sql.postgres statement_timeout:5000\n{query}")).unwrap(), @r"
+    SET statement_timeout = 5000;
+    SELECT
+      *
+    FROM
+      a
+    LIMIT
+      5
+    ");
+
+    assert_snapshot!(compile(&format!("prql target:sql.mysql statement_timeout:5000\n{query}")).unwrap(), @r"
+    SET max_execution_time = 5000;
+    SELECT
+      *
+    FROM
+      a
+    LIMIT
+      5
+    ");
+
+    assert_snapshot!(compile(&format!("prql target:sql.sqlite statement_timeout:5000\n{query}")).unwrap_err(), @r"
+    Error: `statement_timeout` is not supported for sqlite
+    ");
+
+    assert_snapshot!(compile(&format!("prql statement_timeout:\"5s\"\n{query}")).unwrap_err(), @r#"
+    Error:
+       ╭─[:1:1]
+       │
+     1 │ prql statement_timeout:"5s"
+       │ ──────────────┬─────────────
+       │               ╰─────────────── statement_timeout must be an integer number of milliseconds
+    ───╯
+    "#);
+}
+
 #[test]
 fn shortest_prql_version() {
     let mut escape_version = insta::Settings::new();
     escape_version.add_filter(r"'.*'", "[VERSION]");
     escape_version.bind(|| {
-        assert_snapshot!(compile(r#"[{version = prql.version}]"#).unwrap(),@r"
-        WITH table_0 AS (
-          SELECT
-            [VERSION] AS version
-        )
+        assert_snapshot!(compile(r#"[{version = prql.version}]"#).unwrap(),@r"
         SELECT
-          version
-        FROM
-          table_0
+          [VERSION] AS version
         ");
 
         assert_snapshot!(compile(r#"
@@ -4562,7 +7633,7 @@ fn test_loop() {
       SELECT
         1 AS n
     ),
-    table_1 AS (
+    table_2 AS (
       SELECT
         n - 2 AS _expr_0
       FROM
@@ -4576,7 +7647,7 @@ fn test_loop() {
           SELECT
             _expr_0 + 1 AS _expr_1
           FROM
-            table_1
+            table_2
         ) AS table_4
       WHERE
         _expr_1 < 5
@@ -4584,7 +7655,7 @@ fn test_loop() {
     SELECT
       _expr_0 * 2 AS n
     FROM
-      table_1 AS table_3
+      table_2 AS table_1
     LIMIT
       4
     "
@@ -4608,7 +7679,7 @@ fn test_loop_2() {
       FROM
         read_csv('employees.csv')
     ),
-    table_1 AS (
+    table_2 AS (
       SELECT
         *
       FROM
@@ -4620,17 +7691,207 @@ fn test_loop_2() {
       SELECT
         manager.*
       FROM
-        table_1
-        JOIN employees AS manager ON manager.employee_id = table_1.reports_to
+        table_2
+        JOIN employees AS manager ON manager.employee_id = table_2.reports_to
     )
     SELECT
       *
     FROM
-      table_1 AS table_2
+      table_2 AS table_1
     "
     );
 }
 
+#[test]
+fn test_pivot_01() {
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from sales
+    pivot category (sum amount) ["books", "toys"]
+    "#, sql::Dialect::DuckDb).unwrap(), @r"
+    SELECT
+      *
+    FROM
+      sales PIVOT(
+        SUM(amount) FOR category IN ('books' AS books, 'toys' AS toys)
+      )
+    ");
+}
+
+#[test]
+fn test_pivot_02() {
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from sales
+    select {region, category, amount}
+    derive amount = amount * 2
+    pivot category (sum amount) ["books", "toys"]
+    filter books > 10
+    sort region
+    "#, sql::Dialect::Snowflake).unwrap(), @r"
+    WITH sales_0 AS (
+      SELECT
+        region,
+        category,
+        amount * 2 AS _expr_0
+      FROM
+        sales
+    )
+    SELECT
+      region,
+      books,
+      toys
+    FROM
+      sales_0 PIVOT(
+        SUM(_expr_0) FOR category IN ('books' AS books, 'toys' AS toys)
+      )
+    WHERE
+      books > 10
+    ORDER BY
+      region
+    ");
+}
+
+#[test]
+fn test_pivot_03() {
+    // emulation with CASE
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from sales
+    select {region, category, amount}
+    pivot category (sum amount) ["books", "toys", 2020]
+    sort region
+    "#, sql::Dialect::Postgres).unwrap(), @r#"
+    SELECT
+      region,
+      COALESCE(
+        SUM(
+          CASE
+            WHEN category = 'books' THEN amount
+            ELSE NULL
+          END
+        ),
+        0
+      ) AS books,
+      COALESCE(
+        SUM(
+          CASE
+            WHEN category = 'toys' THEN amount
+            ELSE NULL
+          END
+        ),
+        0
+      ) AS toys,
+      COALESCE(
+        SUM(
+          CASE
+            WHEN category = 2020 THEN amount
+            ELSE NULL
+          END
+        ),
+        0
+      ) AS "2020"
+    FROM
+      sales
+    GROUP BY
+      region
+    ORDER BY
+      region
+    "#);
+}
+
+#[test]
+fn test_pivot_04() {
+    // emulation is not possible without knowing all columns
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from sales
+    pivot category (sum amount) ["books", "toys"]
+    "#, sql::Dialect::Postgres).unwrap_err(), @r"
+    Error: The dialect PostgresDialect does not support PIVOT and it cannot be emulated for relations with unknown columns
+    ↳ Hint: select the columns explicitly before `pivot`
+    ");
+
+    assert_snapshot!(compile(r#"
+    from sales
+    pivot category amount ["books", "toys"]
+    "#).unwrap_err(), @r#"
+    Error:
+       ╭─[:3:20]
+       │
+     3 │     pivot category amount ["books", "toys"]
+       │                    ───┬──
+       │                       ╰──── `pivot` expected an aggregation of a column, i.e. `sum amount`, but found this.sales.amount
+    ───╯
+    "#);
+
+    assert_snapshot!(compile(r#"
+    from sales
+    pivot category (sum amount) [books, toys]
+    "#).unwrap_err(), @r"
+    Error:
+       ╭─[:3:34]
+       │
+     3 │     pivot category (sum amount) [books, toys]
+       │                                  ──┬──
+       │                                    ╰──── `pivot` expected a literal value, but found this.sales.books
+    ───╯
+    ");
+}
+
+#[test]
+fn test_unpivot_01() {
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from quarterly
+    select {year, q1, q2}
+    unpivot {q1, q2} names_to:quarter values_to:revenue
+    filter revenue > 0
+    "#, sql::Dialect::DuckDb).unwrap(), @r"
+    WITH quarterly_0 AS (
+      SELECT
+        year,
+        q1,
+        q2
+      FROM
+        quarterly
+    )
+    SELECT
+      year,
+      quarter,
+      revenue
+    FROM
+      quarterly_0 UNPIVOT(revenue FOR quarter IN (q1, q2))
+    WHERE
+      revenue > 0
+    ");
+
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from quarterly
+    unpivot {q1, q2}
+    join regions (==region_id)
+    "#, sql::Dialect::Snowflake).unwrap(), @r"
+    WITH quarterly_0 AS (
+      SELECT
+        *
+      FROM
+        quarterly UNPIVOT(value FOR name IN (q1, q2))
+    )
+    SELECT
+      quarterly_0.*,
+      regions.*
+    FROM
+      quarterly_0
+      JOIN regions ON quarterly_0.region_id = regions.region_id
+    ");
+}
+
+#[test]
+fn test_unpivot_02() {
+    assert_snapshot!(compile_with_sql_dialect(r#"
+    from quarterly
+    unpivot {q1, q2}
+    "#, sql::Dialect::Postgres).unwrap_err(), @r"
+    Error: The dialect PostgresDialect does not support UNPIVOT
+    ↳ Hint: UNPIVOT is supported by DuckDB and Snowflake dialects
+    ");
+}
+
 #[test]
 fn test_params() {
     assert_snapshot!(compile(r#"
@@ -4659,6 +7920,24 @@ fn test_params() {
     )
 }
 
+#[test]
+fn test_params_of_compile_output() {
+    let output = prqlc::compile_with_warnings(
+        r#"
+    let recent = (from invoices | filter date > $since)
+    from customers
+    filter id == $2 || id == $customer
+    join recent (==customer_id)
+    filter recent.total > $2
+    "#,
+        &Options::default(),
+    )
+    .unwrap();
+
+    // without duplicates, in the order of the query
+    assert_eq!(output.params, vec!["since", "2", "customer"]);
+}
+
 // for #1969
 #[test]
 fn test_datetime() {
@@ -4817,19 +8096,12 @@ fn test_excess_columns() {
     select {title}
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
-      SELECT
-        title,
-        track_id AS _expr_0
-      FROM
-        tracks
-    )
     SELECT
       title
     FROM
-      table_0
+      tracks
     ORDER BY
-      _expr_0
+      track_id
     "
     );
 }
@@ -4850,6 +8122,111 @@ fn test_regex_search() {
     );
 }
 
+#[test]
+fn test_null_safe_eq() {
+    let query = r#"
+    from employees
+    join managers (employees.manager_id <=> managers.id)
+    filter (employees.dept <=> managers.dept) && employees.salary > 0
+    "#;
+
+    assert_snapshot!(compile(&format!("prql target:sql.postgres\n{query}")).unwrap(), @r"
+    SELECT
+      employees.*,
+      managers.*
+    FROM
+      employees
+      JOIN managers ON employees.manager_id IS NOT DISTINCT FROM managers.id
+    WHERE
+      employees.dept IS NOT DISTINCT FROM managers.dept
+      AND employees.salary > 0
+    ");
+
+    assert_snapshot!(compile(&format!("prql target:sql.mysql\n{query}")).unwrap(), @r"
+    SELECT
+      employees.*,
+      managers.*
+    FROM
+      employees
+      JOIN managers ON employees.manager_id <=> managers.id
+    WHERE
+      employees.dept <=> managers.dept
+      AND employees.salary > 0
+    ");
+
+    assert_snapshot!(compile(&format!("prql target:sql.mssql\n{query}")).unwrap(), @r"
+    SELECT
+      employees.*,
+      managers.*
+    FROM
+      employees
+      JOIN managers ON (
+        CASE
+          WHEN employees.manager_id = managers.id
+          OR (
+            employees.manager_id IS NULL
+            AND managers.id IS NULL
+          ) THEN 1
+          ELSE 0
+        END = 1
+      )
+    WHERE
+      (
+        CASE
+          WHEN employees.dept = managers.dept
+          OR (
+            employees.dept IS NULL
+            AND managers.dept IS NULL
+          ) THEN 1
+          ELSE 0
+        END = 1
+      )
+      AND employees.salary > 0
+    ");
+
+    // stays on one line when formatted, also with lowercase keywords
+    let options = Options::default()
+        .no_signature()
+        .with_target(Target::Sql(Some(sql::Dialect::Postgres)))
+        .with_uppercase_keywords(false);
+    assert_snapshot!(prqlc::compile(r#"
+    from employees
+    derive same = case [dept <=> region => 'IS DISTINCT FROM', true => 'no']
+    "#, &options).unwrap(), @r"
+    select
+      *,
+      case
+        when dept is not distinct from region then 'IS DISTINCT FROM'
+        else 'no'
+      end as same
+    from
+      employees
+    ");
+
+    // emulated with a `CASE`, so it's false rather than null when only one
+    // value is null
+    assert_snapshot!(compile(r#"
+    from employees
+    filter !(dept <=> region)
+    "#).unwrap(), @r"
+    SELECT
+      *
+    FROM
+      employees
+    WHERE
+      NOT (
+        CASE
+          WHEN dept = region
+          OR (
+            dept IS NULL
+            AND region IS NULL
+          ) THEN 1
+          ELSE 0
+        END = 1
+      )
+    ");
+}
+
 #[test]
 fn test_intervals() {
     assert_snapshot!(compile(r#"
@@ -4890,6 +8267,45 @@ fn test_into() {
     );
 }
 
+#[test]
+fn test_into_table() {
+    let query = r#"
+    from users
+    select {id, name}
+    into staging.users
+    "#;
+
+    assert_snapshot!(compile(query).unwrap(), @r"
+    INSERT INTO
+      staging.users
+    SELECT
+      id,
+      name
+    FROM
+      users
+    ");
+
+    let options = Options::default()
+        .no_signature()
+        .with_write_mode(prqlc::WriteMode::CreateTableAs);
+    assert_snapshot!(prqlc::compile(query, &options).unwrap(), @r"
+    CREATE TABLE staging.users AS
+    SELECT
+      id,
+      name
+    FROM
+      users
+    ");
+
+    // written back as `into`
+    let pl = prqlc::prql_to_pl(query).unwrap();
+    assert_snapshot!(prqlc::pl_to_prql(&pl).unwrap(), @r"
+    from users
+    select {id, name}
+    into staging.users
+    ");
+}
+
 #[test]
 fn test_array_01() {
     compile(
@@ -4947,18 +8363,12 @@ fn test_array_02() {
     ]
     "#)
     .unwrap(), @r"
-    WITH table_0 AS (
-      SELECT
-        NULL AS x
-      UNION
-      ALL
-      SELECT
-        '1' AS x
-    )
     SELECT
-      x
-    FROM
-      table_0
+      NULL AS x
+    UNION
+    ALL
+    SELECT
+      '1' AS x
     ");
 }
 
@@ -4971,7 +8381,7 @@ fn test_double_stars() {
     filter (tb2.c3 < 100)
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
+    WITH tb1_0 AS (
       SELECT
         tb1.*,
         tb2.*
@@ -4984,7 +8394,7 @@ fn test_double_stars() {
     SELECT
       *
     FROM
-      table_0
+      tb1_0
     WHERE
       c3 < 100
     "
@@ -4999,7 +8409,7 @@ fn test_double_stars() {
     filter (tb2.c3 < 100)
     "#).unwrap(),
         @r"
-    WITH table_0 AS (
+    WITH tb1_0 AS (
       SELECT
         tb1.*,
         tb2.*
@@ -5012,7 +8422,7 @@ fn test_double_stars() {
     SELECT
       *
     FROM
-      table_0
+      tb1_0
     WHERE
       c3 < 100
     "
@@ -5102,6 +8512,11 @@ fn test_error_code() {
     )
     .unwrap_err();
     assert_eq!(err.inner[0].code.as_ref().unwrap(), "E0001");
+    assert_eq!(err.inner[0].error_code(), Some(ErrorCode::E0001));
+
+    // errors can be boxed, as by `?` in functions returning `Box<dyn Error>`
+    let err: Box<dyn std::error::Error> = Box::new(err);
+    assert!(err.to_string().contains("Missing main pipeline"));
 }
 
 #[test]
@@ -5144,7 +8559,7 @@ fn test_returning_constants_only() {
     "###,
     )
     .unwrap(), @r"
-    WITH table_0 AS (
+    WITH tb1_0 AS (
       SELECT
         10 AS d,
         a
@@ -5154,7 +8569,7 @@ fn test_returning_constants_only() {
     SELECT
       d
     FROM
-      table_0
+      tb1_0
     ORDER BY
       a
     ");
@@ -5170,18 +8585,18 @@ fn test_returning_constants_only() {
     "###,
     )
     .unwrap(), @r"
-    WITH table_1 AS (
+    WITH tb1_0 AS (
       SELECT
         NULL
       FROM
         tb1
       LIMIT
         10
-    ), table_0 AS (
+    ), tb1_1 AS (
       SELECT
         NULL
       FROM
-        table_1
+        tb1_0
       WHERE
         true
       LIMIT
@@ -5190,7 +8605,7 @@ fn test_returning_constants_only() {
     SELECT
       10 AS d
     FROM
-      table_0
+      tb1_1
     WHERE
       true
     ");
@@ -5212,7 +8627,7 @@ fn test_conflicting_names_at_split() {
     "#,
     )
     .unwrap(), @r"
-    WITH table_0 AS (
+    WITH workflow_steps_0 AS (
       SELECT
         wp.id,
         s.id AS _expr_0,
@@ -5224,11 +8639,11 @@ fn test_conflicting_names_at_split() {
         wp.name = 'CREATE_OUTLET'
     )
     SELECT
-      table_0._expr_0 AS step_id,
-      table_0.id AS phase_id
+      workflow_steps_0._expr_0 AS step_id,
+      workflow_steps_0.id AS phase_id
     FROM
-      table_0
-      JOIN workflow AS w ON table_0.workflow_id = w.id
+      workflow_steps_0
+      JOIN workflow AS w ON workflow_steps_0.workflow_id = w.id
     ");
 }
 
@@ -5257,6 +8672,67 @@ fn test_relation_literal_quoting() {
     "#);
 }
 
+#[test]
+fn test_relation_literal_values() {
+    // dialects with table value constructors name the columns via the alias
+    assert_snapshot!(compile(
+        r###"
+    prql target:sql.postgres
+
+    from [{a=1, `b c`="x"}, {a=3, `b c`="y"}]
+    filter a > 1
+    "###,
+    )
+    .unwrap(), @r#"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        (
+          VALUES
+            (1, 'x'),
+            (3, 'y')
+        ) AS table_1 (a, "b c")
+    )
+    SELECT
+      a,
+      "b c"
+    FROM
+      table_0
+    WHERE
+      a > 1
+    "#);
+
+    // others fall back to SELECT ... UNION ALL SELECT ...
+    assert_snapshot!(compile(
+        r###"
+    prql target:sql.sqlite
+
+    from [{a=1, `b c`="x"}, {a=3, `b c`="y"}]
+    filter a > 1
+    "###,
+    )
+    .unwrap(), @r#"
+    WITH table_0 AS (
+      SELECT
+        1 AS a,
+        'x' AS "b c"
+      UNION
+      ALL
+      SELECT
+        3 AS a,
+        'y' AS "b c"
+    )
+    SELECT
+      a,
+      "b c"
+    FROM
+      table_0
+    WHERE
+      a > 1
+    "#);
+}
+
 #[test]
 fn test_relation_var_name_clashes_01() {
     assert_snapshot!(compile(
@@ -5275,7 +8751,7 @@ fn test_relation_var_name_clashes_01() {
       FROM
         a
     ),
-    table_1 AS (
+    table_0_0 AS (
       SELECT
         *
       FROM
@@ -5286,7 +8762,7 @@ fn test_relation_var_name_clashes_01() {
     SELECT
       *
     FROM
-      table_1
+      table_0_0
     WHERE
       x > 0
     ");
@@ -5304,10 +8780,10 @@ fn test_relation_var_name_clashes_02() {
     .unwrap(), @r"
     SELECT
       t.*,
-      table_0.*
+      t_0.*
     FROM
       t
-      JOIN t AS table_0 ON t.x = table_0.x
+      JOIN t AS t_0 ON t.x = t_0.x
     ");
 }
 
@@ -5477,19 +8953,20 @@ select !{ c }
 
 group { d } ( aggregate { b = sum b } ) 
 sort { d }"###).unwrap(), @r#"
-    WITH table_1 AS (
+    WITH foo_0 AS (
       SELECT
-        b
+        b,
+        c
       FROM
         foo
       LIMIT
         10000
-    ), table_0 AS (
+    ), foo_1 AS (
       SELECT
         b,
-        COUNT(*) AS _expr_0
+        COUNT(c) AS _expr_0
       FROM
-        table_1
+        foo_0
       GROUP BY
         b
     )
@@ -5497,7 +8974,7 @@ sort { d }"###).unwrap(), @r#"
       _expr_0 AS d,
       COALESCE(SUM(b), 0) AS b
     FROM
-      table_0
+      foo_1
     GROUP BY
       _expr_0
     ORDER BY
@@ -5519,3 +8996,107 @@ fn test_type_error_placement() {
       t
     "#);
 }
+
+#[test]
+fn test_nulls_order() {
+    let compile_nulls_last = |target: &str| {
+        let options = Options::default()
+            .no_signature()
+            .no_format()
+            .with_nulls_order(NullsOrder::Last);
+        let prql = format!("prql target:sql.{target}\nfrom t | sort {{a, -b}}");
+        prqlc::compile(&prql, &options).unwrap()
+    };
+
+    // NULLs are last on ascending sorts by default
+    assert_snapshot!(compile_nulls_last("postgres"), @r"
+    SELECT * FROM t ORDER BY a, b DESC NULLS LAST
+    ");
+    // NULLs are last regardless of the direction by default
+    assert_snapshot!(compile_nulls_last("duckdb"), @r"
+    SELECT * FROM t ORDER BY a, b DESC
+    ");
+    // NULLs are first on ascending sorts by default
+    assert_snapshot!(compile_nulls_last("sqlite"), @r"
+    SELECT * FROM t ORDER BY a NULLS LAST, b DESC
+    ");
+    // unknown default
+    assert_snapshot!(compile_nulls_last("generic"), @r"
+    SELECT * FROM t ORDER BY a NULLS LAST, b DESC NULLS LAST
+    ");
+    // there is no NULLS LAST
+    assert_snapshot!(compile_nulls_last("mysql"), @r"
+    SELECT * FROM t ORDER BY CASE WHEN a IS NULL THEN 1 ELSE 0 END, a, b DESC
+    ");
+
+    let options = Options::default()
+        .no_signature()
+        .no_format()
+        .with_nulls_order(NullsOrder::First);
+    assert_snapshot!(prqlc::compile("prql target:sql.mssql\nfrom t | sort {a, -b}", &options).unwrap(), @r"
+    SELECT * FROM t ORDER BY a, CASE WHEN b IS NULL THEN 0 ELSE 1 END, b DESC
+    ");
+}
+
+#[test]
+fn test_group_take_nulls_order() {
+    // the sort within the group is the ORDER BY of the window, which picks the
+    // row of each group, so the row with the latest `updated_at` is kept
+    let compile_dedup = |target: &str, options: &Options| {
+        let prql = format!(
+            "prql target:sql.{target}\nfrom t | group {{id}} (sort {{-updated_at, name}} | take 1)"
+        );
+        prqlc::compile(&prql, options).unwrap()
+    };
+    let options = Options::default().no_signature().no_format();
+
+    assert_snapshot!(compile_dedup("generic", &options), @r"
+    WITH t_0 AS (SELECT *, ROW_NUMBER() OVER (PARTITION BY id ORDER BY updated_at DESC, name) AS _expr_0 FROM t) SELECT * FROM t_0 WHERE _expr_0 <= 1
+    ");
+    assert_snapshot!(compile_dedup("postgres", &options), @r"
+    SELECT DISTINCT ON (id) * FROM t ORDER BY id, updated_at DESC, name
+    ");
+
+    // NULLs are placed as set
+    let options = options.with_nulls_order(NullsOrder::Last);
+    assert_snapshot!(compile_dedup("generic", &options), @r"
+    WITH t_0 AS (SELECT *, ROW_NUMBER() OVER (PARTITION BY id ORDER BY updated_at DESC NULLS LAST, name NULLS LAST) AS _expr_0 FROM t) SELECT * FROM t_0 WHERE _expr_0 <= 1
+    ");
+    assert_snapshot!(compile_dedup("postgres", &options), @r"
+    SELECT DISTINCT ON (id) * FROM t ORDER BY id, updated_at DESC NULLS LAST, name
+    ");
+    // there is no NULLS LAST, and NULLs are last on descending sorts by default
+    assert_snapshot!(compile_dedup("mysql", &options), @r"
+    WITH t_0 AS (SELECT *, ROW_NUMBER() OVER (PARTITION BY id ORDER BY updated_at DESC, CASE WHEN name IS NULL THEN 1 ELSE 0 END, name) AS _expr_0 FROM t) SELECT * FROM t_0 WHERE _expr_0 <= 1
+    ");
+}
+
+#[test]
+fn test_constant_tuple() {
+    // a tuple is a relation of a single row
+    let query = "{a = 1, b = 2}";
+
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Postgres).unwrap(), @r"
+    SELECT
+      1 AS a,
+      2 AS b
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::MySql).unwrap(), @r"
+    SELECT
+      1 AS a,
+      2 AS b
+    ");
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::SQLite).unwrap(), @r"
+    SELECT
+      1 AS a,
+      2 AS b
+    ");
+    // Oracle requires a FROM
+    assert_snapshot!(compile_with_sql_dialect(query, sql::Dialect::Oracle).unwrap(), @r"
+    SELECT
+      1 AS a,
+      2 AS b
+    FROM
+      DUAL
+    ");
+}